@@ -1,6 +1,9 @@
 use axum::http::StatusCode;
 use axum::{routing::*, Json, Router};
+use bakery_model::*;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use vantage::prelude::{GraphQlSchema, OpenApi, Operation};
 
 pub mod orders;
 pub mod products;
@@ -12,10 +15,63 @@ pub fn app() -> Router {
     Router::new()
         .route("/", get(root))
         .route("/users", post(create_user))
+        .route("/schema.graphql", get(graphql_schema))
+        .route("/graphql", post(graphql))
+        .route("/openapi.json", get(openapi))
         .nest("/products", products::router_products())
         .nest("/orders", orders::router_orders())
 }
 
+/// GraphQL types of the bakery model
+fn graphql_types() -> GraphQlSchema {
+    GraphQlSchema::new()
+        .with_table("Bakery", &Bakery::table())
+        .with_table("Client", &Client::table())
+        .with_table("Order", &Order::table())
+        .with_table("LineItem", &LineItem::table())
+        .with_table("Product", &Product::table())
+}
+
+async fn graphql_schema() -> String {
+    graphql_types().to_string()
+}
+
+/// Executes a GraphQL query against the bakery model
+async fn graphql(Json(request): Json<GraphQlRequest>) -> Result<Json<Value>, StatusCode> {
+    let schema = graphql_types()
+        .build()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let response = schema.execute(request.query).await;
+    serde_json::to_value(response)
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[derive(Deserialize)]
+struct GraphQlRequest {
+    query: String,
+}
+
+/// OpenAPI document, describing the routes above
+async fn openapi() -> Json<Value> {
+    Json(
+        OpenApi::new("Bakery API", env!("CARGO_PKG_VERSION"))
+            .with_table(
+                "/products",
+                "Product",
+                &Product::table(),
+                &[Operation::List],
+            )
+            .with_table(
+                "/orders",
+                "Order",
+                &Order::table(),
+                &[Operation::List, Operation::Get],
+            )
+            .to_json(),
+    )
+}
+
 async fn create_user(
     // this argument tells axum to parse the request body
     // as JSON into a `CreateUser` type
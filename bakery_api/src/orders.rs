@@ -1,47 +1,14 @@
-use axum::{response::IntoResponse, routing::get, Json, Router};
+use axum::Router;
 use bakery_model::*;
-use serde::Deserialize;
-use vantage::{prelude::*, sql::query::SqlQuery};
-
-#[derive(Deserialize)]
-struct OrderRequest {
-    client_id: i32,
-}
-
-#[derive(Deserialize)]
-struct Pagination {
-    #[serde(default)]
-    page: i64,
-    #[serde(default = "per_page_default")]
-    per_page: i64,
-}
-
-pub fn per_page_default() -> i64 {
-    10
-}
+use vantage::prelude::*;
+use vantage::web::crud_router;
 
+/// GET /orders?client_id=1&page=0&per_page=10 and GET /orders/:id
 pub fn router_orders() -> Router {
-    Router::new().route("/", get(list_orders))
-}
-
-async fn list_orders(
-    client: axum::extract::Query<OrderRequest>,
-    pager: axum::extract::Query<Pagination>,
-) -> impl IntoResponse {
-    let orders = Client::table()
-        .with_id(client.client_id.into())
-        .ref_orders();
-
-    let mut query = orders.query();
-
-    // Change the query to include pagination
-    query.add_limit(Some(pager.per_page));
-    if pager.page > 0 {
-        query.add_skip(Some(pager.per_page * pager.page));
-    }
-
-    // will serialize type of `Order` struct
-    Json(query.get().await.unwrap())
+    crud_router(Order::table)
+        .with_columns(&["client_id"])
+        .with_operations(&[Operation::List, Operation::Get])
+        .into_router()
 }
 
 #[cfg(test)]
@@ -1,23 +1,24 @@
-use axum::{response::IntoResponse, routing::get, Json, Router};
+use axum::{routing::get, Router};
 use bakery_model::product::Product;
+use serde_json::{Map, Value};
 use vantage::prelude::*;
+use vantage::web::{ApiError, DataSetJson, ListParams};
 
 pub fn router_products() -> Router {
     Router::new().route("/", get(list_products))
 }
 
-async fn list_products() -> impl IntoResponse {
-    // We will work with Product Set
-    let products = Product::table();
+/// GET /products?bakery_id=1&sort=-price&page=0&per_page=10
+async fn list_products(params: ListParams) -> Result<DataSetJson<Map<String, Value>>, ApiError> {
+    let products = params.apply(Product::table(), &["name", "bakery_id", "price"])?;
 
-    //
-    let data = products
+    let query = products
         .query_for_field_names(&["id", "name"])
-        .get_all_untyped()
-        .await
-        .unwrap();
+        .with_skip_and_limit(params.skip(), params.per_page());
 
-    Json(data)
+    let (data, total) =
+        tokio::try_join!(query.get_all_untyped(), ReadableDataSet::count(&products))?;
+    Ok(DataSetJson::new_page(data, total, &params))
 }
 
 #[cfg(test)]
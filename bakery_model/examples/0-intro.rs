@@ -42,7 +42,7 @@ async fn main() -> Result<()> {
     // Generate count() Query from Table<Postgres, Client> and execute it:
     println!(
         "Count of paying clients: {}",
-        paying_clients.count().get_one_untyped().await?
+        paying_clients.count().get_one_as::<i64>().await?
     );
 
     /////////////////////////////////////////////////////////////////////////////////////////
@@ -32,7 +32,7 @@ async fn main() -> Result<()> {
 
     println!(
         "We are starting with {} products",
-        products.count().get_one_untyped().await?
+        products.count().get_one_as::<i64>().await?
     );
 
     println!("");
@@ -50,7 +50,7 @@ async fn main() -> Result<()> {
     println!(
         "After adding \"Nuclear Sandwich\" (id={}) we are left with {} products",
         &nuclear_sandwich_id,
-        products.count().get_one_untyped().await?
+        products.count().get_one_as::<i64>().await?
     );
 
     // So far we didn't know about the soft delete field, but lets add it now
@@ -67,11 +67,11 @@ async fn main() -> Result<()> {
 
     println!(
         "After soft-deleting \"Nuclear Sandwich\" we are left with {} SD (soft delete) products",
-        product_sd.count().get_one_untyped().await?
+        product_sd.count().get_one_as::<i64>().await?
     );
     println!(
         "However as per our old set (no SD) we still have {} products",
-        products.count().get_one_untyped().await?
+        products.count().get_one_as::<i64>().await?
     );
 
     Ok(())
@@ -39,15 +39,12 @@ pub trait BakeryTable: AnyTable {
     fn profit_margin(&self) -> Arc<Column> {
         self.get_column("profit_margin").unwrap()
     }
-
-    fn ref_clients(&self) -> Table<Postgres, Client>;
-    fn ref_products(&self) -> Table<Postgres, Product>;
 }
-impl BakeryTable for Table<Postgres, Bakery> {
-    fn ref_clients(&self) -> Table<Postgres, Client> {
-        self.get_ref_as("clients").unwrap()
-    }
-    fn ref_products(&self) -> Table<Postgres, Product> {
-        self.get_ref_as("products").unwrap()
+impl BakeryTable for Table<Postgres, Bakery> {}
+
+table_refs! {
+    pub trait BakeryRefs for Table<Postgres, Bakery> {
+        fn ref_clients("clients") -> Client;
+        fn ref_products("products") -> Product;
     }
 }
@@ -49,15 +49,12 @@ pub trait ClientTable: SqlTable {
     fn is_paying_client(&self) -> Arc<Column> {
         self.get_column("is_paying_client").unwrap()
     }
-
-    fn ref_bakery(&self) -> Table<Postgres, Bakery>;
-    fn ref_orders(&self) -> Table<Postgres, Order>;
 }
-impl ClientTable for Table<Postgres, Client> {
-    fn ref_bakery(&self) -> Table<Postgres, Bakery> {
-        self.get_ref_as("bakery").unwrap()
-    }
-    fn ref_orders(&self) -> Table<Postgres, Order> {
-        self.get_ref_as("orders").unwrap()
+impl ClientTable for Table<Postgres, Client> {}
+
+table_refs! {
+    pub trait ClientRefs for Table<Postgres, Client> {
+        fn ref_bakery("bakery") -> Bakery;
+        fn ref_orders("orders") -> Order;
     }
 }
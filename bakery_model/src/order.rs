@@ -62,18 +62,17 @@ pub trait OrderTable: SqlTable {
         Order::table().get_column("product_id").unwrap()
     }
 
-    fn ref_client(&self) -> Table<Postgres, Client>;
-    fn ref_line_items(&self) -> Table<Postgres, LineItem>;
     fn sub_line_items(&self) -> Table<Postgres, LineItem>;
 }
 
-impl OrderTable for Table<Postgres, Order> {
-    fn ref_client(&self) -> Table<Postgres, Client> {
-        self.get_ref_as("client").unwrap()
-    }
-    fn ref_line_items(&self) -> Table<Postgres, LineItem> {
-        self.get_ref_as("line_items").unwrap()
+table_refs! {
+    pub trait OrderRefs for Table<Postgres, Order> {
+        fn ref_client("client") -> Client;
+        fn ref_line_items("line_items") -> LineItem;
     }
+}
+
+impl OrderTable for Table<Postgres, Order> {
     fn sub_line_items(&self) -> Table<Postgres, LineItem> {
         self.get_subquery_as("line_items").unwrap()
     }
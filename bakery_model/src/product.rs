@@ -12,6 +12,16 @@ pub struct Product {
 }
 impl Entity for Product {}
 
+table_columns! {
+    pub enum ProductCol {
+        Id = "id",
+        Name = "name",
+        BakeryId = "bakery_id",
+        Calories = "calories",
+        Price = "price",
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 pub struct ProductInventory {
     id: i64,
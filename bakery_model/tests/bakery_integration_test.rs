@@ -2,6 +2,7 @@ use anyhow::Result;
 use bakery_model::*;
 
 use vantage::prelude::*;
+use vantage_testutil::TestPostgres;
 
 // async fn create_bootstrap_db() -> Result<()> {
 //     let client = POSTGRESS.get().unwrap().client();
@@ -56,23 +57,14 @@ use vantage::prelude::*;
 //         .context("starting postgres")
 //         .unwrap();
 // }
-async fn create_bootstrap_db() -> Result<()> {
-    // Connect to postgress and store client statically
-    bakery_model::connect_postgres().await?;
+async fn create_bootstrap_db() -> Result<TestPostgres> {
+    // Start postgres with the schema and store the datasource statically
+    let db = TestPostgres::start(include_str!("../schema-pg.sql")).await?;
+    bakery_model::set_postgres(db.datasource().await?)?;
 
-    // Get the postgres client for batch execution
-    let vantage_client = bakery_model::postgres();
-    let client = vantage_client.client();
-
-    // Read the schema from the file and execute it
-    let schema = tokio::fs::read_to_string("schema-pg.sql").await?;
-    client.batch_execute(&schema).await?;
-
-    Ok(())
+    Ok(db)
 }
 
-// TODO: get rid of testcontainers, yukk.
-
 // #[tokio::test]
 // async fn should_create_bucket() {
 //     init().await;
@@ -93,14 +85,14 @@ async fn create_bootstrap_db() -> Result<()> {
 
 #[tokio::test]
 async fn test_bakery() -> Result<()> {
-    create_bootstrap_db().await?;
+    let db = create_bootstrap_db().await?;
 
     println!("In this example, we will be interracting with the records and testing conditions");
     let products = Product::table();
 
     println!(
         "We are starting with {} products",
-        products.count().get_one_untyped().await?
+        products.count().get_one_as::<i64>().await?
     );
 
     println!("");
@@ -117,8 +109,8 @@ async fn test_bakery() -> Result<()> {
     println!(
         "After adding \"Nuclear Sandwich\" (id={}) we are left with {} products",
         id.unwrap(),
-        products.count().get_one_untyped().await?
+        products.count().get_one_as::<i64>().await?
     );
 
-    Ok(())
+    db.cleanup().await
 }
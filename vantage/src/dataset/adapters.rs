@@ -0,0 +1,260 @@
+//! Datasets, which transform records of another dataset in memory, created with
+//! [`ReadableDataSet::map()`] and [`ReadableDataSet::filter_in_memory()`]:
+//!
+//! ```
+//! let products = Product::table()
+//!     .filter_in_memory(|p| p.is_available())
+//!     .map(|p| ProductCard {
+//!         title: format!("{} ({})", p.name, p.price),
+//!         id: p.id,
+//!     });
+//!
+//! let cards = DataSetJson::from_dataset(&products).await?;
+//! ```
+//!
+//! Adapters are lazy - nothing is fetched until one of the [`ReadableDataSet`]
+//! methods is called. Records are fetched from the underlying dataset and then
+//! transformed, so [`count()`](ReadableDataSet::count) and
+//! [`exists()`](ReadableDataSet::exists) fetch all records too. Untyped methods
+//! return the transformed records converted into JSON.
+
+use std::marker::PhantomData;
+
+use anyhow::{anyhow, Result};
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::hydrate::from_rows;
+use super::ReadableDataSet;
+use crate::sql::Query;
+
+/// Dataset, which converts each record with a closure. See [`ReadableDataSet::map()`].
+pub struct MappedDataSet<D, E, F> {
+    dataset: D,
+    f: F,
+    _phantom: PhantomData<E>,
+}
+
+impl<D, E, F> MappedDataSet<D, E, F> {
+    pub(super) fn new(dataset: D, f: F) -> Self {
+        MappedDataSet {
+            dataset,
+            f,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Dataset, which skips records not matching a closure. See
+/// [`ReadableDataSet::filter_in_memory()`].
+pub struct FilteredDataSet<D, E, F> {
+    dataset: D,
+    f: F,
+    _phantom: PhantomData<E>,
+}
+
+impl<D, E, F> FilteredDataSet<D, E, F> {
+    pub(super) fn new(dataset: D, f: F) -> Self {
+        FilteredDataSet {
+            dataset,
+            f,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Converts records into untyped rows.
+fn to_rows<T: Serialize>(records: Vec<T>) -> Result<Vec<Map<String, Value>>> {
+    records
+        .into_iter()
+        .map(|record| match serde_json::to_value(record)? {
+            Value::Object(row) => Ok(row),
+            value => Err(anyhow!("Expected record to be an object, got {}", value)),
+        })
+        .collect()
+}
+
+fn first_row(rows: Vec<Map<String, Value>>) -> Result<Map<String, Value>> {
+    rows.into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No rows in dataset"))
+}
+
+fn first_value(row: Map<String, Value>) -> Option<Value> {
+    row.into_iter().next().map(|(_, value)| value)
+}
+
+macro_rules! impl_untyped {
+    () => {
+        async fn get_all_untyped(&self) -> Result<Vec<Map<String, Value>>> {
+            to_rows(self.get().await?)
+        }
+
+        async fn get_row_untyped(&self) -> Result<Map<String, Value>> {
+            first_row(self.get_all_untyped().await?)
+        }
+
+        async fn get_col_untyped(&self) -> Result<Vec<Value>> {
+            Ok(self
+                .get_all_untyped()
+                .await?
+                .into_iter()
+                .filter_map(first_value)
+                .collect())
+        }
+
+        async fn get_one_untyped(&self) -> Result<Value> {
+            first_value(self.get_row_untyped().await?)
+                .ok_or_else(|| anyhow!("No cells in a first row of dataset"))
+        }
+
+        async fn count(&self) -> Result<i64> {
+            Ok(self.get().await?.len() as i64)
+        }
+
+        async fn exists(&self) -> Result<bool> {
+            Ok(self.get_some().await?.is_some())
+        }
+
+        async fn get_some(&self) -> Result<Option<E2>> {
+            Ok(self.get().await?.into_iter().next())
+        }
+
+        async fn get_as<T: DeserializeOwned>(&self) -> Result<Vec<T>> {
+            Ok(from_rows(self.get_all_untyped().await?)?)
+        }
+
+        async fn get_as_lenient<T: DeserializeOwned>(&self) -> Result<Vec<T>> {
+            Ok(super::hydrate::from_rows_lenient(
+                self.get_all_untyped().await?,
+            ))
+        }
+
+        async fn get_some_as<T>(&self) -> Result<Option<T>>
+        where
+            T: DeserializeOwned + Default + Serialize,
+        {
+            Ok(self.get_as().await?.into_iter().next())
+        }
+
+        /// Query of the underlying dataset. Records it returns are not transformed.
+        fn select_query(&self) -> Query {
+            self.dataset.select_query()
+        }
+    };
+}
+
+impl<D, E, E2, F> ReadableDataSet<E2> for MappedDataSet<D, E, F>
+where
+    D: ReadableDataSet<E>,
+    F: Fn(E) -> E2,
+    E2: Serialize,
+{
+    async fn get(&self) -> Result<Vec<E2>> {
+        Ok(self.dataset.get().await?.into_iter().map(&self.f).collect())
+    }
+
+    fn get_stream(&self) -> impl Stream<Item = Result<E2>> {
+        self.dataset.get_stream().map(|record| record.map(&self.f))
+    }
+
+    impl_untyped!();
+}
+
+impl<D, E2, F> ReadableDataSet<E2> for FilteredDataSet<D, E2, F>
+where
+    D: ReadableDataSet<E2>,
+    F: Fn(&E2) -> bool,
+    E2: Serialize,
+{
+    async fn get(&self) -> Result<Vec<E2>> {
+        Ok(self
+            .dataset
+            .get()
+            .await?
+            .into_iter()
+            .filter(|record| (self.f)(record))
+            .collect())
+    }
+
+    fn get_stream(&self) -> impl Stream<Item = Result<E2>> {
+        self.dataset.get_stream().filter(|record| {
+            let keep = record.as_ref().map_or(true, |record| (self.f)(record));
+            futures::future::ready(keep)
+        })
+    }
+
+    impl_untyped!();
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use crate::{mocks::datasource::MockDataSource, prelude::*};
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+    struct Product {
+        name: String,
+        price: i64,
+    }
+    impl Entity for Product {}
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Label {
+        label: String,
+    }
+
+    fn products() -> Table<MockDataSource, Product> {
+        let db = MockDataSource::new(&json!([
+            {"name": "Cake", "price": 10},
+            {"name": "Tart", "price": 25},
+            {"name": "Pie", "price": 30},
+        ]));
+        Table::new_with_entity("product", db)
+            .with_column("name")
+            .with_column("price")
+    }
+
+    #[tokio::test]
+    async fn test_map_and_filter() {
+        let labels = products()
+            .filter_in_memory(|p| p.price > 20)
+            .map(|p| Label {
+                label: format!("{} ({})", p.name, p.price),
+            });
+
+        assert_eq!(
+            labels.get().await.unwrap(),
+            vec![
+                Label {
+                    label: "Tart (25)".to_string()
+                },
+                Label {
+                    label: "Pie (30)".to_string()
+                },
+            ]
+        );
+        assert_eq!(ReadableDataSet::count(&labels).await.unwrap(), 2);
+        assert_eq!(
+            labels.get_col_untyped().await.unwrap(),
+            vec![json!("Tart (25)"), json!("Pie (30)")]
+        );
+        assert_eq!(
+            labels.get_stream().try_collect::<Vec<_>>().await.unwrap(),
+            labels.get().await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_filter_nothing() {
+        let expensive = products().filter_in_memory(|p| p.price > 100);
+
+        assert!(!expensive.exists().await.unwrap());
+        assert!(expensive.get_row_untyped().await.is_err());
+    }
+}
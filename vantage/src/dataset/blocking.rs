@@ -0,0 +1,92 @@
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::runtime::block_on;
+use crate::traits::datasource::ExecResult;
+
+use super::{ReadableDataSet, WritableDataSet};
+
+/// Synchronous versions of [`ReadableDataSet`] methods, implemented for every
+/// readable dataset. Requires the `blocking` feature:
+///
+/// ```
+/// fn main() -> Result<()> {
+///     for client in Client::table().get_blocking()? {
+///         println!("{}", client.name);
+///     }
+///     Ok(())
+/// }
+/// ```
+///
+/// Futures are executed with [`block_on()`](crate::runtime::block_on), see its
+/// documentation for use inside async code.
+pub trait BlockingReadableDataSet<E>: ReadableDataSet<E> {
+    fn get_blocking(&self) -> Result<Vec<E>> {
+        block_on(self.get())
+    }
+
+    fn get_some_blocking(&self) -> Result<Option<E>> {
+        block_on(self.get_some())
+    }
+
+    fn get_as_blocking<T: DeserializeOwned>(&self) -> Result<Vec<T>> {
+        block_on(self.get_as())
+    }
+
+    fn get_all_untyped_blocking(&self) -> Result<Vec<Map<String, Value>>> {
+        block_on(self.get_all_untyped())
+    }
+
+    fn count_blocking(&self) -> Result<i64> {
+        block_on(self.count())
+    }
+
+    fn exists_blocking(&self) -> Result<bool> {
+        block_on(self.exists())
+    }
+}
+
+impl<E, D: ReadableDataSet<E>> BlockingReadableDataSet<E> for D {}
+
+/// Synchronous versions of [`WritableDataSet`] methods, see [`BlockingReadableDataSet`].
+pub trait BlockingWritableDataSet<E>: WritableDataSet<E> {
+    fn insert_blocking(&self, record: E) -> Result<Option<Value>> {
+        block_on(self.insert(record))
+    }
+
+    fn update_blocking<F>(&self, f: F) -> Result<ExecResult>
+    where
+        F: FnMut(&mut E),
+    {
+        block_on(self.update(f))
+    }
+
+    fn delete_blocking(&self) -> Result<ExecResult> {
+        block_on(self.delete())
+    }
+}
+
+impl<E, D: WritableDataSet<E>> BlockingWritableDataSet<E> for D {}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{mocks::datasource::MockDataSource, prelude::*};
+
+    #[test]
+    fn test_get_blocking() {
+        let db =
+            MockDataSource::new(&json!([{"id": 1, "name": "Cake"}, {"id": 2, "name": "Tart"}]));
+        let products = Table::new("product", db)
+            .with_id_column("id")
+            .with_column("name");
+
+        let rows = products.get_all_untyped_blocking().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1]["name"], json!("Tart"));
+        assert!(products.delete_blocking().is_ok());
+    }
+}
@@ -0,0 +1,254 @@
+//! Join of two datasets, which may belong to different data sources, for
+//! example a Postgres table with a REST collection. Both datasets are fetched
+//! and rows are matched in memory, using a hash of the right side:
+//!
+//! ```
+//! let orders = Order::table();                       // Postgres
+//! let clients = Client::rest_table();                // RestDataSource
+//!
+//! let rows: Vec<OrderWithClient> = FederatedJoin::new(orders, "client_id", clients, "id")
+//!     .with_prefix("client_")
+//!     .get_as()
+//!     .await?;
+//! ```
+//!
+//! When both datasets use the same data source, a SQL join is much cheaper.
+//! For narrowing down the right side with values from the left side, see
+//! [`AssociatedQuery::glue()`].
+//!
+//! [`AssociatedQuery::glue()`]: crate::datasource::associated_query::AssociatedQuery
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use super::hydrate::{from_row, from_rows};
+use super::ReadableDataSet;
+
+pub struct FederatedJoin<L, R, E1, E2> {
+    left: L,
+    left_key: String,
+    right: R,
+    right_key: String,
+    left_join: bool,
+    prefix: String,
+    _phantom: PhantomData<(E1, E2)>,
+}
+
+impl<L, R, E1, E2> FederatedJoin<L, R, E1, E2>
+where
+    L: ReadableDataSet<E1>,
+    R: ReadableDataSet<E2>,
+{
+    /// Rows are matched, when `left_key` of the left row equals `right_key` of
+    /// the right row. Both keys must be fetched by the datasets.
+    pub fn new(left: L, left_key: &str, right: R, right_key: &str) -> Self {
+        FederatedJoin {
+            left,
+            left_key: left_key.to_string(),
+            right,
+            right_key: right_key.to_string(),
+            left_join: false,
+            prefix: String::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Keep left rows, which have no matching right row
+    pub fn with_left_join(mut self) -> Self {
+        self.left_join = true;
+        self
+    }
+
+    /// Prefix for the fields of the right rows in combined rows. Without a prefix,
+    /// fields of the left row take precedence over the fields with the same name.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Fetches both datasets and returns pairs of matching rows. Right row is
+    /// None for unmatched left rows of a left join.
+    pub async fn get_pairs_untyped(
+        &self,
+    ) -> Result<Vec<(Map<String, Value>, Option<Map<String, Value>>)>> {
+        let (left, right) =
+            futures::try_join!(self.left.get_all_untyped(), self.right.get_all_untyped())?;
+
+        let mut index: HashMap<String, Vec<Map<String, Value>>> = HashMap::new();
+        for row in right {
+            let Some(key) = Self::key(&row, &self.right_key)? else {
+                continue;
+            };
+            index.entry(key).or_default().push(row);
+        }
+
+        let mut pairs = Vec::new();
+        for row in left {
+            let matches = match Self::key(&row, &self.left_key)? {
+                Some(key) => index.get(&key).map(Vec::as_slice).unwrap_or_default(),
+                None => &[],
+            };
+            if matches.is_empty() {
+                if self.left_join {
+                    pairs.push((row, None));
+                }
+                continue;
+            }
+            for right in matches {
+                pairs.push((row.clone(), Some(right.clone())));
+            }
+        }
+        Ok(pairs)
+    }
+
+    /// Same as [`get_pairs_untyped()`], but rows are converted into entities
+    /// of both datasets.
+    ///
+    /// [`get_pairs_untyped()`]: FederatedJoin::get_pairs_untyped
+    pub async fn get_pairs(&self) -> Result<Vec<(E1, Option<E2>)>>
+    where
+        E1: DeserializeOwned,
+        E2: DeserializeOwned,
+    {
+        self.get_pairs_untyped()
+            .await?
+            .into_iter()
+            .enumerate()
+            .map(|(index, (left, right))| {
+                let right = right.map(|right| from_row(right, index)).transpose()?;
+                Ok((from_row(left, index)?, right))
+            })
+            .collect()
+    }
+
+    /// Combines fields of matching rows into a single row.
+    pub async fn get_all_untyped(&self) -> Result<Vec<Map<String, Value>>> {
+        Ok(self
+            .get_pairs_untyped()
+            .await?
+            .into_iter()
+            .map(|(mut left, right)| {
+                for (field, value) in right.unwrap_or_default() {
+                    let field = format!("{}{}", self.prefix, field);
+                    left.entry(field).or_insert(value);
+                }
+                left
+            })
+            .collect())
+    }
+
+    /// Combines fields of matching rows and converts them into `T`.
+    pub async fn get_as<T: DeserializeOwned>(&self) -> Result<Vec<T>> {
+        Ok(from_rows(self.get_all_untyped().await?)?)
+    }
+
+    /// Key for the hash map. Strings match numbers with the same text, as data
+    /// sources may return keys in different types. NULL never matches anything.
+    fn key(row: &Map<String, Value>, field: &str) -> Result<Option<String>> {
+        match row.get(field) {
+            None => Err(anyhow!("Join key {} is missing in a fetched row", field)),
+            Some(Value::Null) => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(value) => Ok(Some(value.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+    struct Order {
+        id: i64,
+        client_id: Option<i64>,
+    }
+    impl Entity for Order {}
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+    struct Client {
+        id: i64,
+        name: String,
+    }
+    impl Entity for Client {}
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct OrderWithClient {
+        id: i64,
+        client_name: Option<String>,
+    }
+
+    fn tables() -> (
+        Table<MockDataSource, Order>,
+        Table<MemoryDataSource, Client>,
+    ) {
+        let orders = MockDataSource::new(&json!([
+            {"id": 10, "client_id": 1},
+            {"id": 11, "client_id": 2},
+            {"id": 12, "client_id": 1},
+            {"id": 13, "client_id": null},
+        ]));
+        let clients = MemoryDataSource::new().with_table(
+            "client",
+            vec![
+                json!({"id": 1, "name": "Marty"})
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+                json!({"id": 3, "name": "Doc"}).as_object().unwrap().clone(),
+            ],
+        );
+        (
+            Table::new_with_entity("ord", orders)
+                .with_id_column("id")
+                .with_column("client_id"),
+            Table::new_with_entity("client", clients)
+                .with_id_column("id")
+                .with_column("name"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_inner_join() {
+        let (orders, clients) = tables();
+        let pairs = FederatedJoin::new(orders, "client_id", clients, "id")
+            .get_pairs()
+            .await
+            .unwrap();
+
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[1].0.id, 12);
+        assert_eq!(pairs[1].1.as_ref().unwrap().name, "Marty");
+    }
+
+    #[tokio::test]
+    async fn test_left_join() {
+        let (orders, clients) = tables();
+        let rows: Vec<OrderWithClient> = FederatedJoin::new(orders, "client_id", clients, "id")
+            .with_left_join()
+            .with_prefix("client_")
+            .get_as()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            rows.iter()
+                .map(|row| (row.id, row.client_name.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![
+                (10, Some("Marty")),
+                (11, None),
+                (12, Some("Marty")),
+                (13, None)
+            ]
+        );
+    }
+}
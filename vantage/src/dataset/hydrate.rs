@@ -0,0 +1,96 @@
+//! Conversion of fetched rows into structs, reporting which row and column
+//! could not be converted.
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::Error;
+
+/// Deserialize a single row. `index` is the position of the row in the result
+/// and is only used for error reporting.
+pub(crate) fn from_row<T: DeserializeOwned>(
+    row: Map<String, Value>,
+    index: usize,
+) -> Result<T, Error> {
+    serde_path_to_error::deserialize(Value::Object(row)).map_err(|e| {
+        let path = e.path().to_string();
+        let source = e.into_inner();
+        let column = if path != "." {
+            Some(path)
+        } else {
+            // missing fields are reported on the struct itself
+            source
+                .to_string()
+                .strip_prefix("missing field `")
+                .and_then(|s| s.split('`').next())
+                .map(|s| s.to_string())
+        };
+        Error::Deserialize {
+            row: Some(index),
+            column,
+            source,
+        }
+    })
+}
+
+/// Deserialize all rows, failing on the first row that can't be deserialized.
+pub(crate) fn from_rows<T: DeserializeOwned>(
+    rows: Vec<Map<String, Value>>,
+) -> Result<Vec<T>, Error> {
+    rows.into_iter()
+        .enumerate()
+        .map(|(index, row)| from_row(row, index))
+        .collect()
+}
+
+/// Deserialize rows, skipping the ones that can't be deserialized with a warning.
+pub(crate) fn from_rows_lenient<T: DeserializeOwned>(rows: Vec<Map<String, Value>>) -> Vec<T> {
+    rows.into_iter()
+        .enumerate()
+        .filter_map(|(index, row)| {
+            from_row(row, index)
+                .map_err(|e| log::warn!("Skipping row: {}", e))
+                .ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug)]
+    struct User {
+        #[allow(dead_code)]
+        name: String,
+    }
+
+    fn rows(data: Value) -> Vec<Map<String, Value>> {
+        serde_json::from_value(data).unwrap()
+    }
+
+    #[test]
+    fn test_from_rows() {
+        let data = json!([{"name": "John"}, {"name": null}]);
+
+        let Err(Error::Deserialize { row, column, .. }) = from_rows::<User>(rows(data.clone()))
+        else {
+            panic!("Expected deserialize error");
+        };
+        assert_eq!(row, Some(1));
+        assert_eq!(column.as_deref(), Some("name"));
+
+        assert_eq!(from_rows_lenient::<User>(rows(data)).len(), 1);
+    }
+
+    #[test]
+    fn test_missing_field() {
+        let Err(Error::Deserialize { column, .. }) = from_rows::<User>(rows(json!([{}]))) else {
+            panic!("Expected deserialize error");
+        };
+        assert_eq!(column.as_deref(), Some("name"));
+    }
+}
@@ -11,8 +11,27 @@
 //!  - [`Table`]: a table is a dataset that stores data in a SQL table and implements both [`ReadableDataSet`] and [`WritableDataSet`].
 //!  - [`Query`]: a generic SELECT query that can fetch data and therefore implements [`ReadableDataSet`].
 //!
+//! With the `blocking` feature, [`BlockingReadableDataSet`] and [`BlockingWritableDataSet`]
+//! add synchronous versions of the methods, such as `get_blocking()`.
+//!
+//! [`MappedDataSet`] and [`FilteredDataSet`] transform records of a readable dataset in memory.
+//!
+//! [`FederatedJoin`] combines rows of two readable datasets, even if they use different data sources.
+//!
 //! [`Table`]: super::table::Table
 //! [`Query`]: super::query::Query
+#[cfg(feature = "blocking")]
+mod blocking;
+#[cfg(feature = "blocking")]
+pub use blocking::{BlockingReadableDataSet, BlockingWritableDataSet};
+
+mod adapters;
+pub use adapters::{FilteredDataSet, MappedDataSet};
+
+mod federated;
+pub use federated::FederatedJoin;
+
+pub(crate) mod hydrate;
 mod readable;
 pub use readable::ReadableDataSet;
 
@@ -1,7 +1,9 @@
 use std::future::Future;
 
+use super::{FilteredDataSet, MappedDataSet};
 use crate::sql::Query;
 use anyhow::Result;
+use futures::Stream;
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::{Map, Value};
 
@@ -48,6 +50,23 @@ pub trait ReadableDataSet<E> {
     /// ```
     fn get_one_untyped(&self) -> impl Future<Output = Result<Value>>;
 
+    /// Count records in the dataset, without fetching them:
+    ///
+    /// ```
+    /// let orders = Client::table().with_id(1).ref_orders();
+    /// let count = ReadableDataSet::count(&orders).await?;
+    /// ```
+    ///
+    /// Note that [`Table::count()`] returns a query instead, which can be used
+    /// in expressions.
+    ///
+    /// [`Table::count()`]: crate::sql::Table::count()
+    fn count(&self) -> impl Future<Output = Result<i64>>;
+
+    /// Returns true if the dataset has at least one record. Renders `SELECT EXISTS (..)`,
+    /// so the database can stop after finding the first row.
+    fn exists(&self) -> impl Future<Output = Result<bool>>;
+
     /// Fetch some record if dataset has at least one record. Works well with Table::with_id().
     ///
     /// ```
@@ -59,6 +78,8 @@ pub trait ReadableDataSet<E> {
     fn get_some(&self) -> impl Future<Output = Result<Option<E>>>;
 
     /// Fetch records into a vector of type `T` using [`serde_json::from_value`].
+    /// If a row can't be deserialized, [`Error::Deserialize`] is returned with
+    /// the index of the row and the offending column.
     ///
     /// ```
     /// struct ClientNameOnly {
@@ -71,13 +92,61 @@ pub trait ReadableDataSet<E> {
     /// ```
     ///
     /// [`serde_json::from_value`]: serde_json::from_value
+    /// [`Error::Deserialize`]: crate::Error::Deserialize
     fn get_as<T: DeserializeOwned>(&self) -> impl Future<Output = Result<Vec<T>>>;
 
+    /// Same as [`get_as()`], but rows which can't be deserialized into `T` are
+    /// skipped with a warning, instead of failing the whole request.
+    ///
+    /// [`get_as()`]: ReadableDataSet::get_as()
+    fn get_as_lenient<T: DeserializeOwned>(&self) -> impl Future<Output = Result<Vec<T>>>;
+
     /// Fetch a single record into a type `T` using [`serde_json::from_value`].
     fn get_some_as<T>(&self) -> impl Future<Output = Result<Option<T>>>
     where
         T: DeserializeOwned + Default + Serialize;
 
+    /// Fetch records one by one as a [`Stream`], without loading the entire result into memory.
+    /// Use this when iterating over large tables:
+    ///
+    /// ```
+    /// let mut clients = Client::table().get_stream();
+    /// while let Some(client) = clients.try_next().await? {
+    ///     dbg!(&client.name);
+    /// }
+    /// ```
+    fn get_stream(&self) -> impl Stream<Item = Result<E>>;
+
     /// TODO: must go away from here, as dataset should not be aware of query
     fn select_query(&self) -> Query;
+
+    /// Dataset, which converts fetched records with `f`, for example to calculate
+    /// display fields:
+    ///
+    /// ```
+    /// let cards = Product::table().map(|p| ProductCard {
+    ///     title: format!("{} ({})", p.name, p.price),
+    /// });
+    /// ```
+    ///
+    /// See [`MappedDataSet`].
+    fn map<E2, F>(self, f: F) -> MappedDataSet<Self, E, F>
+    where
+        Self: Sized,
+        F: Fn(E) -> E2,
+    {
+        MappedDataSet::new(self, f)
+    }
+
+    /// Dataset, which skips fetched records for which `f` returns false. Use
+    /// conditions whenever the database can do the filtering instead.
+    ///
+    /// See [`FilteredDataSet`].
+    fn filter_in_memory<F>(self, f: F) -> FilteredDataSet<Self, E, F>
+    where
+        Self: Sized,
+        F: Fn(&E) -> bool,
+    {
+        FilteredDataSet::new(self, f)
+    }
 }
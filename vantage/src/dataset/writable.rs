@@ -3,6 +3,8 @@ use serde::Serialize;
 use serde_json::Value;
 use std::future::Future;
 
+use crate::traits::datasource::ExecResult;
+
 /// Represents a [`dataset`] that may can add or modify records.
 /// The <E> type parameter represents a record type.
 ///
@@ -27,13 +29,20 @@ pub trait WritableDataSet<E> {
     /// Update all records in the DataSet. When working with Table, it's important to set a condition
     /// if you only want to update some records.
     ///
+    /// Records are fetched one by one and passed into the closure. Only the fields which
+    /// the closure has changed will be written back.
+    ///
     /// ```
     /// let peter_orders = Client::table().with_id(1).ref_orders();
     /// peter_orders.update(|orders| orders.qty += 1).await?;
     /// ```
-    fn update<F>(&self, f: F) -> impl Future<Output = Result<()>>;
+    ///
+    /// Returns the total number of updated rows.
+    fn update<F>(&self, f: F) -> impl Future<Output = Result<ExecResult>>
+    where
+        F: FnMut(&mut E);
 
-    fn update_with<F, E2>(&self, values: E2) -> impl Future<Output = Result<()>>
+    fn update_with<F, E2>(&self, values: E2) -> impl Future<Output = Result<ExecResult>>
     where
         E2: Serialize + Clone;
 
@@ -46,5 +55,7 @@ pub trait WritableDataSet<E> {
     /// peter.delete().await?;                 // delete peter
     ///
     /// ```
-    fn delete(&self) -> impl Future<Output = Result<()>>;
+    ///
+    /// Returns the number of deleted rows.
+    fn delete(&self) -> impl Future<Output = Result<ExecResult>>;
 }
@@ -0,0 +1,577 @@
+use std::any::Any;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::time::Duration;
+
+use crate::dataset::hydrate::{from_row, from_rows, from_rows_lenient};
+use crate::dataset::ReadableDataSet;
+use crate::expr_arc;
+use crate::prelude::Entity;
+use crate::sql::chunk::Chunk;
+use crate::sql::expression::{Expression, ExpressionArc};
+use crate::sql::query::{Direction, QueryType, SqlQuery};
+use crate::sql::Query;
+use crate::sql::{Condition, Operations};
+use crate::traits::datasource::DataSource;
+use crate::traits::from_sql_value::FromSqlValue;
+use anyhow::{anyhow, Result};
+use futures::future::{self, Either};
+use futures::{Stream, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use serde_json::Map;
+use serde_json::Value;
+
+pub struct AssociatedExpressionArc<T: DataSource> {
+    pub expr: ExpressionArc,
+    pub ds: T,
+}
+
+impl<T: DataSource> Deref for AssociatedExpressionArc<T> {
+    type Target = ExpressionArc;
+
+    fn deref(&self) -> &Self::Target {
+        &self.expr
+    }
+}
+
+impl<T: DataSource> AssociatedExpressionArc<T> {
+    pub fn new(expr: ExpressionArc, ds: T) -> Self {
+        Self { expr, ds }
+    }
+    pub async fn get_one(&self) -> Result<Value> {
+        let one = self
+            .ds
+            .query_one(
+                &Query::new().with_type(crate::sql::query::QueryType::Expression(
+                    self.expr.render_chunk(),
+                )),
+            )
+            .await?;
+        Ok(one)
+    }
+}
+
+/// While [`Query`] does not generally associate with the [`DataSource`], it may be inconvenient
+/// to execute it. AssociatedQuery combines query with the datasource, allowing you to ealily
+/// pass it around and execute it.
+///
+/// ```
+/// let clients = Client::table();
+/// let client_count = clients.count();   // returns AssociatedQuery
+///
+/// let cnt: i64 = client_count.get_one().await?;  // actually executes the query
+/// ```
+///
+/// AssociatedQuery can be used to make a link between DataSources:
+///
+/// ```
+/// let clients = Client::table();
+/// let client_code_query = clients.field_query(clients.code())?;
+/// // returns field query (SELECT code FROM client)
+///
+/// let orders = Order::table();
+/// let orders = orders.with_condition(
+///     orders.client_code().in(orders.glue(client_code_query).await?)
+/// );
+/// ```
+/// If Order and Client tables do share same [`DataSource`], the conditioun would be set as
+///  `WHERE (client_code IN (SELECT code FROM client))`, ultimatelly saving you from
+/// redundant query.
+///
+/// When datasources are different, [`glue()`] would execute `SELECT code FROM client`, fetch
+/// the results and use those as a vector of values in a condition clause:
+///  `WHERE (client_code IN [12, 13, 14])`
+///
+/// For a large number of values use [`glue_in()`], which lets the dialect decide how
+/// values are passed.
+///
+/// [`glue_in()`]: AssociatedQuery::glue_in
+/// [`DataSource`]: crate::traits::datasource::DataSource
+/// [`glue()`]: Table::glue
+///
+#[derive(Clone)]
+pub struct AssociatedQuery<T: DataSource, E: Entity> {
+    pub query: Query,
+    pub ds: T,
+    pub timeout: Option<Duration>,
+    pub _phantom: std::marker::PhantomData<E>,
+}
+impl<T: DataSource, E: Entity> Deref for AssociatedQuery<T, E> {
+    type Target = Query;
+
+    fn deref(&self) -> &Self::Target {
+        &self.query
+    }
+}
+impl<T: DataSource, E: Entity> DerefMut for AssociatedQuery<T, E> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.query
+    }
+}
+
+impl<T: DataSource, E: Entity> AssociatedQuery<T, E> {
+    pub fn new(query: Query, ds: T) -> Self {
+        Self {
+            query,
+            ds,
+            timeout: None,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Limits how long the query may run. When the limit is exceeded, the query
+    /// is abandoned, which cancels it with a [`DataSource::cancellable()`] data
+    /// source, and [`Error::Timeout`] is returned:
+    ///
+    /// ```
+    /// let report = orders.sum(orders.total()).with_timeout(Duration::from_secs(5));
+    /// let total = report.get_one_as::<Option<Decimal>>().await?;
+    /// ```
+    ///
+    /// The limit also applies to [`explain()`](Self::explain) and to streams,
+    /// which must be read to the end in time.
+    ///
+    /// Postgres runs such queries on a dedicated connection, so that only the
+    /// abandoned query is cancelled. Use `statement_timeout` of [`ConnectOptions`]
+    /// to limit all queries.
+    ///
+    /// [`Error::Timeout`]: crate::Error::Timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Executes `query` with the data source, cancelling it if it exceeds the
+    /// timeout.
+    async fn run<V, F>(&self, query: impl FnOnce(T) -> F) -> Result<V>
+    where
+        F: Future<Output = Result<V>>,
+    {
+        let Some(timeout) = self.timeout else {
+            return query(self.ds.clone()).await;
+        };
+        match crate::runtime::timeout(timeout, query(self.ds.cancellable())).await {
+            Some(result) => result,
+            None => Err(crate::Error::Timeout(timeout).into()),
+        }
+    }
+
+    pub fn with_skip(mut self, skip: i64) -> Self {
+        self.query.add_skip(Some(skip));
+        self
+    }
+
+    pub fn with_limit(mut self, limit: i64) -> Self {
+        self.query.add_limit(Some(limit));
+        self
+    }
+
+    pub fn with_skip_and_limit(mut self, skip: i64, limit: i64) -> Self {
+        self.query.add_limit(Some(limit));
+        self.query.add_skip(Some(skip));
+        self
+    }
+
+    pub fn with_order_by(mut self, column: impl Chunk, direction: Direction) -> Self {
+        self.query.add_order_by(direction.order(&column));
+        self
+    }
+
+    /// Executes the query and converts the first column of the first row into `V`.
+    /// Handy for aggregates, which return [`EmptyEntity`] queries:
+    ///
+    /// ```
+    /// let count = clients.count().get_one::<i64>().await?;
+    /// let total: Option<f64> = orders.sum(orders.total()).get_one().await?;
+    /// ```
+    pub async fn get_one<V: DeserializeOwned>(&self) -> Result<V> {
+        let value = self
+            .run(|ds| async move { ds.query_one(&self.query).await })
+            .await?;
+        Ok(serde_json::from_value(value).map_err(crate::Error::from)?)
+    }
+
+    /// Executes the query and converts the first column of the first row into a
+    /// scalar using [`FromSqlValue`], which also accepts numbers returned as
+    /// strings or booleans returned as `0` and `1`:
+    ///
+    /// ```
+    /// let count = orders.count().get_one_as::<i64>().await?;
+    /// let total = orders.sum(orders.total()).get_one_as::<Option<Decimal>>().await?;
+    /// ```
+    pub async fn get_one_as<V: FromSqlValue>(&self) -> Result<V> {
+        let value = self
+            .run(|ds| async move { ds.query_one(&self.query).await })
+            .await?;
+        Ok(V::from_sql_value(value)?)
+    }
+
+    /// Returns the execution plan of the query, as chosen by the database,
+    /// without executing it:
+    ///
+    /// ```
+    /// let plan = Client::table().with_id(1.into()).ref_orders().query().explain().await?;
+    /// println!("{}", serde_json::to_string_pretty(&plan)?);
+    /// // {"Plan": {"Node Type": "Seq Scan", "Relation Name": "ord", ..}}
+    /// ```
+    pub async fn explain(&self) -> Result<Value> {
+        self.fetch_plan(self.get_explain_query(false)).await
+    }
+
+    /// Executes the query and returns its execution plan together with the actual
+    /// timings and row counts. Note that UPDATE, INSERT and DELETE queries will
+    /// change the data.
+    pub async fn explain_analyze(&self) -> Result<Value> {
+        self.fetch_plan(self.get_explain_query(true)).await
+    }
+
+    fn get_explain_query(&self, analyze: bool) -> Query {
+        let options = if analyze {
+            "ANALYZE, FORMAT JSON"
+        } else {
+            "FORMAT JSON"
+        };
+        Query::new().with_type(QueryType::Expression(
+            expr_arc!(format!("EXPLAIN ({}) {{}}", options), self.query.clone()).render_chunk(),
+        ))
+    }
+
+    async fn fetch_plan(&self, query: Query) -> Result<Value> {
+        let Some((_, plan)) = self
+            .run(|ds| async move { ds.query_fetch(&query).await })
+            .await?
+            .into_iter()
+            .next()
+            .and_then(|row| row.into_iter().next())
+        else {
+            return Err(anyhow!("EXPLAIN returned no plan"));
+        };
+        // JSON format returns a single plan wrapped into an array
+        match plan {
+            Value::Array(plans) => plans
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow!("EXPLAIN returned no plan")),
+            plan => Ok(plan),
+        }
+    }
+
+    /// Presented with another AssociatedQuery - calculate if queries
+    /// are linked with the same or different [`DataSource`]s.
+    ///
+    /// The same - return expression as-is.
+    /// Different - execute the query and return the result as a vector of values.
+    /// Data sources may be of a different type, for example a REST API query can be
+    /// glued into a condition of a Postgres table.
+    #[allow(dead_code)]
+    async fn glue<T2: DataSource, E2: Entity>(
+        &self,
+        other: AssociatedQuery<T2, E2>,
+    ) -> Result<Expression> {
+        let other_ds: &dyn Any = &other.ds;
+        if other_ds.downcast_ref::<T>() == Some(&self.ds) {
+            Ok(other.query.try_render_chunk()?)
+        } else {
+            let vals = other.get_col_untyped().await?;
+            let tpl = vec!["{}"; vals.len()].join(", ");
+            Ok(Expression::new(tpl, vals))
+        }
+    }
+
+    /// Same as [`glue()`], but returns a complete condition for `field`. Values
+    /// fetched from a different data source are passed to
+    /// [`Dialect::render_in_values()`], so thousands of values won't exceed the
+    /// limit of query parameters:
+    ///
+    /// ```
+    /// let orders = orders.with_condition(
+    ///     orders.glue_in(&orders.client_code(), client_code_query).await?
+    /// );
+    /// // WHERE (client_code = ANY ({}))
+    /// ```
+    ///
+    /// [`glue()`]: AssociatedQuery::glue
+    pub async fn glue_in<T2: DataSource, E2: Entity>(
+        &self,
+        field: &impl Operations,
+        other: AssociatedQuery<T2, E2>,
+    ) -> Result<Condition> {
+        let other_ds: &dyn Any = &other.ds;
+        if other_ds.downcast_ref::<T>() == Some(&self.ds) {
+            Ok(field.in_expr(&other.query.try_render_chunk()?))
+        } else {
+            let vals = other.get_col_untyped().await?;
+            Ok(self
+                .ds
+                .dialect()
+                .render_in_values(field.render_chunk(), vals))
+        }
+    }
+}
+impl<D: DataSource + Sync, E: Entity> Chunk for AssociatedQuery<D, E> {
+    fn render_chunk(&self) -> Expression {
+        self.query.render_chunk()
+    }
+
+    fn try_render_chunk(&self) -> Result<Expression, crate::Error> {
+        self.query.try_render_chunk()
+    }
+}
+impl<D: DataSource, E: Entity> std::fmt::Debug for AssociatedQuery<D, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AssociatedQuery")
+            .field("query", &self.query)
+            .field("ds", &self.ds)
+            .finish()
+    }
+}
+impl<T: DataSource + Sync, E: Entity> ReadableDataSet<E> for AssociatedQuery<T, E> {
+    async fn get_all_untyped(&self) -> Result<Vec<Map<String, Value>>> {
+        self.run(|ds| async move { ds.query_fetch(&self.query).await })
+            .await
+    }
+
+    async fn get_row_untyped(&self) -> Result<Map<String, Value>> {
+        self.run(|ds| async move { ds.query_row(&self.query).await })
+            .await
+    }
+
+    async fn get_one_untyped(&self) -> Result<Value> {
+        self.run(|ds| async move { ds.query_one(&self.query).await })
+            .await
+    }
+
+    async fn get_col_untyped(&self) -> Result<Vec<Value>> {
+        self.run(|ds| async move { ds.query_col(&self.query).await })
+            .await
+    }
+
+    async fn count(&self) -> Result<i64> {
+        let query = self.query.get_count_query();
+        Ok(i64::from_sql_value(
+            self.run(|ds| async move { ds.query_one(&query).await })
+                .await?,
+        )?)
+    }
+
+    async fn exists(&self) -> Result<bool> {
+        let query = self.query.get_exists_query();
+        Ok(bool::from_sql_value(
+            self.run(|ds| async move { ds.query_one(&query).await })
+                .await?,
+        )?)
+    }
+
+    async fn get(&self) -> Result<Vec<E>> {
+        Ok(from_rows(self.get_all_untyped().await?)?)
+    }
+
+    async fn get_as<T2: serde::de::DeserializeOwned>(&self) -> Result<Vec<T2>> {
+        Ok(from_rows(self.get_all_untyped().await?)?)
+    }
+
+    async fn get_as_lenient<T2: serde::de::DeserializeOwned>(&self) -> Result<Vec<T2>> {
+        Ok(from_rows_lenient(self.get_all_untyped().await?))
+    }
+
+    async fn get_some(&self) -> Result<Option<E>> {
+        let data = self.get_all_untyped().await?;
+        if data.len() > 0 {
+            let row = data[0].clone();
+            Ok(Some(from_row(row, 0)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn get_some_as<T2: serde::de::DeserializeOwned>(&self) -> Result<Option<T2>> {
+        let data = self.get_all_untyped().await?;
+        if data.len() > 0 {
+            let row = data[0].clone();
+            Ok(Some(from_row(row, 0)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// With a timeout, the whole stream must complete within it. When it doesn't,
+    /// the stream ends with [`Error::Timeout`](crate::Error::Timeout).
+    fn get_stream(&self) -> impl Stream<Item = Result<E>> {
+        let query = self.query.clone();
+        let ds = match self.timeout {
+            Some(_) => self.ds.cancellable(),
+            None => self.ds.clone(),
+        };
+        let rows = futures::stream::once(async move { ds.query_stream(&query).await })
+            .try_flatten()
+            .enumerate()
+            .map(|(index, row)| Ok(from_row(row?, index)?));
+        match self.timeout {
+            Some(timeout) => Either::Left(with_deadline(rows, timeout)),
+            None => Either::Right(rows),
+        }
+    }
+
+    fn select_query(&self) -> Query {
+        self.query.clone()
+    }
+}
+
+/// Ends `stream` with [`Error::Timeout`](crate::Error::Timeout) if it does not
+/// complete within `timeout`. Dropping the stream abandons the query.
+fn with_deadline<V>(
+    stream: impl Stream<Item = Result<V>>,
+    timeout: Duration,
+) -> impl Stream<Item = Result<V>> {
+    let deadline = Box::pin(crate::runtime::sleep(timeout));
+    futures::stream::unfold(
+        Some((Box::pin(stream), deadline)),
+        move |state| async move {
+            let (mut stream, mut deadline) = state?;
+            match future::select(stream.next(), deadline.as_mut()).await {
+                Either::Left((Some(item), _)) => Some((item, Some((stream, deadline)))),
+                Either::Left((None, _)) => None,
+                Either::Right(_) => Some((Err(crate::Error::Timeout(timeout).into()), None)),
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+    use serde_json::json;
+
+    use super::*;
+    use crate::expr;
+    use crate::mocks::MockDataSource;
+    use crate::prelude::EmptyEntity;
+
+    #[tokio::test]
+    async fn test_explain() {
+        let plan = json!({"Plan": {"Node Type": "Seq Scan", "Relation Name": "client"}});
+        let ds = MockDataSource::new(&json!([{ "QUERY PLAN": [plan] }]));
+        let query = AssociatedQuery::<_, EmptyEntity>::new(
+            Query::new()
+                .with_table("client", None)
+                .with_column_field("name"),
+            ds,
+        );
+
+        assert_eq!(
+            query.get_explain_query(true).preview(),
+            "EXPLAIN (ANALYZE, FORMAT JSON) SELECT name FROM client"
+        );
+        assert_eq!(
+            query.explain().await.unwrap()["Plan"]["Node Type"],
+            "Seq Scan"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let ds =
+            MockDataSource::new(&json!([{ "cnt": 3 }])).with_latency(Duration::from_millis(200));
+        let query = AssociatedQuery::<_, EmptyEntity>::new(
+            Query::new()
+                .with_table("client", None)
+                .with_column_field("cnt"),
+            ds,
+        );
+
+        let error = query
+            .clone()
+            .with_timeout(Duration::from_millis(10))
+            .get_one_as::<i64>()
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<crate::Error>(),
+            Some(crate::Error::Timeout(_))
+        ));
+        assert_eq!(query.get_one_as::<i64>().await.unwrap(), 3);
+
+        let query = query.with_timeout(Duration::from_millis(10));
+        let error = query.explain().await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<crate::Error>(),
+            Some(crate::Error::Timeout(_))
+        ));
+        let rows: Vec<Result<EmptyEntity>> = query.get_stream().collect().await;
+        assert_eq!(rows.len(), 1);
+        assert!(matches!(
+            rows[0].as_ref().unwrap_err().downcast_ref::<crate::Error>(),
+            Some(crate::Error::Timeout(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_one() {
+        use crate::datasource::memory::MemoryDataSource;
+
+        let memory = MemoryDataSource::new().with_table(
+            "product",
+            vec![json!({ "name": "Cake", "stock": 12 })
+                .as_object()
+                .unwrap()
+                .clone()],
+        );
+        let stock = AssociatedQuery::<_, EmptyEntity>::new(
+            Query::new()
+                .with_table("product", None)
+                .with_column_field("stock"),
+            memory,
+        );
+
+        assert_eq!(stock.get_one::<i64>().await.unwrap(), 12);
+        assert_eq!(stock.get_one::<Option<f64>>().await.unwrap(), Some(12.0));
+        assert_eq!(stock.get_one_as::<String>().await.unwrap(), "12");
+        assert_eq!(
+            stock.get_one_as::<Decimal>().await.unwrap(),
+            Decimal::from(12)
+        );
+        assert!(stock
+            .get_one::<String>()
+            .await
+            .unwrap_err()
+            .downcast_ref::<crate::Error>()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_glue_in() {
+        use crate::datasource::memory::MemoryDataSource;
+
+        let clients = MemoryDataSource::new().with_table(
+            "client",
+            (1..=3)
+                .map(|id| json!({ "id": id }).as_object().unwrap().clone())
+                .collect(),
+        );
+        let client_ids = AssociatedQuery::<_, EmptyEntity>::new(
+            Query::new()
+                .with_table("client", None)
+                .with_column_field("id"),
+            clients,
+        );
+        let orders = AssociatedQuery::<_, EmptyEntity>::new(
+            Query::new().with_table("ord", None),
+            MockDataSource::new(&json!([])),
+        );
+
+        let condition = orders
+            .glue_in(&expr!("client_id"), client_ids.clone())
+            .await
+            .unwrap()
+            .render_chunk();
+        assert_eq!(condition.sql(), "(client_id = ANY ({}))");
+        assert_eq!(condition.params(), &vec![json!([1, 2, 3])]);
+
+        let condition = client_ids
+            .glue_in(&expr!("id"), client_ids.clone())
+            .await
+            .unwrap()
+            .render_chunk();
+        assert_eq!(condition.preview(), "(id IN (SELECT id FROM client))");
+    }
+}
@@ -0,0 +1,281 @@
+//! Read-only ClickHouse data source, using the HTTP interface. Enabled with
+//! `clickhouse` feature.
+//!
+//! ```
+//! let clickhouse = ClickHouse::new("http://localhost:8123")
+//!     .with_database("analytics")
+//!     .with_credentials("reporter", "secret");
+//!
+//! let events = Table::new("events", clickhouse)
+//!     .with_column("kind")
+//!     .with_column("created_at");
+//! let rows = events.get_select_query().with_final().with_sample(0.1)?;
+//! // SELECT kind, created_at FROM events FINAL SAMPLE 0.1
+//! ```
+//!
+//! Only reading is supported: tables can be used as [`ReadableDataSet`], but
+//! inserts, updates and deletes fail with an error. Parameters are inlined as
+//! escaped literals, as the HTTP interface requires typed placeholders.
+//!
+//! [`ReadableDataSet`]: crate::dataset::ReadableDataSet
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use futures::stream::{self, BoxStream, StreamExt};
+use serde_json::{Map, Value};
+
+use crate::sql::chunk::Chunk;
+use crate::sql::dialect::{ClickHouseDialect, Dialect};
+use crate::sql::query::{QuerySource, SqlQuery};
+use crate::sql::{Expression, Query};
+use crate::traits::datasource::{DataSource, ExecResult};
+
+#[derive(Clone)]
+pub struct ClickHouse {
+    client: reqwest::Client,
+    url: String,
+    database: Option<String>,
+    user: Option<String>,
+    password: Option<String>,
+}
+
+/// ClickHouse is equal to another one, connecting to the same database as the same user.
+impl PartialEq for ClickHouse {
+    fn eq(&self, other: &ClickHouse) -> bool {
+        self.url == other.url && self.database == other.database && self.user == other.user
+    }
+}
+
+impl std::fmt::Debug for ClickHouse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClickHouse")
+            .field("url", &self.url)
+            .field("database", &self.database)
+            .field("user", &self.user)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ClickHouse {
+    pub fn new(url: &str) -> ClickHouse {
+        ClickHouse {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            database: None,
+            user: None,
+            password: None,
+        }
+    }
+
+    pub fn with_database(mut self, database: &str) -> Self {
+        self.database = Some(database.to_string());
+        self
+    }
+
+    pub fn with_credentials(mut self, user: &str, password: &str) -> Self {
+        self.user = Some(user.to_string());
+        self.password = Some(password.to_string());
+        self
+    }
+
+    /// Parses response in JSONEachRow format: one object per line.
+    fn parse_rows(body: &str) -> Result<Vec<Map<String, Value>>> {
+        body.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    async fn query_raw(&self, query: &Query) -> Result<Vec<Map<String, Value>>> {
+        let query_rendered = query.try_render_chunk()?;
+        let sql = format!(
+            "{} FORMAT JSONEachRow",
+            query_rendered.render_inline(&ClickHouseDialect)
+        );
+
+        let mut request = self
+            .client
+            .post(&self.url)
+            .query(&[("output_format_json_quote_64bit_integers", "0")])
+            .body(sql);
+        if let Some(database) = &self.database {
+            request = request.query(&[("database", database)]);
+        }
+        if let Some(user) = &self.user {
+            request = request.header("X-ClickHouse-User", user);
+        }
+        if let Some(password) = &self.password {
+            request = request.header("X-ClickHouse-Key", password);
+        }
+
+        let response = request
+            .send()
+            .await
+            .with_context(|| anyhow!("Unable to reach ClickHouse at {}", self.url))?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(anyhow!(
+                "Error in query {}: {}",
+                query_rendered.preview(),
+                body.trim()
+            ));
+        }
+        Self::parse_rows(&body)
+    }
+
+    fn read_only<T>(&self) -> Result<T> {
+        Err(anyhow!("ClickHouse data source is read-only"))
+    }
+}
+
+impl DataSource for ClickHouse {
+    fn dialect(&self) -> Arc<dyn Dialect> {
+        Arc::new(ClickHouseDialect)
+    }
+
+    async fn query_fetch(&self, query: &Query) -> Result<Vec<Map<String, Value>>> {
+        self.query_raw(query).await
+    }
+
+    async fn query_exec(&self, _query: &Query) -> Result<ExecResult> {
+        self.read_only()
+    }
+
+    async fn query_insert(&self, _query: &Query, _rows: Vec<Vec<Value>>) -> Result<()> {
+        self.read_only()
+    }
+
+    async fn query_one(&self, query: &Query) -> Result<Value> {
+        let Some(row) = self.query_raw(query).await?.into_iter().next() else {
+            return Err(anyhow!("No rows for query_one"));
+        };
+        let Some((_, res)) = row.into_iter().next() else {
+            return Err(anyhow!("No cells in a first row of query_one"));
+        };
+        Ok(res)
+    }
+
+    async fn query_row(&self, query: &Query) -> Result<Map<String, Value>> {
+        let Some(row) = self.query_raw(query).await?.into_iter().next() else {
+            return Err(anyhow!("No rows for query_row"));
+        };
+        Ok(row)
+    }
+
+    async fn query_col(&self, query: &Query) -> Result<Vec<Value>> {
+        Ok(self
+            .query_raw(query)
+            .await?
+            .into_iter()
+            .filter_map(|row| Some(row.into_iter().next()?.1))
+            .collect())
+    }
+
+    /// Response is read in full before the stream is returned.
+    async fn query_stream(
+        &self,
+        query: &Query,
+    ) -> Result<BoxStream<'static, Result<Map<String, Value>>>> {
+        let rows = self.query_raw(query).await?;
+        Ok(stream::iter(rows.into_iter().map(Ok)).boxed())
+    }
+}
+
+/// ClickHouse-specific clauses, following the table name in a SELECT query.
+pub trait ClickHouseQuery: Sized {
+    /// Merges rows of ReplacingMergeTree and similar engines before returning them:
+    /// `FROM events FINAL`
+    fn with_final(self) -> Self;
+
+    /// Reads only a fraction of the data, for tables which have a sampling key:
+    /// `FROM events SAMPLE 0.1`. Fails unless `ratio` is above 0 and at most 1.
+    fn with_sample(self, ratio: f64) -> Result<Self>;
+}
+
+impl ClickHouseQuery for Query {
+    fn with_final(self) -> Self {
+        with_table_modifier(self, "FINAL".to_string())
+    }
+
+    fn with_sample(self, ratio: f64) -> Result<Self> {
+        if !(ratio > 0.0 && ratio <= 1.0) {
+            return Err(anyhow!(
+                "Sample ratio must be above 0 and at most 1, got {}",
+                ratio
+            ));
+        }
+        Ok(with_table_modifier(self, format!("SAMPLE {}", ratio)))
+    }
+}
+
+/// Modifiers follow the alias (`FROM events AS e FINAL`), so the table is
+/// replaced with an expression, which includes the alias.
+fn with_table_modifier(query: Query, modifier: String) -> Query {
+    let dialect = query.get_dialect().clone();
+    let source = match query.get_source().clone() {
+        QuerySource::Table(table, alias) => {
+            let mut sql = dialect.quote_identifier(&table);
+            if let Some(alias) = alias {
+                sql = format!("{} AS {}", sql, dialect.quote_identifier(&alias));
+            }
+            Expression::new(format!("{} {}", sql, modifier), vec![])
+        }
+        QuerySource::Expression(expression, None) => Expression::new(
+            format!("{} {}", expression.sql(), modifier),
+            expression.params().clone(),
+        ),
+        _ => return query,
+    };
+    query.with_source(QuerySource::Expression(source, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::expr;
+
+    #[test]
+    fn test_inline_params() {
+        let query = expr!(
+            "SELECT * FROM events WHERE kind = {} AND id IN {} AND deleted = {}",
+            "it's \\ odd",
+            [1, 2],
+            false
+        );
+        assert_eq!(
+            query.render_inline(&ClickHouseDialect),
+            "SELECT * FROM events WHERE kind = 'it\\'s \\\\ odd' AND id IN [1, 2] AND deleted = false"
+        );
+    }
+
+    #[test]
+    fn test_table_modifiers() {
+        let query = Query::new()
+            .with_dialect(Arc::new(ClickHouseDialect))
+            .with_table("events", Some("e".to_string()))
+            .with_column_field("kind")
+            .with_final()
+            .with_sample(0.1)
+            .unwrap();
+        assert_eq!(
+            query.preview(),
+            "SELECT kind FROM events AS e FINAL SAMPLE 0.1"
+        );
+        for ratio in [0.0, -0.5, 1.5, f64::NAN, f64::INFINITY] {
+            assert!(query.clone().with_sample(ratio).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let rows =
+            ClickHouse::parse_rows("{\"kind\":\"click\",\"n\":3}\n{\"kind\":\"view\",\"n\":5}\n")
+                .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1]["n"], json!(5));
+    }
+}
@@ -0,0 +1,166 @@
+//! Connecting to Postgres with retries, for servers which are still starting
+//! up (containers, fresh deployments):
+//!
+//! ```
+//! let postgres = Postgres::connect(
+//!     "postgres://postgres@localhost:5432/postgres",
+//!     ConnectOptions::default()
+//!         .with_retries(10)
+//!         .with_statement_timeout(Duration::from_secs(30)),
+//! )
+//! .await?;
+//! ```
+//!
+//! [`Postgres`]: super::postgres::Postgres
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tokio_postgres::tls::NoTlsStream;
+use tokio_postgres::{Client, Config, Connection, NoTls, Socket};
+
+/// Options for [`Postgres::connect()`](super::postgres::Postgres::connect).
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    /// Attempts after the first one has failed
+    pub retries: u32,
+    /// Delay before the first retry, doubled for each following one
+    pub backoff: Duration,
+    /// Limit for establishing a single connection
+    pub connect_timeout: Duration,
+    /// Sets `statement_timeout` for the session, queries running longer are
+    /// cancelled by the server
+    pub statement_timeout: Option<Duration>,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        ConnectOptions {
+            retries: 5,
+            backoff: Duration::from_millis(100),
+            connect_timeout: Duration::from_secs(10),
+            statement_timeout: None,
+        }
+    }
+}
+
+impl ConnectOptions {
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn with_statement_timeout(mut self, statement_timeout: Duration) -> Self {
+        self.statement_timeout = Some(statement_timeout);
+        self
+    }
+
+    /// Delay before the retry number `attempt`, starting from 0.
+    fn delay(&self, attempt: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(attempt))
+    }
+
+    fn config(&self, url: &str) -> Result<Config> {
+        let mut config: Config = url
+            .parse()
+            .with_context(|| format!("Invalid connection string {}", url))?;
+        config.connect_timeout(self.connect_timeout);
+        if let Some(timeout) = self.statement_timeout {
+            config.options(format!("-c statement_timeout={}", timeout.as_millis()));
+        }
+        Ok(config)
+    }
+}
+
+/// Connects to `url`, retrying with exponential backoff. The connection must
+/// be polled for the client to work.
+pub(super) async fn open(
+    url: &str,
+    options: &ConnectOptions,
+) -> Result<(Client, Connection<Socket, NoTlsStream>)> {
+    let config = options.config(url)?;
+    let mut attempt = 0;
+    loop {
+        match config.connect(NoTls).await {
+            Ok(connected) => return Ok(connected),
+            Err(e) if attempt < options.retries => {
+                let delay = options.delay(attempt);
+                log::warn!(
+                    "Unable to connect to Postgres: {}, retrying in {:?}",
+                    e,
+                    delay
+                );
+                crate::runtime::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "Unable to connect to Postgres after {} attempts",
+                        attempt + 1
+                    )
+                })
+            }
+        }
+    }
+}
+
+/// Same as [`open()`], but the connection is driven by a spawned task.
+pub(super) async fn connect(url: &str, options: &ConnectOptions) -> Result<Client> {
+    let (client, connection) = open(url, options).await?;
+    crate::runtime::spawn(async move {
+        if let Err(e) = connection.await {
+            log::error!("Postgres connection error: {}", e);
+        }
+    });
+    Ok(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let options = ConnectOptions::default().with_backoff(Duration::from_millis(50));
+        assert_eq!(options.delay(0), Duration::from_millis(50));
+        assert_eq!(options.delay(3), Duration::from_millis(400));
+        assert_eq!(
+            options
+                .with_statement_timeout(Duration::from_secs(2))
+                .config("postgres://postgres@localhost/postgres")
+                .unwrap()
+                .get_options(),
+            Some("-c statement_timeout=2000")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_retries() {
+        let options = ConnectOptions::default()
+            .with_retries(2)
+            .with_backoff(Duration::from_millis(20));
+        let start = Instant::now();
+        // nothing listens on port 1
+        let error = connect("postgres://postgres@127.0.0.1:1/postgres", &options)
+            .await
+            .unwrap_err();
+        assert!(start.elapsed() >= Duration::from_millis(60));
+        assert_eq!(
+            error.to_string(),
+            "Unable to connect to Postgres after 3 attempts"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Read-only data source for a directory of CSV files, one file per table.
+//! Enabled with `csv` feature.
+//!
+//! ```
+//! let csv = CsvDataSource::new("data/");
+//!
+//! // reads data/product.csv
+//! let products = Table::new("product", csv)
+//!     .with_column("name")
+//!     .with_column("price");
+//! let cheap = products.with_condition(products.get_column("price").unwrap().lt(10));
+//! ```
+//!
+//! The first line of a file holds the field names. Values are converted into
+//! numbers and booleans where possible, and empty values become NULL. Files are
+//! read on every query, then conditions, order, skip and limit are applied
+//! in-process, the same way as in [`MemoryDataSource`].
+//!
+//! [`MemoryDataSource`]: crate::datasource::memory::MemoryDataSource
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use futures::stream::{self, BoxStream, StreamExt};
+use serde_json::{Map, Number, Value};
+
+use super::memory::{apply_query, Rows};
+use crate::sql::dialect::{Dialect, PostgresDialect};
+use crate::sql::query::{QuerySource, SqlQuery};
+use crate::sql::Query;
+use crate::traits::datasource::{DataSource, ExecResult};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvDataSource {
+    directory: PathBuf,
+    delimiter: u8,
+}
+
+impl CsvDataSource {
+    pub fn new(directory: impl Into<PathBuf>) -> CsvDataSource {
+        CsvDataSource {
+            directory: directory.into(),
+            delimiter: b',',
+        }
+    }
+
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    fn path(&self, query: &Query) -> Result<PathBuf> {
+        match query.get_source() {
+            QuerySource::Table(table, _) => Ok(self.directory.join(format!("{}.csv", table))),
+            _ => Err(anyhow!("CsvDataSource can only query tables")),
+        }
+    }
+
+    fn parse_rows(&self, data: &[u8]) -> Result<Rows> {
+        let mut reader = ::csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .from_reader(data);
+        let headers = reader.headers()?.clone();
+        reader
+            .records()
+            .map(|record| {
+                Ok(headers
+                    .iter()
+                    .zip(record?.iter())
+                    .map(|(field, value)| (field.to_string(), Self::parse_value(value)))
+                    .collect())
+            })
+            .collect()
+    }
+
+    fn parse_value(value: &str) -> Value {
+        if value.is_empty() {
+            return Value::Null;
+        }
+        if let Ok(b) = value.parse::<bool>() {
+            return Value::Bool(b);
+        }
+        let is_number = value.parse::<f64>().is_ok_and(|n| n.is_finite());
+        match value.parse::<Number>() {
+            Ok(n) if is_number => Value::Number(n),
+            _ => Value::String(value.to_string()),
+        }
+    }
+
+    async fn fetch(&self, query: &Query) -> Result<Rows> {
+        let path = self.path(query)?;
+        let data = tokio::fs::read(&path)
+            .await
+            .with_context(|| anyhow!("Unable to read {}", path.display()))?;
+        let rows = self
+            .parse_rows(&data)
+            .with_context(|| anyhow!("Invalid CSV in {}", path.display()))?;
+        apply_query(rows.into_iter(), query)
+    }
+
+    fn read_only<T>(&self) -> Result<T> {
+        Err(anyhow!("CSV data source is read-only"))
+    }
+}
+
+impl DataSource for CsvDataSource {
+    fn dialect(&self) -> Arc<dyn Dialect> {
+        Arc::new(PostgresDialect)
+    }
+
+    async fn query_fetch(&self, query: &Query) -> Result<Vec<Map<String, Value>>> {
+        self.fetch(query).await
+    }
+
+    async fn query_exec(&self, _query: &Query) -> Result<ExecResult> {
+        self.read_only()
+    }
+
+    async fn query_insert(&self, _query: &Query, _rows: Vec<Vec<Value>>) -> Result<()> {
+        self.read_only()
+    }
+
+    async fn query_one(&self, query: &Query) -> Result<Value> {
+        let Some(row) = self.fetch(query).await?.into_iter().next() else {
+            return Err(anyhow!("No rows for query_one"));
+        };
+        let Some((_, res)) = row.into_iter().next() else {
+            return Err(anyhow!("No cells in a first row of query_one"));
+        };
+        Ok(res)
+    }
+
+    async fn query_row(&self, query: &Query) -> Result<Map<String, Value>> {
+        let Some(row) = self.fetch(query).await?.into_iter().next() else {
+            return Err(anyhow!("No rows for query_row"));
+        };
+        Ok(row)
+    }
+
+    async fn query_col(&self, query: &Query) -> Result<Vec<Value>> {
+        Ok(self
+            .fetch(query)
+            .await?
+            .into_iter()
+            .filter_map(|row| Some(row.into_iter().next()?.1))
+            .collect())
+    }
+
+    async fn query_stream(
+        &self,
+        query: &Query,
+    ) -> Result<BoxStream<'static, Result<Map<String, Value>>>> {
+        let rows = self.fetch(query).await?;
+        Ok(stream::iter(rows.into_iter().map(Ok)).boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::expr;
+    use crate::sql::Expression;
+
+    #[test]
+    fn test_parse_rows() {
+        let csv = CsvDataSource::new(".").with_delimiter(b';');
+        let rows = csv
+            .parse_rows(b"name;price;vegan;note\nCake;12.5;false;\nPie;8;true;\"a;b\"\n")
+            .unwrap();
+        assert_eq!(
+            Value::Array(rows.into_iter().map(Value::Object).collect()),
+            json!([
+                {"name": "Cake", "price": 12.5, "vegan": false, "note": null},
+                {"name": "Pie", "price": 8, "vegan": true, "note": "a;b"},
+            ])
+        );
+        assert_eq!(CsvDataSource::parse_value("NaN"), json!("NaN"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch() {
+        let directory = std::env::temp_dir().join(format!("vantage-csv-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(
+            directory.join("product.csv"),
+            "name,price\nCake,12\nPie,8\nTart,10\n",
+        )
+        .unwrap();
+
+        let csv = CsvDataSource::new(&directory);
+        let query = Query::new()
+            .with_table("product", None)
+            .with_column_field("name")
+            .with_condition(expr!("(price < {})", 11))
+            .with_order_by(expr!("name DESC"));
+        let names = csv.query_col(&query).await.unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(names, vec![json!("Tart"), json!("Pie")]);
+    }
+}
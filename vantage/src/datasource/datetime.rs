@@ -0,0 +1,237 @@
+//! Conversion of Postgres date/time types, enabled with the `chrono` feature.
+//!
+//! Values are represented in JSON as strings:
+//!
+//!  - `date` - `2024-05-01`
+//!  - `time` - `13:45:00`
+//!  - `timestamp` - `2024-05-01 13:45:00`
+//!  - `timestamptz` - `2024-05-01T13:45:00+00:00`, always converted into UTC
+//!  - `interval` - ISO 8601 duration, such as `P1M2DT3H`
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use bytes::{Buf, BufMut, BytesMut};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde_json::{json, Value};
+use tokio_postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+use tokio_postgres::Row;
+
+type BoxError = Box<dyn std::error::Error + Sync + Send>;
+
+/// Postgres `interval`. Months and days are stored separately from the time
+/// part, because their length varies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Interval {
+    pub months: i32,
+    pub days: i32,
+    pub microseconds: i64,
+}
+
+impl<'a> FromSql<'a> for Interval {
+    fn from_sql(_ty: &Type, mut raw: &'a [u8]) -> Result<Self, BoxError> {
+        if raw.len() != 16 {
+            return Err("invalid interval length".into());
+        }
+        let microseconds = raw.get_i64();
+        let days = raw.get_i32();
+        let months = raw.get_i32();
+        Ok(Interval {
+            months,
+            days,
+            microseconds,
+        })
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::INTERVAL
+    }
+}
+
+impl ToSql for Interval {
+    fn to_sql(&self, _ty: &Type, out: &mut BytesMut) -> Result<IsNull, BoxError> {
+        out.put_i64(self.microseconds);
+        out.put_i32(self.days);
+        out.put_i32(self.months);
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::INTERVAL
+    }
+
+    to_sql_checked!();
+}
+
+/// Formats interval as ISO 8601 duration.
+impl Display for Interval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if *self == Interval::default() {
+            return write!(f, "PT0S");
+        }
+        write!(f, "P")?;
+        let (years, months) = (self.months / 12, self.months % 12);
+        for (value, unit) in [(years, "Y"), (months, "M"), (self.days, "D")] {
+            if value != 0 {
+                write!(f, "{}{}", value, unit)?;
+            }
+        }
+        if self.microseconds == 0 {
+            return Ok(());
+        }
+        write!(f, "T")?;
+        let hours = self.microseconds / 3_600_000_000;
+        let minutes = self.microseconds / 60_000_000 % 60;
+        let micros = self.microseconds % 60_000_000;
+        for (value, unit) in [(hours, "H"), (minutes, "M")] {
+            if value != 0 {
+                write!(f, "{}{}", value, unit)?;
+            }
+        }
+        if micros != 0 {
+            let sign = if micros < 0 { "-" } else { "" };
+            let (secs, fraction) = (micros.abs() / 1_000_000, micros.abs() % 1_000_000);
+            if fraction == 0 {
+                write!(f, "{}{}S", sign, secs)?;
+            } else {
+                let fraction = format!("{:06}", fraction);
+                write!(f, "{}{}.{}S", sign, secs, fraction.trim_end_matches('0'))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses ISO 8601 duration, such as `P1Y2M3DT4H5M6.5S`. Years, months, weeks
+/// and days must be whole numbers, because they can't be carried into the time part.
+impl FromStr for Interval {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let rest = s
+            .strip_prefix('P')
+            .ok_or_else(|| anyhow!("Interval '{}' must start with P", s))?;
+
+        let mut interval = Interval::default();
+        let mut time = false;
+        let mut number = String::new();
+        for c in rest.chars() {
+            match (c, time) {
+                ('T', false) => time = true,
+                ('0'..='9' | '.' | '-', _) => number.push(c),
+                (unit, _) => {
+                    let value: f64 = number
+                        .parse()
+                        .with_context(|| anyhow!("Malformed interval '{}'", s))?;
+                    number.clear();
+                    let whole = |multiplier: f64| -> Result<i32> {
+                        if value.fract() != 0.0 {
+                            return Err(anyhow!(
+                                "Interval '{}' can only have a fraction in the time part",
+                                s
+                            ));
+                        }
+                        let value = value * multiplier;
+                        if value < i32::MIN as f64 || value > i32::MAX as f64 {
+                            return Err(anyhow!("Interval '{}' is out of range", s));
+                        }
+                        Ok(value as i32)
+                    };
+                    match (unit, time) {
+                        ('Y', false) => interval.months += whole(12.0)?,
+                        ('M', false) => interval.months += whole(1.0)?,
+                        ('W', false) => interval.days += whole(7.0)?,
+                        ('D', false) => interval.days += whole(1.0)?,
+                        ('H', true) => interval.microseconds += (value * 3_600_000_000.0) as i64,
+                        ('M', true) => interval.microseconds += (value * 60_000_000.0) as i64,
+                        ('S', true) => interval.microseconds += (value * 1_000_000.0) as i64,
+                        _ => return Err(anyhow!("Malformed interval '{}'", s)),
+                    }
+                }
+            }
+        }
+        if !number.is_empty() {
+            return Err(anyhow!("Malformed interval '{}'", s));
+        }
+        Ok(interval)
+    }
+}
+
+/// Converts a string value into a date/time parameter of type `ty`. Returns
+/// `None` if `ty` is not a date/time type or if the value can't be parsed.
+pub(crate) fn convert_value_tosql(value: &str, ty: &Type) -> Option<Box<dyn ToSql + Sync + Send>> {
+    Some(match *ty {
+        Type::DATE => Box::new(NaiveDate::from_str(value).ok()?),
+        Type::TIME => Box::new(NaiveTime::from_str(value).ok()?),
+        Type::TIMESTAMP => Box::new(parse_naive_datetime(value)?),
+        Type::TIMESTAMPTZ => Box::new(match DateTime::parse_from_rfc3339(value) {
+            Ok(dt) => dt.with_timezone(&Utc),
+            // timestamp without offset is assumed to be in UTC
+            Err(_) => parse_naive_datetime(value)?.and_utc(),
+        }),
+        Type::INTERVAL => Box::new(Interval::from_str(value).ok()?),
+        _ => return None,
+    })
+}
+
+fn parse_naive_datetime(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f"))
+        .ok()
+}
+
+/// Reads date/time column `i` from the row. Returns `None` if column type is
+/// not a date/time type.
+pub(crate) fn convert_value_fromsql(row: &Row, i: usize, col_type: &str) -> Option<Value> {
+    Some(match col_type {
+        "date" => json!(row.get::<_, Option<NaiveDate>>(i).map(|d| d.to_string())),
+        "time" => json!(row.get::<_, Option<NaiveTime>>(i).map(|t| t.to_string())),
+        "timestamp" => json!(row
+            .get::<_, Option<NaiveDateTime>>(i)
+            .map(|dt| dt.to_string())),
+        "timestamptz" => json!(row
+            .get::<_, Option<DateTime<Utc>>>(i)
+            .map(|dt| dt.to_rfc3339())),
+        "interval" => json!(row.get::<_, Option<Interval>>(i).map(|i| i.to_string())),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_string() {
+        let interval = Interval {
+            months: 14,
+            days: 3,
+            microseconds: 4 * 3_600_000_000 + 1_500_000,
+        };
+        assert_eq!(interval.to_string(), "P1Y2M3DT4H1.5S");
+        assert_eq!(interval.to_string().parse::<Interval>().unwrap(), interval);
+
+        assert_eq!(Interval::default().to_string(), "PT0S");
+        assert_eq!("PT0S".parse::<Interval>().unwrap(), Interval::default());
+        assert_eq!("P2W".parse::<Interval>().unwrap().days, 14);
+        assert!("1 day".parse::<Interval>().is_err());
+        assert!("P1".parse::<Interval>().is_err());
+        assert!("P1.5D".parse::<Interval>().is_err());
+        assert!("P0.5Y".parse::<Interval>().is_err());
+        assert!("P9999999999D".parse::<Interval>().is_err());
+        assert_eq!(
+            "PT1.5H".parse::<Interval>().unwrap().microseconds,
+            5_400_000_000
+        );
+    }
+
+    #[test]
+    fn test_convert_tosql() {
+        assert!(convert_value_tosql("2024-05-01", &Type::DATE).is_some());
+        assert!(convert_value_tosql("2024-05-01 13:45:00", &Type::TIMESTAMP).is_some());
+        assert!(convert_value_tosql("2024-05-01T13:45:00+02:00", &Type::TIMESTAMPTZ).is_some());
+        assert!(convert_value_tosql("yesterday", &Type::DATE).is_none());
+        assert!(convert_value_tosql("2024-05-01", &Type::TEXT).is_none());
+    }
+}
@@ -0,0 +1,334 @@
+//! DuckDB data source, running queries with the `duckdb` command line client.
+//! Enabled with `duckdb` feature.
+//!
+//! ```
+//! let duckdb = DuckDb::new("analytics.duckdb")
+//!     .with_file("sales", "data/sales/*.parquet")
+//!     .with_file("region", "data/region.csv");
+//!
+//! let sales = Table::new("sales", duckdb)
+//!     .with_column("region_id")
+//!     .with_column("total");
+//! let big = sales.with_condition(sales.get_column("total").unwrap().gt(1000));
+//! // CREATE OR REPLACE TEMP VIEW sales AS SELECT * FROM 'data/sales/*.parquet';
+//! // SELECT region_id, total FROM sales WHERE (total > 1000)
+//! ```
+//!
+//! Every query starts a `duckdb` process, so the same [`Table`] definitions can
+//! be used to explore Parquet and CSV files or in tests without a database server.
+//! Use [`DuckDb::in_memory()`] when only files are queried. Files are exposed as
+//! temporary views, which are created before each query.
+//!
+//! Parameters are inlined with [`DuckDbDialect::render_literal()`], as the client
+//! has no way to bind them. Rows are read in the JSON output mode, so DuckDB
+//! types are converted the same way as by the client: integers, decimals and
+//! doubles become numbers, dates, timestamps and intervals become strings, lists
+//! and structs become arrays and objects.
+//!
+//! [`Table`]: crate::sql::Table
+//! [`DuckDbDialect::render_literal()`]: crate::sql::Dialect::render_literal()
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use futures::stream::{self, BoxStream, StreamExt};
+use serde_json::{Map, Value};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::sql::dialect::{Dialect, DuckDbDialect};
+use crate::sql::query::{QueryReturning, QuerySource, QueryType, SqlQuery};
+use crate::sql::{Chunk, Expression, Query};
+use crate::traits::datasource::{DataSource, ExecResult};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DuckDb {
+    binary: PathBuf,
+    database: Option<PathBuf>,
+    files: Vec<(String, String)>,
+}
+
+impl DuckDb {
+    /// Database file, which is created if it does not exist.
+    pub fn new(database: impl Into<PathBuf>) -> DuckDb {
+        DuckDb {
+            binary: PathBuf::from("duckdb"),
+            database: Some(database.into()),
+            files: Vec::new(),
+        }
+    }
+
+    /// Transient database, which only lives for a single query.
+    pub fn in_memory() -> DuckDb {
+        DuckDb {
+            binary: PathBuf::from("duckdb"),
+            database: None,
+            files: Vec::new(),
+        }
+    }
+
+    /// Path to the `duckdb` client, if it is not on `PATH`.
+    pub fn with_binary(mut self, binary: impl Into<PathBuf>) -> Self {
+        self.binary = binary.into();
+        self
+    }
+
+    /// Expose Parquet, CSV or JSON files as a view, so that a table can select from
+    /// them. `path` may contain wildcards.
+    pub fn with_file(mut self, view: &str, path: &str) -> Self {
+        self.files.push((view.to_string(), path.to_string()));
+        self
+    }
+
+    /// Statements sent to the client: views for the files, followed by the query
+    /// with inlined parameters.
+    fn script(&self, query: &Query) -> Result<String> {
+        let dialect = DuckDbDialect;
+        let mut script = String::new();
+        for (view, path) in &self.files {
+            script.push_str(&format!(
+                "CREATE OR REPLACE TEMP VIEW {} AS SELECT * FROM {};\n",
+                dialect.quote_identifier(view),
+                dialect.render_literal(&Value::String(path.clone()))
+            ));
+        }
+        script.push_str(&query.try_render_chunk()?.render_inline(&dialect));
+        script.push_str(";\n");
+        Ok(script)
+    }
+
+    /// Parses output of the JSON mode. Each statement, which returns rows, prints an
+    /// array and the rows of the last one are returned.
+    fn parse_rows(output: &str) -> Result<Vec<Map<String, Value>>> {
+        let mut rows = Vec::new();
+        for result in
+            serde_json::Deserializer::from_str(output).into_iter::<Vec<Map<String, Value>>>()
+        {
+            rows = result.context("Unable to parse DuckDB output")?;
+        }
+        Ok(rows)
+    }
+
+    /// Single INSERT for all the rows, with values in the order of the query fields.
+    fn insert_query(query: &Query, rows: Vec<Vec<Value>>) -> Result<Query> {
+        let QuerySource::Table(table, _) = query.get_source() else {
+            return Err(anyhow!("DuckDb can only insert into tables"));
+        };
+        let fields = query.get_field_names();
+        let mut insert = Query::new()
+            .with_table(table, None)
+            .with_type(QueryType::Insert);
+        for (i, row) in rows.into_iter().enumerate() {
+            if row.len() != fields.len() {
+                return Err(anyhow!(
+                    "Row {} has {} values, but query has {} fields",
+                    i + 1,
+                    row.len(),
+                    fields.len()
+                ));
+            }
+            if i == 0 {
+                for (field, value) in fields.iter().zip(row) {
+                    insert = insert.with_set_field(field, value);
+                }
+            } else {
+                insert = insert.with_values_row(
+                    fields
+                        .iter()
+                        .cloned()
+                        .zip(row)
+                        .map(|(field, value)| {
+                            (field, Expression::new("{}".to_string(), vec![value]))
+                        })
+                        .collect(),
+                );
+            }
+        }
+        Ok(insert)
+    }
+
+    async fn query_raw(&self, query: &Query) -> Result<Vec<Map<String, Value>>> {
+        let script = self.script(query)?;
+
+        let mut command = Command::new(&self.binary);
+        command.arg("-json").arg("-bail");
+        if let Some(database) = &self.database {
+            command.arg(database);
+        }
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| anyhow!("Unable to start {}", self.binary.display()))?;
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("Unable to write to {}", self.binary.display()))?;
+        stdin.write_all(script.as_bytes()).await?;
+        drop(stdin);
+
+        let output = child.wait_with_output().await?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Error in query {}: {}",
+                query.preview(),
+                stderr.trim()
+            ));
+        }
+        Self::parse_rows(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+impl DataSource for DuckDb {
+    fn dialect(&self) -> Arc<dyn Dialect> {
+        Arc::new(DuckDbDialect)
+    }
+
+    async fn query_fetch(&self, query: &Query) -> Result<Vec<Map<String, Value>>> {
+        self.query_raw(query).await
+    }
+
+    /// Without RETURNING, DuckDB responds with the number of affected rows in
+    /// a `Count` column.
+    async fn query_exec(&self, query: &Query) -> Result<ExecResult> {
+        let rows = self.query_raw(query).await?;
+        if !matches!(query.get_returning(), QueryReturning::None) {
+            return Ok(ExecResult {
+                rows_affected: rows.len() as u64,
+                returned: rows,
+            });
+        }
+        let rows_affected = rows
+            .first()
+            .and_then(|row| row.get("Count"))
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        Ok(ExecResult {
+            rows_affected,
+            returned: Vec::new(),
+        })
+    }
+
+    /// Rows are inserted with a single INSERT, values are inlined.
+    async fn query_insert(&self, query: &Query, rows: Vec<Vec<Value>>) -> Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        self.query_raw(&Self::insert_query(query, rows)?).await?;
+        Ok(())
+    }
+
+    async fn query_one(&self, query: &Query) -> Result<Value> {
+        let Some(row) = self.query_raw(query).await?.into_iter().next() else {
+            return Err(anyhow!("No rows for query_one"));
+        };
+        let Some((_, res)) = row.into_iter().next() else {
+            return Err(anyhow!("No cells in a first row of query_one"));
+        };
+        Ok(res)
+    }
+
+    async fn query_row(&self, query: &Query) -> Result<Map<String, Value>> {
+        let Some(row) = self.query_raw(query).await?.into_iter().next() else {
+            return Err(anyhow!("No rows for query_row"));
+        };
+        Ok(row)
+    }
+
+    async fn query_col(&self, query: &Query) -> Result<Vec<Value>> {
+        Ok(self
+            .query_raw(query)
+            .await?
+            .into_iter()
+            .filter_map(|row| Some(row.into_iter().next()?.1))
+            .collect())
+    }
+
+    /// Output is read in full before the stream is returned.
+    async fn query_stream(
+        &self,
+        query: &Query,
+    ) -> Result<BoxStream<'static, Result<Map<String, Value>>>> {
+        let rows = self.query_raw(query).await?;
+        Ok(stream::iter(rows.into_iter().map(Ok)).boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::prelude::*;
+    use crate::sql::Table;
+
+    #[test]
+    fn test_script() {
+        let duckdb = DuckDb::in_memory().with_file("sales", "data/it's/*.parquet");
+        let sales = Table::new("sales", duckdb.clone())
+            .with_column("region")
+            .with_column("total");
+        let query = sales
+            .clone()
+            .with_condition(sales.get_column("region").unwrap().eq(&"O'Hare"))
+            .get_select_query();
+
+        assert_eq!(
+            duckdb.script(&query).unwrap(),
+            "CREATE OR REPLACE TEMP VIEW sales AS SELECT * FROM 'data/it''s/*.parquet';\n\
+            SELECT region, total FROM sales WHERE (region = 'O''Hare');\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let rows = DuckDb::parse_rows(
+            "[{\"region\":\"north\",\"total\":3.5},\n{\"region\":\"south\",\"total\":5}\n]\n",
+        )
+        .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1]["total"], json!(5));
+
+        // rows of the last statement are returned, empty output has no rows
+        let rows = DuckDb::parse_rows("[{\"Count\":1}]\n[{\"Count\":2}]\n").unwrap();
+        assert_eq!(rows[0]["Count"], json!(2));
+        assert!(DuckDb::parse_rows("").unwrap().is_empty());
+        assert!(DuckDb::parse_rows("[{\"a\":").is_err());
+    }
+
+    #[test]
+    fn test_insert_query() {
+        let duckdb = DuckDb::in_memory();
+        let query = Query::new()
+            .with_table("sales", None)
+            .with_column_field("region")
+            .with_column_field("total");
+
+        let insert = DuckDb::insert_query(
+            &query,
+            vec![
+                vec![json!("north"), json!(3.5)],
+                vec![json!("it's"), Value::Null],
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            duckdb.script(&insert).unwrap(),
+            "INSERT INTO sales (region, total) VALUES ('north', 3.5), ('it''s', NULL);\n"
+        );
+        assert!(DuckDb::insert_query(&query, vec![vec![json!("north")]]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_missing_binary() {
+        let duckdb = DuckDb::in_memory().with_binary("/nonexistent/duckdb");
+        let table = Table::new("sales", duckdb).with_column("total");
+        assert!(table.get_all_untyped().await.is_err());
+    }
+}
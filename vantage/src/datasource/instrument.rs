@@ -0,0 +1,36 @@
+//! Recording of query execution into [`tracing`] spans, enabled with the `tracing`
+//! feature. Spans are created by `#[instrument]` on the query methods of
+//! [`Postgres`] and nest under the span of the caller, such as the request span
+//! of `tower_http::trace::TraceLayer`.
+//!
+//! Without the feature, these functions do nothing.
+//!
+//! [`tracing`]: https://docs.rs/tracing
+//! [`Postgres`]: super::postgres::Postgres
+
+use std::time::Instant;
+
+use crate::sql::Expression;
+
+/// Records rendered SQL (with placeholders, but without values) and number of
+/// parameters on the current span.
+#[cfg(feature = "tracing")]
+pub(crate) fn record_query(query: &Expression) {
+    let span = tracing::Span::current();
+    span.record("sql", query.sql_final());
+    span.record("params", query.params().len());
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn record_query(_query: &Expression) {}
+
+/// Records number of returned or affected rows and the time since `started`.
+#[cfg(feature = "tracing")]
+pub(crate) fn record_result(rows: u64, started: Instant) {
+    let span = tracing::Span::current();
+    span.record("rows", rows);
+    span.record("latency_ms", started.elapsed().as_millis() as u64);
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn record_result(_rows: u64, _started: Instant) {}
@@ -0,0 +1,405 @@
+//! Data source, which keeps rows in memory. Useful for examples, tests and
+//! small tools, which need real dataset behavior without a database:
+//!
+//! ```
+//! let memory = MemoryDataSource::new().with_table(
+//!     "product",
+//!     vec![
+//!         json!({"id": 1, "name": "Cake", "price": 12}).as_object().unwrap().clone(),
+//!         json!({"id": 2, "name": "Pie", "price": 8}).as_object().unwrap().clone(),
+//!     ],
+//! );
+//!
+//! let products = Table::new("product", memory)
+//!     .with_id_column("id")
+//!     .with_column("name")
+//!     .with_column("price");
+//! let cheap = products.with_condition(products.get_column("price").unwrap().lt(10));
+//! ```
+//!
+//! Conditions, order, skip and limit are applied in-process. Conditions must
+//! compare a field with a value, see [`Filter`]. Inserts, updates and deletes
+//! change the rows in memory, which are shared between clones.
+
+use std::cmp::Ordering;
+use std::sync::{Arc, RwLock};
+
+use anyhow::{anyhow, Result};
+use futures::stream::{self, BoxStream, StreamExt};
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+
+use crate::sql::dialect::{Dialect, PostgresDialect};
+use crate::sql::query::{
+    compare, Direction, Filter, QueryReturning, QuerySource, QueryType, SqlQuery,
+};
+use crate::sql::Query;
+use crate::traits::datasource::{DataSource, ExecResult};
+
+pub type Rows = Vec<Map<String, Value>>;
+
+#[derive(Clone, Debug, Default)]
+pub struct MemoryDataSource {
+    tables: Arc<RwLock<IndexMap<String, Rows>>>,
+}
+
+/// MemoryDataSource is equal to its clones.
+impl PartialEq for MemoryDataSource {
+    fn eq(&self, other: &MemoryDataSource) -> bool {
+        Arc::ptr_eq(&self.tables, &other.tables)
+    }
+}
+
+impl MemoryDataSource {
+    pub fn new() -> MemoryDataSource {
+        MemoryDataSource::default()
+    }
+
+    pub fn with_table(self, table: &str, rows: Rows) -> Self {
+        self.set_table(table, rows);
+        self
+    }
+
+    /// Replaces all rows of a table
+    pub fn set_table(&self, table: &str, rows: Rows) {
+        self.tables.write().unwrap().insert(table.to_string(), rows);
+    }
+
+    /// Returns copy of all rows in a table
+    pub fn rows(&self, table: &str) -> Option<Rows> {
+        self.tables.read().unwrap().get(table).cloned()
+    }
+
+    fn table_name(query: &Query) -> Result<&str> {
+        match query.get_source() {
+            QuerySource::Table(table, _) => Ok(table),
+            _ => Err(anyhow!("MemoryDataSource can only query tables")),
+        }
+    }
+
+    fn fetch(&self, query: &Query) -> Result<Rows> {
+        let table = Self::table_name(query)?;
+        let tables = self.tables.read().unwrap();
+        let rows = tables
+            .get(table)
+            .ok_or_else(|| anyhow!("Table {} not found", table))?;
+        apply_query(rows.iter().cloned(), query)
+    }
+
+    fn update(&self, query: &Query) -> Result<Rows> {
+        let filters = query.get_filters()?;
+        let values = query.get_set_values()?;
+        let mut tables = self.tables.write().unwrap();
+        let table = Self::table_name(query)?;
+        let rows = tables
+            .get_mut(table)
+            .ok_or_else(|| anyhow!("Table {} not found", table))?;
+        let mut updated = Vec::new();
+        for row in rows.iter_mut().filter(|row| matches_all(&filters, row)) {
+            row.extend(values.clone());
+            updated.push(row.clone());
+        }
+        Ok(updated)
+    }
+
+    fn delete(&self, query: &Query) -> Result<Rows> {
+        let filters = query.get_filters()?;
+        let mut tables = self.tables.write().unwrap();
+        let table = Self::table_name(query)?;
+        let rows = tables
+            .get_mut(table)
+            .ok_or_else(|| anyhow!("Table {} not found", table))?;
+        let (deleted, kept) = std::mem::take(rows)
+            .into_iter()
+            .partition(|row| matches_all(&filters, row));
+        *rows = kept;
+        Ok(deleted)
+    }
+
+    fn insert(&self, table: &str, row: Map<String, Value>) {
+        let mut tables = self.tables.write().unwrap();
+        tables.entry(table.to_string()).or_default().push(row);
+    }
+}
+
+/// Affected rows, with fields requested by RETURNING of the query.
+fn exec_result(query: &Query, rows: Rows) -> ExecResult {
+    let returned = match query.get_returning() {
+        QueryReturning::None => vec![],
+        QueryReturning::All => rows.clone(),
+        QueryReturning::Fields(fields) => rows
+            .iter()
+            .map(|row| {
+                fields
+                    .iter()
+                    .map(|field| {
+                        (
+                            field.clone(),
+                            row.get(field).cloned().unwrap_or(Value::Null),
+                        )
+                    })
+                    .collect()
+            })
+            .collect(),
+    };
+    ExecResult {
+        rows_affected: rows.len() as u64,
+        returned,
+    }
+}
+
+fn matches_all(filters: &[Filter], row: &Map<String, Value>) -> bool {
+    filters.iter().all(|filter| filter.matches(row))
+}
+
+/// Applies conditions, order, skip and limit of the query to the rows and keeps
+/// only the fields, which query selects.
+pub(crate) fn apply_query(
+    rows: impl Iterator<Item = Map<String, Value>>,
+    query: &Query,
+) -> Result<Rows> {
+    let filters = query.get_filters()?;
+    let order = query.get_order()?;
+    let fields = query.get_field_names();
+
+    let mut rows: Rows = rows.filter(|row| matches_all(&filters, row)).collect();
+    if !order.is_empty() {
+        rows.sort_by(|a, b| {
+            order
+                .iter()
+                .map(|(field, direction)| {
+                    let a = a.get(field).unwrap_or(&Value::Null);
+                    let b = b.get(field).unwrap_or(&Value::Null);
+                    // NULLs go last, like in Postgres
+                    let ordering = match (a.is_null(), b.is_null()) {
+                        (true, true) => Ordering::Equal,
+                        (true, false) => Ordering::Greater,
+                        (false, true) => Ordering::Less,
+                        _ => compare(a, b).unwrap_or(Ordering::Equal),
+                    };
+                    match direction {
+                        Direction::Asc => ordering,
+                        Direction::Desc => ordering.reverse(),
+                    }
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+
+    let skip = query.get_skip().unwrap_or(0);
+    let skip =
+        usize::try_from(skip).map_err(|_| anyhow!("Skip must not be negative, got {}", skip))?;
+    let limit = match query.get_limit() {
+        Some(limit) => usize::try_from(limit)
+            .map_err(|_| anyhow!("Limit must not be negative, got {}", limit))?,
+        None => usize::MAX,
+    };
+    Ok(rows
+        .into_iter()
+        .skip(skip)
+        .take(limit)
+        .map(|mut row| {
+            if fields.is_empty() {
+                return row;
+            }
+            fields
+                .iter()
+                .map(|field| (field.clone(), row.remove(field).unwrap_or(Value::Null)))
+                .collect()
+        })
+        .collect())
+}
+
+impl DataSource for MemoryDataSource {
+    fn dialect(&self) -> Arc<dyn Dialect> {
+        Arc::new(PostgresDialect)
+    }
+
+    async fn query_fetch(&self, query: &Query) -> Result<Vec<Map<String, Value>>> {
+        match query.get_type() {
+            QueryType::Select => self.fetch(query),
+            query_type => Err(anyhow!(
+                "MemoryDataSource can't fetch {:?} query, use query_exec",
+                query_type
+            )),
+        }
+    }
+
+    async fn query_exec(&self, query: &Query) -> Result<ExecResult> {
+        match query.get_type() {
+            QueryType::Select => {
+                let returned = self.fetch(query)?;
+                Ok(ExecResult {
+                    rows_affected: returned.len() as u64,
+                    returned,
+                })
+            }
+            QueryType::Insert | QueryType::Replace => {
+                let row = query.get_set_values()?;
+                self.insert(Self::table_name(query)?, row.clone());
+                Ok(exec_result(query, vec![row]))
+            }
+            QueryType::Update => Ok(exec_result(query, self.update(query)?)),
+            QueryType::Delete => Ok(exec_result(query, self.delete(query)?)),
+            QueryType::Expression(_) => {
+                Err(anyhow!("MemoryDataSource can't execute SQL expressions"))
+            }
+        }
+    }
+
+    async fn query_insert(&self, query: &Query, rows: Vec<Vec<Value>>) -> Result<()> {
+        let table = Self::table_name(query)?;
+        let fields = query.get_field_names();
+        for row in rows {
+            self.insert(table, fields.iter().cloned().zip(row).collect());
+        }
+        Ok(())
+    }
+
+    async fn query_one(&self, query: &Query) -> Result<Value> {
+        let Some(row) = self.fetch(query)?.into_iter().next() else {
+            return Err(anyhow!("No rows for query_one"));
+        };
+        let Some((_, res)) = row.into_iter().next() else {
+            return Err(anyhow!("No cells in a first row of query_one"));
+        };
+        Ok(res)
+    }
+
+    async fn query_row(&self, query: &Query) -> Result<Map<String, Value>> {
+        let Some(row) = self.fetch(query)?.into_iter().next() else {
+            return Err(anyhow!("No rows for query_row"));
+        };
+        Ok(row)
+    }
+
+    async fn query_col(&self, query: &Query) -> Result<Vec<Value>> {
+        Ok(self
+            .fetch(query)?
+            .into_iter()
+            .filter_map(|row| Some(row.into_iter().next()?.1))
+            .collect())
+    }
+
+    async fn query_stream(
+        &self,
+        query: &Query,
+    ) -> Result<BoxStream<'static, Result<Map<String, Value>>>> {
+        let rows = self.fetch(query)?;
+        Ok(stream::iter(rows.into_iter().map(Ok)).boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+    struct Product {
+        name: String,
+        price: i64,
+    }
+    impl Entity for Product {}
+
+    fn rows(value: Value) -> Rows {
+        value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row.as_object().unwrap().clone())
+            .collect()
+    }
+
+    fn products() -> (MemoryDataSource, Table<MemoryDataSource, Product>) {
+        let memory = MemoryDataSource::new().with_table(
+            "product",
+            rows(json!([
+                {"id": 1, "name": "Cake", "price": 12},
+                {"id": 2, "name": "Pie", "price": 8},
+                {"id": 3, "name": "Tart", "price": 10},
+            ])),
+        );
+        let products = Table::new_with_entity("product", memory.clone())
+            .with_id_column("id")
+            .with_column("name")
+            .with_column("price");
+        (memory, products)
+    }
+
+    #[tokio::test]
+    async fn test_fetch() {
+        let (memory, products) = products();
+        let price = products.get_column("price").unwrap();
+        let cheap = products.clone().with_condition(price.lt(11));
+
+        let query = cheap
+            .get_select_query()
+            .with_order_by(expr!("price DESC"))
+            .with_limit(1);
+        let result = memory.query_fetch(&query).await.unwrap();
+        assert_eq!(
+            Value::Array(result.into_iter().map(Value::Object).collect()),
+            json!([{"id": 3, "name": "Tart", "price": 10}])
+        );
+
+        let names: Vec<Product> = cheap.get().await.unwrap();
+        assert_eq!(names.len(), 2);
+
+        let query = cheap.get_select_query();
+        assert!(memory
+            .query_fetch(&query.clone().with_limit(-1))
+            .await
+            .is_err());
+        assert!(memory.query_fetch(&query.with_skip(-1)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write() {
+        let (memory, products) = products();
+        products
+            .insert(Product {
+                name: "Scone".to_string(),
+                price: 3,
+            })
+            .await
+            .unwrap();
+        let updated = products
+            .clone()
+            .with_id(2.into())
+            .update(|product| product.price = 9)
+            .await
+            .unwrap();
+        assert_eq!(updated.rows_affected, 1);
+        let price = products.get_column("price").unwrap();
+        let deleted = products
+            .clone()
+            .with_condition(price.gt(10))
+            .delete()
+            .await
+            .unwrap();
+        assert_eq!(deleted.rows_affected, 1);
+        let deleted = products.entry(42.into()).delete().await.unwrap();
+        assert_eq!(deleted.rows_affected, 0);
+
+        assert_eq!(
+            Value::Array(
+                memory
+                    .rows("product")
+                    .unwrap()
+                    .into_iter()
+                    .map(Value::Object)
+                    .collect()
+            ),
+            json!([
+                {"id": 2, "name": "Pie", "price": 9},
+                {"id": 3, "name": "Tart", "price": 10},
+                {"name": "Scone", "price": 3},
+            ])
+        );
+    }
+}
@@ -1 +1,24 @@
+pub mod associated_query;
+#[cfg(feature = "clickhouse")]
+pub mod clickhouse;
+#[cfg(feature = "postgres")]
+mod connect;
+#[cfg(feature = "csv")]
+pub mod csv;
+#[cfg(all(feature = "postgres", feature = "chrono"))]
+pub mod datetime;
+#[cfg(feature = "duckdb")]
+pub mod duckdb;
+#[cfg(feature = "postgres")]
+mod instrument;
+pub mod memory;
+#[cfg(feature = "mongodb")]
+pub mod mongo;
+#[cfg(feature = "mssql")]
+pub mod mssql;
+#[cfg(feature = "postgres")]
+mod observer;
+#[cfg(feature = "postgres")]
 pub mod postgres;
+#[cfg(feature = "rest")]
+pub mod rest;
@@ -0,0 +1,421 @@
+//! MongoDB data source. Enabled with `mongodb` feature.
+//!
+//! ```
+//! let mongo = Mongo::connect("mongodb://localhost:27017", "shop").await?;
+//!
+//! let products = Table::new("product", mongo)
+//!     .with_id_column("_id")
+//!     .with_column("name")
+//!     .with_column("price");
+//! let cheap = products.with_condition(products.get_column("price").unwrap().lt(10));
+//! // db.product.find({ "price": { "$lt": 10 } }, { "_id": 1, "name": 1, "price": 1 })
+//! ```
+//!
+//! Queries are not rendered into SQL. Instead table name, conditions, order,
+//! skip and limit are translated into a `find()` call. Conditions must compare
+//! a field with a value (`=`, `!=`, `>`, `>=`, `<`, `<=`, `IN`, `NOT IN`, `LIKE`),
+//! which includes conditions created by [`AssociatedQuery::glue()`] for queries
+//! of other data sources.
+//!
+//! Values of `_id` field, which look like an ObjectId, are converted into one,
+//! and ObjectIds are returned as hex strings.
+//!
+//! [`AssociatedQuery::glue()`]: crate::datasource::associated_query::AssociatedQuery
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use futures::stream::{BoxStream, StreamExt, TryStreamExt};
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use mongodb::{Client, Collection, Database};
+use serde_json::{Map, Number, Value};
+
+use crate::sql::dialect::{Dialect, PostgresDialect};
+use crate::sql::query::{
+    like_to_regex, Direction, Filter, FilterOperation, QuerySource, QueryType, SqlQuery,
+};
+use crate::sql::Query;
+use crate::traits::datasource::{DataSource, ExecResult};
+
+#[derive(Clone, Debug)]
+pub struct Mongo {
+    client: Arc<Client>,
+    database: Database,
+}
+
+/// Mongo is equal to its clones, which use the same client and database.
+impl PartialEq for Mongo {
+    fn eq(&self, other: &Mongo) -> bool {
+        Arc::ptr_eq(&self.client, &other.client) && self.database.name() == other.database.name()
+    }
+}
+
+impl Mongo {
+    pub fn new(database: Database) -> Mongo {
+        Mongo {
+            client: Arc::new(database.client().clone()),
+            database,
+        }
+    }
+
+    pub async fn connect(uri: &str, database: &str) -> Result<Mongo> {
+        let client = Client::with_uri_str(uri)
+            .await
+            .context("Unable to connect to MongoDB")?;
+        Ok(Mongo::new(client.database(database)))
+    }
+
+    fn collection(&self, query: &Query) -> Result<Collection<Document>> {
+        match query.get_source() {
+            QuerySource::Table(table, _) => Ok(self.database.collection(table)),
+            _ => Err(anyhow!("Mongo can only query collections")),
+        }
+    }
+
+    /// Translates query conditions into a filter document:
+    /// `{ "$and": [{ "age": { "$gt": 18 } }, { "role": { "$in": ["admin", "owner"] } }] }`
+    fn filter_document(query: &Query) -> Result<Document> {
+        let mut filters = query
+            .get_filters()?
+            .into_iter()
+            .map(|filter| Self::filter_condition(&filter))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(match filters.len() {
+            0 => Document::new(),
+            1 => filters.remove(0),
+            _ => doc! { "$and": filters },
+        })
+    }
+
+    fn filter_condition(filter: &Filter) -> Result<Document> {
+        let value = Self::to_bson(&filter.field, &filter.value);
+        let operator = match filter.operation {
+            // values, which are documents, must be compared as is and not treated
+            // as operators, such as `{ "$ne": null }`
+            FilterOperation::Eq => "$eq",
+            FilterOperation::Ne => "$ne",
+            FilterOperation::Gt => "$gt",
+            FilterOperation::Gte => "$gte",
+            FilterOperation::Lt => "$lt",
+            FilterOperation::Lte => "$lte",
+            FilterOperation::In => "$in",
+            FilterOperation::NotIn => "$nin",
+            FilterOperation::Like => {
+                let Value::String(pattern) = &filter.value else {
+                    return Err(anyhow!(
+                        "LIKE pattern for {} must be a string",
+                        filter.field
+                    ));
+                };
+                return Ok(doc! { &filter.field: { "$regex": like_to_regex(pattern) } });
+            }
+        };
+        Ok(doc! { &filter.field: { operator: value } })
+    }
+
+    fn sort_document(query: &Query) -> Result<Document> {
+        Ok(query
+            .get_order()?
+            .into_iter()
+            .map(|(field, direction)| {
+                let order = match direction {
+                    Direction::Asc => 1,
+                    Direction::Desc => -1,
+                };
+                (field, Bson::Int32(order))
+            })
+            .collect())
+    }
+
+    fn projection_document(query: &Query) -> Option<Document> {
+        let fields = query.get_field_names();
+        if fields.is_empty() {
+            return None;
+        }
+        let mut projection: Document = fields
+            .into_iter()
+            .map(|field| (field, Bson::Int32(1)))
+            .collect();
+        // _id is returned, unless excluded explicitly
+        projection
+            .entry("_id".to_string())
+            .or_insert(Bson::Int32(0));
+        Some(projection)
+    }
+
+    fn set_document(query: &Query) -> Result<Document> {
+        Ok(query
+            .get_set_values()?
+            .iter()
+            .map(|(field, value)| (field.clone(), Self::to_bson(field, value)))
+            .collect())
+    }
+
+    fn to_bson(field: &str, value: &Value) -> Bson {
+        match value {
+            Value::Null => Bson::Null,
+            Value::Bool(b) => Bson::Boolean(*b),
+            Value::Number(n) => match n.as_i64() {
+                Some(n) => Bson::Int64(n),
+                None => Bson::Double(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            Value::String(s) if field == "_id" => match ObjectId::parse_str(s) {
+                Ok(oid) => Bson::ObjectId(oid),
+                Err(_) => Bson::String(s.clone()),
+            },
+            Value::String(s) => Bson::String(s.clone()),
+            Value::Array(values) => {
+                Bson::Array(values.iter().map(|v| Self::to_bson(field, v)).collect())
+            }
+            Value::Object(object) => Bson::Document(
+                object
+                    .iter()
+                    .map(|(k, v)| (k.clone(), Self::to_bson(k, v)))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn from_bson(value: Bson) -> Value {
+        match value {
+            Bson::ObjectId(oid) => Value::String(oid.to_hex()),
+            Bson::DateTime(dt) => match dt.try_to_rfc3339_string() {
+                Ok(dt) => Value::String(dt),
+                Err(_) => Bson::DateTime(dt).into_relaxed_extjson(),
+            },
+            Bson::Decimal128(d) => match d.to_string().parse::<Number>() {
+                Ok(n) => Value::Number(n),
+                Err(_) => Value::String(d.to_string()),
+            },
+            Bson::Array(values) => Value::Array(values.into_iter().map(Self::from_bson).collect()),
+            Bson::Document(document) => Value::Object(Self::from_document(document)),
+            value => value.into_relaxed_extjson(),
+        }
+    }
+
+    fn from_document(document: Document) -> Map<String, Value> {
+        document
+            .into_iter()
+            .map(|(k, v)| (k, Self::from_bson(v)))
+            .collect()
+    }
+
+    async fn find(&self, query: &Query) -> Result<BoxStream<'static, Result<Map<String, Value>>>> {
+        let collection = self.collection(query)?;
+        let mut find = collection
+            .find(Self::filter_document(query)?)
+            .sort(Self::sort_document(query)?);
+        if let Some(projection) = Self::projection_document(query) {
+            find = find.projection(projection);
+        }
+        if let Some(skip) = query.get_skip() {
+            let skip = u64::try_from(skip)
+                .map_err(|_| anyhow!("Skip must not be negative, got {}", skip))?;
+            find = find.skip(skip);
+        }
+        if let Some(limit) = query.get_limit() {
+            find = find.limit(limit);
+        }
+
+        let cursor = find.await?;
+        Ok(cursor
+            .map_ok(Self::from_document)
+            .map_err(anyhow::Error::from)
+            .boxed())
+    }
+}
+
+impl DataSource for Mongo {
+    fn dialect(&self) -> Arc<dyn Dialect> {
+        Arc::new(PostgresDialect)
+    }
+
+    async fn query_fetch(&self, query: &Query) -> Result<Vec<Map<String, Value>>> {
+        self.find(query).await?.try_collect().await
+    }
+
+    async fn query_exec(&self, query: &Query) -> Result<ExecResult> {
+        let collection = self.collection(query)?;
+        match query.get_type() {
+            QueryType::Select => {
+                let returned: Vec<_> = self.find(query).await?.try_collect().await?;
+                Ok(ExecResult {
+                    rows_affected: returned.len() as u64,
+                    returned,
+                })
+            }
+            QueryType::Insert | QueryType::Replace => {
+                let result = collection.insert_one(Self::set_document(query)?).await?;
+                let mut row = Map::new();
+                row.insert("_id".to_string(), Self::from_bson(result.inserted_id));
+                Ok(ExecResult {
+                    rows_affected: 1,
+                    returned: vec![row],
+                })
+            }
+            QueryType::Update => {
+                let result = collection
+                    .update_many(
+                        Self::filter_document(query)?,
+                        doc! { "$set": Self::set_document(query)? },
+                    )
+                    .await?;
+                Ok(ExecResult {
+                    rows_affected: result.matched_count,
+                    returned: vec![],
+                })
+            }
+            QueryType::Delete => {
+                let result = collection
+                    .delete_many(Self::filter_document(query)?)
+                    .await?;
+                Ok(ExecResult {
+                    rows_affected: result.deleted_count,
+                    returned: vec![],
+                })
+            }
+            QueryType::Expression(_) => Err(anyhow!("Mongo can't execute SQL expressions")),
+        }
+    }
+
+    async fn query_insert(&self, query: &Query, rows: Vec<Vec<Value>>) -> Result<()> {
+        let fields = query.get_field_names();
+        let documents = rows.into_iter().map(|row| {
+            fields
+                .iter()
+                .zip(row)
+                .map(|(field, value)| (field.clone(), Self::to_bson(field, &value)))
+                .collect::<Document>()
+        });
+        self.collection(query)?.insert_many(documents).await?;
+        Ok(())
+    }
+
+    async fn query_one(&self, query: &Query) -> Result<Value> {
+        let Some(row) = self.find(query).await?.try_next().await? else {
+            return Err(anyhow!("No rows for query_one"));
+        };
+        let Some((_, res)) = row.into_iter().next() else {
+            return Err(anyhow!("No cells in a first row of query_one"));
+        };
+        Ok(res)
+    }
+
+    async fn query_row(&self, query: &Query) -> Result<Map<String, Value>> {
+        let Some(row) = self.find(query).await?.try_next().await? else {
+            return Err(anyhow!("No rows for query_row"));
+        };
+        Ok(row)
+    }
+
+    async fn query_col(&self, query: &Query) -> Result<Vec<Value>> {
+        Ok(self
+            .query_fetch(query)
+            .await?
+            .into_iter()
+            .filter_map(|row| Some(row.into_iter().next()?.1))
+            .collect())
+    }
+
+    async fn query_stream(
+        &self,
+        query: &Query,
+    ) -> Result<BoxStream<'static, Result<Map<String, Value>>>> {
+        self.find(query).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::expr;
+    use crate::sql::Expression;
+
+    #[tokio::test]
+    async fn test_eq_and_skip() {
+        // clients connect lazily, so no server is needed
+        let shop = Mongo::connect("mongodb://localhost:27017", "shop")
+            .await
+            .unwrap();
+        let other = Mongo::connect("mongodb://example.com:27017", "shop")
+            .await
+            .unwrap();
+        assert_eq!(shop, shop.clone());
+        assert_ne!(shop, other);
+        assert_ne!(shop, Mongo::new(shop.database.client().database("archive")));
+
+        let query = Query::new().with_table("product", None).with_skip(-1);
+        assert_eq!(
+            shop.query_fetch(&query).await.unwrap_err().to_string(),
+            "Skip must not be negative, got -1"
+        );
+    }
+
+    #[test]
+    fn test_filter_document() {
+        let query = Query::new()
+            .with_table("product", None)
+            .with_condition(expr!("(price > {})", 10))
+            .with_condition(expr!("(category IN ({}, {}))", "cake", "pie"))
+            .with_condition(expr!("(name LIKE {})", "Apple%"));
+        assert_eq!(
+            Mongo::filter_document(&query).unwrap(),
+            doc! { "$and": [
+                { "price": { "$gt": 10_i64 } },
+                { "category": { "$in": ["cake", "pie"] } },
+                { "name": { "$regex": "^Apple.*$" } },
+            ] }
+        );
+
+        let query = Query::new()
+            .with_table("product", None)
+            .with_condition(expr!("(_id = {})", "65f1c0a2b3d4e5f607182930"));
+        assert_eq!(
+            Mongo::filter_document(&query).unwrap(),
+            doc! { "_id": { "$eq": ObjectId::parse_str("65f1c0a2b3d4e5f607182930").unwrap() } }
+        );
+
+        let query = Query::new()
+            .with_table("user", None)
+            .with_condition(expr!("(password = {})", serde_json::json!({"$ne": null})));
+        assert_eq!(
+            Mongo::filter_document(&query).unwrap(),
+            doc! { "password": { "$eq": { "$ne": null } } }
+        );
+
+        let query = Query::new()
+            .with_table("product", None)
+            .with_condition(expr!("(price > cost)"));
+        assert!(Mongo::filter_document(&query).is_err());
+    }
+
+    #[test]
+    fn test_sort_and_projection() {
+        let query = Query::new()
+            .with_table("product", None)
+            .with_column_field("name")
+            .with_order_by(expr!("price DESC"));
+        assert_eq!(Mongo::sort_document(&query).unwrap(), doc! { "price": -1 });
+        assert_eq!(
+            Mongo::projection_document(&query).unwrap(),
+            doc! { "name": 1, "_id": 0 }
+        );
+    }
+
+    #[test]
+    fn test_from_bson() {
+        let oid = ObjectId::parse_str("65f1c0a2b3d4e5f607182930").unwrap();
+        let row = Mongo::from_document(doc! {
+            "_id": oid,
+            "name": "Pie",
+            "qty": 3,
+            "tags": ["sweet"],
+        });
+        assert_eq!(
+            Value::Object(row),
+            json!({"_id": "65f1c0a2b3d4e5f607182930", "name": "Pie", "qty": 3, "tags": ["sweet"]})
+        );
+    }
+}
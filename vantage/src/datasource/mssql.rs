@@ -0,0 +1,256 @@
+//! SQL Server data source, based on [`tiberius`]. Enabled with `mssql` feature.
+//!
+//! ```
+//! let mssql = Mssql::connect(
+//!     "server=tcp:localhost,1433;user=sa;password=...;TrustServerCertificate=true",
+//! )
+//! .await?;
+//!
+//! let products = Table::new("product", mssql)
+//!     .with_id_column("id")
+//!     .with_column("name");
+//! // SELECT TOP (10) id, name FROM product
+//! let first = products.get_select_query().with_limit(10);
+//! ```
+//!
+//! Queries are rendered with [`MssqlDialect`], which uses `@P1` placeholders,
+//! `[bracket]` quoting, `TOP`/`OFFSET .. FETCH` for pagination and
+//! `OUTPUT INSERTED.id` instead of `RETURNING`.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use futures::stream::{self, BoxStream, StreamExt};
+use serde_json::{json, Map, Number, Value};
+use tiberius::{Client, ColumnData, Config, FromSql};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
+
+use crate::sql::chunk::Chunk;
+use crate::sql::dialect::{Dialect, MssqlDialect};
+use crate::sql::query::{QueryReturning, QueryType, SqlQuery};
+use crate::sql::{Expression, Query};
+use crate::traits::datasource::{DataSource, ExecResult};
+
+pub type MssqlClient = Client<Compat<TcpStream>>;
+
+#[derive(Clone, Debug)]
+pub struct Mssql {
+    client: Arc<Mutex<MssqlClient>>,
+}
+
+/// Mssql is equal to its clones.
+impl PartialEq for Mssql {
+    fn eq(&self, other: &Mssql) -> bool {
+        Arc::ptr_eq(&self.client, &other.client)
+    }
+}
+
+impl Mssql {
+    pub fn new(client: MssqlClient) -> Mssql {
+        Mssql {
+            client: Arc::new(Mutex::new(client)),
+        }
+    }
+
+    /// Connects using ADO.NET connection string
+    pub async fn connect(connection_string: &str) -> Result<Mssql> {
+        let config = Config::from_ado_string(connection_string)?;
+        let tcp = TcpStream::connect(config.get_addr())
+            .await
+            .context("Unable to connect to SQL Server")?;
+        tcp.set_nodelay(true)?;
+        let client = Client::connect(config, tcp.compat_write()).await?;
+        Ok(Mssql::new(client))
+    }
+
+    fn bind(query: &Expression) -> tiberius::Query<'static> {
+        let mut result = tiberius::Query::new(query.sql_final_for(&MssqlDialect));
+        for param in query.params() {
+            match param.clone() {
+                Value::Null => result.bind(None as Option<&str>),
+                Value::Bool(b) => result.bind(b),
+                Value::Number(n) => match n.as_i64() {
+                    Some(n) => result.bind(n),
+                    None => result.bind(n.as_f64()),
+                },
+                Value::String(s) => result.bind(s),
+                value => result.bind(value.to_string()),
+            }
+        }
+        result
+    }
+
+    fn convert_value_fromsql(data: &ColumnData<'static>) -> Result<Value> {
+        Ok(match data {
+            ColumnData::U8(v) => json!(v),
+            ColumnData::I16(v) => json!(v),
+            ColumnData::I32(v) => json!(v),
+            ColumnData::I64(v) => json!(v),
+            ColumnData::F32(v) => json!(v),
+            ColumnData::F64(v) => json!(v),
+            ColumnData::Bit(v) => json!(v),
+            ColumnData::String(v) => json!(v.as_deref()),
+            ColumnData::Guid(v) => json!(v.map(|g| g.to_string())),
+            ColumnData::Binary(v) => json!(v.as_deref()),
+            ColumnData::Numeric(v) => match v {
+                // keep the exact value, as arbitrary_precision is enabled
+                Some(n) => Value::Number(n.to_string().parse::<Number>()?),
+                None => Value::Null,
+            },
+            ColumnData::Xml(v) => json!(v.as_ref().map(|x| x.to_string())),
+            ColumnData::Date(_) => json!(NaiveDate::from_sql(data)?.map(|d| d.to_string())),
+            ColumnData::Time(_) => json!(NaiveTime::from_sql(data)?.map(|t| t.to_string())),
+            ColumnData::DateTime(_) | ColumnData::SmallDateTime(_) | ColumnData::DateTime2(_) => {
+                json!(NaiveDateTime::from_sql(data)?.map(|dt| dt.to_string()))
+            }
+            ColumnData::DateTimeOffset(_) => {
+                json!(DateTime::<Utc>::from_sql(data)?.map(|dt| dt.to_rfc3339()))
+            }
+        })
+    }
+
+    async fn query_raw(&self, query: &Query) -> Result<Vec<Map<String, Value>>> {
+        let query_rendered = query.try_render_chunk()?;
+        let mut client = self.client.lock().await;
+
+        let rows = Self::bind(&query_rendered)
+            .query(&mut client)
+            .await
+            .with_context(|| anyhow!("Error in query {}", query_rendered.preview()))?
+            .into_first_result()
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let names = row
+                    .columns()
+                    .iter()
+                    .map(|c| c.name().to_string())
+                    .collect::<Vec<_>>();
+                names
+                    .into_iter()
+                    .zip(row)
+                    .map(|(name, data)| Ok((name, Self::convert_value_fromsql(&data)?)))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl DataSource for Mssql {
+    fn dialect(&self) -> Arc<dyn Dialect> {
+        Arc::new(MssqlDialect)
+    }
+
+    async fn query_fetch(&self, query: &Query) -> Result<Vec<Map<String, Value>>> {
+        self.query_raw(query).await
+    }
+
+    async fn query_exec(&self, query: &Query) -> Result<ExecResult> {
+        if matches!(query.get_returning(), QueryReturning::None)
+            && matches!(
+                query.get_type(),
+                QueryType::Insert | QueryType::Update | QueryType::Delete
+            )
+        {
+            let query_rendered = query.try_render_chunk()?;
+            let mut client = self.client.lock().await;
+            let result = Self::bind(&query_rendered)
+                .execute(&mut client)
+                .await
+                .with_context(|| anyhow!("Error in query {}", query_rendered.preview()))?;
+            return Ok(ExecResult {
+                rows_affected: result.total(),
+                returned: vec![],
+            });
+        }
+        let returned = self.query_raw(query).await?;
+        Ok(ExecResult {
+            rows_affected: returned.len() as u64,
+            returned,
+        })
+    }
+
+    async fn query_insert(&self, _query: &Query, _rows: Vec<Vec<Value>>) -> Result<()> {
+        Err(anyhow!("query_insert is not supported by Mssql"))
+    }
+
+    async fn query_one(&self, query: &Query) -> Result<Value> {
+        let Some(row) = self.query_raw(query).await?.into_iter().next() else {
+            return Err(anyhow!("No rows for query_one"));
+        };
+        let Some((_, res)) = row.into_iter().next() else {
+            return Err(anyhow!("No cells in a first row of query_one"));
+        };
+        Ok(res)
+    }
+
+    async fn query_row(&self, query: &Query) -> Result<Map<String, Value>> {
+        let Some(row) = self.query_raw(query).await?.into_iter().next() else {
+            return Err(anyhow!("No rows for query_row"));
+        };
+        Ok(row)
+    }
+
+    async fn query_col(&self, query: &Query) -> Result<Vec<Value>> {
+        Ok(self
+            .query_raw(query)
+            .await?
+            .into_iter()
+            .filter_map(|row| Some(row.into_iter().next()?.1))
+            .collect())
+    }
+
+    /// Rows are fetched before the stream is returned, as the connection can't be
+    /// shared while the result is being read.
+    async fn query_stream(
+        &self,
+        query: &Query,
+    ) -> Result<BoxStream<'static, Result<Map<String, Value>>>> {
+        let rows = self.query_raw(query).await?;
+        Ok(stream::iter(rows.into_iter().map(Ok)).boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use tiberius::numeric::Numeric;
+
+    use super::*;
+    use crate::expr;
+
+    #[test]
+    fn test_convert_value_fromsql() {
+        assert_eq!(
+            Mssql::convert_value_fromsql(&ColumnData::I32(Some(5))).unwrap(),
+            json!(5)
+        );
+        assert_eq!(
+            Mssql::convert_value_fromsql(&ColumnData::String(Some(Cow::from("Pie")))).unwrap(),
+            json!("Pie")
+        );
+        assert_eq!(
+            Mssql::convert_value_fromsql(&ColumnData::Numeric(Some(Numeric::new_with_scale(
+                1250, 2
+            ))))
+            .unwrap()
+            .to_string(),
+            "12.50"
+        );
+        assert_eq!(
+            Mssql::convert_value_fromsql(&ColumnData::Bit(None)).unwrap(),
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn test_bind() {
+        let query = Mssql::bind(&expr!("SELECT {} + {}", 1, "a"));
+        assert!(format!("{:?}", query).contains("SELECT @P1 + @P2"));
+    }
+}
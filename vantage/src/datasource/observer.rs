@@ -0,0 +1,90 @@
+//! Observing queries executed by a [`Postgres`] data source, for collecting
+//! metrics or finding slow and repetitive (N+1) queries:
+//!
+//! ```
+//! let postgres = Postgres::new(client)
+//!     .with_observer(SlowQueryLog::new(Duration::from_millis(200)));
+//! ```
+//!
+//! [`Postgres`]: super::postgres::Postgres
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// Information about an executed query, passed to [`QueryObserver`].
+#[derive(Debug)]
+pub struct QueryEvent<'a> {
+    /// SQL with placeholders, without parameter values.
+    pub sql: &'a str,
+    /// Number of parameters.
+    pub params: usize,
+    pub duration: Duration,
+    /// Number of returned or affected rows, if known.
+    pub rows: Option<u64>,
+    /// Error, if the query has failed.
+    pub error: Option<&'a anyhow::Error>,
+}
+
+/// Receives an event for each query executed by the data source, successful or not.
+pub trait QueryObserver: Debug + Send + Sync {
+    fn on_query(&self, event: &QueryEvent);
+}
+
+/// Logs queries, which take longer than the threshold, with `log::warn!()`.
+#[derive(Debug, Clone)]
+pub struct SlowQueryLog {
+    threshold: Duration,
+}
+
+impl SlowQueryLog {
+    pub fn new(threshold: Duration) -> Self {
+        SlowQueryLog { threshold }
+    }
+
+    fn message(&self, event: &QueryEvent) -> Option<String> {
+        if event.duration < self.threshold {
+            return None;
+        }
+        Some(format!(
+            "Slow query ({} ms, {} rows): {}",
+            event.duration.as_millis(),
+            event
+                .rows
+                .map(|rows| rows.to_string())
+                .unwrap_or_else(|| "?".to_string()),
+            event.sql
+        ))
+    }
+}
+
+impl QueryObserver for SlowQueryLog {
+    fn on_query(&self, event: &QueryEvent) {
+        if let Some(message) = self.message(event) {
+            log::warn!("{}", message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_query_log() {
+        let log = SlowQueryLog::new(Duration::from_millis(100));
+        let mut event = QueryEvent {
+            sql: "SELECT id FROM client WHERE (id = $1)",
+            params: 1,
+            duration: Duration::from_millis(20),
+            rows: Some(1),
+            error: None,
+        };
+        assert_eq!(log.message(&event), None);
+
+        event.duration = Duration::from_millis(250);
+        assert_eq!(
+            log.message(&event).unwrap(),
+            "Slow query (250 ms, 1 rows): SELECT id FROM client WHERE (id = $1)"
+        );
+    }
+}
@@ -1,30 +1,212 @@
 #![allow(dead_code)]
 
-use std::ops::{Deref, DerefMut};
-use std::sync::Arc;
-
-use crate::dataset::ReadableDataSet;
-use crate::prelude::{EmptyEntity, Entity};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+pub use super::connect::ConnectOptions;
+#[cfg(feature = "chrono")]
+use super::datetime;
+use super::instrument::{record_query, record_result};
+pub use super::observer::{QueryEvent, QueryObserver, SlowQueryLog};
+use crate::datasource::associated_query::AssociatedQuery;
+use crate::expr;
+use crate::prelude::Entity;
+use crate::runtime::TaskKey;
 use crate::sql::chunk::Chunk;
-use crate::sql::expression::{Expression, ExpressionArc};
-use crate::sql::query::SqlQuery;
+use crate::sql::dialect::{Dialect, PostgresDialect};
+use crate::sql::expression::Expression;
+use crate::sql::query::{QueryReturning, QueryType, SqlQuery};
+use crate::sql::table::{ColumnSchema, ForeignKeySchema, TableSchema};
 use crate::sql::Query;
-use crate::traits::datasource::DataSource;
+use crate::traits::datasource::{DataSource, ExecResult};
 use anyhow::Context;
 use anyhow::{anyhow, Result};
-use futures::{pin_mut, TryStreamExt};
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::stream::BoxStream;
+use futures::{pin_mut, SinkExt, Stream, StreamExt, TryStreamExt};
 use indexmap::IndexMap;
 use rust_decimal::Decimal;
 use serde_json::json;
 use serde_json::Map;
 use serde_json::Value;
-use tokio_postgres::types::ToSql;
-use tokio_postgres::Client;
+use tokio_postgres::types::{to_sql_checked, IsNull, ToSql, Type, WrongType};
+pub use tokio_postgres::Notification;
 use tokio_postgres::Row;
+use tokio_postgres::{AsyncMessage, CancelToken, Client, NoTls};
+
+/// NULL parameter, which is accepted for any column type.
+#[derive(Debug)]
+struct Null;
+
+impl ToSql for Null {
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        _out: &mut BytesMut,
+    ) -> std::result::Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(IsNull::Yes)
+    }
+
+    fn accepts(_ty: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+/// Converts JSON array, which elements are all of the same type, into a Postgres
+/// array, so that a list of values can be bound as a single parameter:
+/// `id = ANY($1)`. NULL elements are allowed. Returns None for empty arrays and
+/// arrays of mixed or nested values.
+fn convert_homogeneous_array(values: &[Value]) -> Option<Box<dyn ToSql + Sync + Send>> {
+    let items = values.iter().filter(|v| !v.is_null());
+    let first = items.clone().next()?;
+    Some(match first {
+        Value::Number(_) if items.clone().all(|v| v.is_i64()) => {
+            Box::new(values.iter().map(Value::as_i64).collect::<Vec<_>>())
+        }
+        Value::Number(_) if items.clone().all(Value::is_number) => {
+            Box::new(values.iter().map(Value::as_f64).collect::<Vec<_>>())
+        }
+        Value::String(_) if items.clone().all(Value::is_string) => Box::new(
+            values
+                .iter()
+                .map(|v| v.as_str().map(str::to_string))
+                .collect::<Vec<_>>(),
+        ),
+        Value::Bool(_) if items.clone().all(Value::is_boolean) => {
+            Box::new(values.iter().map(Value::as_bool).collect::<Vec<_>>())
+        }
+        _ => return None,
+    })
+}
+
+/// Statements applying [`settings`](Postgres::with_settings) to the current
+/// transaction.
+fn settings_sql(settings: &[(String, String)]) -> String {
+    settings
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "{};",
+                expr!("SELECT set_config({}, {}, true)", name, value).preview()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Connections opened in addition to the shared client. Transactions are
+/// executed on a connection of their own, as queries of other tasks would
+/// otherwise become a part of them.
+#[derive(Default)]
+struct Connections {
+    /// Connections, which can be reused
+    idle: Mutex<Vec<Arc<Client>>>,
+    /// Connections with an open transaction, by the task which started it
+    transactions: Mutex<HashMap<TaskKey, Transaction>>,
+}
+
+struct Transaction {
+    client: Arc<Client>,
+    /// Transactions started within this one, which became savepoints
+    savepoints: usize,
+}
+
+impl std::fmt::Debug for Connections {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Connections")
+            .field("idle", &self.idle.lock().unwrap().len())
+            .field("transactions", &self.transactions.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl Connections {
+    /// Returns connection to the pool, unless it was closed.
+    fn release(&self, client: Arc<Client>) {
+        if !client.is_closed() {
+            self.idle.lock().unwrap().push(client);
+        }
+    }
+}
+
+/// Client, which executes a query.
+#[derive(Clone)]
+enum Connection<'a> {
+    Shared(&'a Client),
+    Dedicated(Arc<Client>),
+}
+
+/// Cancels the query on the server, unless disarmed once the query completes.
+/// Dropping the future of a query only stops waiting for it, while the server
+/// would keep running it.
+struct CancelGuard(Option<CancelToken>);
+
+impl CancelGuard {
+    fn disarm(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        if let Some(token) = self.0.take() {
+            crate::runtime::spawn(async move {
+                let _ = token.cancel_query(NoTls).await;
+            });
+        }
+    }
+}
+
+impl std::ops::Deref for Connection<'_> {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        match self {
+            Connection::Shared(client) => client,
+            Connection::Dedicated(client) => client,
+        }
+    }
+}
+
+/// Error for a value, which does not fit into the parameter type `ty`, such as
+/// 70000 bound to an `int2` column.
+fn wrong_type<T>(value: &Value, ty: &Type) -> anyhow::Error {
+    anyhow::Error::new(WrongType::new::<T>(ty.clone()))
+        .context(format!("Unable to bind {} as {}", value, ty))
+}
+
+/// Converts elements of an array parameter with `convert`. NULL elements are kept,
+/// other elements must be converted.
+fn convert_elements<T>(
+    values: &[Value],
+    ty: &Type,
+    convert: impl Fn(&Value) -> Option<T>,
+) -> Result<Vec<Option<T>>> {
+    values
+        .iter()
+        .map(|v| match v {
+            Value::Null => Ok(None),
+            v => convert(v).map(Some).ok_or_else(|| wrong_type::<T>(v, ty)),
+        })
+        .collect()
+}
 
 #[derive(Clone, Debug)]
 pub struct Postgres {
     client: Arc<Box<Client>>,
+    observers: Vec<Arc<dyn QueryObserver>>,
+    /// Connection string and options, when created with [`Postgres::connect()`]
+    server: Option<(String, ConnectOptions)>,
+    /// Configuration parameters set for each transaction, see [`Postgres::with_settings()`]
+    settings: Vec<(String, String)>,
+    /// Shared with the clones
+    connections: Arc<Connections>,
+    /// Queries are cancelled when abandoned, see [`DataSource::cancellable()`]
+    cancellable: bool,
 }
 
 /// Postgres is equal to its clones.
@@ -36,18 +218,266 @@ impl PartialEq for Postgres {
 
 impl Postgres {
     pub fn new(client: Arc<Box<Client>>) -> Postgres {
-        Postgres { client }
+        Postgres {
+            client,
+            observers: vec![],
+            server: None,
+            settings: vec![],
+            connections: Arc::new(Connections::default()),
+            cancellable: false,
+        }
+    }
+
+    /// Connects to `url`, retrying while the server is unavailable. See
+    /// [`ConnectOptions`] for retries and timeouts.
+    pub async fn connect(url: &str, options: ConnectOptions) -> Result<Postgres> {
+        let client = super::connect::connect(url, &options).await?;
+        Ok(Postgres {
+            server: Some((url.to_string(), options)),
+            ..Postgres::new(Arc::new(Box::new(client)))
+        })
+    }
+
+    /// Client for the queries of the current task: the connection of a transaction,
+    /// which the task has started, or the shared client.
+    fn connection(&self) -> Connection<'_> {
+        let transactions = self.connections.transactions.lock().unwrap();
+        match transactions.get(&crate::runtime::task_key()) {
+            Some(transaction) => Connection::Dedicated(transaction.client.clone()),
+            None => Connection::Shared(&self.client),
+        }
+    }
+
+    fn in_transaction(&self) -> bool {
+        let transactions = self.connections.transactions.lock().unwrap();
+        transactions.contains_key(&crate::runtime::task_key())
+    }
+
+    /// Takes an idle connection or opens a new one.
+    async fn dedicated_connection(&self) -> Result<Arc<Client>> {
+        loop {
+            let idle = self.connections.idle.lock().unwrap().pop();
+            match idle {
+                Some(client) if client.is_closed() => continue,
+                Some(client) => return Ok(client),
+                None => break,
+            }
+        }
+        let Some((url, options)) = &self.server else {
+            return Err(anyhow!(
+                "Transactions and settings require Postgres created with Postgres::connect()"
+            ));
+        };
+        let client = super::connect::connect(url, options).await?;
+        Ok(Arc::new(client))
     }
 
+    /// Finishes the innermost transaction of the current task. A savepoint is
+    /// released or rolled back to, otherwise the transaction is committed or
+    /// rolled back and its connection is returned to the pool.
+    async fn end_transaction(&self, commit: bool) -> Result<()> {
+        let (client, savepoint) = {
+            let mut transactions = self.connections.transactions.lock().unwrap();
+            let task = crate::runtime::task_key();
+            let Some(transaction) = transactions.get_mut(&task) else {
+                return Err(anyhow!("No transaction was started by the current task"));
+            };
+            match transaction.savepoints {
+                0 => (transactions.remove(&task).unwrap().client, None),
+                savepoints => {
+                    transaction.savepoints -= 1;
+                    (transaction.client.clone(), Some(savepoints))
+                }
+            }
+        };
+
+        let statement = match (savepoint, commit) {
+            (Some(n), true) => format!("RELEASE SAVEPOINT sp_{}", n),
+            (Some(n), false) => format!("ROLLBACK TO SAVEPOINT sp_{n}; RELEASE SAVEPOINT sp_{n}"),
+            (None, true) => "COMMIT".to_string(),
+            (None, false) => "ROLLBACK".to_string(),
+        };
+        client.batch_execute(&statement).await?;
+        if savepoint.is_none() {
+            self.connections.release(client);
+        }
+        Ok(())
+    }
+
+    /// Notifications sent to `channel` with `NOTIFY` or `pg_notify()`:
+    ///
+    /// ```
+    /// let mut notifications = postgres.listen("cache").await?;
+    /// while let Some(notification) = notifications.next().await {
+    ///     cache.invalidate(notification?.payload());
+    /// }
+    /// ```
+    ///
+    /// Listening requires a dedicated connection, so the data source must be
+    /// created with [`Postgres::connect()`]. The connection is closed when the
+    /// stream is dropped. The channel name is quoted, so it is case-sensitive, the
+    /// same as with `pg_notify()`.
+    pub async fn listen(&self, channel: &str) -> Result<BoxStream<'static, Result<Notification>>> {
+        let Some((url, options)) = &self.server else {
+            return Err(anyhow!(
+                "listen() requires Postgres created with Postgres::connect()"
+            ));
+        };
+        let (client, mut connection) = super::connect::open(url, options).await?;
+
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+        crate::runtime::spawn(async move {
+            let mut messages = futures::stream::poll_fn(|cx| connection.poll_message(cx));
+            while let Some(message) = messages.next().await {
+                let notification = match message {
+                    Ok(AsyncMessage::Notification(notification)) => Ok(notification),
+                    Ok(_) => continue,
+                    Err(e) => Err(e.into()),
+                };
+                if sender.unbounded_send(notification).is_err() {
+                    break;
+                }
+            }
+        });
+
+        client
+            .batch_execute(&format!("LISTEN {}", self.escape(channel.to_string())))
+            .await?;
+        // dropping the client closes the connection, so it lives in the stream
+        Ok(receiver
+            .map(move |notification| {
+                let _client = &client;
+                notification
+            })
+            .boxed())
+    }
+
+    /// Add an observer, which is notified about every executed query. See
+    /// [`SlowQueryLog`] for an observer logging slow queries.
+    pub fn with_observer(mut self, observer: impl QueryObserver + 'static) -> Self {
+        self.add_observer(observer);
+        self
+    }
+
+    pub fn add_observer(&mut self, observer: impl QueryObserver + 'static) {
+        self.observers.push(Arc::new(observer));
+    }
+
+    /// Notify observers and the tracing span about an executed query.
+    fn observe(
+        &self,
+        query: &Expression,
+        started: Instant,
+        rows: Option<u64>,
+        error: Option<&anyhow::Error>,
+    ) {
+        if let Some(rows) = rows {
+            record_result(rows, started);
+        }
+        if self.observers.is_empty() {
+            return;
+        }
+        let sql = query.sql_final();
+        let event = QueryEvent {
+            sql: &sql,
+            params: query.params().len(),
+            duration: started.elapsed(),
+            rows,
+            error,
+        };
+        for observer in &self.observers {
+            observer.on_query(&event);
+        }
+    }
+
+    /// Set configuration parameters with `SET LOCAL` for every transaction, so that
+    /// row-level security policies can see who is making the request:
+    ///
+    /// ```
+    /// let postgres = postgres().with_settings([("app.current_user", user_id)]);
+    /// let orders = Order::table_with(postgres).get().await?;
+    /// // policy: USING (user_id = current_setting('app.current_user')::int)
+    /// ```
+    ///
+    /// Queries executed outside of [`begin_transaction()`] are wrapped into a
+    /// transaction of their own, on a dedicated connection, so that the settings
+    /// are not seen by concurrent queries of other clones. Such connections are
+    /// reused and require the data source to be created with [`Postgres::connect()`].
+    ///
+    /// [`begin_transaction()`]: DataSource::begin_transaction
+    pub fn with_settings<K: Into<String>, V: ToString>(
+        mut self,
+        settings: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        for (name, value) in settings {
+            self.add_setting(name, value);
+        }
+        self
+    }
+
+    pub fn add_setting(&mut self, name: impl Into<String>, value: impl ToString) {
+        let name = name.into();
+        self.settings.retain(|(n, _)| *n != name);
+        self.settings.push((name, value.to_string()));
+    }
+
+    /// Runs `query` with the client of the current task. Unless a transaction is
+    /// already open, a dedicated connection is used for a transaction with the
+    /// settings applied, or for a query, which may be cancelled.
+    async fn with_connection<'a, V, F>(
+        &'a self,
+        query: impl FnOnce(Connection<'a>) -> F,
+    ) -> Result<V>
+    where
+        F: Future<Output = Result<V>>,
+    {
+        let dedicated = !self.in_transaction()
+            && (!self.settings.is_empty() || (self.cancellable && self.server.is_some()));
+        if !dedicated {
+            let client = self.connection();
+            // the shared client runs queries of other clones too
+            let guard = match &client {
+                Connection::Dedicated(client) if self.cancellable => {
+                    Some(CancelGuard(Some(client.cancel_token())))
+                }
+                _ => None,
+            };
+            let result = query(client).await;
+            if let Some(guard) = guard {
+                guard.disarm();
+            }
+            return result;
+        }
+
+        let client = self.dedicated_connection().await?;
+        let guard = CancelGuard(self.cancellable.then(|| client.cancel_token()));
+        if !self.settings.is_empty() {
+            client
+                .batch_execute(&format!("BEGIN; {}", settings_sql(&self.settings)))
+                .await?;
+        }
+        // if the query is abandoned, the connection is closed
+        let result = query(Connection::Dedicated(client.clone())).await;
+        guard.disarm();
+        if !self.settings.is_empty() {
+            client
+                .batch_execute(if result.is_ok() { "COMMIT" } else { "ROLLBACK" })
+                .await?;
+        }
+        self.connections.release(client);
+        result
+    }
+
+    /// Quotes an identifier, doubling quotes inside of it.
     pub fn escape(&self, expr: String) -> String {
-        format!("\"{}\"", expr)
+        format!("\"{}\"", expr.replace('"', "\"\""))
     }
 
     pub fn annotate_type(&self, expr: String, as_type: String) -> String {
         format!("{}::{}", expr, as_type)
     }
 
-    pub fn convert_value_tosql(&self, value: Value) -> Box<dyn ToSql + Sync> {
+    pub fn convert_value_tosql(&self, value: Value) -> Box<dyn ToSql + Sync + Send> {
         match value {
             Value::Null => Box::new(None as Option<bool>),
             Value::Bool(b) => Box::new(b),
@@ -59,17 +489,127 @@ impl Postgres {
                 }
             }
             Value::String(s) => Box::new(s),
-            Value::Array(a) => Box::new(serde_json::to_string(&a).unwrap()),
+            Value::Array(a) => match convert_homogeneous_array(&a) {
+                Some(array) => array,
+                None => Box::new(serde_json::to_string(&a).unwrap()),
+            },
             Value::Object(o) => Box::new(serde_json::to_string(&o).unwrap()),
         }
     }
 
+    /// Converts value into a type expected by the query parameter. Types are known
+    /// once the statement is prepared, either from the context or from an explicit
+    /// cast (`{}::int8`). Falls back to [`Postgres::convert_value_tosql()`] for
+    /// types which are not recognized.
+    pub fn convert_value_tosql_typed(
+        &self,
+        value: Value,
+        ty: &Type,
+    ) -> Result<Box<dyn ToSql + Sync + Send>> {
+        #[cfg(feature = "chrono")]
+        if let Value::String(s) = &value {
+            if let Some(value) = datetime::convert_value_tosql(s, ty) {
+                return Ok(value);
+            }
+        }
+        Ok(match (value, ty) {
+            (Value::Null, _) => Box::new(Null),
+            (Value::Number(n), &Type::INT2) if n.is_i64() => Box::new(
+                i16::try_from(n.as_i64().unwrap())
+                    .map_err(|_| wrong_type::<i16>(&Value::Number(n), ty))?,
+            ),
+            (Value::Number(n), &Type::INT4) if n.is_i64() => Box::new(
+                i32::try_from(n.as_i64().unwrap())
+                    .map_err(|_| wrong_type::<i32>(&Value::Number(n), ty))?,
+            ),
+            (Value::Number(n), &Type::INT8) if n.is_i64() => Box::new(n.as_i64().unwrap()),
+            (Value::Number(n), &Type::FLOAT4) => Box::new(n.as_f64().unwrap() as f32),
+            (Value::Number(n), &Type::FLOAT8) => Box::new(n.as_f64().unwrap()),
+            (Value::Number(n), &Type::NUMERIC) => match n.to_string().parse::<Decimal>() {
+                Ok(d) => Box::new(d),
+                Err(_) => self.convert_value_tosql(Value::Number(n)),
+            },
+            (Value::String(s), &Type::NUMERIC) => match s.parse::<Decimal>() {
+                Ok(d) => Box::new(d),
+                Err(_) => Box::new(s),
+            },
+            (Value::Number(n), &Type::TEXT | &Type::VARCHAR) => Box::new(n.to_string()),
+            (Value::Bool(b), &Type::TEXT | &Type::VARCHAR) => Box::new(b.to_string()),
+            (value, &Type::JSON | &Type::JSONB) => Box::new(value),
+            (Value::Array(a), &Type::BYTEA) => Box::new(
+                a.iter()
+                    .map(|b| {
+                        b.as_u64()
+                            .and_then(|b| u8::try_from(b).ok())
+                            .ok_or_else(|| wrong_type::<u8>(b, ty))
+                    })
+                    .collect::<Result<Vec<u8>>>()?,
+            ),
+            (Value::String(s), &Type::BYTEA) => Box::new(s.into_bytes()),
+            (Value::Array(a), ty) if ty.name().starts_with('_') => {
+                match self.convert_array_tosql(&a, ty)? {
+                    Some(array) => array,
+                    None => self.convert_value_tosql(Value::Array(a)),
+                }
+            }
+            // arrays stored in a text column are sent as JSON
+            (Value::Array(a), _) => Box::new(serde_json::to_string(&a).unwrap()),
+            (value, _) => self.convert_value_tosql(value),
+        })
+    }
+
+    /// Converts JSON array into a Postgres array of type `ty`, such as `int4[]`.
+    /// Elements which don't match the type result in error.
+    fn convert_array_tosql(
+        &self,
+        values: &[Value],
+        ty: &Type,
+    ) -> Result<Option<Box<dyn ToSql + Sync + Send>>> {
+        Ok(Some(match *ty {
+            Type::INT2_ARRAY => Box::new(convert_elements(values, ty, |v| {
+                i16::try_from(v.as_i64()?).ok()
+            })?),
+            Type::INT4_ARRAY => Box::new(convert_elements(values, ty, |v| {
+                i32::try_from(v.as_i64()?).ok()
+            })?),
+            Type::INT8_ARRAY => Box::new(convert_elements(values, ty, Value::as_i64)?),
+            Type::FLOAT4_ARRAY => Box::new(convert_elements(values, ty, |v| {
+                v.as_f64().map(|n| n as f32)
+            })?),
+            Type::FLOAT8_ARRAY => Box::new(convert_elements(values, ty, Value::as_f64)?),
+            Type::BOOL_ARRAY => Box::new(convert_elements(values, ty, Value::as_bool)?),
+            Type::NUMERIC_ARRAY => Box::new(convert_elements(values, ty, |v| match v {
+                Value::Number(n) => n.to_string().parse::<Decimal>().ok(),
+                Value::String(s) => s.parse::<Decimal>().ok(),
+                _ => None,
+            })?),
+            Type::TEXT_ARRAY | Type::VARCHAR_ARRAY => Box::new(
+                values
+                    .iter()
+                    .map(|v| match v {
+                        Value::Null => None,
+                        Value::String(s) => Some(s.clone()),
+                        v => Some(v.to_string()),
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            _ => return Ok(None),
+        }))
+    }
+
     pub fn convert_value_fromsql(&self, row: Row) -> Result<Value> {
         let mut json_map: IndexMap<String, Value> = IndexMap::new();
 
         for (i, col) in row.columns().iter().enumerate() {
             let name = col.name().to_string();
             let col_type = col.type_().name();
+
+            #[cfg(feature = "chrono")]
+            if let Some(value) = datetime::convert_value_fromsql(&row, i, col_type) {
+                json_map.insert(name, value);
+                continue;
+            }
+
             let value = match col_type {
                 "int4" => json!(row.get::<_, Option<i32>>(i)), // int4 as i32
                 "int8" => json!(row.get::<_, Option<i64>>(i)), // int8 as i64
@@ -78,12 +618,14 @@ impl Postgres {
                 "float4" => json!(row.get::<_, Option<f32>>(i)),              // float4 as f32
                 "float8" => json!(row.get::<_, Option<f64>>(i)),              // float8 as f64
                 "numeric" => json!(row.get::<_, Option<Decimal>>(i)),         // numeric as f64
-                // "date" => row
-                //     .get::<_, Option<chrono::NaiveDate>>(i)
-                //     .map(|d| json!(d.to_string())), // date as ISO8601 string
-                // "timestamp" => row
-                //     .get::<_, Option<chrono::NaiveDateTime>>(i)
-                //     .map(|dt| json!(dt.to_string())), // timestamp as ISO8601 string
+                "json" | "jsonb" => row.get::<_, Option<Value>>(i).unwrap_or(Value::Null),
+                "_int2" => json!(row.get::<_, Option<Vec<Option<i16>>>>(i)),
+                "_int4" => json!(row.get::<_, Option<Vec<Option<i32>>>>(i)),
+                "_int8" => json!(row.get::<_, Option<Vec<Option<i64>>>>(i)),
+                "_varchar" | "_text" => json!(row.get::<_, Option<Vec<Option<String>>>>(i)),
+                "_bool" => json!(row.get::<_, Option<Vec<Option<bool>>>>(i)),
+                "_float4" => json!(row.get::<_, Option<Vec<Option<f32>>>>(i)),
+                "_float8" => json!(row.get::<_, Option<Vec<Option<f64>>>>(i)),
                 _ => {
                     return Err(anyhow!(
                         "Unsupported type: {} for column {}",
@@ -104,114 +646,403 @@ impl Postgres {
     }
 
     pub async fn query_into_statement(&self, query: &Query) -> Result<tokio_postgres::Statement> {
-        let query_rendered = query.render_chunk();
-        self.client
+        let query_rendered = query.try_render_chunk()?;
+        self.connection()
             .prepare(&query_rendered.sql_final())
             .await
             .with_context(|| format!("Attempting to execute query {}", query_rendered.preview()))
     }
 
-    pub async fn query_raw(&self, query: &Query) -> Result<Vec<Value>> {
-        let query_rendered = query.render_chunk();
+    /// Prepares the query and converts its parameters into the types expected
+    /// by the statement.
+    async fn prepare_with_params(
+        &self,
+        client: &Client,
+        query_rendered: &Expression,
+    ) -> Result<(tokio_postgres::Statement, Vec<Box<dyn ToSql + Sync + Send>>)> {
+        record_query(query_rendered);
+        let statement = client
+            .prepare(&query_rendered.sql_final())
+            .await
+            .with_context(|| format!("Attempting to execute query {}", query_rendered.preview()))?;
         let params_tosql = query_rendered
             .params()
             .iter()
-            .map(|v| self.convert_value_tosql(v.clone()));
+            .zip(statement.params())
+            .map(|(v, ty)| self.convert_value_tosql_typed(v.clone(), ty))
+            .collect::<Result<_>>()?;
+        Ok((statement, params_tosql))
+    }
 
-        // let params_tosql_refs = params_tosql
-        //     .iter()
-        //     .map(|b| b.as_ref())
-        //     .collect::<Vec<&(dyn ToSql + Sync)>>();
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "query_raw",
+            level = "debug",
+            skip_all,
+            fields(sql, params, rows, latency_ms)
+        )
+    )]
+    pub async fn query_raw(&self, query: &Query) -> Result<Vec<Value>> {
+        let started = Instant::now();
+        let query_rendered = query.try_render_chunk()?;
+
+        let query_rendered = &query_rendered;
+        let results: Result<Vec<Value>> = self
+            .with_connection(|client| async move {
+                let (statement, params_tosql) =
+                    self.prepare_with_params(&client, query_rendered).await?;
+
+                let result = client
+                    .query_raw(&statement, params_tosql)
+                    .await
+                    .context(anyhow!("Error in query {}", query_rendered.preview()))?;
+
+                pin_mut!(result);
+                let mut results = Vec::new();
+                while let Some(row) = result.try_next().await? {
+                    results.push(self.convert_value_fromsql(row)?);
+                }
+                Ok(results)
+            })
+            .await;
+
+        self.observe(
+            query_rendered,
+            started,
+            results.as_ref().ok().map(|rows| rows.len() as u64),
+            results.as_ref().err(),
+        );
+        results
+    }
 
-        let result = self
-            .client
-            .query_raw(&query_rendered.sql_final(), params_tosql)
-            .await
-            .context(anyhow!("Error in query {}", query.preview()))?;
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "execute",
+            level = "debug",
+            skip_all,
+            fields(sql, params, rows, latency_ms)
+        )
+    )]
+    /// Executes the query without fetching rows, returns number of affected rows.
+    pub async fn execute(&self, query: &Query) -> Result<u64> {
+        let started = Instant::now();
+        let query_rendered = query.try_render_chunk()?;
+
+        let query_rendered = &query_rendered;
+        let result: Result<u64> = self
+            .with_connection(|client| async move {
+                let (statement, params_tosql) =
+                    self.prepare_with_params(&client, query_rendered).await?;
+                client
+                    .execute_raw(&statement, params_tosql)
+                    .await
+                    .context(anyhow!("Error in query {}", query_rendered.preview()))
+            })
+            .await;
+
+        self.observe(
+            query_rendered,
+            started,
+            result.as_ref().ok().copied(),
+            result.as_ref().err(),
+        );
+        result
+    }
 
-        pin_mut!(result);
-        let mut results = Vec::new();
-        while let Some(row) = result.try_next().await? {
-            // for row in result {
-            results.push(self.convert_value_fromsql(row)?);
+    pub async fn query_opt(&self, query: &Query) -> Result<Option<Value>> {
+        Ok(self.query_raw(query).await?.into_iter().next())
+    }
+
+    /// Names of the tables in the current schema, in alphabetical order.
+    pub async fn list_tables(&self) -> Result<Vec<String>> {
+        let tables = self
+            .query_col(&Query::new().with_type(QueryType::Expression(expr!(
+                "SELECT table_name::text FROM information_schema.tables \
+                WHERE table_schema = current_schema() AND table_type = 'BASE TABLE' \
+                ORDER BY table_name"
+            ))))
+            .await?;
+        Ok(tables
+            .into_iter()
+            .filter_map(|name| name.as_str().map(|s| s.to_string()))
+            .collect())
+    }
+
+    /// Read structure of a table from `information_schema`, including columns,
+    /// primary key and foreign keys. Use [`Table::from_introspection()`] to
+    /// create a table from the result.
+    ///
+    /// [`Table::from_introspection()`]: crate::sql::Table::from_introspection
+    pub async fn introspect_table(&self, table_name: &str) -> Result<TableSchema> {
+        let columns = self
+            .query_fetch(&Query::new().with_type(QueryType::Expression(expr!(
+                "SELECT column_name::text, data_type::text, is_nullable::text \
+                FROM information_schema.columns \
+                WHERE table_schema = current_schema() AND table_name = {} \
+                ORDER BY ordinal_position",
+                table_name
+            ))))
+            .await?;
+
+        if columns.is_empty() {
+            return Err(anyhow!("Table {} does not exist", table_name));
         }
 
-        Ok(results)
+        let constraints = self
+            .query_fetch(&Query::new().with_type(QueryType::Expression(expr!(
+                "SELECT tc.constraint_type::text, kcu.column_name::text, \
+                ccu.table_name::text AS foreign_table, ccu.column_name::text AS foreign_column \
+                FROM information_schema.table_constraints tc \
+                JOIN information_schema.key_column_usage kcu \
+                ON kcu.constraint_name = tc.constraint_name AND kcu.table_schema = tc.table_schema \
+                LEFT JOIN information_schema.constraint_column_usage ccu \
+                ON tc.constraint_type = 'FOREIGN KEY' \
+                AND ccu.constraint_name = tc.constraint_name AND ccu.table_schema = tc.table_schema \
+                WHERE tc.table_schema = current_schema() AND tc.table_name = {} \
+                AND tc.constraint_type IN ('PRIMARY KEY', 'FOREIGN KEY') \
+                ORDER BY kcu.ordinal_position",
+                table_name
+            ))))
+            .await?;
+
+        let text = |row: &Map<String, Value>, key: &str| -> String {
+            row.get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        let mut schema = TableSchema::new(table_name);
+        for row in &columns {
+            schema.columns.push(ColumnSchema {
+                name: text(row, "column_name"),
+                data_type: text(row, "data_type"),
+                nullable: text(row, "is_nullable") == "YES",
+            });
+        }
+        for row in &constraints {
+            match text(row, "constraint_type").as_str() {
+                "PRIMARY KEY" => schema.primary_key.push(text(row, "column_name")),
+                _ => schema.foreign_keys.push(ForeignKeySchema {
+                    column: text(row, "column_name"),
+                    foreign_table: text(row, "foreign_table"),
+                    foreign_column: text(row, "foreign_column"),
+                }),
+            }
+        }
+        Ok(schema)
     }
 
-    pub async fn query_opt(&self, query: &Query) -> Result<Option<Value>> {
-        Ok(self.query_raw(query).await?.into_iter().next())
+    /// Loads rows into the table using `COPY .. FROM STDIN`, which is much faster
+    /// than INSERT for large amounts of data. Values of each row must follow the
+    /// order of `columns`. Returns number of copied rows. If the stream yields an
+    /// error, COPY is aborted and nothing is loaded. COPY runs in the transaction
+    /// of the current task and with the settings, like other queries.
+    ///
+    /// ```
+    /// let rows = futures::stream::iter(vec![
+    ///     Ok(vec![json!("John"), json!("john@example.com")]),
+    ///     Ok(vec![json!("Jane"), Value::Null]),
+    /// ]);
+    /// postgres.copy_in("client", &["name", "email"], rows).await?;
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "copy_in",
+            level = "debug",
+            skip_all,
+            fields(sql, params, rows, latency_ms)
+        )
+    )]
+    pub async fn copy_in(
+        &self,
+        table: &str,
+        columns: &[&str],
+        rows: impl Stream<Item = Result<Vec<Value>>>,
+    ) -> Result<u64> {
+        let started = Instant::now();
+        let dialect = self.dialect();
+        let statement = format!(
+            "COPY {} ({}) FROM STDIN",
+            dialect.quote_identifier(table),
+            columns
+                .iter()
+                .map(|column| dialect.quote_identifier(column))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let query_rendered = Expression::new(statement.clone(), vec![]);
+        record_query(&query_rendered);
+
+        let statement = &statement;
+        let result: Result<u64> = self
+            .with_connection(|client| async move {
+                let sink = client
+                    .copy_in::<_, Bytes>(statement)
+                    .await
+                    .context(anyhow!("Error in query {}", statement))?;
+                pin_mut!(sink);
+                pin_mut!(rows);
+
+                let mut buffer = BytesMut::new();
+                while let Some(row) = rows.next().await {
+                    // returning before finish() aborts COPY
+                    let row = row?;
+                    if row.len() != columns.len() {
+                        return Err(anyhow!(
+                            "Row has {} values, but {} columns are copied",
+                            row.len(),
+                            columns.len()
+                        ));
+                    }
+                    encode_copy_row(&row, &mut buffer);
+                    if buffer.len() >= COPY_BUFFER_SIZE {
+                        sink.send(buffer.split().freeze()).await?;
+                    }
+                }
+                if !buffer.is_empty() {
+                    sink.send(buffer.freeze()).await?;
+                }
+                Ok(sink.finish().await?)
+            })
+            .await;
+
+        self.observe(
+            &query_rendered,
+            started,
+            result.as_ref().ok().copied(),
+            result.as_ref().err(),
+        );
+        result
     }
 }
 
+/// Amount of data sent to the server at once by [`Postgres::copy_in()`]
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Appends row in the text format of COPY: values are separated by tabs, NULL
+/// is `\N` and special characters are escaped with a backslash.
+fn encode_copy_row(row: &[Value], buffer: &mut BytesMut) {
+    for (i, value) in row.iter().enumerate() {
+        if i > 0 {
+            buffer.put_u8(b'\t');
+        }
+        let text = match value {
+            Value::Null => {
+                buffer.put_slice(b"\\N");
+                continue;
+            }
+            Value::Bool(b) => if *b { "t" } else { "f" }.to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::String(s) => s.clone(),
+            value => value.to_string(),
+        };
+        for c in text.chars() {
+            match c {
+                '\\' => buffer.put_slice(b"\\\\"),
+                '\t' => buffer.put_slice(b"\\t"),
+                '\n' => buffer.put_slice(b"\\n"),
+                '\r' => buffer.put_slice(b"\\r"),
+                c => buffer.put_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+    }
+    buffer.put_u8(b'\n');
+}
+
 trait InsertRows {
     async fn insert_rows(&self, query: &Query, rows: &Vec<Vec<Value>>) -> Result<Vec<Value>>;
 }
 
 impl InsertRows for Postgres {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "insert_rows",
+            level = "debug",
+            skip_all,
+            fields(sql, params, rows, latency_ms)
+        )
+    )]
     async fn insert_rows(&self, query: &Query, rows: &Vec<Vec<Value>>) -> Result<Vec<Value>> {
+        let started = Instant::now();
         // no rows to insert
         if rows.len() == 0 {
             return Ok(vec![]);
         }
 
-        let query_rendered = query.render_chunk();
+        let query_rendered = query.try_render_chunk()?;
         let num_rows = query_rendered.params().len();
+        record_query(&query_rendered);
 
         if rows.len() == 0 {
             return Err(anyhow!("Insert query contains zero fields"));
         }
 
-        let statement = self
-            .client
-            .prepare(&query_rendered.sql_final())
-            .await
-            .context("Attempting to execute an insert query")?;
-
-        let mut row_cnt = 0;
-        let mut ids = Vec::new();
-        for row_set in rows {
-            row_cnt += 1;
-            if row_set.len() != num_rows {
-                return Err(anyhow!(
-                    "Number of columns in a row {} does not match number of fields in a query {} at row {}",
-                    row_set.len(), num_rows, row_cnt
-                ));
-            }
-
-            let params_tosql = row_set
-                .iter()
-                .map(|v| self.convert_value_tosql(v.clone()))
-                .collect::<Vec<_>>();
+        let query_rendered = &query_rendered;
+        let ids: Result<Vec<Value>> = self
+            .with_connection(|client| async move {
+            let statement = client
+                .prepare(&query_rendered.sql_final())
+                .await
+                .context("Attempting to execute an insert query")?;
+
+            let mut row_cnt = 0;
+            let mut ids = Vec::new();
+            for row_set in rows {
+                row_cnt += 1;
+                if row_set.len() != num_rows {
+                    return Err(anyhow!(
+                        "Number of columns in a row {} does not match number of fields in a query {} at row {}",
+                        row_set.len(), num_rows, row_cnt
+                    ));
+                }
 
-            let params_tosql_refs = params_tosql
-                .iter()
-                .map(|b| b.as_ref())
-                .collect::<Vec<&(dyn ToSql + Sync)>>();
+                let params_tosql = row_set
+                    .iter()
+                    .zip(statement.params())
+                    .map(|(v, ty)| self.convert_value_tosql_typed(v.clone(), ty))
+                    .collect::<Result<Vec<_>>>()?;
 
-            let row = self
-                .client
-                .query_one(&statement, params_tosql_refs.as_slice())
-                .await?;
+                let params_tosql_refs = params_tosql
+                    .iter()
+                    .map(|b| b.as_ref() as &(dyn ToSql + Sync))
+                    .collect::<Vec<_>>();
 
-            let row = self.convert_value_fromsql(row)?;
+                let row = client
+                    .query_one(&statement, params_tosql_refs.as_slice())
+                    .await?;
 
-            let row = if let Value::Object(obj) = row {
-                obj
-            } else {
-                return Err(anyhow!("Expected query_one to return an Value::Object"));
-            };
+                let row = self.convert_value_fromsql(row)?;
 
-            let id = row
-                .into_iter()
-                .next()
-                .context("query_one returned empty object")?
-                .1;
+                let row = if let Value::Object(obj) = row {
+                    obj
+                } else {
+                    return Err(anyhow!("Expected query_one to return an Value::Object"));
+                };
 
-            ids.push(id)
-        }
+                let id = row
+                    .into_iter()
+                    .next()
+                    .context("query_one returned empty object")?
+                    .1;
 
-        Ok(ids)
+                ids.push(id)
+            }
+            Ok(ids)
+        })
+        .await;
+
+        self.observe(
+            query_rendered,
+            started,
+            ids.as_ref().ok().map(|ids| ids.len() as u64),
+            ids.as_ref().err(),
+        );
+        ids
     }
 }
 
@@ -227,6 +1058,10 @@ impl SelectRows for Postgres {
 }
 
 impl DataSource for Postgres {
+    fn dialect(&self) -> Arc<dyn Dialect> {
+        Arc::new(PostgresDialect)
+    }
+
     async fn query_fetch(&self, query: &Query) -> Result<Vec<Map<String, Value>>> {
         let res = self.query_raw(query).await?;
         let res = res
@@ -237,13 +1072,31 @@ impl DataSource for Postgres {
         Ok(res)
     }
 
-    async fn query_exec(&self, query: &Query) -> Result<Option<Value>> {
-        let res = self.query_raw(query).await?;
-        if res.len() == 0 {
-            Ok(None)
-        } else {
-            Ok(Some(res[0].clone()))
+    async fn query_exec(&self, query: &Query) -> Result<ExecResult> {
+        if matches!(query.get_returning(), QueryReturning::None)
+            && matches!(
+                query.get_type(),
+                QueryType::Insert | QueryType::Update | QueryType::Delete
+            )
+        {
+            return Ok(ExecResult {
+                rows_affected: self.execute(query).await?,
+                returned: vec![],
+            });
         }
+        let returned: Vec<_> = self
+            .query_raw(query)
+            .await?
+            .into_iter()
+            .filter_map(|row| match row {
+                Value::Object(row) => Some(row),
+                _ => None,
+            })
+            .collect();
+        Ok(ExecResult {
+            rows_affected: returned.len() as u64,
+            returned,
+        })
     }
 
     async fn query_insert(&self, _query: &Query, _rows: Vec<Vec<Value>>) -> Result<()> {
@@ -272,206 +1125,191 @@ impl DataSource for Postgres {
             .collect();
         Ok(res)
     }
-}
-
-pub struct AssociatedExpressionArc<T: DataSource> {
-    pub expr: ExpressionArc,
-    pub ds: T,
-}
-
-impl<T: DataSource> Deref for AssociatedExpressionArc<T> {
-    type Target = ExpressionArc;
-
-    fn deref(&self) -> &Self::Target {
-        &self.expr
-    }
-}
-
-impl<T: DataSource> AssociatedExpressionArc<T> {
-    pub fn new(expr: ExpressionArc, ds: T) -> Self {
-        Self { expr, ds }
-    }
-    pub async fn get_one(&self) -> Result<Value> {
-        let one = self
-            .ds
-            .query_one(
-                &Query::new().with_type(crate::sql::query::QueryType::Expression(
-                    self.expr.render_chunk(),
-                )),
-            )
-            .await?;
-        Ok(one)
-    }
-}
-
-/// While [`Query`] does not generally associate with the [`DataSource`], it may be inconvenient
-/// to execute it. AssociatedQuery combines query with the datasource, allowing you to ealily
-/// pass it around and execute it.
-///
-/// ```
-/// let clients = Client::table();
-/// let client_count = clients.count();   // returns AssociatedQuery
-///
-/// let cnt: Value = client_count.get_one_untuped().await?;  // actually executes the query
-/// ```
-///
-/// AssociatedQuery can be used to make a link between DataSources:
-///
-/// ```
-/// let clients = Client::table();
-/// let client_code_query = clients.field_query(clients.code())?;
-/// // returns field query (SELECT code FROM client)
-///
-/// let orders = Order::table();
-/// let orders = orders.with_condition(
-///     orders.client_code().in(orders.glue(client_code_query).await?)
-/// );
-/// ```
-/// If Order and Client tables do share same [`DataSource`], the conditioun would be set as
-///  `WHERE (client_code IN (SELECT code FROM client))`, ultimatelly saving you from
-/// redundant query.
-///
-/// When datasources are different, [`glue()`] would execute `SELECT code FROM client`, fetch
-/// the results and use those as a vector of values in a condition clause:
-///  `WHERE (client_code IN [12, 13, 14])`
-///
-/// [`DataSource`]: crate::traits::datasource::DataSource
-/// [`glue()`]: Table::glue
-///
-#[derive(Clone)]
-pub struct AssociatedQuery<T: DataSource, E: Entity> {
-    pub query: Query,
-    pub ds: T,
-    pub _phantom: std::marker::PhantomData<E>,
-}
-impl<T: DataSource, E: Entity> Deref for AssociatedQuery<T, E> {
-    type Target = Query;
-
-    fn deref(&self) -> &Self::Target {
-        &self.query
-    }
-}
-impl<T: DataSource, E: Entity> DerefMut for AssociatedQuery<T, E> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.query
-    }
-}
-
-impl<T: DataSource, E: Entity> AssociatedQuery<T, E> {
-    pub fn new(query: Query, ds: T) -> Self {
-        Self {
-            query,
-            ds,
-            _phantom: std::marker::PhantomData,
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "query_stream",
+            level = "debug",
+            skip_all,
+            fields(sql, params, rows, latency_ms)
+        )
+    )]
+    async fn query_stream(
+        &self,
+        query: &Query,
+    ) -> Result<BoxStream<'static, Result<Map<String, Value>>>> {
+        // rows must be read before the transaction with settings is committed
+        if !self.settings.is_empty() && !self.in_transaction() {
+            let rows = self.query_fetch(query).await?;
+            return Ok(futures::stream::iter(rows.into_iter().map(Ok)).boxed());
         }
+        let started = Instant::now();
+        let query_rendered = query.try_render_chunk()?;
+
+        // RowStream receives rows from the connection as they arrive
+        let client = self.connection();
+        let result: Result<_> = async {
+            let (statement, params_tosql) =
+                self.prepare_with_params(&client, &query_rendered).await?;
+            client
+                .query_raw(&statement, params_tosql)
+                .await
+                .context(anyhow!("Error in query {}", query_rendered.preview()))
+        }
+        .await;
+
+        // rows are not known until the stream is consumed
+        self.observe(&query_rendered, started, None, result.as_ref().err());
+        let result = result?;
+
+        let postgres = self.clone();
+        Ok(result
+            .map(move |row| match postgres.convert_value_fromsql(row?)? {
+                Value::Object(row) => Ok(row),
+                _ => Err(anyhow!("Expected row to be converted into Value::Object")),
+            })
+            .boxed())
     }
 
-    pub fn with_skip(mut self, skip: i64) -> Self {
-        self.query.add_skip(Some(skip));
-        self
-    }
-
-    pub fn with_limit(mut self, limit: i64) -> Self {
-        self.query.add_limit(Some(limit));
-        self
-    }
-
-    pub fn with_skip_and_limit(mut self, skip: i64, limit: i64) -> Self {
-        self.query.add_limit(Some(limit));
-        self.query.add_skip(Some(skip));
-        self
+    /// Queries of the clone run on a dedicated connection, or on the connection of
+    /// the current task's transaction, which is then aborted. Without
+    /// [`Postgres::connect()`] there is no connection of its own to cancel, so the
+    /// query is left to finish on the server.
+    fn cancellable(&self) -> Self {
+        Postgres {
+            cancellable: true,
+            ..self.clone()
+        }
     }
 
-    /// Presented with another AssociatedQuery - calculate if queries
-    /// are linked with the same or different [`DataSource`]s.
+    /// The transaction runs on a dedicated connection and belongs to the current
+    /// task: its queries, executed through any clone of the data source, join the
+    /// transaction, while queries of other tasks don't see it. It must be
+    /// committed or rolled back by the same task.
     ///
-    /// The same - return expression as-is.
-    /// Different - execute the query and return the result as a vector of values.
-    async fn glue(&self, other: AssociatedQuery<T, E>) -> Result<Expression> {
-        if self.ds.eq(&other.ds) {
-            Ok(other.query.render_chunk())
-        } else {
-            let vals = other.get_col_untyped().await?;
-            let tpl = vec!["{}"; vals.len()].join(", ");
-            Ok(Expression::new(tpl, vals))
+    /// A transaction started within another one becomes a savepoint, so that it
+    /// can be rolled back without affecting the outer transaction.
+    async fn begin_transaction(&self) -> Result<()> {
+        let savepoint = {
+            let mut transactions = self.connections.transactions.lock().unwrap();
+            transactions
+                .get_mut(&crate::runtime::task_key())
+                .map(|transaction| {
+                    transaction.savepoints += 1;
+                    (transaction.client.clone(), transaction.savepoints)
+                })
+        };
+        if let Some((client, n)) = savepoint {
+            let result = client.batch_execute(&format!("SAVEPOINT sp_{}", n)).await;
+            if result.is_err() {
+                // the caller won't end a savepoint, which wasn't created
+                let mut transactions = self.connections.transactions.lock().unwrap();
+                if let Some(transaction) = transactions.get_mut(&crate::runtime::task_key()) {
+                    transaction.savepoints -= 1;
+                }
+            }
+            return Ok(result?);
         }
+
+        let client = self.dedicated_connection().await?;
+        client
+            .batch_execute(&format!("BEGIN; {}", settings_sql(&self.settings)))
+            .await?;
+        self.connections.transactions.lock().unwrap().insert(
+            crate::runtime::task_key(),
+            Transaction {
+                client,
+                savepoints: 0,
+            },
+        );
+        Ok(())
     }
-}
-impl<D: DataSource + Sync, E: Entity> Chunk for AssociatedQuery<D, E> {
-    fn render_chunk(&self) -> Expression {
-        self.query.render_chunk()
+    async fn commit_transaction(&self) -> Result<()> {
+        self.end_transaction(true).await
     }
-}
-impl<D: DataSource, E: Entity> std::fmt::Debug for AssociatedQuery<D, E> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("AssociatedQuery")
-            .field("query", &self.query)
-            .field("ds", &self.ds)
-            .finish()
+    async fn rollback_transaction(&self) -> Result<()> {
+        self.end_transaction(false).await
     }
 }
-impl<T: DataSource + Sync, E: Entity> ReadableDataSet<E> for AssociatedQuery<T, E> {
-    async fn get_all_untyped(&self) -> Result<Vec<Map<String, Value>>> {
-        self.ds.query_fetch(&self.query).await
-    }
-
-    async fn get_row_untyped(&self) -> Result<Map<String, Value>> {
-        self.ds.query_row(&self.query).await
-    }
 
-    async fn get_one_untyped(&self) -> Result<Value> {
-        self.ds.query_one(&self.query).await
-    }
-
-    async fn get_col_untyped(&self) -> Result<Vec<Value>> {
-        self.ds.query_col(&self.query).await
+impl<E: Entity> AssociatedQuery<Postgres, E> {
+    /// Request-scoped setting for this query, see [`Postgres::with_settings()`]:
+    ///
+    /// ```
+    /// let total = orders
+    ///     .sum(orders.total())
+    ///     .with_context("app.current_user", user_id)
+    ///     .get_one_as::<Decimal>()
+    ///     .await?;
+    /// ```
+    pub fn with_context(mut self, name: &str, value: impl ToString) -> Self {
+        self.ds.add_setting(name, value);
+        self
     }
+}
 
-    async fn get(&self) -> Result<Vec<E>> {
-        let data = self.get_all_untyped().await?;
-        Ok(data
-            .into_iter()
-            .map(|row| serde_json::from_value(Value::Object(row)).unwrap())
-            .collect())
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    async fn get_as<T2: serde::de::DeserializeOwned>(&self) -> Result<Vec<T2>> {
-        let data = self.get_all_untyped().await?;
-        Ok(data
-            .into_iter()
-            .map(|row| serde_json::from_value(Value::Object(row)).unwrap())
-            .collect())
+    #[test]
+    fn test_convert_homogeneous_array() {
+        let encode = |values: Value, ty: &Type| {
+            let array = convert_homogeneous_array(values.as_array().unwrap())?;
+            Some(array.to_sql_checked(ty, &mut BytesMut::new()).is_ok())
+        };
+        assert_eq!(encode(json!([1, 2, null]), &Type::INT8_ARRAY), Some(true));
+        assert_eq!(encode(json!([1, 2.5]), &Type::FLOAT8_ARRAY), Some(true));
+        assert_eq!(encode(json!(["a", "b"]), &Type::TEXT_ARRAY), Some(true));
+        assert_eq!(encode(json!([true]), &Type::BOOL_ARRAY), Some(true));
+        assert_eq!(encode(json!(["a", "b"]), &Type::INT8_ARRAY), Some(false));
+        assert_eq!(encode(json!([1, "a"]), &Type::TEXT_ARRAY), None);
+        assert_eq!(encode(json!([[1]]), &Type::INT8_ARRAY), None);
+        assert_eq!(encode(json!([]), &Type::INT8_ARRAY), None);
     }
 
-    async fn get_some(&self) -> Result<Option<E>> {
-        let data = self.ds.query_fetch(&self.query).await?;
-        if data.len() > 0 {
-            let row = data[0].clone();
-            let row = serde_json::from_value(Value::Object(row)).unwrap();
-            Ok(Some(row))
-        } else {
-            Ok(None)
-        }
+    #[test]
+    fn test_convert_elements() {
+        let int2 = |v: &Value| i16::try_from(v.as_i64()?).ok();
+        assert_eq!(
+            convert_elements(&[json!(1), Value::Null], &Type::INT2_ARRAY, int2).unwrap(),
+            vec![Some(1), None]
+        );
+        assert!(convert_elements(&[json!(70000)], &Type::INT2_ARRAY, int2).is_err());
+        assert!(convert_elements(&[json!("a")], &Type::INT2_ARRAY, int2).is_err());
     }
 
-    async fn get_some_as<T2: serde::de::DeserializeOwned>(&self) -> Result<Option<T2>> {
-        let data = self.ds.query_fetch(&self.query).await?;
-        if data.len() > 0 {
-            let row = data[0].clone();
-            let row = serde_json::from_value(Value::Object(row)).unwrap();
-            Ok(Some(row))
-        } else {
-            Ok(None)
-        }
+    #[test]
+    fn test_settings_sql() {
+        assert_eq!(settings_sql(&[]), "");
+        assert_eq!(
+            settings_sql(&[
+                ("app.current_user".to_string(), "42".to_string()),
+                ("app.tenant".to_string(), "o'hara".to_string()),
+            ]),
+            "SELECT set_config('app.current_user', '42', true); \
+            SELECT set_config('app.tenant', 'o''hara', true);"
+        );
     }
 
-    fn select_query(&self) -> Query {
-        self.query.clone()
+    #[test]
+    fn test_encode_copy_row() {
+        let mut buffer = BytesMut::new();
+        encode_copy_row(
+            &[
+                json!(1),
+                json!("tab\there\nback\\slash"),
+                Value::Null,
+                json!(true),
+                json!({"a": 1}),
+            ],
+            &mut buffer,
+        );
+        assert_eq!(
+            &buffer[..],
+            b"1\ttab\\there\\nback\\\\slash\t\\N\tt\t{\"a\":1}\n"
+        );
     }
-}
-
-#[cfg(test)]
-mod tests {
 
     // #[tokio::test]
     // async fn test_insert_async() {
@@ -0,0 +1,598 @@
+//! Data source for remote HTTP APIs, which expose tables as JSON collections.
+//! Enabled with `rest` feature.
+//!
+//! ```
+//! let api = RestDataSource::new("https://api.example.com/v1")
+//!     .with_pagination("page", "per_page", NonZeroU32::new(50).unwrap())
+//!     .with_data_key("data")
+//!     .with_auth_header(|| format!("Bearer {}", token_store.current()));
+//!
+//! let users = Table::new("users", api)
+//!     .with_id_column("id")
+//!     .with_column("name")
+//!     .with_column("role");
+//! let admins = users.with_condition(users.get_column("role").unwrap().eq(&"admin".to_string()));
+//! // GET https://api.example.com/v1/users?role=admin&page=1&per_page=50
+//! ```
+//!
+//! Table conditions are sent as query parameters: equality as `field=value`,
+//! `IN` as `field=a,b,c` and other operations as `field[gt]=value`. Conditions,
+//! which can't be represented this way, result in error. Pages are requested
+//! until a short page is returned or the query limit is reached.
+//!
+//! Inserts are sent as `POST` to the collection URL, updates and deletes as
+//! `PATCH` and `DELETE` to the URL of each affected item. Unless the query
+//! addresses a single item by id, affected items are fetched first.
+//!
+//! Because REST tables implement [`DataSource`], their queries can be glued
+//! into conditions of SQL tables, see [`AssociatedQuery`].
+//!
+//! The data source also works in browser frontends compiled to wasm32, where
+//! requests go through the fetch API. Postgres and tokio timers are not
+//! available there, so build with `default-features = false` and features
+//! `rest` and `async-std`.
+//!
+//! [`AssociatedQuery`]: crate::datasource::associated_query::AssociatedQuery
+
+use std::future::Future;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use futures::stream::{self, BoxStream, StreamExt};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use reqwest::{Method, RequestBuilder};
+use serde_json::{Map, Value};
+
+use crate::sql::dialect::{Dialect, PostgresDialect};
+use crate::sql::query::{Filter, FilterOperation, QuerySource, QueryType, SqlQuery};
+use crate::sql::Query;
+use crate::traits::datasource::{DataSource, ExecResult};
+
+type AuthHeader = Arc<dyn Fn() -> String + Send + Sync>;
+
+/// Characters, which can't appear in a path segment as they are
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'\\')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+#[derive(Clone)]
+pub struct RestDataSource {
+    client: reqwest::Client,
+    base_url: String,
+    collection_path: String,
+    item_path: String,
+    id_field: String,
+    page_param: String,
+    page_size_param: String,
+    page_size: i64,
+    data_key: Option<String>,
+    auth_header: Option<AuthHeader>,
+}
+
+/// RestDataSource is equal to another one, using the same API.
+impl PartialEq for RestDataSource {
+    fn eq(&self, other: &RestDataSource) -> bool {
+        self.base_url == other.base_url
+            && self.collection_path == other.collection_path
+            && self.item_path == other.item_path
+    }
+}
+
+impl std::fmt::Debug for RestDataSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RestDataSource")
+            .field("base_url", &self.base_url)
+            .field("collection_path", &self.collection_path)
+            .field("item_path", &self.item_path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RestDataSource {
+    pub fn new(base_url: &str) -> RestDataSource {
+        RestDataSource {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            collection_path: "/{table}".to_string(),
+            item_path: "/{table}/{id}".to_string(),
+            id_field: "id".to_string(),
+            page_param: "page".to_string(),
+            page_size_param: "per_page".to_string(),
+            page_size: 100,
+            data_key: None,
+            auth_header: None,
+        }
+    }
+
+    /// URL template of a collection, relative to the base URL. Default is `/{table}`.
+    pub fn with_collection_path(mut self, template: &str) -> Self {
+        self.collection_path = template.to_string();
+        self
+    }
+
+    /// URL template of a single item, relative to the base URL. Default is `/{table}/{id}`.
+    pub fn with_item_path(mut self, template: &str) -> Self {
+        self.item_path = template.to_string();
+        self
+    }
+
+    /// Field, which identifies items in item URLs. Default is `id`.
+    pub fn with_id_field(mut self, id_field: &str) -> Self {
+        self.id_field = id_field.to_string();
+        self
+    }
+
+    /// Query parameters for page number (starting with 1) and page size.
+    /// Default is `page` and `per_page` with 100 items per page.
+    pub fn with_pagination(
+        mut self,
+        page_param: &str,
+        page_size_param: &str,
+        page_size: NonZeroU32,
+    ) -> Self {
+        self.page_param = page_param.to_string();
+        self.page_size_param = page_size_param.to_string();
+        self.page_size = page_size.get().into();
+        self
+    }
+
+    /// Key of the response object, which holds the rows. By default the
+    /// response is expected to be an array.
+    pub fn with_data_key(mut self, data_key: &str) -> Self {
+        self.data_key = Some(data_key.to_string());
+        self
+    }
+
+    /// Value of `Authorization` header is requested before each request, so
+    /// that expired tokens can be refreshed.
+    pub fn with_auth_header<F>(mut self, auth_header: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        self.auth_header = Some(Arc::new(auth_header));
+        self
+    }
+
+    fn table_name(query: &Query) -> Result<&str> {
+        match query.get_source() {
+            QuerySource::Table(table, _) => Ok(table),
+            _ => Err(anyhow!("RestDataSource can only query tables")),
+        }
+    }
+
+    fn collection_url(&self, table: &str) -> String {
+        format!(
+            "{}{}",
+            self.base_url,
+            self.collection_path.replace("{table}", table)
+        )
+    }
+
+    /// Id is percent-encoded, so it can't change the path of the URL.
+    fn item_url(&self, table: &str, id: &Value) -> Result<String> {
+        let id = Self::param_value(id);
+        if id.is_empty() || id == "." || id == ".." {
+            return Err(anyhow!("Invalid id '{}' for an item of {}", id, table));
+        }
+        let id = utf8_percent_encode(&id, PATH_SEGMENT).to_string();
+        Ok(format!(
+            "{}{}",
+            self.base_url,
+            self.item_path
+                .replace("{table}", table)
+                .replace("{id}", &id)
+        ))
+    }
+
+    fn param_value(value: &Value) -> String {
+        match value {
+            Value::String(s) => s.clone(),
+            Value::Array(values) => values
+                .iter()
+                .map(Self::param_value)
+                .collect::<Vec<_>>()
+                .join(","),
+            value => value.to_string(),
+        }
+    }
+
+    fn filter_params(filters: &[Filter]) -> Vec<(String, String)> {
+        filters
+            .iter()
+            .map(|filter| {
+                let suffix = match (filter.operation, &filter.value) {
+                    (FilterOperation::Eq, Value::Null) => {
+                        return (format!("{}[null]", filter.field), "true".to_string())
+                    }
+                    (FilterOperation::Ne, Value::Null) => {
+                        return (format!("{}[null]", filter.field), "false".to_string())
+                    }
+                    (FilterOperation::Eq | FilterOperation::In, _) => "",
+                    (FilterOperation::Ne, _) => "[ne]",
+                    (FilterOperation::Gt, _) => "[gt]",
+                    (FilterOperation::Gte, _) => "[gte]",
+                    (FilterOperation::Lt, _) => "[lt]",
+                    (FilterOperation::Lte, _) => "[lte]",
+                    (FilterOperation::NotIn, _) => "[nin]",
+                    (FilterOperation::Like, _) => "[like]",
+                };
+                (
+                    format!("{}{}", filter.field, suffix),
+                    Self::param_value(&filter.value),
+                )
+            })
+            .collect()
+    }
+
+    fn parse_rows(&self, body: Value) -> Result<Vec<Map<String, Value>>> {
+        let rows = match (&self.data_key, body) {
+            (Some(key), Value::Object(mut object)) => object
+                .remove(key)
+                .ok_or_else(|| anyhow!("Response has no \"{}\" key", key))?,
+            (_, body) => body,
+        };
+        let Value::Array(rows) = rows else {
+            return Err(anyhow!("Response is not an array of rows"));
+        };
+        rows.into_iter()
+            .map(|row| match row {
+                Value::Object(row) => Ok(row),
+                row => Err(anyhow!("Row {} is not an object", row)),
+            })
+            .collect()
+    }
+
+    /// Keeps only the fields, which query selects, in their order.
+    fn project(row: Map<String, Value>, fields: &[String]) -> Map<String, Value> {
+        if fields.is_empty() {
+            return row;
+        }
+        let mut row = row;
+        fields
+            .iter()
+            .filter_map(|field| Some((field.clone(), row.remove(field)?)))
+            .collect()
+    }
+
+    /// Under wasm32 the request is sent with the browser fetch API, whose futures
+    /// are not `Send`. Browser code runs on a single thread, so the future is
+    /// wrapped to satisfy the bounds of [`DataSource`].
+    fn send(&self, request: RequestBuilder) -> impl Future<Output = Result<Value>> + Send + '_ {
+        let future = self.send_request(request);
+        #[cfg(target_arch = "wasm32")]
+        return send_wrapper::SendWrapper::new(future);
+        #[cfg(not(target_arch = "wasm32"))]
+        future
+    }
+
+    async fn send_request(&self, request: RequestBuilder) -> Result<Value> {
+        let request = match &self.auth_header {
+            Some(auth_header) => request.header(reqwest::header::AUTHORIZATION, auth_header()),
+            None => request,
+        };
+        let request = request.build()?;
+        let description = format!("{} {}", request.method(), request.url());
+
+        let response = self
+            .client
+            .execute(request)
+            .await
+            .with_context(|| anyhow!("Unable to reach API for {}", description))?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(anyhow!(
+                "Error in {}: {} {}",
+                description,
+                status,
+                body.trim()
+            ));
+        }
+        if body.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_str(&body).with_context(|| anyhow!("Invalid JSON from {}", description))
+    }
+
+    fn request(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<&Map<String, Value>>,
+    ) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        match body {
+            Some(body) => request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(Value::Object(body.clone()).to_string()),
+            None => request,
+        }
+    }
+
+    async fn fetch(&self, query: &Query) -> Result<Vec<Map<String, Value>>> {
+        let url = self.collection_url(Self::table_name(query)?);
+        let params = Self::filter_params(&query.get_filters()?);
+        let fields = query.get_field_names();
+
+        let skip = query.get_skip().unwrap_or(0);
+        let mut page = skip / self.page_size + 1;
+        let mut to_skip = (skip % self.page_size) as usize;
+        let mut rows = Vec::new();
+
+        loop {
+            let request = self
+                .request(Method::GET, &url, None)
+                .query(&params)
+                .query(&[
+                    (&self.page_param, page.to_string()),
+                    (&self.page_size_param, self.page_size.to_string()),
+                ]);
+            let page_rows = self.parse_rows(self.send(request).await?)?;
+            let last_page = (page_rows.len() as i64) < self.page_size;
+
+            rows.extend(
+                page_rows
+                    .into_iter()
+                    .skip(to_skip)
+                    .map(|row| Self::project(row, &fields)),
+            );
+            to_skip = 0;
+
+            if let Some(limit) = query.get_limit() {
+                if rows.len() as i64 >= limit {
+                    rows.truncate(limit as usize);
+                    break;
+                }
+            }
+            if last_page {
+                break;
+            }
+            page += 1;
+        }
+        Ok(rows)
+    }
+
+    /// Ids of the items, which update or delete query affects.
+    async fn affected_ids(&self, query: &Query) -> Result<Vec<Value>> {
+        let filters = query.get_filters()?;
+        if let [filter] = filters.as_slice() {
+            if filter.field == self.id_field && filter.operation == FilterOperation::Eq {
+                return Ok(vec![filter.value.clone()]);
+            }
+        }
+
+        let select = query
+            .clone()
+            .with_type(QueryType::Select)
+            .without_fields()
+            .with_column_field(&self.id_field);
+        self.fetch(&select)
+            .await?
+            .into_iter()
+            .map(|row| {
+                row.get(&self.id_field)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("Row is missing id field {}", self.id_field))
+            })
+            .collect()
+    }
+
+    async fn post(&self, table: &str, row: &Map<String, Value>) -> Result<Option<Value>> {
+        let request = self.request(Method::POST, &self.collection_url(table), Some(row));
+        match self.send(request).await? {
+            Value::Object(row) => Ok(Some(Value::Object(row))),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl DataSource for RestDataSource {
+    fn dialect(&self) -> Arc<dyn Dialect> {
+        Arc::new(PostgresDialect)
+    }
+
+    async fn query_fetch(&self, query: &Query) -> Result<Vec<Map<String, Value>>> {
+        match query.get_type() {
+            QueryType::Select => self.fetch(query).await,
+            query_type => Err(anyhow!(
+                "RestDataSource can't fetch {:?} query, use query_exec",
+                query_type
+            )),
+        }
+    }
+
+    async fn query_exec(&self, query: &Query) -> Result<ExecResult> {
+        let table = Self::table_name(query)?;
+        match query.get_type() {
+            QueryType::Select => {
+                let returned = self.fetch(query).await?;
+                Ok(ExecResult {
+                    rows_affected: returned.len() as u64,
+                    returned,
+                })
+            }
+            QueryType::Insert | QueryType::Replace => {
+                let returned = match self.post(table, &query.get_set_values()?).await? {
+                    Some(Value::Object(row)) => vec![row],
+                    _ => vec![],
+                };
+                Ok(ExecResult {
+                    rows_affected: 1,
+                    returned,
+                })
+            }
+            QueryType::Update => {
+                let values = query.get_set_values()?;
+                let ids = self.affected_ids(query).await?;
+                for id in &ids {
+                    let url = self.item_url(table, id)?;
+                    self.send(self.request(Method::PATCH, &url, Some(&values)))
+                        .await?;
+                }
+                Ok(ExecResult {
+                    rows_affected: ids.len() as u64,
+                    returned: vec![],
+                })
+            }
+            QueryType::Delete => {
+                let ids = self.affected_ids(query).await?;
+                for id in &ids {
+                    let url = self.item_url(table, id)?;
+                    self.send(self.request(Method::DELETE, &url, None)).await?;
+                }
+                Ok(ExecResult {
+                    rows_affected: ids.len() as u64,
+                    returned: vec![],
+                })
+            }
+            QueryType::Expression(_) => Err(anyhow!("RestDataSource can't execute expressions")),
+        }
+    }
+
+    /// Rows are posted one by one.
+    async fn query_insert(&self, query: &Query, rows: Vec<Vec<Value>>) -> Result<()> {
+        let table = Self::table_name(query)?;
+        let fields = query.get_field_names();
+        for row in rows {
+            let row = fields.iter().cloned().zip(row).collect();
+            self.post(table, &row).await?;
+        }
+        Ok(())
+    }
+
+    async fn query_one(&self, query: &Query) -> Result<Value> {
+        let Some(row) = self.fetch(query).await?.into_iter().next() else {
+            return Err(anyhow!("No rows for query_one"));
+        };
+        let Some((_, res)) = row.into_iter().next() else {
+            return Err(anyhow!("No cells in a first row of query_one"));
+        };
+        Ok(res)
+    }
+
+    async fn query_row(&self, query: &Query) -> Result<Map<String, Value>> {
+        let Some(row) = self.fetch(query).await?.into_iter().next() else {
+            return Err(anyhow!("No rows for query_row"));
+        };
+        Ok(row)
+    }
+
+    async fn query_col(&self, query: &Query) -> Result<Vec<Value>> {
+        Ok(self
+            .fetch(query)
+            .await?
+            .into_iter()
+            .filter_map(|row| Some(row.into_iter().next()?.1))
+            .collect())
+    }
+
+    /// All pages are fetched before the stream is returned.
+    async fn query_stream(
+        &self,
+        query: &Query,
+    ) -> Result<BoxStream<'static, Result<Map<String, Value>>>> {
+        let rows = self.fetch(query).await?;
+        Ok(stream::iter(rows.into_iter().map(Ok)).boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::expr;
+    use crate::sql::Expression;
+
+    #[test]
+    fn test_urls() {
+        let api =
+            RestDataSource::new("https://api.example.com/v1/").with_item_path("/{table}/{id}/");
+        assert_eq!(
+            api.collection_url("users"),
+            "https://api.example.com/v1/users"
+        );
+        assert_eq!(
+            api.item_url("users", &json!("u-12")).unwrap(),
+            "https://api.example.com/v1/users/u-12/"
+        );
+        assert_eq!(
+            api.item_url("users", &json!(12)).unwrap(),
+            "https://api.example.com/v1/users/12/"
+        );
+        assert_eq!(
+            api.item_url("users", &json!("../admin?x=1#y z%")).unwrap(),
+            "https://api.example.com/v1/users/..%2Fadmin%3Fx=1%23y%20z%25/"
+        );
+        assert!(api.item_url("users", &json!("..")).is_err());
+        assert!(api.item_url("users", &json!("")).is_err());
+    }
+
+    #[test]
+    fn test_pagination() {
+        let api = RestDataSource::new("https://api.example.com").with_pagination(
+            "p",
+            "size",
+            NonZeroU32::new(25).unwrap(),
+        );
+        assert_eq!(
+            (
+                api.page_param.as_str(),
+                api.page_size_param.as_str(),
+                api.page_size
+            ),
+            ("p", "size", 25)
+        );
+    }
+
+    #[test]
+    fn test_filter_params() {
+        let query = Query::new()
+            .with_table("users", None)
+            .with_condition(expr!("(role = {})", "admin"))
+            .with_condition(expr!("(age >= {})", 18))
+            .with_condition(expr!("(id IN ({}, {}))", 1, 2))
+            .with_condition(expr!("(deleted_at IS NULL)"));
+        assert_eq!(
+            RestDataSource::filter_params(&query.get_filters().unwrap()),
+            vec![
+                ("role".to_string(), "admin".to_string()),
+                ("age[gte]".to_string(), "18".to_string()),
+                ("id".to_string(), "1,2".to_string()),
+                ("deleted_at[null]".to_string(), "true".to_string()),
+            ]
+        );
+
+        let query = Query::new()
+            .with_table("users", None)
+            .with_condition(expr!("(a = b)"));
+        assert!(query.get_filters().is_err());
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let api = RestDataSource::new("https://api.example.com").with_data_key("data");
+        let rows = api
+            .parse_rows(json!({"data": [{"id": 1, "name": "John"}], "total": 1}))
+            .unwrap();
+        assert_eq!(rows[0]["name"], json!("John"));
+        assert!(api.parse_rows(json!({"total": 1})).is_err());
+
+        let row = RestDataSource::project(
+            rows[0].clone(),
+            &["name".to_string(), "missing".to_string()],
+        );
+        assert_eq!(Value::Object(row), json!({"name": "John"}));
+    }
+}
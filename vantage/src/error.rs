@@ -0,0 +1,172 @@
+//! Errors returned by vantage.
+//!
+//! Most of the APIs return [`anyhow::Result`], but errors caused by vantage itself
+//! are reported as [`Error`], so you can tell them apart:
+//!
+//! ```
+//! match clients.load(id).await {
+//!     Ok(client) => Ok(Json(client.into_entity())),
+//!     Err(e) => match e.downcast_ref::<vantage::Error>() {
+//!         Some(vantage::Error::NotFound { .. }) => Err(StatusCode::NOT_FOUND),
+//!         _ => Err(StatusCode::INTERNAL_SERVER_ERROR),
+//!     },
+//! }
+//! ```
+//!
+//! Methods that used to panic have a `try_` counterpart returning [`Error`],
+//! such as [`try_id()`] or [`try_add_join()`].
+//!
+//! [`try_id()`]: crate::sql::table::TableWithColumns::try_id()
+//! [`try_add_join()`]: crate::sql::Table::try_add_join()
+
+use std::fmt::Display;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Table has no such column
+    MissingColumn { table: String, column: String },
+    /// Tables can't be joined, because their aliases clash. Contains the aliases
+    /// used by both tables.
+    AliasConflict {
+        table: String,
+        other: String,
+        aliases: Vec<String>,
+    },
+    /// Query already has a field with the same alias, see [`Query::with_strict_fields()`]
+    ///
+    /// [`Query::with_strict_fields()`]: crate::sql::Query::with_strict_fields()
+    DuplicateField { table: String, field: String },
+    /// Query can't be rendered, for example it has no table set
+    RenderError(String),
+    /// Error reported by the database
+    DataSourceError(anyhow::Error),
+    /// Row can't be converted into the entity. Contains position of the row in
+    /// the result and the offending column, when known.
+    Deserialize {
+        row: Option<usize>,
+        column: Option<String>,
+        source: serde_json::Error,
+    },
+    /// Record with the requested id does not exist in the table
+    NotFound {
+        table: String,
+        id: serde_json::Value,
+    },
+    /// Fields of a struct, which can't be selected from the table
+    UnmatchedFields { table: String, fields: Vec<String> },
+    /// JSON filter document can't be converted into a condition
+    InvalidFilter(String),
+    /// Query didn't complete within the time limit and was cancelled
+    Timeout(Duration),
+}
+
+impl Error {
+    pub(crate) fn missing_column(table: impl Display, column: &str) -> Self {
+        Error::MissingColumn {
+            table: table.to_string(),
+            column: column.to_string(),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::MissingColumn { table, column } => {
+                write!(f, "Table '{}' has no field '{}'", table, column)
+            }
+            Error::AliasConflict {
+                table,
+                other,
+                aliases,
+            } => {
+                write!(
+                    f,
+                    "Table alias conflict while joining '{}' with '{}': both tables use alias {}",
+                    table,
+                    other,
+                    aliases.join(", ")
+                )
+            }
+            Error::DuplicateField { table, field } => {
+                write!(f, "Query for '{}' already has a field '{}'", table, field)
+            }
+            Error::RenderError(message) => write!(f, "Unable to render query: {}", message),
+            Error::DataSourceError(e) => write!(f, "Data source error: {}", e),
+            Error::Deserialize {
+                row,
+                column,
+                source,
+            } => {
+                write!(f, "Unable to deserialize record")?;
+                if let Some(row) = row {
+                    write!(f, " in row {}", row)?;
+                }
+                if let Some(column) = column {
+                    write!(f, ", column '{}'", column)?;
+                }
+                write!(f, ": {}", source)
+            }
+            Error::NotFound { table, id } => {
+                write!(f, "Record with id={} not found in '{}'", id, table)
+            }
+            Error::UnmatchedFields { table, fields } => {
+                write!(
+                    f,
+                    "Table '{}' has nothing to select for fields: {}",
+                    table,
+                    fields.join(", ")
+                )
+            }
+            Error::InvalidFilter(message) => write!(f, "Invalid filter: {}", message),
+            Error::Timeout(timeout) => write!(f, "Query timed out after {:?}", timeout),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::DataSourceError(e) => Some(e.as_ref()),
+            Error::Deserialize { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Deserialize {
+            row: None,
+            column: None,
+            source: e,
+        }
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl From<tokio_postgres::Error> for Error {
+    fn from(e: tokio_postgres::Error) -> Self {
+        Error::DataSourceError(e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downcast() {
+        let e: anyhow::Error = Error::missing_column("<User> { name=users }", "email").into();
+
+        assert_eq!(
+            e.to_string(),
+            "Table '<User> { name=users }' has no field 'email'"
+        );
+        assert!(matches!(
+            e.downcast_ref::<Error>(),
+            Some(Error::MissingColumn { column, .. }) if column == "email"
+        ));
+    }
+}
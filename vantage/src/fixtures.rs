@@ -0,0 +1,289 @@
+//! Records for integration tests, inserted before the test and deleted
+//! afterwards.
+//!
+//! [`FixtureSet`] inserts records in the order they were added. Children are
+//! inserted after their parent, with the foreign key set to the id of the
+//! parent:
+//!
+//! ```
+//! let mut fixtures = FixtureSet::new()
+//!     .add(Client::table(), Client { name: "Marty".to_string(), ..Default::default() })
+//!     .add_child(Order::table(), "client_id", Order { product_id: 1, ..Default::default() })
+//!     .add_child(Order::table(), "client_id", Order { product_id: 2, ..Default::default() });
+//!
+//! fixtures.insert().await?;
+//! let client_id = fixtures.id(0).unwrap();
+//! // ...
+//! fixtures.teardown().await?;
+//! ```
+//!
+//! Records are inserted through [`WritableDataSet`], so validation and hooks
+//! of the tables apply.
+
+use anyhow::{anyhow, Result};
+use futures::future::LocalBoxFuture;
+use serde_json::Value;
+
+use crate::dataset::WritableDataSet;
+use crate::prelude::{Entity, RelatedTable, Table};
+use crate::traits::datasource::DataSource;
+
+type InsertFn = Box<dyn Fn(Value) -> LocalBoxFuture<'static, Result<Option<Value>>>>;
+type DeleteFn = Box<dyn Fn(Value) -> LocalBoxFuture<'static, Result<()>>>;
+
+struct Fixture {
+    table_name: String,
+    values: Value,
+    /// Position of the parent fixture and the foreign key column
+    parent: Option<(usize, String)>,
+    insert: InsertFn,
+    delete: DeleteFn,
+}
+
+/// Related records for a test, see [`fixtures`](crate::fixtures).
+#[derive(Default)]
+pub struct FixtureSet {
+    fixtures: Vec<Fixture>,
+    ids: Vec<Option<Value>>,
+    last_parent: Option<usize>,
+    /// First error of the builder, returned by [`insert()`](Self::insert)
+    error: Option<String>,
+}
+
+impl FixtureSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a record, which becomes the parent for following
+    /// [`add_child()`](Self::add_child) calls.
+    pub fn add<T: DataSource, E: Entity>(mut self, table: Table<T, E>, record: E) -> Self {
+        self.last_parent = Some(self.fixtures.len());
+        self.push(table, record, None);
+        self
+    }
+
+    /// Adds a record, setting `foreign_key` to the id of the record added with
+    /// the last [`add()`](Self::add) call. Without a parent record,
+    /// [`insert()`](Self::insert) fails.
+    pub fn add_child<T: DataSource, E: Entity>(
+        mut self,
+        table: Table<T, E>,
+        foreign_key: &str,
+        record: E,
+    ) -> Self {
+        let parent = match self.last_parent {
+            Some(parent) => Some((parent, foreign_key.to_string())),
+            None => {
+                self.fail("add_child() must follow add() of the parent record".to_string());
+                None
+            }
+        };
+        self.push(table, record, parent);
+        self
+    }
+
+    fn fail(&mut self, error: String) {
+        self.error.get_or_insert(error);
+    }
+
+    fn push<T: DataSource, E: Entity>(
+        &mut self,
+        table: Table<T, E>,
+        record: E,
+        parent: Option<(usize, String)>,
+    ) {
+        let insert_table = table.clone();
+        let insert: InsertFn = Box::new(move |values| {
+            let table = insert_table.clone();
+            Box::pin(async move {
+                let record: E = serde_json::from_value(values)?;
+                table.insert(record).await
+            })
+        });
+        let delete_table = table.clone();
+        let delete: DeleteFn = Box::new(move |id| {
+            let table = delete_table.clone().with_id(id);
+            Box::pin(async move { table.delete().await.map(|_| ()) })
+        });
+
+        let table_name = table.get_table_name().cloned().unwrap_or_default();
+        let values = serde_json::to_value(record).unwrap_or_else(|e| {
+            self.fail(format!(
+                "Unable to serialize '{}' record: {}",
+                table_name, e
+            ));
+            Value::Null
+        });
+        self.fixtures.push(Fixture {
+            table_name,
+            values,
+            parent,
+            insert,
+            delete,
+        });
+    }
+
+    /// Inserts all records. If one of them fails, records inserted before it
+    /// can still be removed with [`teardown()`](Self::teardown). Nothing is
+    /// inserted if the set was built with an error.
+    pub async fn insert(&mut self) -> Result<()> {
+        if let Some(error) = &self.error {
+            return Err(anyhow!("{}", error));
+        }
+        for fixture in &self.fixtures[self.ids.len()..] {
+            let mut values = fixture.values.clone();
+            if let Some((parent, foreign_key)) = &fixture.parent {
+                let Some(Some(id)) = self.ids.get(*parent) else {
+                    return Err(anyhow!(
+                        "Parent of '{}' record has no id",
+                        fixture.table_name
+                    ));
+                };
+                let Value::Object(values) = &mut values else {
+                    return Err(anyhow!(
+                        "Record for '{}' must be a struct",
+                        fixture.table_name
+                    ));
+                };
+                values.insert(foreign_key.clone(), id.clone());
+            }
+            let id = (fixture.insert)(values).await?;
+            self.ids.push(id);
+        }
+        Ok(())
+    }
+
+    /// Id of the inserted record, in the order records were added.
+    pub fn id(&self, index: usize) -> Option<&Value> {
+        self.ids.get(index)?.as_ref()
+    }
+
+    /// Deletes inserted records, children first. Records, which were inserted
+    /// without returning an id, are left in place.
+    pub async fn teardown(&mut self) -> Result<()> {
+        while let Some(id) = self.ids.pop() {
+            if let Some(id) = id {
+                (self.fixtures[self.ids.len()].delete)(id).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+    struct Client {
+        id: i64,
+        name: String,
+    }
+    impl Entity for Client {}
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+    struct Order {
+        id: i64,
+        client_id: i64,
+        qty: i64,
+    }
+    impl Entity for Order {}
+
+    #[tokio::test]
+    async fn test_fixture_set() {
+        let memory = MemoryDataSource::new()
+            .with_table("client", vec![])
+            .with_table("order", vec![]);
+        let clients = Table::new_with_entity("client", memory.clone())
+            .with_id_column("id")
+            .with_column("name");
+        let orders = Table::new_with_entity("order", memory.clone())
+            .with_id_column("id")
+            .with_column("client_id")
+            .with_column("qty");
+
+        let client = |id: i64, name: &str| Client {
+            id,
+            name: name.to_string(),
+        };
+        let order = |id: i64, qty: i64| Order {
+            id,
+            qty,
+            ..Default::default()
+        };
+        let mut fixtures = FixtureSet::new()
+            .add(clients.clone(), client(1, "Marty"))
+            .add_child(orders.clone(), "client_id", order(10, 3))
+            .add_child(orders.clone(), "client_id", order(11, 5))
+            .add(clients.clone(), client(2, "Doc"))
+            .add_child(orders.clone(), "client_id", order(12, 1));
+
+        fixtures.insert().await.unwrap();
+        assert_eq!(fixtures.id(3), Some(&json!(2)));
+        assert_eq!(
+            orders
+                .get_all_untyped()
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|o| o["client_id"].clone())
+                .collect::<Vec<_>>(),
+            vec![json!(1), json!(1), json!(2)]
+        );
+
+        fixtures.teardown().await.unwrap();
+        assert!(memory.rows("client").unwrap().is_empty());
+        assert!(memory.rows("order").unwrap().is_empty());
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+    struct Grid {
+        cells: HashMap<(i64, i64), i64>,
+    }
+    impl Entity for Grid {}
+
+    #[tokio::test]
+    async fn test_builder_errors() {
+        let memory = MemoryDataSource::new()
+            .with_table("client", vec![])
+            .with_table("order", vec![])
+            .with_table("grid", vec![]);
+        let clients = Table::new_with_entity("client", memory.clone())
+            .with_id_column("id")
+            .with_column("name");
+        let orders = Table::new_with_entity("order", memory.clone())
+            .with_id_column("id")
+            .with_column("client_id")
+            .with_column("qty");
+
+        let mut fixtures = FixtureSet::new()
+            .add_child(orders.clone(), "client_id", Order::default())
+            .add(clients.clone(), Client::default());
+        assert_eq!(
+            fixtures.insert().await.unwrap_err().to_string(),
+            "add_child() must follow add() of the parent record"
+        );
+        assert!(memory.rows("client").unwrap().is_empty());
+
+        let grid = Grid {
+            cells: HashMap::from([((0, 0), 1)]),
+        };
+        let mut fixtures = FixtureSet::new()
+            .add(clients, Client::default())
+            .add(Table::new_with_entity("grid", memory.clone()), grid);
+        assert!(fixtures
+            .insert()
+            .await
+            .unwrap_err()
+            .to_string()
+            .starts_with("Unable to serialize 'grid' record"));
+        assert!(memory.rows("client").unwrap().is_empty());
+    }
+}
@@ -1,24 +1,29 @@
 use core::fmt;
 use std::sync::Arc;
 
-use serde_json::Value;
-
 use crate::{
     prelude::{Expression, Table},
-    traits::{datasource::DataSource, entity::Entity},
+    traits::{datasource::DataSource, entity::EmptyEntity},
 };
 
+/// Expression of a table, which is calculated when the query is built. Storage does
+/// not depend on the entity of the table, so that [`Table::into_entity()`] keeps
+/// lazy expressions. Tables are passed to `BeforeQuery` as [`EmptyEntity`].
 #[derive(Clone)]
-pub enum LazyExpression<T: DataSource, E: Entity> {
-    AfterQuery(Arc<Box<dyn Fn(&Value) -> Value + Send + Sync + 'static>>),
-    BeforeQuery(Arc<Box<dyn Fn(&Table<T, E>) -> Expression + Send + Sync + 'static>>),
+pub enum LazyExpression<T: DataSource> {
+    BeforeQuery(TableExpressionFx<T>),
+    /// Same as `BeforeQuery`, but always selected, like a physical column
+    Computed(TableExpressionFx<T>),
 }
 
-impl<T: DataSource, E: Entity> fmt::Debug for LazyExpression<T, E> {
+pub type TableExpressionFx<T> =
+    Arc<Box<dyn Fn(&Table<T, EmptyEntity>) -> Expression + Send + Sync + 'static>>;
+
+impl<T: DataSource> fmt::Debug for LazyExpression<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            LazyExpression::AfterQuery(_) => f.write_str("AfterQuery(<closure>)"),
             LazyExpression::BeforeQuery(_) => f.write_str("BeforeQuery(<closure>)"),
+            LazyExpression::Computed(_) => f.write_str("Computed(<closure>)"),
         }
     }
 }
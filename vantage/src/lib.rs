@@ -2,9 +2,18 @@
 pub mod dataset;
 
 mod datasource;
+pub mod error;
+pub mod fixtures;
 mod lazy_expression;
 pub mod mocks;
 pub mod prelude;
+pub mod runtime;
+pub mod session;
 pub mod sql;
+pub mod testing;
 mod traits;
 mod uniqid;
+#[cfg(feature = "axum")]
+pub mod web;
+
+pub use error::Error;
@@ -1,64 +1,382 @@
-use std::{ops::Deref, sync::Arc};
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use crate::sql::Query;
-use crate::traits::datasource::DataSource;
-use anyhow::Result;
+use crate::sql::dialect::{Dialect, PostgresDialect};
+use crate::sql::query::{QuerySource, QueryType, SqlQuery};
+use crate::sql::{Chunk, Query};
+use crate::testing::{normalize_sql, render_sql};
+use crate::traits::datasource::{DataSource, ExecResult};
+use anyhow::{anyhow, Result};
+use futures::stream::{self, BoxStream, StreamExt};
+use regex::Regex;
 use serde_json::{Map, Value};
 
+/// Decides if an expectation of [`MockDataSource`] applies to a query.
+#[derive(Clone, Debug)]
+pub enum QueryMatcher {
+    /// SQL with `{}` placeholders, compared after [`normalize_sql()`]
+    Sql(String),
+    /// Regular expression, matched against SQL with `{}` placeholders
+    Regex(Regex),
+    /// Type of the query (`SELECT`, `INSERT`, `UPDATE`, `REPLACE`, `DELETE`)
+    /// and the table it is built on
+    Shape { query_type: String, table: String },
+}
+
+impl QueryMatcher {
+    pub fn sql(sql: &str) -> Self {
+        QueryMatcher::Sql(normalize_sql(sql))
+    }
+
+    /// Panics if the regular expression is invalid.
+    pub fn regex(pattern: &str) -> Self {
+        QueryMatcher::Regex(Regex::new(pattern).unwrap())
+    }
+
+    pub fn select(table: &str) -> Self {
+        Self::shape("SELECT", table)
+    }
+    pub fn insert(table: &str) -> Self {
+        Self::shape("INSERT", table)
+    }
+    pub fn update(table: &str) -> Self {
+        Self::shape("UPDATE", table)
+    }
+    pub fn delete(table: &str) -> Self {
+        Self::shape("DELETE", table)
+    }
+
+    fn shape(query_type: &str, table: &str) -> Self {
+        QueryMatcher::Shape {
+            query_type: query_type.to_string(),
+            table: table.to_string(),
+        }
+    }
+
+    pub fn matches(&self, query: &Query) -> bool {
+        match self {
+            QueryMatcher::Sql(sql) => render_sql(query) == *sql,
+            QueryMatcher::Regex(regex) => regex.is_match(&render_sql(query)),
+            QueryMatcher::Shape { query_type, table } => {
+                query_type_name(query.get_type()) == Some(query_type.as_str())
+                    && matches!(query.get_source(), QuerySource::Table(t, _) if t == table)
+            }
+        }
+    }
+}
+
+impl Display for QueryMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryMatcher::Sql(sql) => write!(f, "{}", sql),
+            QueryMatcher::Regex(regex) => write!(f, "/{}/", regex),
+            QueryMatcher::Shape { query_type, table } => write!(f, "{} {}", query_type, table),
+        }
+    }
+}
+
+fn query_type_name(query_type: &QueryType) -> Option<&'static str> {
+    match query_type {
+        QueryType::Select => Some("SELECT"),
+        QueryType::Insert => Some("INSERT"),
+        QueryType::Update => Some("UPDATE"),
+        QueryType::Replace => Some("REPLACE"),
+        QueryType::Delete => Some("DELETE"),
+        QueryType::Expression(_) => None,
+    }
+}
+
+/// Failure returned by [`MockDataSource`] instead of rows. Model code can
+/// find it with `error.downcast_ref::<MockError>()`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MockError {
+    /// Connection to the database was lost
+    Connection,
+    /// Query violates the named constraint
+    ConstraintViolation(String),
+    /// Any other error reported by the database
+    Other(String),
+}
+
+impl Display for MockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MockError::Connection => write!(f, "Connection to the database was lost"),
+            MockError::ConstraintViolation(constraint) => {
+                write!(f, "Query violates constraint '{}'", constraint)
+            }
+            MockError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for MockError {}
+
+#[derive(Debug)]
+enum MockResponse {
+    Rows(Vec<Map<String, Value>>),
+    Error(MockError),
+}
+
+#[derive(Debug)]
+struct Expectation {
+    matcher: QueryMatcher,
+    response: MockResponse,
+    hits: usize,
+}
+
+/// Query executed by [`MockDataSource`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockCall {
+    /// SQL with `{}` placeholders
+    pub sql: String,
+    /// Values of the placeholders
+    pub params: Vec<Value>,
+    /// False if expectations were registered, but none matched the query
+    pub expected: bool,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    expectations: Vec<Expectation>,
+    calls: Vec<MockCall>,
+    latency: Duration,
+}
+
+/// Data source for tests. Without expectations every query returns `data`.
+///
+/// Once [`with_expectation()`](Self::with_expectation) is used, queries are
+/// answered only by matching expectations and [`verify()`](Self::verify) can
+/// check that all of them were used:
+///
+/// ```
+/// let db = MockDataSource::new(&json!([]))
+///     .with_expectation(QueryMatcher::select("product"), &json!([{"id": 1}]));
+///
+/// let products = Table::new("product", db.clone()).with_id_column("id");
+/// products.get_all_untyped().await?;
+///
+/// db.verify();
+/// ```
+///
+/// Failures are programmed the same way, so an expectation with
+/// [`with_error()`](Self::with_error) followed by a regular one makes the
+/// first query fail and the retry succeed.
+///
+/// Clones share expectations and recorded calls.
 #[derive(Clone, Debug)]
 pub struct MockDataSource {
     data: Arc<Vec<Map<String, Value>>>,
+    state: Arc<Mutex<MockState>>,
+    dialect: Arc<dyn Dialect>,
+}
+
+fn rows(data: &Value) -> Vec<Map<String, Value>> {
+    data.as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x.as_object().unwrap().clone())
+        .collect()
 }
 
 impl MockDataSource {
     pub fn new(data: &Value) -> MockDataSource {
-        let data = data
-            .as_array()
-            .unwrap()
-            .clone()
-            .into_iter()
-            .map(|x| x.as_object().unwrap().clone())
-            .collect();
         MockDataSource {
-            data: Arc::new(data),
+            data: Arc::new(rows(data)),
+            state: Arc::new(Mutex::new(MockState::default())),
+            dialect: Arc::new(PostgresDialect),
         }
     }
 
+    /// Render queries for another database, such as MySQL. Postgres by default.
+    pub fn with_dialect(mut self, dialect: impl Dialect + 'static) -> Self {
+        self.dialect = Arc::new(dialect);
+        self
+    }
+
     pub fn data(&self) -> &Vec<Map<String, Value>> {
         &self.data
     }
+
+    /// Queries matching `matcher` will return `rows` (array of objects).
+    /// Expectations matching the same query are used in turn, the last
+    /// one repeats.
+    pub fn with_expectation(self, matcher: QueryMatcher, rows: &Value) -> Self {
+        self.add_expectation(matcher, rows);
+        self
+    }
+
+    pub fn add_expectation(&self, matcher: QueryMatcher, data: &Value) {
+        self.push_expectation(matcher, MockResponse::Rows(rows(data)));
+    }
+
+    /// Queries matching `matcher` will fail with `error`. Used in turn with
+    /// other expectations, same as [`with_expectation()`](Self::with_expectation).
+    pub fn with_error(self, matcher: QueryMatcher, error: MockError) -> Self {
+        self.add_error(matcher, error);
+        self
+    }
+
+    pub fn add_error(&self, matcher: QueryMatcher, error: MockError) {
+        self.push_expectation(matcher, MockResponse::Error(error));
+    }
+
+    fn push_expectation(&self, matcher: QueryMatcher, response: MockResponse) {
+        self.state.lock().unwrap().expectations.push(Expectation {
+            matcher,
+            response,
+            hits: 0,
+        });
+    }
+
+    /// Delays every query by `latency`.
+    pub fn with_latency(self, latency: Duration) -> Self {
+        self.set_latency(latency);
+        self
+    }
+
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// Queries executed so far, in order.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// Panics, listing expectations which were never used and queries which
+    /// didn't match any expectation.
+    #[track_caller]
+    pub fn verify(&self) {
+        let state = self.state.lock().unwrap();
+        let mut problems = Vec::new();
+        for expectation in state.expectations.iter().filter(|e| e.hits == 0) {
+            problems.push(format!("  unmatched: {}", expectation.matcher));
+        }
+        for call in state.calls.iter().filter(|c| !c.expected) {
+            problems.push(format!("  unexpected: {}", call.sql));
+        }
+        if !problems.is_empty() {
+            panic!(
+                "MockDataSource verification failed:\n{}",
+                problems.join("\n")
+            );
+        }
+    }
+
+    /// Records the query and finds rows for it, after the configured latency.
+    async fn respond(&self, query: &Query) -> Result<Vec<Map<String, Value>>> {
+        let latency = self.state.lock().unwrap().latency;
+        if !latency.is_zero() {
+            crate::runtime::sleep(latency).await;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let sql = render_sql(query);
+        let params = query.render_chunk().split().1;
+        if state.expectations.is_empty() {
+            state.calls.push(MockCall {
+                sql,
+                params,
+                expected: true,
+            });
+            return Ok(self.data.to_vec());
+        }
+
+        let matching: Vec<usize> = state
+            .expectations
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.matcher.matches(query))
+            .map(|(i, _)| i)
+            .collect();
+        let found = matching
+            .iter()
+            .find(|&&i| state.expectations[i].hits == 0)
+            .or(matching.last())
+            .copied();
+
+        state.calls.push(MockCall {
+            sql: sql.clone(),
+            params,
+            expected: found.is_some(),
+        });
+        let Some(i) = found else {
+            return Err(anyhow!("Unexpected query: {}", sql));
+        };
+        let expectation = &mut state.expectations[i];
+        expectation.hits += 1;
+        match &expectation.response {
+            MockResponse::Rows(rows) => Ok(rows.clone()),
+            MockResponse::Error(error) => Err(error.clone().into()),
+        }
+    }
 }
 
 impl DataSource for MockDataSource {
-    async fn query_fetch(&self, _query: &Query) -> Result<Vec<Map<String, Value>>> {
-        Ok(self.data.deref().clone())
+    fn dialect(&self) -> Arc<dyn Dialect> {
+        self.dialect.clone()
     }
 
-    async fn query_exec(&self, _query: &Query) -> Result<Option<Value>> {
-        Ok(None)
+    async fn query_fetch(&self, query: &Query) -> Result<Vec<Map<String, Value>>> {
+        self.respond(query).await
+    }
+
+    /// Without expectations `data` stands for rows of the SELECT queries, so write
+    /// queries affect nothing. Rows of a matching expectation are returned as
+    /// RETURNING rows and their number is reported as affected.
+    async fn query_exec(&self, query: &Query) -> Result<ExecResult> {
+        let returned = self.respond(query).await?;
+        if self.state.lock().unwrap().expectations.is_empty() {
+            return Ok(ExecResult::default());
+        }
+        Ok(ExecResult {
+            rows_affected: returned.len() as u64,
+            returned,
+        })
     }
 
     async fn query_insert(
         &self,
-        _query: &Query,
+        query: &Query,
         _rows: Vec<Vec<serde_json::Value>>,
     ) -> anyhow::Result<()> {
-        todo!()
+        self.respond(query).await.map(|_| ())
     }
 
-    async fn query_one(&self, _query: &Query) -> Result<Value> {
-        todo!()
+    async fn query_one(&self, query: &Query) -> Result<Value> {
+        let row = self.query_row(query).await?;
+        row.into_iter()
+            .next()
+            .map(|(_, value)| value)
+            .ok_or_else(|| anyhow!("Row has no columns"))
     }
     async fn query_row(&self, query: &Query) -> Result<Map<String, Value>> {
-        todo!()
+        self.respond(query)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No rows returned"))
     }
     async fn query_col(&self, query: &Query) -> Result<Vec<Value>> {
-        todo!()
+        Ok(self
+            .respond(query)
+            .await?
+            .into_iter()
+            .filter_map(|row| row.into_iter().next().map(|(_, value)| value))
+            .collect())
+    }
+    async fn query_stream(
+        &self,
+        query: &Query,
+    ) -> Result<BoxStream<'static, Result<Map<String, Value>>>> {
+        Ok(stream::iter(self.respond(query).await?.into_iter().map(Ok)).boxed())
     }
 }
 
 impl PartialEq for MockDataSource {
-    fn eq(&self, other: &MockDataSource) -> bool {
+    fn eq(&self, _other: &MockDataSource) -> bool {
         false
     }
 }
@@ -68,6 +386,7 @@ mod tests {
     use super::*;
     use crate::sql::Query;
     use crate::traits::datasource::DataSource;
+    use crate::{expr, sql::Expression};
     use serde_json::json;
     use tokio;
 
@@ -93,4 +412,88 @@ mod tests {
 
         assert_eq!(result.unwrap(), *data_source.data());
     }
+
+    #[tokio::test]
+    async fn test_expectations() {
+        let data_source = MockDataSource::new(&json!([]))
+            .with_expectation(
+                QueryMatcher::sql("SELECT name FROM users WHERE id = $1"),
+                &json!([{"name": "John"}]),
+            )
+            .with_expectation(QueryMatcher::regex("^SELECT .* FROM users"), &json!([]))
+            .with_expectation(QueryMatcher::delete("users"), &json!([]))
+            .with_expectation(QueryMatcher::update("users"), &json!([]));
+
+        let users = Query::new()
+            .with_table("users", None)
+            .with_column_field("name");
+        let query = users.clone().with_where_condition(expr!("id = {}", 1));
+        assert_eq!(data_source.query_one(&query).await.unwrap(), json!("John"));
+        assert!(data_source.query_fetch(&users).await.unwrap().is_empty());
+        data_source
+            .query_exec(&users.clone().with_type(QueryType::Delete))
+            .await
+            .unwrap();
+        assert!(data_source
+            .query_fetch(&Query::new().with_table("orders", None))
+            .await
+            .is_err());
+
+        assert_eq!(
+            data_source
+                .calls()
+                .iter()
+                .map(|c| c.sql.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "SELECT name FROM users WHERE id = {}",
+                "SELECT name FROM users",
+                "DELETE FROM users",
+                "SELECT * FROM orders",
+            ]
+        );
+
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| data_source.verify()));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert_eq!(
+            message,
+            "MockDataSource verification failed:\n  unmatched: UPDATE users\n  unexpected: SELECT * FROM orders"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_errors_and_latency() {
+        let data_source = MockDataSource::new(&json!([]))
+            .with_latency(Duration::from_millis(20))
+            .with_error(QueryMatcher::select("users"), MockError::Connection)
+            .with_expectation(QueryMatcher::select("users"), &json!([{"id": 1}]))
+            .with_expectation(QueryMatcher::select("orders"), &json!([{"id": 2}]))
+            .with_error(
+                QueryMatcher::insert("users"),
+                MockError::ConstraintViolation("users_pkey".to_string()),
+            );
+
+        let users = Query::new().with_table("users", None);
+        let orders = Query::new().with_table("orders", None);
+
+        let start = std::time::Instant::now();
+        let error = data_source.query_fetch(&users).await.unwrap_err();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert_eq!(
+            error.downcast_ref::<MockError>(),
+            Some(&MockError::Connection)
+        );
+
+        assert_eq!(data_source.query_one(&users).await.unwrap(), json!(1));
+        assert_eq!(data_source.query_one(&orders).await.unwrap(), json!(2));
+        assert_eq!(data_source.query_one(&users).await.unwrap(), json!(1));
+
+        let error = data_source
+            .query_insert(&users.clone().with_type(QueryType::Insert), vec![])
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Query violates constraint 'users_pkey'");
+        data_source.verify();
+    }
 }
@@ -2,4 +2,4 @@ pub mod datasource;
 // mod postgres;
 // mod rusqlite;
 
-pub use datasource::MockDataSource;
+pub use datasource::{MockCall, MockDataSource, MockError, QueryMatcher};
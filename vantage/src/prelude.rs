@@ -1,19 +1,50 @@
+pub use crate::assert_sql;
+pub use crate::dataset::FederatedJoin;
 pub use crate::dataset::ReadableDataSet;
 pub use crate::dataset::WritableDataSet;
+#[cfg(feature = "blocking")]
+pub use crate::dataset::{BlockingReadableDataSet, BlockingWritableDataSet};
+pub use crate::datasource::associated_query::{AssociatedExpressionArc, AssociatedQuery};
+#[cfg(feature = "clickhouse")]
+pub use crate::datasource::clickhouse::{ClickHouse, ClickHouseQuery};
+#[cfg(feature = "csv")]
+pub use crate::datasource::csv::CsvDataSource;
+#[cfg(feature = "duckdb")]
+pub use crate::datasource::duckdb::DuckDb;
+pub use crate::datasource::memory::MemoryDataSource;
+#[cfg(feature = "mongodb")]
+pub use crate::datasource::mongo::Mongo;
+#[cfg(feature = "mssql")]
+pub use crate::datasource::mssql::Mssql;
+#[cfg(feature = "postgres")]
 pub use crate::datasource::postgres::*;
+#[cfg(feature = "rest")]
+pub use crate::datasource::rest::RestDataSource;
 pub use crate::expr;
 pub use crate::expr_arc;
-pub use crate::mocks::MockDataSource;
+pub use crate::fixtures::FixtureSet;
+pub use crate::mocks::{MockDataSource, MockError, QueryMatcher};
+pub use crate::session::Session;
 pub use crate::sql::table::Column;
+pub use crate::table_columns;
+pub use crate::table_refs;
 pub use crate::traits::column::SqlField;
-pub use crate::traits::DataSource;
+pub use crate::traits::{DataSource, ExecResult};
 pub use crate::{
     sql::{
+        aggregate::{avg, count, count_all, count_distinct, max, min, string_agg, sum, Aggregate},
         chunk::Chunk,
+        condition_tree::ConditionTree,
+        dialect::{
+            ClickHouseDialect, Dialect, DuckDbDialect, MssqlDialect, MySqlDialect, PostgresDialect,
+            SqliteDialect,
+        },
         expression::{Expression, ExpressionArc},
-        query::{JoinQuery, Query},
+        query::{Direction, JoinQuery, JoinType, LockMode, Query},
+        sql_type::SqlType,
         table::*,
         Operations, WrapArc,
     },
     traits::entity::{EmptyEntity, Entity},
+    traits::from_sql_value::FromSqlValue,
 };
@@ -0,0 +1,164 @@
+//! Async runtime used by data sources.
+//!
+//! Data sources spawn background tasks (for example to drive a Postgres connection),
+//! sleep between connection attempts and enforce query timeouts. All of that goes
+//! through this module, so the rest of the crate does not depend on a particular
+//! runtime. It is backed by tokio, or by async-std with the `async-std` feature.
+//! Data sources built on tokio clients (Postgres, SQL Server, DuckDB, CSV files)
+//! still need a tokio runtime for their I/O.
+//!
+//! Under wasm32 the `async-std` feature is required, as tokio has no timers in
+//! the browser. Tasks are then spawned on the thread of the page.
+//!
+//! With the `blocking` feature, [`block_on()`] runs a future to completion from
+//! synchronous code - CLI tools, scripts or sync tests:
+//!
+//! ```
+//! let postgres = runtime::block_on(Postgres::connect(&url, ConnectOptions::default()))?;
+//! let clients = Client::table().get_blocking()?;
+//! ```
+
+use std::future::Future;
+use std::time::Duration;
+
+/// Runs `future` in the background. Its result is discarded.
+#[cfg_attr(not(any(feature = "postgres", feature = "graphql")), allow(dead_code))]
+pub(crate) fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    #[cfg(all(feature = "async-std", target_arch = "wasm32"))]
+    async_std::task::spawn_local(future);
+    #[cfg(all(feature = "async-std", not(target_arch = "wasm32")))]
+    async_std::task::spawn(future);
+    #[cfg(not(feature = "async-std"))]
+    tokio::spawn(future);
+}
+
+/// Identifies the task, which is currently running, or the thread when called
+/// outside of a task (for example from `block_on()`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum TaskKey {
+    Task(tokio::task::Id),
+    #[cfg(feature = "async-std")]
+    AsyncStdTask(async_std::task::TaskId),
+    Thread(std::thread::ThreadId),
+}
+
+/// Tasks of both runtimes are recognized, as tokio may still run the code, which
+/// uses a data source.
+#[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+pub(crate) fn task_key() -> TaskKey {
+    #[cfg(feature = "async-std")]
+    if let Some(task) = async_std::task::try_current() {
+        return TaskKey::AsyncStdTask(task.id());
+    }
+    match tokio::task::try_id() {
+        Some(id) => TaskKey::Task(id),
+        None => TaskKey::Thread(std::thread::current().id()),
+    }
+}
+
+/// Waits until `duration` has elapsed.
+pub(crate) async fn sleep(duration: Duration) {
+    #[cfg(feature = "async-std")]
+    async_std::task::sleep(duration).await;
+    #[cfg(not(feature = "async-std"))]
+    tokio::time::sleep(duration).await
+}
+
+/// Awaits `future`, returning `None` if it does not complete within `duration`.
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    #[cfg(feature = "async-std")]
+    return async_std::future::timeout(duration, future).await.ok();
+    #[cfg(not(feature = "async-std"))]
+    tokio::time::timeout(duration, future).await.ok()
+}
+
+/// Runs `future` to completion, blocking the current thread.
+///
+/// Outside of an async context the future is executed on a shared runtime, which
+/// is started on first use and also runs the background tasks of data sources
+/// connected through it. When called from a task of a multi-threaded runtime, the
+/// future is executed by that runtime without stalling its other tasks.
+///
+/// Panics when called from a current-thread runtime, such as the one created by
+/// `#[tokio::test]`.
+#[cfg(all(feature = "blocking", not(feature = "async-std")))]
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    use std::sync::OnceLock;
+    use tokio::runtime::{Handle, Runtime};
+
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+    match Handle::try_current() {
+        Ok(handle) => tokio::task::block_in_place(|| handle.block_on(future)),
+        Err(_) => RUNTIME
+            .get_or_init(|| Runtime::new().expect("Unable to start async runtime"))
+            .block_on(future),
+    }
+}
+
+/// Runs `future` to completion, blocking the current thread. Background tasks
+/// of data sources run on the executor of async-std.
+#[cfg(all(feature = "blocking", feature = "async-std"))]
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    async_std::task::block_on(future)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_timeout() {
+        assert_eq!(timeout(Duration::from_secs(1), async { 1 }).await, Some(1));
+        assert_eq!(
+            timeout(Duration::from_millis(1), sleep(Duration::from_secs(1))).await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_task_key() {
+        let key = task_key();
+        assert_eq!(key, task_key());
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        spawn(async move {
+            sender.send(task_key()).unwrap();
+        });
+        assert_ne!(receiver.await.unwrap(), key);
+    }
+
+    #[cfg(feature = "async-std")]
+    #[test]
+    fn test_async_std() {
+        async_std::task::block_on(async {
+            assert_eq!(timeout(Duration::from_secs(1), async { 1 }).await, Some(1));
+            assert_eq!(
+                timeout(Duration::from_millis(1), sleep(Duration::from_secs(1))).await,
+                None
+            );
+
+            let key = task_key();
+            assert!(matches!(key, TaskKey::AsyncStdTask(_)));
+            let (sender, receiver) = futures::channel::oneshot::channel();
+            spawn(async move {
+                sender.send(task_key()).unwrap();
+            });
+            assert_ne!(receiver.await.unwrap(), key);
+        });
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_block_on() {
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        block_on(async {
+            spawn(async move {
+                sender.send(42).unwrap();
+            })
+        });
+        assert_eq!(block_on(receiver).unwrap(), 42);
+    }
+}
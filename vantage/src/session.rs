@@ -0,0 +1,254 @@
+//! Unit of work for business operations, which touch many records.
+//!
+//! [`Session`] remembers loaded entities by table and id, so loading the same
+//! record again does not execute a query. Changes are collected and written
+//! on [`commit()`](Session::commit) within one transaction:
+//!
+//! ```
+//! let mut session = Session::new(postgres());
+//!
+//! let mut client = session.load(&Client::table(), 1.into()).await?;
+//! client.balance -= 10;
+//! session.update(&Client::table(), 1.into(), client)?;
+//! session.insert(&Order::table(), Order { client_id: 1, ..Default::default() });
+//! session.delete(&Cart::table(), 7.into());
+//!
+//! session.commit().await?;
+//! ```
+//!
+//! Inserts are executed first, in the order they were added, so parents should
+//! be added before their children. Updates follow, and deletes are executed
+//! last, in reverse order.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use futures::future::LocalBoxFuture;
+use serde_json::Value;
+
+use crate::dataset::WritableDataSet;
+use crate::prelude::{Entity, RelatedTable, Table, TableWithColumns};
+use crate::traits::datasource::DataSource;
+
+type Operation = Box<dyn FnOnce() -> LocalBoxFuture<'static, Result<()>>>;
+
+/// Table name and id of a record, as JSON
+type Key = (String, String);
+
+fn key<T: DataSource, E: Entity>(table: &Table<T, E>, id: &Value) -> Key {
+    (
+        table.get_table_name().cloned().unwrap_or_default(),
+        id.to_string(),
+    )
+}
+
+/// Identity map with pending changes, see [`session`](crate::session).
+pub struct Session<T: DataSource> {
+    data_source: T,
+    loaded: HashMap<Key, Value>,
+    inserts: Vec<Operation>,
+    updates: Vec<(Key, Operation)>,
+    deletes: Vec<Operation>,
+}
+
+impl<T: DataSource> Session<T> {
+    pub fn new(data_source: T) -> Self {
+        Session {
+            data_source,
+            loaded: HashMap::new(),
+            inserts: Vec::new(),
+            updates: Vec::new(),
+            deletes: Vec::new(),
+        }
+    }
+
+    /// Fetches the record, unless it was loaded or updated in this session.
+    /// Returns [`Error::NotFound`](crate::Error::NotFound) if it does not exist.
+    pub async fn load<E: Entity>(&mut self, table: &Table<T, E>, id: Value) -> Result<E> {
+        let key = key(table, &id);
+        if let Some(data) = self.loaded.get(&key) {
+            return Ok(serde_json::from_value(data.clone()).map_err(crate::Error::from)?);
+        }
+        let entity = table.entry(id).get().await?;
+        self.loaded.insert(key, serde_json::to_value(&entity)?);
+        Ok(entity)
+    }
+
+    /// Schedules an insert of the record.
+    pub fn insert<E: Entity>(&mut self, table: &Table<T, E>, entity: E) {
+        let table = table.clone();
+        self.inserts.push(Box::new(move || {
+            Box::pin(async move { table.insert(entity).await.map(|_| ()) })
+        }));
+    }
+
+    /// Schedules an update of all fields of the record, except id. Following
+    /// loads will return `entity`, repeated updates replace each other.
+    pub fn update<E: Entity>(&mut self, table: &Table<T, E>, id: Value, entity: E) -> Result<()> {
+        let mut values = serde_json::to_value(&entity)?;
+        let key = key(table, &id);
+        self.loaded.insert(key.clone(), values.clone());
+
+        if let Value::Object(values) = &mut values {
+            values.remove(&table.try_id()?.name());
+        }
+        let entry = table.entry(id);
+        self.updates.retain(|(k, _)| *k != key);
+        self.updates.push((
+            key,
+            Box::new(move || Box::pin(async move { entry.patch(values).await.map(|_| ()) })),
+        ));
+        Ok(())
+    }
+
+    /// Schedules a delete of the record. Its pending update is dropped.
+    pub fn delete<E: Entity>(&mut self, table: &Table<T, E>, id: Value) {
+        let key = key(table, &id);
+        self.loaded.remove(&key);
+        self.updates.retain(|(k, _)| *k != key);
+
+        let entry = table.entry(id);
+        self.deletes.push(Box::new(move || {
+            Box::pin(async move { entry.delete().await.map(|_| ()) })
+        }));
+    }
+
+    /// Number of scheduled inserts, updates and deletes.
+    pub fn pending(&self) -> usize {
+        self.inserts.len() + self.updates.len() + self.deletes.len()
+    }
+
+    /// Discards scheduled changes and forgets loaded records.
+    pub fn clear(&mut self) {
+        self.loaded.clear();
+        self.inserts.clear();
+        self.updates.clear();
+        self.deletes.clear();
+    }
+
+    /// Executes scheduled changes in a transaction. If one of them or the commit
+    /// fails, the transaction is rolled back, loaded records are forgotten and the
+    /// error of the change is returned. Scheduled changes are discarded either way.
+    pub async fn commit(&mut self) -> Result<()> {
+        let operations: Vec<Operation> = self
+            .inserts
+            .drain(..)
+            .chain(self.updates.drain(..).map(|(_, op)| op))
+            .chain(self.deletes.drain(..).rev())
+            .collect();
+        if operations.is_empty() {
+            return Ok(());
+        }
+
+        self.data_source.begin_transaction().await?;
+        for operation in operations {
+            if let Err(e) = operation().await {
+                self.loaded.clear();
+                if let Err(rollback) = self.data_source.rollback_transaction().await {
+                    log::error!("Unable to roll back session changes: {:#}", rollback);
+                }
+                return Err(e);
+            }
+        }
+        let result = self.data_source.commit_transaction().await;
+        if result.is_err() {
+            self.loaded.clear();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+    struct Client {
+        id: i64,
+        name: String,
+    }
+    impl Entity for Client {}
+
+    fn clients(db: MockDataSource) -> Table<MockDataSource, Client> {
+        Table::new_with_entity("client", db)
+            .with_id_column("id")
+            .with_column("name")
+    }
+
+    #[tokio::test]
+    async fn test_session() {
+        let db = MockDataSource::new(&json!([]))
+            .with_expectation(
+                QueryMatcher::select("client"),
+                &json!([{"id": 1, "name": "Marty"}]),
+            )
+            .with_expectation(QueryMatcher::insert("client"), &json!([{"id": 3}]))
+            .with_expectation(QueryMatcher::update("client"), &json!([]))
+            .with_expectation(QueryMatcher::delete("client"), &json!([]));
+        let clients = clients(db.clone());
+        let mut session = Session::new(db.clone());
+
+        let mut marty = session.load(&clients, 1.into()).await.unwrap();
+        assert_eq!(session.load(&clients, 1.into()).await.unwrap(), marty);
+
+        marty.name = "Marty McFly".to_string();
+        session.update(&clients, 1.into(), marty.clone()).unwrap();
+        assert_eq!(session.load(&clients, 1.into()).await.unwrap(), marty);
+
+        session.delete(&clients, 2.into());
+        session.insert(
+            &clients,
+            Client {
+                id: 3,
+                name: "Doc".to_string(),
+            },
+        );
+        assert_eq!(session.pending(), 3);
+        session.commit().await.unwrap();
+        assert_eq!(session.pending(), 0);
+
+        assert_eq!(
+            db.calls().into_iter().map(|c| c.sql).collect::<Vec<_>>(),
+            vec![
+                "SELECT id, name FROM client WHERE (id = {})",
+                "INSERT INTO client (id, name) VALUES ({}, {}) RETURNING id",
+                "UPDATE client SET name = {} WHERE (id = {})",
+                "DELETE FROM client WHERE (id = {})",
+            ]
+        );
+        db.verify();
+    }
+
+    #[tokio::test]
+    async fn test_failed_commit() {
+        let db = MockDataSource::new(&json!([]))
+            .with_expectation(
+                QueryMatcher::select("client"),
+                &json!([{"id": 1, "name": "Marty"}]),
+            )
+            .with_error(
+                QueryMatcher::update("client"),
+                MockError::ConstraintViolation("name_unique".to_string()),
+            );
+        let clients = clients(db.clone());
+        let mut session = Session::new(db.clone());
+
+        let mut marty = session.load(&clients, 1.into()).await.unwrap();
+        marty.name = "Doc".to_string();
+        session.update(&clients, 1.into(), marty).unwrap();
+        let error = session.commit().await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<MockError>(),
+            Some(&MockError::ConstraintViolation("name_unique".to_string()))
+        );
+
+        // the record is fetched again
+        assert_eq!(session.pending(), 0);
+        session.load(&clients, 1.into()).await.unwrap();
+        assert_eq!(db.calls().len(), 3);
+    }
+}
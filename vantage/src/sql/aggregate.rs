@@ -0,0 +1,138 @@
+use crate::expr_arc;
+use crate::sql::{Chunk, Expression, ExpressionArc, Operations};
+
+/// Aggregate function with an alias, used with [`GroupedTable::aggregate()`]:
+///
+/// ```
+/// let stats = orders
+///     .group_by(orders.client_id())
+///     .aggregate([count_all().as_("orders"), sum(orders.total()).as_("total")]);
+/// // SELECT client_id, COUNT(*) AS orders, SUM(total) AS total FROM ord GROUP BY client_id
+/// ```
+///
+/// Aggregate can also be used in a condition: `count_all().gt(10)`.
+///
+/// [`GroupedTable::aggregate()`]: crate::sql::table::GroupedTable::aggregate()
+#[derive(Debug, Clone)]
+pub struct Aggregate {
+    expression: Expression,
+    alias: String,
+}
+
+impl Aggregate {
+    pub fn new(expression: Expression, alias: &str) -> Aggregate {
+        Aggregate {
+            expression,
+            alias: alias.to_string(),
+        }
+    }
+
+    /// Set name of the field for the aggregate.
+    pub fn as_(mut self, alias: &str) -> Self {
+        self.alias = alias.to_string();
+        self
+    }
+
+    pub fn alias(&self) -> &str {
+        &self.alias
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+}
+
+impl Chunk for Aggregate {
+    fn render_chunk(&self) -> Expression {
+        self.expression.clone()
+    }
+}
+impl Operations for Aggregate {}
+
+/// `COUNT(*)`, aliased as `count`
+pub fn count_all() -> Aggregate {
+    Aggregate::new(expr_arc!("COUNT(*)").render_chunk(), "count")
+}
+
+/// `COUNT(column)`, aliased as `count`
+pub fn count(column: impl Chunk) -> Aggregate {
+    Aggregate::new(
+        expr_arc!("COUNT({})", column.render_chunk()).render_chunk(),
+        "count",
+    )
+}
+
+/// `SUM(column)`, aliased as `sum`
+pub fn sum(column: impl Chunk) -> Aggregate {
+    Aggregate::new(
+        expr_arc!("SUM({})", column.render_chunk()).render_chunk(),
+        "sum",
+    )
+}
+
+/// `COUNT(DISTINCT column)`, aliased as `count_distinct`
+pub fn count_distinct(column: impl Chunk) -> Aggregate {
+    Aggregate::new(
+        expr_arc!("COUNT(DISTINCT {})", column.render_chunk()).render_chunk(),
+        "count_distinct",
+    )
+}
+
+/// `MIN(column)`, aliased as `min`
+pub fn min(column: impl Chunk) -> Aggregate {
+    Aggregate::new(
+        expr_arc!("MIN({})", column.render_chunk()).render_chunk(),
+        "min",
+    )
+}
+
+/// `MAX(column)`, aliased as `max`
+pub fn max(column: impl Chunk) -> Aggregate {
+    Aggregate::new(
+        expr_arc!("MAX({})", column.render_chunk()).render_chunk(),
+        "max",
+    )
+}
+
+/// `AVG(column)`, aliased as `avg`
+pub fn avg(column: impl Chunk) -> Aggregate {
+    Aggregate::new(
+        expr_arc!("AVG({})", column.render_chunk()).render_chunk(),
+        "avg",
+    )
+}
+
+/// `STRING_AGG(column, separator)`, aliased as `string_agg`
+pub fn string_agg(column: impl Chunk, separator: &str) -> Aggregate {
+    Aggregate::new(
+        expr_arc!(
+            "STRING_AGG({}, {})",
+            column.render_chunk(),
+            separator.to_string()
+        )
+        .render_chunk(),
+        "string_agg",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::prelude::Column;
+
+    #[test]
+    fn test_aggregate() {
+        let total = Arc::new(Column::new("total".to_string(), None));
+
+        let agg = sum(total).as_("total_sum");
+        assert_eq!(agg.alias(), "total_sum");
+        assert_eq!(agg.render_chunk().sql(), "SUM(total)");
+
+        assert_eq!(
+            count_all().gt(10).render_chunk().preview(),
+            "(COUNT(*) > 10)"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::sql::Expression;
+use crate::Error;
 use rust_decimal::Decimal;
 use serde_json::{to_value, Value};
 use std::fmt::Debug;
@@ -73,6 +74,15 @@ pub trait Chunk: Debug + Sync + Send {
     /// # Returns
     /// - Returns a `String` that contains the SQL statement.
     fn render_chunk(&self) -> Expression;
+
+    /// Same as [`render_chunk()`], but returns [`Error::RenderError`] instead of
+    /// panicking if the chunk (or a nested chunk) can't be rendered, for example
+    /// an INSERT query without a table.
+    ///
+    /// [`render_chunk()`]: Chunk::render_chunk()
+    fn try_render_chunk(&self) -> Result<Expression, Error> {
+        Ok(self.render_chunk())
+    }
 }
 
 impl Chunk for String {
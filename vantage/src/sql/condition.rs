@@ -1,11 +1,12 @@
 use std::sync::Arc;
 
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 
-use crate::expr;
 use crate::prelude::Column;
 use crate::sql::expression::{Expression, ExpressionArc};
-use crate::sql::Chunk;
+use crate::sql::{Chunk, ConditionTree};
+use crate::{expr, expr_arc, Error};
 
 #[derive(Debug, Clone)]
 enum ConditionOperand {
@@ -13,13 +14,32 @@ enum ConditionOperand {
     Expression(Box<Expression>),
     Condition(Box<Condition>),
     Value(Value),
+    Tree(Box<ConditionTree>),
+    None,
+}
+
+/// Right side of the condition. A column is kept as is, so that its table alias
+/// can be updated along with the left side, see [`Condition::from_columns()`].
+#[derive(Debug, Clone)]
+enum ConditionValue {
+    Chunk(Arc<Box<dyn Chunk>>),
+    Column(Arc<Column>),
+}
+
+impl ConditionValue {
+    fn chunk(&self) -> Arc<Box<dyn Chunk>> {
+        match self {
+            ConditionValue::Chunk(chunk) => chunk.clone(),
+            ConditionValue::Column(column) => Arc::new(Box::new(column.clone())),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Condition {
     field: ConditionOperand,
     operation: String,
-    value: Arc<Box<dyn Chunk>>,
+    value: ConditionValue,
 }
 
 #[allow(dead_code)]
@@ -32,7 +52,7 @@ impl Condition {
         Condition {
             field: ConditionOperand::Column(field),
             operation: operation.to_string(),
-            value,
+            value: ConditionValue::Chunk(value),
         }
     }
     pub fn from_expression(
@@ -43,7 +63,7 @@ impl Condition {
         Condition {
             field: ConditionOperand::Expression(Box::new(expression)),
             operation: operation.to_string(),
-            value,
+            value: ConditionValue::Chunk(value),
         }
     }
     pub fn from_condition(
@@ -54,18 +74,45 @@ impl Condition {
         Condition {
             field: ConditionOperand::Condition(Box::new(condition)),
             operation: operation.to_string(),
-            value,
+            value: ConditionValue::Chunk(value),
+        }
+    }
+
+    /// Compares two columns, which may belong to different tables:
+    ///
+    /// ```
+    /// let condition = Condition::from_columns(users.role_id(), "=", roles.id());
+    /// // (u.role_id = r.id)
+    /// ```
+    ///
+    /// If both columns belong to the same table, [`set_table_alias()`] changes
+    /// alias of both. See [`Column::same_table()`].
+    ///
+    /// [`set_table_alias()`]: Condition::set_table_alias()
+    pub fn from_columns(left: Arc<Column>, operation: &str, right: Arc<Column>) -> Condition {
+        Condition {
+            field: ConditionOperand::Column(left),
+            operation: operation.to_string(),
+            value: ConditionValue::Column(right),
         }
     }
 
     pub fn set_table_alias(&mut self, alias: &str) {
         match &mut self.field {
             ConditionOperand::Column(field) => {
+                if let ConditionValue::Column(value) = &mut self.value {
+                    if value.same_table(field) {
+                        let mut v = value.as_ref().clone();
+                        v.set_table_alias(alias.to_string());
+                        *value = Arc::new(v);
+                    }
+                }
                 let mut f = field.as_ref().clone();
                 f.set_table_alias(alias.to_string());
                 *field = Arc::new(f);
             }
             ConditionOperand::Condition(condition) => condition.set_table_alias(alias),
+            ConditionOperand::Tree(tree) => tree.set_table_alias(alias),
             _ => {}
         }
     }
@@ -74,38 +121,176 @@ impl Condition {
         Condition {
             field: ConditionOperand::Value(operand),
             operation: operation.to_string(),
-            value,
+            value: ConditionValue::Chunk(value),
+        }
+    }
+
+    /// Condition, which is true if the subquery returns any rows:
+    ///
+    /// ```
+    /// let condition = Condition::exists(&orders.get_select_query());
+    /// // (EXISTS (SELECT ... FROM orders))
+    /// ```
+    pub fn exists(query: &impl Chunk) -> Condition {
+        Condition {
+            field: ConditionOperand::None,
+            operation: "EXISTS".to_string(),
+            value: ConditionValue::Chunk(Arc::new(Box::new(expr_arc!(
+                "({})",
+                query.render_chunk()
+            )))),
         }
     }
 
-    fn render_operand(&self) -> Expression {
-        match self.field.clone() {
+    pub fn not_exists(query: &impl Chunk) -> Condition {
+        Condition {
+            field: ConditionOperand::None,
+            operation: "NOT EXISTS".to_string(),
+            value: ConditionValue::Chunk(Arc::new(Box::new(expr_arc!(
+                "({})",
+                query.render_chunk()
+            )))),
+        }
+    }
+
+    fn render_operand(&self) -> Result<Expression, Error> {
+        Ok(match self.field.clone() {
             ConditionOperand::Column(field) => field.render_chunk(),
             ConditionOperand::Expression(expression) => expression.render_chunk(),
-            ConditionOperand::Condition(condition) => condition.render_chunk(),
+            ConditionOperand::Condition(condition) => condition.try_render_chunk()?,
             ConditionOperand::Value(value) => expr!("{}", value.clone()).render_chunk(),
-        }
+            ConditionOperand::Tree(tree) => tree.try_render_chunk()?,
+            ConditionOperand::None => Expression::empty(),
+        })
     }
 
     pub fn and(self, other: Condition) -> Condition {
-        Condition::from_condition(self, "AND", Arc::new(Box::new(other)))
+        self.into_tree().and(other).into()
     }
 
     pub fn or(self, other: Condition) -> Condition {
-        Condition::from_condition(self, "OR", Arc::new(Box::new(other)))
+        self.into_tree().or(other).into()
+    }
+
+    /// Negates the condition: `(NOT (age > 18))`
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Condition {
+        self.into_tree().not().into()
+    }
+
+    /// Converts condition into a [`ConditionTree`] for further composition.
+    pub fn into_tree(self) -> ConditionTree {
+        match self.field {
+            ConditionOperand::Tree(tree) => *tree,
+            _ => ConditionTree::Condition(self),
+        }
+    }
+}
+
+impl From<ConditionTree> for Condition {
+    fn from(tree: ConditionTree) -> Self {
+        match tree {
+            ConditionTree::Condition(condition) => condition,
+            tree => Condition {
+                field: ConditionOperand::Tree(Box::new(tree)),
+                operation: String::new(),
+                value: ConditionValue::Chunk(Arc::new(Box::new(Expression::empty()))),
+            },
+        }
+    }
+}
+
+/// Serialized form of [`Condition`]. Columns are stored rendered, as an
+/// expression.
+#[derive(Serialize, Deserialize)]
+struct ConditionAst {
+    operand: OperandAst,
+    operation: String,
+    value: Expression,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum OperandAst {
+    Expression(Expression),
+    Condition(Box<Condition>),
+    Value(Value),
+    Tree(Box<ConditionTree>),
+    None,
+}
+
+/// Condition serializes as `{"operand": .., "operation": "=", "value": ..}`,
+/// where the value is an [`Expression`]. A column operand is stored as an
+/// expression, so a deserialized condition ignores
+/// [`set_table_alias()`](Condition::set_table_alias).
+impl Serialize for Condition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let operand = match &self.field {
+            ConditionOperand::Column(column) => OperandAst::Expression(column.render_chunk()),
+            ConditionOperand::Expression(expression) => {
+                OperandAst::Expression(expression.as_ref().clone())
+            }
+            ConditionOperand::Condition(condition) => OperandAst::Condition(condition.clone()),
+            ConditionOperand::Value(value) => OperandAst::Value(value.clone()),
+            ConditionOperand::Tree(tree) => OperandAst::Tree(tree.clone()),
+            ConditionOperand::None => OperandAst::None,
+        };
+        ConditionAst {
+            operand,
+            operation: self.operation.clone(),
+            value: self
+                .value
+                .chunk()
+                .try_render_chunk()
+                .map_err(serde::ser::Error::custom)?,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Condition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let ast = ConditionAst::deserialize(deserializer)?;
+        Ok(Condition {
+            field: match ast.operand {
+                OperandAst::Expression(expression) => {
+                    ConditionOperand::Expression(Box::new(expression))
+                }
+                OperandAst::Condition(condition) => ConditionOperand::Condition(condition),
+                OperandAst::Value(value) => ConditionOperand::Value(value),
+                OperandAst::Tree(tree) => ConditionOperand::Tree(tree),
+                OperandAst::None => ConditionOperand::None,
+            },
+            operation: ast.operation,
+            value: ConditionValue::Chunk(Arc::new(Box::new(ast.value))),
+        })
     }
 }
 
 impl Chunk for Condition {
     fn render_chunk(&self) -> Expression {
+        self.try_render_chunk().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render_chunk(&self) -> Result<Expression, Error> {
+        if let ConditionOperand::Tree(tree) = &self.field {
+            return tree.try_render_chunk();
+        }
+        if let ConditionOperand::None = self.field {
+            return ExpressionArc::new(
+                format!("({} {{}})", self.operation),
+                vec![self.value.chunk()],
+            )
+            .try_render_chunk();
+        }
         ExpressionArc::new(
             format!("({{}} {} {{}})", self.operation),
             vec![
-                Arc::new(Box::new(self.render_operand())),
-                self.value.clone(),
+                Arc::new(Box::new(self.render_operand()?)),
+                self.value.chunk(),
             ],
         )
-        .render_chunk()
+        .try_render_chunk()
     }
 }
 
@@ -155,4 +340,61 @@ mod tests {
         assert_eq!(params[0], "yes");
         assert_eq!(params[1], "yes");
     }
+
+    #[test]
+    fn test_serialize() {
+        let name = Arc::new(Column::new("name".to_string(), Some("u".to_string())));
+        let age = Arc::new(Column::new("age".to_string(), None));
+        let condition = Condition::from_field(name, "=", Arc::new(Box::new(expr!("{}", "John"))))
+            .or(Condition::from_field(age, ">", Arc::new(Box::new(expr!("{}", 18)))).not());
+
+        let json = serde_json::to_value(&condition).unwrap();
+        assert_eq!(
+            json["operand"]["tree"]["or"][0]["condition"],
+            serde_json::json!({
+                "operand": {"expression": {"sql": "u.name", "params": []}},
+                "operation": "=",
+                "value": {"sql": "{}", "params": ["John"]}
+            })
+        );
+
+        let restored: Condition = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            restored.render_chunk().preview(),
+            condition.render_chunk().preview()
+        );
+    }
+
+    #[test]
+    fn test_from_columns() {
+        let role_id = Arc::new(Column::new("role_id".to_string(), Some("u".to_string())));
+        let id = Arc::new(Column::new("id".to_string(), Some("r".to_string())));
+
+        let mut condition = Condition::from_columns(role_id, "=", id);
+        assert_eq!(condition.render_chunk().sql(), "(u.role_id = r.id)");
+
+        // column of another table keeps its alias
+        condition.set_table_alias("users");
+        assert_eq!(condition.render_chunk().sql(), "(users.role_id = r.id)");
+
+        let starts = Arc::new(Column::new("starts_at".to_string(), None));
+        let ends = Arc::new(Column::new("ends_at".to_string(), None));
+        let mut condition = Condition::from_columns(starts, ">", ends);
+        condition.set_table_alias("p");
+        assert_eq!(condition.render_chunk().sql(), "(p.starts_at > p.ends_at)");
+    }
+
+    #[test]
+    fn test_exists() {
+        let query = expr!("SELECT 1 FROM orders WHERE orders.user_id = users.id");
+
+        assert_eq!(
+            Condition::exists(&query).render_chunk().sql(),
+            "(EXISTS (SELECT 1 FROM orders WHERE orders.user_id = users.id))"
+        );
+        assert_eq!(
+            Condition::not_exists(&query).render_chunk().sql(),
+            "(NOT EXISTS (SELECT 1 FROM orders WHERE orders.user_id = users.id))"
+        );
+    }
 }
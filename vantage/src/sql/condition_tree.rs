@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+use crate::sql::{Chunk, Condition, Expression, ExpressionArc};
+use crate::{expr, expr_arc, Error};
+
+/// Boolean tree of [`Condition`]s with arbitrary nesting of AND, OR and NOT.
+///
+/// Unlike conditions rendered into an expression, the tree keeps all of its
+/// conditions, so a table alias set later (for example when the table is joined)
+/// is applied to every column of the tree:
+///
+/// ```
+/// let condition = ConditionTree::any([
+///     role_type.eq(&"admin"),
+///     role_type.eq(&"writer"),
+/// ])
+/// .and(deleted.eq(&false).not());
+///
+/// // ((role_type = {}) OR (role_type = {})) AND (NOT (deleted = {}))
+/// ```
+///
+/// [`Condition::and()`], [`Condition::or()`] and [`Condition::not()`] build the
+/// tree for you.
+///
+/// Serializes as `{"and": [..]}`, `{"or": [..]}`, `{"not": ..}` or
+/// `{"condition": ..}`, see [`Condition`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConditionTree {
+    Condition(Condition),
+    And(Vec<ConditionTree>),
+    Or(Vec<ConditionTree>),
+    Not(Box<ConditionTree>),
+}
+
+impl ConditionTree {
+    /// True if all of the conditions are true. Empty tree is always true.
+    pub fn all(conditions: impl IntoIterator<Item = impl Into<ConditionTree>>) -> ConditionTree {
+        ConditionTree::And(conditions.into_iter().map(Into::into).collect())
+    }
+
+    /// True if any of the conditions is true. Empty tree is always false.
+    pub fn any(conditions: impl IntoIterator<Item = impl Into<ConditionTree>>) -> ConditionTree {
+        ConditionTree::Or(conditions.into_iter().map(Into::into).collect())
+    }
+
+    pub fn and(self, other: impl Into<ConditionTree>) -> ConditionTree {
+        match self {
+            ConditionTree::And(mut items) => {
+                items.push(other.into());
+                ConditionTree::And(items)
+            }
+            tree => ConditionTree::And(vec![tree, other.into()]),
+        }
+    }
+
+    pub fn or(self, other: impl Into<ConditionTree>) -> ConditionTree {
+        match self {
+            ConditionTree::Or(mut items) => {
+                items.push(other.into());
+                ConditionTree::Or(items)
+            }
+            tree => ConditionTree::Or(vec![tree, other.into()]),
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> ConditionTree {
+        match self {
+            ConditionTree::Not(tree) => *tree,
+            tree => ConditionTree::Not(Box::new(tree)),
+        }
+    }
+
+    /// Prefix columns of all conditions in the tree with the table alias.
+    pub fn set_table_alias(&mut self, alias: &str) {
+        match self {
+            ConditionTree::Condition(condition) => condition.set_table_alias(alias),
+            ConditionTree::And(items) | ConditionTree::Or(items) => {
+                for item in items {
+                    item.set_table_alias(alias);
+                }
+            }
+            ConditionTree::Not(tree) => tree.set_table_alias(alias),
+        }
+    }
+}
+
+impl From<Condition> for ConditionTree {
+    fn from(condition: Condition) -> Self {
+        condition.into_tree()
+    }
+}
+
+impl Chunk for ConditionTree {
+    fn render_chunk(&self) -> Expression {
+        self.try_render_chunk().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render_chunk(&self) -> Result<Expression, Error> {
+        let (items, glue, empty) = match self {
+            ConditionTree::Condition(condition) => return condition.try_render_chunk(),
+            ConditionTree::Not(tree) => {
+                return expr_arc!("(NOT {})", tree.try_render_chunk()?).try_render_chunk()
+            }
+            ConditionTree::And(items) => (items, " AND ", "true"),
+            ConditionTree::Or(items) => (items, " OR ", "false"),
+        };
+        if items.is_empty() {
+            return Ok(expr!(empty));
+        }
+        let items = items
+            .iter()
+            .map(|x| x.try_render_chunk())
+            .collect::<Result<Vec<_>, Error>>()?;
+        expr_arc!("({})", Expression::from_vec(items, glue)).try_render_chunk()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::prelude::Column;
+    use crate::sql::Operations;
+
+    #[test]
+    fn test_tree() {
+        let role_type = Arc::new(Column::new("role_type".to_string(), None));
+        let deleted = Arc::new(Column::new("deleted".to_string(), None));
+
+        let mut tree = ConditionTree::any([
+            role_type.eq(&json!("admin")),
+            role_type.eq(&json!("writer")),
+        ])
+        .and(deleted.eq(&json!(true)).not());
+
+        assert_eq!(
+            tree.render_chunk().sql(),
+            "(((role_type = {}) OR (role_type = {})) AND (NOT (deleted = {})))"
+        );
+
+        tree.set_table_alias("r");
+        assert_eq!(
+            tree.render_chunk().sql(),
+            "(((r.role_type = {}) OR (r.role_type = {})) AND (NOT (r.deleted = {})))"
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(
+            ConditionTree::all(Vec::<Condition>::new())
+                .render_chunk()
+                .sql(),
+            "true"
+        );
+        assert_eq!(
+            ConditionTree::any(Vec::<Condition>::new())
+                .render_chunk()
+                .sql(),
+            "false"
+        );
+    }
+
+    #[test]
+    fn test_double_not() {
+        let deleted = Arc::new(Column::new("deleted".to_string(), None));
+        let tree = ConditionTree::from(deleted.eq(&json!(true))).not().not();
+
+        assert_eq!(tree.render_chunk().sql(), "(deleted = {})");
+    }
+}
@@ -0,0 +1,566 @@
+//! SQL syntax, which differs between database engines.
+//!
+//! [`Query`] is rendered for a [`Dialect`], which is supplied by the [`DataSource`]
+//! when the query is built by a [`Table`]. Queries created with `Query::new()`
+//! use [`PostgresDialect`]:
+//!
+//! ```
+//! let query = Query::new()
+//!     .with_dialect(Arc::new(MySqlDialect))
+//!     .with_table("product", None)
+//!     .with_skip_and_limit(10, 20);
+//!
+//! // SELECT * FROM product LIMIT 20 OFFSET 10
+//! ```
+//!
+//! [`Query`]: crate::sql::Query
+//! [`DataSource`]: crate::traits::datasource::DataSource
+//! [`Table`]: crate::sql::Table
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::{
+    expr, expr_arc,
+    sql::expression::expression::sql_literal,
+    sql::{Chunk, Condition, Expression, ExpressionArc},
+    Error,
+};
+
+/// Maximum number of values in a single IN list, see [`Dialect::render_in_values()`]
+pub const IN_LIST_CHUNK_SIZE: usize = 1000;
+
+pub trait Dialect: Debug + Send + Sync {
+    /// Quotes table or column name, if it is a reserved word or contains characters
+    /// other than letters, digits and underscores. Dots separate schema, table and
+    /// column names:
+    ///
+    /// ```
+    /// PostgresDialect.quote_identifier("o.order");  // o."order"
+    /// ```
+    fn quote_identifier(&self, identifier: &str) -> String {
+        quote_with(identifier, '"', '"')
+    }
+
+    /// Renders value as a literal, which is safe to place into a query:
+    ///
+    /// ```
+    /// PostgresDialect.render_literal(&json!("O'Brien"));  // 'O''Brien'
+    /// ```
+    fn render_literal(&self, value: &Value) -> String {
+        sql_literal(value)
+    }
+
+    /// Placeholder for a query parameter. `index` starts with 1.
+    fn placeholder(&self, _index: usize) -> String {
+        "?".to_string()
+    }
+
+    /// Renders the part of SELECT query, which limits returned rows
+    fn render_pagination(&self, skip: Option<i64>, limit: Option<i64>) -> Expression {
+        default_pagination(skip, limit)
+    }
+
+    /// Renders the limit placed right after `SELECT`, such as `TOP (10)`
+    fn render_top(&self, _skip: Option<i64>, _limit: Option<i64>) -> Expression {
+        Expression::empty()
+    }
+
+    /// Pagination is only allowed in ordered queries
+    fn requires_order_for_offset(&self) -> bool {
+        false
+    }
+
+    /// INSERT, UPDATE and DELETE queries can return data with RETURNING clause
+    fn supports_returning(&self) -> bool {
+        false
+    }
+
+    /// Returned data is specified with `OUTPUT INSERTED.id` in the middle of the
+    /// query instead of RETURNING at the end of it
+    fn uses_output_clause(&self) -> bool {
+        false
+    }
+
+    /// DELETE query accepts LIMIT, so that only some of the matching rows are deleted
+    fn supports_delete_limit(&self) -> bool {
+        false
+    }
+
+    /// WITH clause may contain INSERT, UPDATE and DELETE queries, which return rows:
+    /// `WITH changed AS (UPDATE .. RETURNING *) SELECT .. FROM changed`
+    fn supports_writable_cte(&self) -> bool {
+        false
+    }
+
+    /// Condition, which is true if `operand` equals one of the `values`. Values are
+    /// split into IN lists of up to [`IN_LIST_CHUNK_SIZE`] values joined with OR:
+    /// `((id IN ({}, {}, ..)) OR (id IN ({}, ..)))`
+    fn render_in_values(&self, operand: Expression, values: Vec<Value>) -> Condition {
+        chunked_in_values(operand, values)
+    }
+
+    /// Renders a query, returning whether `query` has any rows
+    fn render_exists(&self, query: Expression) -> Expression {
+        expr_arc!("SELECT EXISTS ({})", query).render_chunk()
+    }
+
+    /// Renders the clause following INSERT ... VALUES, which updates `update_fields`
+    /// if a record with the same `conflict_fields` already exists.
+    fn render_upsert(
+        &self,
+        conflict_fields: &[String],
+        update_fields: &[String],
+    ) -> Result<String, Error>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PostgresDialect;
+
+impl Dialect for PostgresDialect {
+    fn placeholder(&self, index: usize) -> String {
+        format!("${}", index)
+    }
+
+    fn render_pagination(&self, skip: Option<i64>, limit: Option<i64>) -> Expression {
+        let mut result = Vec::new();
+        if let Some(skip) = skip {
+            result.push(expr!(" OFFSET {}::int4", skip));
+        }
+        if let Some(limit) = limit {
+            result.push(expr!(" LIMIT {}::int4", limit));
+        }
+        Expression::from_vec(result, "")
+    }
+
+    fn supports_returning(&self) -> bool {
+        true
+    }
+
+    fn supports_writable_cte(&self) -> bool {
+        true
+    }
+
+    /// Values are sent as a single array parameter, `(id = ANY ({}))`, which does
+    /// not run into the limit of 65535 parameters per query.
+    fn render_in_values(&self, operand: Expression, values: Vec<Value>) -> Condition {
+        Condition::from_expression(
+            operand,
+            "= ANY",
+            Arc::new(Box::new(expr!("({})", Value::Array(values)))),
+        )
+    }
+
+    fn render_upsert(
+        &self,
+        conflict_fields: &[String],
+        update_fields: &[String],
+    ) -> Result<String, Error> {
+        on_conflict(conflict_fields, update_fields)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SqliteDialect;
+
+impl Dialect for SqliteDialect {
+    fn render_pagination(&self, skip: Option<i64>, limit: Option<i64>) -> Expression {
+        match (skip, limit) {
+            // OFFSET is only allowed after LIMIT
+            (Some(skip), None) => expr!(" LIMIT -1 OFFSET {}", skip),
+            (skip, limit) => default_pagination(skip, limit),
+        }
+    }
+
+    fn supports_returning(&self) -> bool {
+        true
+    }
+
+    fn render_upsert(
+        &self,
+        conflict_fields: &[String],
+        update_fields: &[String],
+    ) -> Result<String, Error> {
+        on_conflict(conflict_fields, update_fields)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MySqlDialect;
+
+impl Dialect for MySqlDialect {
+    fn quote_identifier(&self, identifier: &str) -> String {
+        quote_with(identifier, '`', '`')
+    }
+
+    /// Backslash is an escape character in MySQL strings
+    fn render_literal(&self, value: &Value) -> String {
+        match value {
+            Value::String(s) => backslash_quoted(s),
+            Value::Array(items) => list_literal(self, items, "(", ")"),
+            Value::Object(_) => backslash_quoted(&value.to_string()),
+            _ => sql_literal(value),
+        }
+    }
+
+    fn render_pagination(&self, skip: Option<i64>, limit: Option<i64>) -> Expression {
+        match (skip, limit) {
+            // OFFSET is only allowed after LIMIT
+            (Some(skip), None) => expr!(" LIMIT 18446744073709551615 OFFSET {}", skip),
+            (skip, limit) => default_pagination(skip, limit),
+        }
+    }
+
+    fn supports_delete_limit(&self) -> bool {
+        true
+    }
+
+    fn render_upsert(
+        &self,
+        _conflict_fields: &[String],
+        update_fields: &[String],
+    ) -> Result<String, Error> {
+        // MySQL picks the conflicting unique key by itself
+        if update_fields.is_empty() {
+            return Err(Error::RenderError(
+                "MySQL upsert requires at least one field to update".to_string(),
+            ));
+        }
+        Ok(format!(
+            " ON DUPLICATE KEY UPDATE {}",
+            update_fields
+                .iter()
+                .map(|f| format!("{} = VALUES({})", f, f))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MssqlDialect;
+
+impl Dialect for MssqlDialect {
+    fn quote_identifier(&self, identifier: &str) -> String {
+        quote_with(identifier, '[', ']')
+    }
+
+    fn placeholder(&self, index: usize) -> String {
+        format!("@P{}", index)
+    }
+
+    fn render_pagination(&self, skip: Option<i64>, limit: Option<i64>) -> Expression {
+        match (skip, limit) {
+            // without skip, TOP is used
+            (None, _) => Expression::empty(),
+            (Some(skip), None) => expr!(" OFFSET {} ROWS", skip),
+            (Some(skip), Some(limit)) => {
+                expr!(" OFFSET {} ROWS FETCH NEXT {} ROWS ONLY", skip, limit)
+            }
+        }
+    }
+
+    fn render_top(&self, skip: Option<i64>, limit: Option<i64>) -> Expression {
+        match (skip, limit) {
+            (None, Some(limit)) => expr!(" TOP ({})", limit),
+            _ => Expression::empty(),
+        }
+    }
+
+    fn requires_order_for_offset(&self) -> bool {
+        true
+    }
+
+    fn supports_returning(&self) -> bool {
+        true
+    }
+
+    fn uses_output_clause(&self) -> bool {
+        true
+    }
+
+    /// EXISTS can only be used in a condition
+    fn render_exists(&self, query: Expression) -> Expression {
+        expr_arc!("SELECT CASE WHEN EXISTS ({}) THEN 1 ELSE 0 END", query).render_chunk()
+    }
+
+    fn render_upsert(
+        &self,
+        _conflict_fields: &[String],
+        _update_fields: &[String],
+    ) -> Result<String, Error> {
+        Err(Error::RenderError(
+            "Upsert is not supported for SQL Server, use MERGE instead".to_string(),
+        ))
+    }
+}
+
+/// DuckDB follows Postgres syntax closely, but casts are not needed for pagination.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DuckDbDialect;
+
+impl Dialect for DuckDbDialect {
+    fn placeholder(&self, index: usize) -> String {
+        format!("${}", index)
+    }
+
+    fn render_literal(&self, value: &Value) -> String {
+        match value {
+            Value::Array(items) => list_literal(self, items, "[", "]"),
+            _ => sql_literal(value),
+        }
+    }
+
+    fn supports_returning(&self) -> bool {
+        true
+    }
+
+    fn render_upsert(
+        &self,
+        conflict_fields: &[String],
+        update_fields: &[String],
+    ) -> Result<String, Error> {
+        on_conflict(conflict_fields, update_fields)
+    }
+}
+
+/// ClickHouse has no transactional updates, so RETURNING and upserts are not
+/// available. Use ReplacingMergeTree tables and `FINAL` instead of upserts.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClickHouseDialect;
+
+impl Dialect for ClickHouseDialect {
+    fn quote_identifier(&self, identifier: &str) -> String {
+        quote_with(identifier, '`', '`')
+    }
+
+    fn render_literal(&self, value: &Value) -> String {
+        match value {
+            Value::String(s) => backslash_quoted(s),
+            Value::Array(items) => list_literal(self, items, "[", "]"),
+            Value::Object(_) => backslash_quoted(&value.to_string()),
+            _ => sql_literal(value),
+        }
+    }
+
+    fn render_upsert(
+        &self,
+        _conflict_fields: &[String],
+        _update_fields: &[String],
+    ) -> Result<String, Error> {
+        Err(Error::RenderError(
+            "Upsert is not supported for ClickHouse, use ReplacingMergeTree instead".to_string(),
+        ))
+    }
+}
+
+/// SQL keywords, which can't be used as identifiers without quoting
+#[rustfmt::skip]
+const RESERVED_WORDS: &[&str] = &[
+    "all", "and", "any", "as", "asc", "between", "both", "by", "case", "cast", "check", "collate",
+    "column", "constraint", "create", "cross", "current_date", "current_time", "current_timestamp",
+    "current_user", "default", "delete", "desc", "distinct", "do", "else", "end", "except",
+    "exists", "false", "fetch", "for", "foreign", "from", "full", "grant", "group", "having", "in",
+    "index", "inner", "insert", "intersect", "into", "is", "join", "key", "leading", "left", "like",
+    "limit", "natural", "not", "null", "offset", "on", "or", "order", "outer", "primary",
+    "references", "right", "select", "session_user", "set", "some", "table", "then", "to",
+    "trailing", "true", "union", "unique", "update", "user", "using", "values", "when", "where",
+    "window", "with",
+];
+
+/// Identifier can be used in a query without quoting
+pub fn is_plain_identifier(identifier: &str) -> bool {
+    let mut chars = identifier.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !RESERVED_WORDS.contains(&identifier.to_ascii_lowercase().as_str())
+}
+
+fn quote_with(identifier: &str, open: char, close: char) -> String {
+    identifier
+        .split('.')
+        .map(|part| {
+            if part == "*" || is_plain_identifier(part) {
+                part.to_string()
+            } else {
+                format!(
+                    "{}{}{}",
+                    open,
+                    part.replace(close, &format!("{}{}", close, close)),
+                    close
+                )
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn backslash_quoted(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn list_literal(dialect: &dyn Dialect, items: &[Value], open: &str, close: &str) -> String {
+    format!(
+        "{}{}{}",
+        open,
+        items
+            .iter()
+            .map(|item| dialect.render_literal(item))
+            .collect::<Vec<_>>()
+            .join(", "),
+        close
+    )
+}
+
+fn default_pagination(skip: Option<i64>, limit: Option<i64>) -> Expression {
+    match (skip, limit) {
+        (None, None) => Expression::empty(),
+        (None, Some(limit)) => expr!(" LIMIT {}", limit),
+        (Some(skip), None) => expr!(" OFFSET {}", skip),
+        (Some(skip), Some(limit)) => expr!(" LIMIT {} OFFSET {}", limit, skip),
+    }
+}
+
+pub(crate) fn chunked_in_values(operand: Expression, values: Vec<Value>) -> Condition {
+    values
+        .chunks(IN_LIST_CHUNK_SIZE)
+        .map(|chunk| {
+            let list = Expression::new(
+                format!("({})", vec!["{}"; chunk.len()].join(", ")),
+                chunk.to_vec(),
+            );
+            Condition::from_expression(operand.clone(), "IN", Arc::new(Box::new(list)))
+        })
+        .reduce(|a, b| a.or(b))
+        // empty list matches nothing
+        .unwrap_or_else(|| {
+            Condition::from_expression(operand, "IN", Arc::new(Box::new(expr!("(NULL)"))))
+        })
+}
+
+fn on_conflict(conflict_fields: &[String], update_fields: &[String]) -> Result<String, Error> {
+    if conflict_fields.is_empty() {
+        return Err(Error::RenderError(
+            "Upsert requires at least one conflict field".to_string(),
+        ));
+    }
+    let action = if update_fields.is_empty() {
+        "NOTHING".to_string()
+    } else {
+        format!(
+            "UPDATE SET {}",
+            update_fields
+                .iter()
+                .map(|f| format!("{} = EXCLUDED.{}", f, f))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    };
+    Ok(format!(
+        " ON CONFLICT ({}) DO {}",
+        conflict_fields.join(", "),
+        action
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_literal() {
+        let value = serde_json::json!(["it's", "a\\b", 1, null]);
+        assert_eq!(
+            PostgresDialect.render_literal(&value),
+            "ARRAY['it''s', 'a\\b', 1, NULL]"
+        );
+        assert_eq!(
+            MySqlDialect.render_literal(&value),
+            "('it\\'s', 'a\\\\b', 1, NULL)"
+        );
+        assert_eq!(
+            DuckDbDialect.render_literal(&value),
+            "['it''s', 'a\\b', 1, NULL]"
+        );
+        assert_eq!(
+            ClickHouseDialect.render_literal(&value),
+            "['it\\'s', 'a\\\\b', 1, NULL]"
+        );
+    }
+
+    #[test]
+    fn test_dialects() {
+        assert_eq!(PostgresDialect.placeholder(2), "$2");
+        assert_eq!(MySqlDialect.placeholder(2), "?");
+
+        assert_eq!(PostgresDialect.quote_identifier("order"), "\"order\"");
+        assert_eq!(PostgresDialect.quote_identifier("o.name"), "o.name");
+        assert_eq!(PostgresDialect.quote_identifier("o.from"), "o.\"from\"");
+        assert_eq!(
+            PostgresDialect.quote_identifier("x\"; DROP TABLE client; --"),
+            "\"x\"\"; DROP TABLE client; --\""
+        );
+        assert_eq!(MySqlDialect.quote_identifier("a`b"), "`a``b`");
+        assert_eq!(MssqlDialect.quote_identifier("o.a]b"), "o.[a]]b]");
+        assert_eq!(MssqlDialect.placeholder(3), "@P3");
+        assert_eq!(
+            DuckDbDialect.render_pagination(Some(10), Some(5)).preview(),
+            " LIMIT 5 OFFSET 10"
+        );
+
+        assert_eq!(
+            SqliteDialect.render_pagination(Some(10), None).preview(),
+            " LIMIT -1 OFFSET 10"
+        );
+        assert_eq!(
+            MySqlDialect.render_pagination(Some(10), Some(5)).preview(),
+            " LIMIT 5 OFFSET 10"
+        );
+
+        let conflict = vec!["sku".to_string()];
+        let update = vec!["name".to_string(), "price".to_string()];
+        assert_eq!(
+            PostgresDialect.render_upsert(&conflict, &update).unwrap(),
+            " ON CONFLICT (sku) DO UPDATE SET name = EXCLUDED.name, price = EXCLUDED.price"
+        );
+        assert_eq!(
+            MySqlDialect.render_upsert(&conflict, &update).unwrap(),
+            " ON DUPLICATE KEY UPDATE name = VALUES(name), price = VALUES(price)"
+        );
+    }
+
+    #[test]
+    fn test_in_values() {
+        use crate::sql::Chunk;
+
+        let ids: Vec<Value> = (0..2500).map(Value::from).collect();
+        let condition = PostgresDialect
+            .render_in_values(expr!("id"), ids.clone())
+            .render_chunk();
+        assert_eq!(condition.sql(), "(id = ANY ({}))");
+        assert_eq!(condition.params().len(), 1);
+
+        let condition = MySqlDialect
+            .render_in_values(expr!("id"), ids)
+            .render_chunk();
+        assert_eq!(condition.sql().matches(" OR ").count(), 2);
+        assert_eq!(condition.params().len(), 2500);
+
+        assert_eq!(
+            SqliteDialect
+                .render_in_values(expr!("id"), vec![1.into(), 2.into()])
+                .render_chunk()
+                .preview(),
+            "(id IN (1, 2))"
+        );
+        assert_eq!(
+            SqliteDialect
+                .render_in_values(expr!("id"), vec![])
+                .render_chunk()
+                .preview(),
+            "(id IN (NULL))"
+        );
+    }
+}
@@ -1,6 +1,12 @@
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::{sql::chunk::Chunk, sql::Operations, traits::column::SqlField};
+use crate::{
+    sql::chunk::Chunk,
+    sql::dialect::{Dialect, PostgresDialect},
+    sql::Operations,
+    traits::column::SqlField,
+};
 
 /// Constructs [`Expression`] from a format scring and several parameters by passing those
 /// into [`json!`]
@@ -28,10 +34,12 @@ macro_rules! expr {
 /// Expression is a basic piece of SQL query template that contains a format string
 /// and several parameters. Easiest way to create Expression is with [`expr!`] macro
 ///
-
-#[derive(Debug, Clone)]
+/// Serializes as `{"sql": "name = {}", "params": ["John"]}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Expression {
+    #[serde(rename = "sql")]
     expression: String,
+    #[serde(rename = "params", default)]
     parameters: Vec<Value>,
 }
 
@@ -76,13 +84,25 @@ impl Expression {
     /// let final = expr!("{} + {}", 2, 3);  // "$1 + $2"
     /// ```
     pub fn sql_final(&self) -> String {
+        self.sql_final_for(&PostgresDialect)
+    }
+
+    /// Converts template by replacing {} with placeholders of the dialect
+    ///
+    /// ```
+    /// let final = expr!("{} + {}", 2, 3).sql_final_for(&MySqlDialect);  // "? + ?"
+    /// ```
+    pub fn sql_final_for(&self, dialect: &dyn Dialect) -> String {
         let mut sql_final = self.expression.clone();
 
         let token = "{}";
         let mut num = 0;
-        while let Some(index) = sql_final.find(token) {
+        let mut start = 0;
+        while let Some(index) = sql_final[start..].find(token) {
             num += 1;
-            sql_final.replace_range(index..index + token.len(), &format!("${}", num));
+            let placeholder = dialect.placeholder(num);
+            sql_final.replace_range(start + index..start + index + token.len(), &placeholder);
+            start += index + placeholder.len();
         }
         sql_final
     }
@@ -124,14 +144,60 @@ impl Expression {
         (self.expression, self.parameters)
     }
 
-    /// Places values into the template and returns a String.
+    /// Places values into the template as SQL literals and returns a String.
     /// Useful for debugging, but not for SQL execution.
+    ///
+    /// ```
+    /// expr!("name = {} AND tags = {}", "O'Brien", ["a", "b"]).preview();
+    /// // name = 'O''Brien' AND tags = ARRAY['a', 'b']
+    /// ```
     pub fn preview(&self) -> String {
-        let mut preview = self.expression.clone();
-        for param in &self.parameters {
-            preview = preview.replacen("{}", &param.to_string(), 1);
+        self.fill_placeholders(sql_literal)
+    }
+
+    /// Places values into the template as literals of the `dialect`, for data
+    /// sources, which can't send parameters separately from the query.
+    pub fn render_inline(&self, dialect: &dyn Dialect) -> String {
+        self.fill_placeholders(|value| dialect.render_literal(value))
+    }
+
+    /// Same as [`preview()`](Self::preview), but parameters are replaced with
+    /// `?`, so the query can be logged without exposing the values.
+    pub fn redact_params(&self) -> String {
+        self.fill_placeholders(|_| "?".to_string())
+    }
+
+    /// Replaces placeholders of the template in one pass, so that `{}` inside
+    /// of a value is left alone.
+    fn fill_placeholders(&self, render: impl Fn(&Value) -> String) -> String {
+        let mut result = String::with_capacity(self.expression.len());
+        let mut parameters = self.parameters.iter();
+        let mut rest = self.expression.as_str();
+        while let Some(pos) = rest.find("{}") {
+            result.push_str(&rest[..pos]);
+            match parameters.next() {
+                Some(value) => result.push_str(&render(value)),
+                None => result.push_str("{}"),
+            }
+            rest = &rest[pos + 2..];
         }
-        preview
+        result.push_str(rest);
+        result
+    }
+}
+
+/// Renders value as an SQL literal. Objects become JSON strings.
+pub(crate) fn sql_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format!("'{}'", s.replace('\'', "''")),
+        Value::Array(items) => format!(
+            "ARRAY[{}]",
+            items.iter().map(sql_literal).collect::<Vec<_>>().join(", ")
+        ),
+        Value::Object(_) => sql_literal(&Value::String(value.to_string())),
     }
 }
 
@@ -157,6 +223,29 @@ mod tests {
     use crate::sql::chunk::Chunk;
     use serde_json::json;
 
+    #[test]
+    fn test_preview() {
+        let expression = expr!(
+            "name = {} AND note = {} AND deleted_at IS {} AND tags = {} AND meta = {} AND qty > {}",
+            "O'Brien",
+            "{}",
+            Value::Null,
+            json!(["a", 1]),
+            json!({"a": "it's"}),
+            3
+        );
+        assert_eq!(
+            expression.preview(),
+            "name = 'O''Brien' AND note = '{}' AND deleted_at IS NULL \
+            AND tags = ARRAY['a', 1] AND meta = '{\"a\":\"it''s\"}' AND qty > 3"
+        );
+        assert_eq!(
+            expression.redact_params(),
+            "name = ? AND note = ? AND deleted_at IS ? AND tags = ? AND meta = ? AND qty > ?"
+        );
+        assert_eq!(expr!("a = {} AND b = {}", 1).preview(), "a = 1 AND b = {}");
+    }
+
     #[test]
     fn test_as_type() {
         let expression = Expression::as_type(json!(1), "int");
@@ -5,6 +5,7 @@ use crate::{
     sql::chunk::Chunk,
     // operations::Operations,
     traits::column::SqlField,
+    Error,
 };
 
 pub trait WrapArc {
@@ -68,6 +69,10 @@ impl ExpressionArc {
 
 impl Chunk for ExpressionArc {
     fn render_chunk(&self) -> Expression {
+        self.try_render_chunk().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render_chunk(&self) -> Result<Expression, Error> {
         let token = "{}";
 
         let mut param_iter = self.parameters.iter();
@@ -77,13 +82,18 @@ impl Chunk for ExpressionArc {
         let mut sql_out: String = String::from(sql.next().unwrap());
 
         while let Some(param) = param_iter.next() {
-            let (param_sql, param_values) = param.render_chunk().split();
+            let (param_sql, param_values) = param.try_render_chunk()?.split();
             sql_out.push_str(&param_sql);
             param_out.extend(param_values);
-            sql_out.push_str(sql.next().unwrap());
+            sql_out.push_str(sql.next().ok_or_else(|| {
+                Error::RenderError(format!(
+                    "Expression '{}' has more parameters than placeholders",
+                    self.expression
+                ))
+            })?);
         }
 
-        Expression::new(sql_out, param_out)
+        Ok(Expression::new(sql_out, param_out))
     }
 }
 
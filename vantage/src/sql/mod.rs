@@ -1,9 +1,18 @@
+/// [`Aggregate`] functions for use with grouped tables
+pub mod aggregate;
+
 /// [`Chunk`] trait for generating SQL queries and their associated parameters
 pub mod chunk;
 
 /// [`Condition`] struct for building operations out of fields and expressions
 pub mod condition;
 
+/// [`ConditionTree`] for nesting conditions with AND, OR and NOT
+pub mod condition_tree;
+
+/// [`Dialect`] trait for SQL syntax specific to a database engine
+pub mod dialect;
+
 pub mod expression;
 
 /// [`Operations`] trait for syntactic sugar for operations on fields
@@ -12,8 +21,12 @@ pub mod operations;
 /// [`Query`] struct for building entire SQL queries
 pub mod query;
 
+/// [`SqlType`] enum for typed columns
+pub mod sql_type;
+
 pub mod table;
 
+pub use aggregate::Aggregate;
 pub use chunk::Chunk;
 pub use expression::Expression;
 pub use expression::ExpressionArc;
@@ -21,9 +34,13 @@ pub use expression::WrapArc;
 
 pub use query::Query;
 
+pub use sql_type::SqlType;
+
 pub use operations::Operations;
 
 pub use condition::Condition;
+pub use condition_tree::ConditionTree;
+pub use dialect::Dialect;
 
 pub use table::Column;
 pub use table::Join;
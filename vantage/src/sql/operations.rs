@@ -1,8 +1,11 @@
 use std::sync::Arc;
 
+use serde_json::Value;
+
 use crate::{
     expr_arc,
     sql::chunk::Chunk,
+    sql::dialect::chunked_in_values,
     sql::expression::{Expression, ExpressionArc},
     sql::Condition,
 };
@@ -23,6 +26,12 @@ use crate::{
 /// [`Field`]: crate::field::Field
 
 pub trait Operations: Chunk {
+    /// Builds a condition with `self` as the left operand. Columns override this,
+    /// so that their table alias can still be changed after the condition is built.
+    fn condition(&self, operation: &str, value: Arc<Box<dyn Chunk>>) -> Condition {
+        Condition::from_expression(self.render_chunk(), operation, value)
+    }
+
     // fn in_vec(&self, other: Vec<impl SqlChunk>) -> Condition {
     //     Condition::from_expression(
     //         self.render_chunk(),
@@ -34,50 +43,89 @@ pub trait Operations: Chunk {
     //     )
     // }
     fn in_expr(&self, other: &impl Chunk) -> Condition {
-        Condition::from_expression(
-            self.render_chunk(),
+        self.condition(
             "IN",
             Arc::new(Box::new(expr_arc!("({})", other.render_chunk()))),
         )
     }
 
-    fn is(&self, other: &impl Chunk) -> Condition {
-        Condition::from_expression(
-            self.render_chunk(),
-            "IS",
-            Arc::new(Box::new(other.render_chunk())),
+    /// Matches one of the values. Columns render long lists with the dialect of
+    /// their data source, as an array parameter or several IN lists, see
+    /// [`Dialect::render_in_values()`]:
+    ///
+    /// ```
+    /// let condition = orders.client_id().in_values(client_ids);
+    /// // (client_id = ANY ({}))
+    /// ```
+    ///
+    /// Expressions don't know the dialect, so they are split into IN lists, which
+    /// all databases accept.
+    ///
+    /// [`Dialect::render_in_values()`]: crate::sql::dialect::Dialect::render_in_values()
+    fn in_values(&self, values: Vec<Value>) -> Condition {
+        chunked_in_values(self.render_chunk(), values)
+    }
+
+    fn not_in_expr(&self, other: &impl Chunk) -> Condition {
+        self.condition(
+            "NOT IN",
+            Arc::new(Box::new(expr_arc!("({})", other.render_chunk()))),
         )
     }
 
+    fn is(&self, other: &impl Chunk) -> Condition {
+        self.condition("IS", Arc::new(Box::new(other.render_chunk())))
+    }
+
     fn eq(&self, other: &impl Chunk) -> Condition {
-        Condition::from_expression(
-            self.render_chunk(),
-            "=",
-            Arc::new(Box::new(other.render_chunk())),
-        )
+        self.condition("=", Arc::new(Box::new(other.render_chunk())))
     }
 
     fn ne(&self, other: impl Chunk) -> Condition {
-        Condition::from_expression(
-            self.render_chunk(),
-            "!=",
-            Arc::new(Box::new(other.render_chunk())),
-        )
+        self.condition("!=", Arc::new(Box::new(other.render_chunk())))
     }
 
     fn gt(&self, other: impl Chunk) -> Condition {
-        Condition::from_expression(
-            self.render_chunk(),
-            ">",
-            Arc::new(Box::new(other.render_chunk())),
-        )
+        self.condition(">", Arc::new(Box::new(other.render_chunk())))
     }
 
     fn lt(&self, other: impl Chunk) -> Condition {
-        Condition::from_expression(
-            self.render_chunk(),
-            "<",
-            Arc::new(Box::new(other.render_chunk())),
+        self.condition("<", Arc::new(Box::new(other.render_chunk())))
+    }
+
+    /// Value is within the range, including both ends: `age BETWEEN 18 AND 65`
+    fn between(&self, from: impl Chunk, to: impl Chunk) -> Condition {
+        self.condition(
+            "BETWEEN",
+            Arc::new(Box::new(expr_arc!(
+                "{} AND {}",
+                from.render_chunk(),
+                to.render_chunk()
+            ))),
+        )
+    }
+
+    /// Case-sensitive pattern match: `name LIKE 'J%'`
+    fn like(&self, pattern: impl Chunk) -> Condition {
+        self.condition("LIKE", Arc::new(Box::new(pattern.render_chunk())))
+    }
+
+    /// Case-insensitive pattern match: `name ILIKE 'j%'`
+    fn ilike(&self, pattern: impl Chunk) -> Condition {
+        self.condition("ILIKE", Arc::new(Box::new(pattern.render_chunk())))
+    }
+
+    fn is_null(&self) -> Condition {
+        self.condition(
+            "IS",
+            Arc::new(Box::new(Expression::new("NULL".to_string(), vec![]))),
+        )
+    }
+
+    fn is_not_null(&self) -> Condition {
+        self.condition(
+            "IS NOT",
+            Arc::new(Box::new(Expression::new("NULL".to_string(), vec![]))),
         )
     }
 
@@ -114,6 +162,48 @@ pub trait Operations: Chunk {
     fn upper(&self) -> Expression {
         expr_arc!("UPPER({})", self.render_chunk()).render_chunk()
     }
+
+    /// Get JSON object field by key, as JSON: `metadata -> 'key'`
+    fn json_get(&self, key: &str) -> Expression {
+        expr_arc!("({}) -> {}", self.render_chunk(), key.to_string()).render_chunk()
+    }
+
+    /// Get JSON object field by key, as text: `metadata ->> 'key'`
+    fn json_get_text(&self, key: &str) -> Expression {
+        expr_arc!("({}) ->> {}", self.render_chunk(), key.to_string()).render_chunk()
+    }
+
+    /// JSONB value contains the other value: `metadata @> '{"gift": true}'`
+    fn contains(&self, value: Value) -> Condition {
+        self.condition(
+            "@>",
+            Arc::new(Box::new(Expression::as_type(value, "jsonb"))),
+        )
+    }
+
+    /// Array column contains the value: `{} = ANY(tags)`
+    fn any_eq(&self, value: impl Chunk) -> Condition {
+        Condition::from_expression(
+            value.render_chunk(),
+            "=",
+            Arc::new(Box::new(expr_arc!("ANY({})", self.render_chunk()))),
+        )
+    }
+
+    /// Array column contains all of the values: `tags @> {}`
+    fn contains_all(&self, values: impl Chunk) -> Condition {
+        self.condition("@>", Arc::new(Box::new(values.render_chunk())))
+    }
+
+    /// Array column has at least one of the values: `tags && {}`
+    fn overlaps(&self, values: impl Chunk) -> Condition {
+        self.condition("&&", Arc::new(Box::new(values.render_chunk())))
+    }
+
+    /// JSONB object has a top-level key: `metadata ? 'gift'`
+    fn has_key(&self, key: &str) -> Condition {
+        self.condition("?", Arc::new(Box::new(key.to_string())))
+    }
 }
 
 #[cfg(test)]
@@ -131,6 +221,103 @@ mod tests {
         assert_eq!(b.render_chunk().sql(), "UPPER(name)");
     }
 
+    #[test]
+    fn test_predicates() {
+        let age = Arc::new(Column::new("age".to_string(), None));
+        let name = Arc::new(Column::new("name".to_string(), None));
+
+        assert_eq!(
+            age.between(18, 65).render_chunk().split(),
+            (
+                "(age BETWEEN {} AND {})".to_string(),
+                vec![json!(18), json!(65)]
+            )
+        );
+        assert_eq!(
+            name.like("J%".to_string()).render_chunk().split(),
+            ("(name LIKE {})".to_string(), vec![json!("J%")])
+        );
+        assert_eq!(
+            name.ilike("j%".to_string()).render_chunk().split(),
+            ("(name ILIKE {})".to_string(), vec![json!("j%")])
+        );
+        assert_eq!(name.is_null().render_chunk().sql(), "(name IS NULL)");
+        assert_eq!(
+            name.is_not_null().render_chunk().sql(),
+            "(name IS NOT NULL)"
+        );
+        assert_eq!(
+            age.ne(18).render_chunk().split(),
+            ("(age != {})".to_string(), vec![json!(18)])
+        );
+        assert_eq!(
+            age.gt(65).or(name.is_null()).not().render_chunk().sql(),
+            "(NOT ((age > {}) OR (name IS NULL)))"
+        );
+    }
+
+    #[test]
+    fn test_in_values() {
+        assert_eq!(
+            expr!("id")
+                .in_values(vec![json!(1), json!(2)])
+                .render_chunk()
+                .split(),
+            ("(id IN ({}, {}))".to_string(), vec![json!(1), json!(2)])
+        );
+
+        let mut id = Column::new("id".to_string(), None);
+        id.set_dialect(Arc::new(MssqlDialect));
+        assert_eq!(
+            id.in_values(vec![json!(1)]).render_chunk().sql(),
+            "(id IN ({}))"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let metadata = Arc::new(Column::new("metadata".to_string(), None));
+
+        assert_eq!(
+            metadata.json_get("address").json_get_text("city").preview(),
+            "((metadata) -> 'address') ->> 'city'"
+        );
+        assert_eq!(
+            metadata
+                .contains(json!({"gift": true}))
+                .render_chunk()
+                .preview(),
+            r#"(metadata @> '{"gift":true}'::jsonb)"#
+        );
+        assert_eq!(
+            metadata.has_key("gift").render_chunk().split(),
+            ("(metadata ? {})".to_string(), vec![json!("gift")])
+        );
+    }
+
+    #[test]
+    fn test_array() {
+        let tags = Arc::new(Column::new("tags".to_string(), None));
+
+        assert_eq!(
+            tags.any_eq("vegan").render_chunk().preview(),
+            "('vegan' = ANY(tags))"
+        );
+        assert_eq!(
+            tags.contains_all(json!(["vegan", "gluten-free"]))
+                .render_chunk()
+                .split(),
+            (
+                "(tags @> {})".to_string(),
+                vec![json!(["vegan", "gluten-free"])]
+            )
+        );
+        assert_eq!(
+            tags.overlaps(json!(["nuts"])).render_chunk().sql(),
+            "(tags && {})"
+        );
+    }
+
     #[test]
     fn test_upper_in_table() {
         let data = json!([]);
@@ -1,32 +1,44 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use indexmap::IndexMap;
-use serde_json::Value;
+use serde_json::{Map, Value};
 pub use with_traits::SqlQuery;
 
 use crate::{
     expr, expr_arc,
     sql::{
         chunk::Chunk,
+        dialect::{Dialect, PostgresDialect},
         expression::{Expression, ExpressionArc},
         table::Column,
     },
     traits::column::SqlField,
+    Error,
 };
 
+mod ast;
+mod filter;
 mod parts;
 
+pub use filter::*;
 pub use parts::*;
 
 #[derive(Debug, Clone)]
 pub struct Query {
     table: QuerySource,
     with: IndexMap<String, QuerySource>,
+    recursive: bool,
     distinct: bool,
+    distinct_on: Vec<Expression>,
     query_type: QueryType,
     fields: IndexMap<Option<String>, Arc<Box<dyn SqlField>>>,
-    set_fields: IndexMap<String, Value>,
+    strict_fields: bool,
+    set_fields: IndexMap<String, Expression>,
+    values_rows: Vec<IndexMap<String, Expression>>,
+    returning: QueryReturning,
+    on_conflict: Option<Vec<String>>,
 
     where_conditions: QueryConditions,
     having_conditions: QueryConditions,
@@ -37,6 +49,9 @@ pub struct Query {
 
     group_by: Vec<Expression>,
     order_by: Vec<Expression>,
+    lock: Option<LockMode>,
+    comment: BTreeMap<String, String>,
+    dialect: Arc<dyn Dialect>,
 }
 
 #[derive(Debug)]
@@ -50,11 +65,17 @@ impl Query {
         Query {
             table: QuerySource::None,
             with: IndexMap::new(),
+            recursive: false,
             distinct: false,
+            distinct_on: Vec::new(),
             query_type: QueryType::Select,
             fields: IndexMap::new(),
+            strict_fields: false,
 
             set_fields: IndexMap::new(),
+            values_rows: Vec::new(),
+            returning: QueryReturning::None,
+            on_conflict: None,
 
             where_conditions: QueryConditions::where_(),
             having_conditions: QueryConditions::having(),
@@ -65,14 +86,79 @@ impl Query {
 
             group_by: Vec::new(),
             order_by: Vec::new(),
+            lock: None,
+            comment: BTreeMap::new(),
+            dialect: Arc::new(PostgresDialect),
         }
     }
 
+    /// Render query for a different database engine, see [`Dialect`].
+    pub fn with_dialect(mut self, dialect: Arc<dyn Dialect>) -> Self {
+        self.set_dialect(dialect);
+        self
+    }
+
     pub fn with_distinct(mut self) -> Self {
         self.set_distinct(true);
         self
     }
 
+    /// Only keep the first row for each combination of the values. Which row is
+    /// first is determined by ORDER BY, which must start with the same expressions:
+    ///
+    /// ```
+    /// let query = Query::new()
+    ///     .with_table("ord", None)
+    ///     .with_distinct_on(vec![expr!("client_id")])
+    ///     .with_order_by(expr!("created_at DESC"))
+    ///     .with_order_by(expr!("client_id"));
+    /// // SELECT DISTINCT ON (client_id) * FROM ord ORDER BY client_id, created_at DESC
+    /// ```
+    pub fn with_distinct_on(mut self, expressions: Vec<Expression>) -> Self {
+        for expression in expressions {
+            self.add_distinct_on(expression);
+        }
+        self
+    }
+
+    /// Lock selected rows, for example to pick a pending job, which is not being
+    /// processed by anyone else:
+    ///
+    /// ```
+    /// let query = Query::new()
+    ///     .with_table("job", None)
+    ///     .with_condition(expr!("status = 'pending'"))
+    ///     .with_lock(LockMode::ForUpdate { skip_locked: true, nowait: false });
+    /// // SELECT * FROM job WHERE status = 'pending' FOR UPDATE SKIP LOCKED
+    /// ```
+    pub fn with_lock(mut self, lock: LockMode) -> Self {
+        self.set_lock(Some(lock));
+        self
+    }
+
+    /// Prefix the query with a comment in [sqlcommenter] format, so that slow
+    /// queries in `pg_stat_statements` or server logs can be traced back to the
+    /// endpoint or request. `comment` contains `key:value` pairs:
+    ///
+    /// ```
+    /// let query = Query::new()
+    ///     .with_table("orders", None)
+    ///     .with_comment("api:list_orders req:1234");
+    /// // /*api='list_orders',req='1234'*/ SELECT * FROM orders
+    /// ```
+    ///
+    /// Words without a colon are added with an empty value. Keys and values are
+    /// URL-encoded, so they can't close the comment or add parameters.
+    ///
+    /// [sqlcommenter]: https://google.github.io/sqlcommenter/spec/
+    pub fn with_comment(mut self, comment: &str) -> Self {
+        for pair in comment.split_whitespace() {
+            let (key, value) = pair.split_once(':').unwrap_or((pair, ""));
+            self.add_comment(key, value);
+        }
+        self
+    }
+
     pub fn with_table(mut self, table: &str, alias: Option<String>) -> Self {
         self.set_table(table, alias);
         self
@@ -86,6 +172,89 @@ impl Query {
         self
     }
 
+    /// Adds a recursive common table expression. The `recursive_query` may
+    /// reference `alias` and its results are appended to the `base_query`
+    /// until no new rows are produced:
+    ///
+    /// ```
+    /// let query = Query::new()
+    ///     .with_recursive("tree", base_query, recursive_query)
+    ///     .with_table("tree", None);
+    /// // WITH RECURSIVE tree AS (base UNION ALL recursive) SELECT * FROM tree
+    /// ```
+    pub fn with_recursive(
+        mut self,
+        alias: &str,
+        base_query: Query,
+        recursive_query: Query,
+    ) -> Self {
+        self.recursive = true;
+        self.add_with(
+            alias.to_string(),
+            QuerySource::Expression(
+                expr_arc!("({} UNION ALL {})", base_query, recursive_query).render_chunk(),
+                None,
+            ),
+        );
+        self
+    }
+
+    fn combine(self, operator: &str, other: Query) -> Query {
+        Query::new().with_source(QuerySource::Expression(
+            expr_arc!(format!("(({{}}) {} ({{}}))", operator), self, other).render_chunk(),
+            Some("u".to_string()),
+        ))
+    }
+
+    /// Combines results of two queries, wrapping them into a sub-query, so
+    /// that it can be further filtered, sorted or limited:
+    ///
+    /// ```
+    /// let query = current_orders.union_all(archived_orders).with_limit(10);
+    /// // SELECT * FROM ((SELECT ..) UNION ALL (SELECT ..)) AS u LIMIT 10
+    /// ```
+    pub fn union(self, other: Query) -> Query {
+        self.combine("UNION", other)
+    }
+
+    pub fn union_all(self, other: Query) -> Query {
+        self.combine("UNION ALL", other)
+    }
+
+    pub fn intersect(self, other: Query) -> Query {
+        self.combine("INTERSECT", other)
+    }
+
+    pub fn except(self, other: Query) -> Query {
+        self.combine("EXCEPT", other)
+    }
+
+    /// Counts rows, which would be returned by this query:
+    ///
+    /// ```
+    /// let query = Query::new().with_table("ord", None).with_column_field("id");
+    /// // SELECT (COUNT(*)) AS count FROM (SELECT id FROM ord) AS c
+    /// let count = query.get_count_query();
+    /// ```
+    pub fn get_count_query(&self) -> Query {
+        Query::new()
+            .with_dialect(self.dialect.clone())
+            .with_source(QuerySource::Query(
+                Arc::new(Box::new(self.clone())),
+                Some("c".to_string()),
+            ))
+            .with_field("count".to_string(), expr!("COUNT(*)"))
+    }
+
+    /// Returns true, if this query returns any rows, see [`Dialect::render_exists()`]
+    pub fn get_exists_query(&self) -> Query {
+        Query::new()
+            .with_dialect(self.dialect.clone())
+            .with_type(QueryType::Expression(
+                self.dialect.render_exists(self.render_chunk()),
+            ))
+    }
+
     pub fn with_source(mut self, source: QuerySource) -> Self {
         self.set_source(source);
         self
@@ -112,6 +281,54 @@ impl Query {
         self
     }
 
+    /// Specify which fields should be returned by INSERT, UPDATE or DELETE query
+    pub fn with_returning(mut self, fields: &[&str]) -> Self {
+        self.set_returning(QueryReturning::Fields(
+            fields.iter().map(|f| f.to_string()).collect(),
+        ));
+        self
+    }
+
+    pub fn with_returning_all(mut self) -> Self {
+        self.set_returning(QueryReturning::All);
+        self
+    }
+
+    pub fn without_returning(mut self) -> Self {
+        self.set_returning(QueryReturning::None);
+        self
+    }
+
+    /// Turns INSERT into an upsert: if a record with the same `conflict_fields` exists,
+    /// the remaining fields are updated instead. Syntax depends on the [`Dialect`]:
+    ///
+    /// ```
+    /// let query = Query::new()
+    ///     .with_table("product", None)
+    ///     .with_type(QueryType::Insert)
+    ///     .with_set_field("sku", "PIE-1".into())
+    ///     .with_set_field("name", "Pie".into())
+    ///     .with_upsert(&["sku"]);
+    /// // INSERT INTO product (sku, name) VALUES ({}, {}) ON CONFLICT (sku) DO UPDATE SET name = EXCLUDED.name
+    /// ```
+    pub fn with_upsert(mut self, conflict_fields: &[&str]) -> Self {
+        self.set_upsert(Some(
+            conflict_fields.iter().map(|f| f.to_string()).collect(),
+        ));
+        self
+    }
+
+    /// Reject fields with an alias, which is already used in the query. By default
+    /// the new field replaces the old one. With strict fields [`with_field()`] will
+    /// panic and [`try_add_field()`] will return [`Error::DuplicateField`].
+    ///
+    /// [`with_field()`]: Query::with_field()
+    /// [`try_add_field()`]: SqlQuery::try_add_field()
+    pub fn with_strict_fields(mut self) -> Self {
+        self.strict_fields = true;
+        self
+    }
+
     pub fn without_fields(mut self) -> Self {
         self.fields = IndexMap::new();
         self
@@ -123,10 +340,9 @@ impl Query {
     }
     // Simplified ways to define a field with a string
     pub fn with_column_field(self, name: &str) -> Self {
-        self.with_field(
-            name.to_string(),
-            Arc::new(Column::new(name.to_string(), None)),
-        )
+        let mut column = Column::new(name.to_string(), None);
+        column.set_dialect(self.dialect.clone());
+        self.with_field(name.to_string(), Arc::new(column))
     }
 
     pub fn with_field_arc(mut self, name: String, field: Arc<Box<dyn SqlField>>) -> Self {
@@ -172,19 +388,49 @@ impl Query {
         self
     }
 
-    fn render_with(&self) -> Expression {
+    pub fn with_set_field_expression(mut self, field: &str, expression: Expression) -> Self {
+        self.set_field_expression(field, expression);
+        self
+    }
+
+    /// Insert another row with the same fields as set with `with_set_field()`,
+    /// rendering `INSERT INTO t (a, b) VALUES (..), (..)`.
+    pub fn with_values_row(mut self, row: IndexMap<String, Expression>) -> Self {
+        self.add_values_row(row);
+        self
+    }
+
+    fn render_with(&self) -> Result<Expression, Error> {
         if self.with.is_empty() {
-            Expression::empty()
+            return Ok(Expression::empty());
+        }
+        let with = self
+            .with
+            .iter()
+            .map(|(name, query)| {
+                Ok(expr_arc!(
+                    format!("{} AS {{}}", self.dialect.quote_identifier(name)),
+                    query.try_render_prefix_for("", self.dialect.as_ref())?
+                )
+                .render_chunk())
+            })
+            .collect::<Result<Vec<Expression>, Error>>()?;
+        let e = Expression::from_vec(with, ", ");
+        Ok(if self.recursive {
+            expr_arc!("WITH RECURSIVE {} ", e).render_chunk()
         } else {
-            let with = self
-                .with
-                .iter()
-                .map(|(name, query)| {
-                    expr_arc!(format!("{} AS {{}}", name), query.render_prefix("")).render_chunk()
-                })
-                .collect::<Vec<Expression>>();
-            let e = Expression::from_vec(with, ", ");
             expr_arc!("WITH {} ", e).render_chunk()
+        })
+    }
+
+    fn render_distinct(&self) -> Expression {
+        if !self.distinct_on.is_empty() {
+            let distinct_on = Expression::from_vec(self.distinct_on.clone(), ", ");
+            expr_arc!(" DISTINCT ON ({})", distinct_on).render_chunk()
+        } else if self.distinct {
+            expr!(" DISTINCT")
+        } else {
+            Expression::empty()
         }
     }
 
@@ -199,6 +445,9 @@ impl Query {
 
     fn render_order_by(&self) -> Expression {
         if self.order_by.is_empty() {
+            if self.skip_items.is_some() && self.dialect.requires_order_for_offset() {
+                return expr!(" ORDER BY (SELECT NULL)");
+            }
             Expression::empty()
         } else {
             let mut rev_vec = self.order_by.clone();
@@ -209,29 +458,66 @@ impl Query {
     }
 
     fn render_pagination(&self) -> Expression {
-        if self.skip_items.is_none() && self.limit_items.is_none() {
-            Expression::empty()
-        } else {
-            let mut rev_vec = Vec::new();
-            if let Some(skip) = self.skip_items {
-                rev_vec.push(expr!(" OFFSET {}::int4", skip));
-            }
-            if let Some(limit) = self.limit_items {
-                rev_vec.push(expr!(" LIMIT {}::int4", limit));
+        self.dialect
+            .render_pagination(self.skip_items, self.limit_items)
+    }
+
+    fn render_returning(&self) -> Result<Expression, Error> {
+        if !matches!(self.returning, QueryReturning::None) && !self.dialect.supports_returning() {
+            return Err(Error::RenderError(format!(
+                "{:?} does not support RETURNING",
+                self.dialect
+            )));
+        }
+        if self.dialect.uses_output_clause() {
+            return Ok(Expression::empty());
+        }
+        Ok(self.returning.render_for(self.dialect.as_ref()))
+    }
+
+    /// `OUTPUT INSERTED.id` clause for dialects, which use it instead of RETURNING
+    fn render_output(&self, pseudo_table: &str) -> String {
+        if !self.dialect.uses_output_clause() {
+            return String::new();
+        }
+        self.returning
+            .render_output(self.dialect.as_ref(), pseudo_table)
+    }
+
+    fn render_upsert(&self) -> Result<String, Error> {
+        let Some(conflict_fields) = &self.on_conflict else {
+            return Ok(String::new());
+        };
+        let update_fields = self
+            .set_fields
+            .keys()
+            .filter(|field| !conflict_fields.contains(field))
+            .map(|field| self.dialect.quote_identifier(field))
+            .collect::<Vec<_>>();
+        let conflict_fields = conflict_fields
+            .iter()
+            .map(|field| self.dialect.quote_identifier(field))
+            .collect::<Vec<_>>();
+        self.dialect.render_upsert(&conflict_fields, &update_fields)
+    }
+
+    /// Name of the table or alias of the subquery, used in error messages.
+    fn source_name(&self) -> String {
+        match &self.table {
+            QuerySource::Table(table, _) => table.clone(),
+            QuerySource::Query(_, Some(alias)) | QuerySource::Expression(_, Some(alias)) => {
+                alias.clone()
             }
-            Expression::from_vec(rev_vec, "")
+            _ => "<query>".to_string(),
         }
     }
 
-    fn render_select(&self) -> Result<Expression> {
+    fn render_select(&self) -> Result<Expression, Error> {
         let fields = if self.fields.len() > 0 {
             Expression::from_vec(
                 self.fields
                     .iter()
-                    .map(|f| {
-                        f.1.render_column(f.0.as_ref().map(|s| s.as_str()))
-                            .render_chunk()
-                    })
+                    .map(|(alias, field)| self.render_field(alias, field))
                     .collect(),
                 ", ",
             )
@@ -239,75 +525,113 @@ impl Query {
             Expression::new("*".to_string(), vec![])
         };
 
-        Ok(expr_arc!(
-            format!(
-                "{{}}SELECT{} {{}} {{}}{{}}{{}}{{}}{{}}{{}}{{}}",
-                if self.distinct { " DISTINCT" } else { "" }
-            ),
-            self.render_with(),
+        expr_arc!(
+            "{}SELECT{}{} {} {}{}{}{}{}{}{}{}",
+            self.render_with()?,
+            self.render_distinct(),
+            self.dialect.render_top(self.skip_items, self.limit_items),
             fields,
-            self.table.render_chunk(),
-            Expression::from_vec(self.joins.iter().map(|x| x.render_chunk()).collect(), ""),
+            self.table
+                .try_render_prefix_for("FROM ", self.dialect.as_ref())?,
+            Expression::from_vec(
+                self.joins
+                    .iter()
+                    .map(|join| join.try_render_for(self.dialect.as_ref()))
+                    .collect::<Result<Vec<_>, Error>>()?,
+                ""
+            ),
             self.where_conditions.render_chunk(),
             self.render_group_by(),
+            self.having_conditions.render_chunk(),
             self.render_order_by(),
             self.render_pagination(),
-            self.having_conditions.render_chunk()
+            self.lock
+                .map(|lock| lock.render_chunk())
+                .unwrap_or_else(Expression::empty)
         )
-        .render_chunk())
+        .try_render_chunk()
     }
 
-    fn render_insert(&self) -> Result<Expression> {
+    fn render_field(&self, alias: &Option<String>, field: &Arc<Box<dyn SqlField>>) -> Expression {
+        // columns quote their own alias, as they omit the one matching their name
+        let alias = match alias {
+            Some(alias) if field.calculated() => Some(self.dialect.quote_identifier(alias)),
+            alias => alias.clone(),
+        };
+        field.render_column(alias.as_deref()).render_chunk()
+    }
+
+    fn render_insert(&self) -> Result<Expression, Error> {
         let QuerySource::Table(table, _) = self.table.clone() else {
-            return Err(anyhow!("Call set_table() for insert query"));
+            return Err(Error::RenderError(
+                "Call set_table() for insert query".to_string(),
+            ));
         };
 
         let fields = self
             .set_fields
             .iter()
-            .map(|(k, _)| k.clone())
-            .collect::<Vec<String>>()
-            .join(", ");
-
-        let values_str = self
-            .set_fields
-            .iter()
-            .map(|_| "{}".to_string())
+            .map(|(k, _)| self.dialect.quote_identifier(k))
             .collect::<Vec<String>>()
             .join(", ");
 
-        let values = self
-            .set_fields
-            .iter()
-            .map(|(_, f)| f.clone())
-            .collect::<Vec<Value>>();
+        let mut rows = vec![expr_arc!(
+            "({})",
+            Expression::from_vec(self.set_fields.values().cloned().collect(), ", ")
+        )
+        .render_chunk()];
+        for row in &self.values_rows {
+            let values = self
+                .set_fields
+                .keys()
+                .map(|field| {
+                    row.get(field).cloned().ok_or_else(|| {
+                        Error::RenderError(format!("Row is missing value for field {}", field))
+                    })
+                })
+                .collect::<Result<Vec<Expression>, Error>>()?;
+            if values.len() != row.len() {
+                return Err(Error::RenderError(
+                    "Row has fields, which are not in the insert query".to_string(),
+                ));
+            }
+            rows.push(expr_arc!("({})", Expression::from_vec(values, ", ")).render_chunk());
+        }
 
         Ok(expr_arc!(
             format!(
-                "{} INTO {} ({}) VALUES ({{}}) returning id",
+                "{} INTO {} ({}){} VALUES {{}}{}{{}}",
                 match self.query_type {
                     QueryType::Insert => "INSERT",
                     QueryType::Replace => "REPLACE",
                     _ => panic!("Invalid query type"),
                 },
-                table,
-                fields
+                self.dialect.quote_identifier(&table),
+                fields,
+                self.render_output("INSERTED"),
+                self.render_upsert()?
             ),
-            Expression::new(values_str, values)
+            Expression::from_vec(rows, ", "),
+            self.render_returning()?
         )
         .render_chunk())
     }
 
-    fn render_update(&self) -> Result<Expression> {
+    fn render_update(&self) -> Result<Expression, Error> {
         let QuerySource::Table(table, _) = self.table.clone() else {
-            return Err(anyhow!("Call set_table() for insert query"));
+            return Err(Error::RenderError(
+                "Call set_table() for update query".to_string(),
+            ));
         };
 
         let set_fields = self
             .set_fields
             .iter()
             .map(|(k, v)| {
-                let expr = expr_arc!(format!("{} = {{}}", k), v.clone());
+                let expr = expr_arc!(
+                    format!("{} = {{}}", self.dialect.quote_identifier(k)),
+                    v.clone()
+                );
                 let boxed_chunk: Box<dyn Chunk> = Box::new(expr);
                 Arc::new(boxed_chunk)
             })
@@ -316,40 +640,94 @@ impl Query {
         let set_fields = ExpressionArc::from_vec(set_fields, ", ");
 
         Ok(expr_arc!(
-            format!("UPDATE {} SET {{}}{{}}", table),
+            format!(
+                "UPDATE {} SET {{}}{}{{}}{{}}",
+                self.dialect.quote_identifier(&table),
+                self.render_output("INSERTED")
+            ),
             set_fields,
-            self.where_conditions.render_chunk()
+            self.where_conditions.render_chunk(),
+            self.render_returning()?
         )
         .render_chunk())
     }
 
-    fn render_delete(&self) -> Result<Expression> {
+    fn render_delete(&self) -> Result<Expression, Error> {
         let QuerySource::Table(table, _) = self.table.clone() else {
-            return Err(anyhow!("Call set_table() for insert query"));
+            return Err(Error::RenderError(
+                "Call set_table() for delete query".to_string(),
+            ));
+        };
+
+        // without support for DELETE .. LIMIT, the limit is ignored
+        let limit = match self.limit_items {
+            Some(limit) if self.dialect.supports_delete_limit() => expr!(" LIMIT {}", limit),
+            _ => Expression::empty(),
         };
 
         Ok(expr_arc!(
-            format!("DELETE FROM {}{{}}", table),
-            self.where_conditions.render_chunk()
+            format!(
+                "DELETE FROM {}{}{{}}{{}}{{}}",
+                self.dialect.quote_identifier(&table),
+                self.render_output("DELETED")
+            ),
+            self.where_conditions.render_chunk(),
+            limit,
+            self.render_returning()?
         )
         .render_chunk())
     }
 
+    fn render_comment(&self) -> String {
+        let pairs = self
+            .comment
+            .iter()
+            .map(|(key, value)| format!("{}='{}'", url_encode(key), url_encode(value)))
+            .collect::<Vec<_>>();
+        format!("/*{}*/ ", pairs.join(","))
+    }
+
     pub fn preview(&self) -> String {
         self.render_chunk().preview()
     }
+
+    /// Query with parameters replaced by `?`, see [`Expression::redact_params()`].
+    pub fn redact_params(&self) -> String {
+        self.render_chunk().redact_params()
+    }
+}
+
+/// Percent-encodes everything except unreserved characters (RFC 3986).
+fn url_encode(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                result.push(byte as char)
+            }
+            _ => result.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    result
 }
 
 impl Chunk for Query {
     fn render_chunk(&self) -> Expression {
-        match &self.query_type {
-            QueryType::Select => self.render_select(),
-            QueryType::Insert | QueryType::Replace => self.render_insert(),
-            QueryType::Update => self.render_update(),
-            QueryType::Delete => self.render_delete(),
-            QueryType::Expression(expr) => Ok(expr.clone()),
+        self.try_render_chunk().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render_chunk(&self) -> Result<Expression, Error> {
+        let query = match &self.query_type {
+            QueryType::Select => self.render_select()?,
+            QueryType::Insert | QueryType::Replace => self.render_insert()?,
+            QueryType::Update => self.render_update()?,
+            QueryType::Delete => self.render_delete()?,
+            QueryType::Expression(expr) => expr.clone(),
+        };
+        if self.comment.is_empty() {
+            return Ok(query);
         }
-        .unwrap()
+        Ok(expr_arc!(format!("{}{{}}", self.render_comment()), query).render_chunk())
     }
 }
 
@@ -358,6 +736,15 @@ impl SqlQuery for Query {
     fn set_distinct(&mut self, distinct: bool) {
         self.distinct = distinct;
     }
+    fn add_distinct_on(&mut self, expression: Expression) {
+        self.distinct_on.push(expression);
+    }
+    fn set_lock(&mut self, lock: Option<LockMode>) {
+        self.lock = lock;
+    }
+    fn add_values_row(&mut self, row: IndexMap<String, Expression>) {
+        self.values_rows.push(row);
+    }
     fn set_table(&mut self, table: &str, alias: Option<String>) {
         self.table = QuerySource::Table(table.to_string(), alias);
     }
@@ -370,11 +757,34 @@ impl SqlQuery for Query {
     fn set_type(&mut self, query_type: QueryType) {
         self.query_type = query_type;
     }
+    fn set_returning(&mut self, returning: QueryReturning) {
+        self.returning = returning;
+    }
+    fn set_upsert(&mut self, conflict_fields: Option<Vec<String>>) {
+        self.on_conflict = conflict_fields;
+    }
+    fn set_dialect(&mut self, dialect: Arc<dyn Dialect>) {
+        self.dialect = dialect;
+    }
     fn add_field(&mut self, name: Option<String>, field: Arc<Box<dyn SqlField>>) {
-        if self.fields.insert(name, field).is_some() {
-            // panic!("Field is already defined");
-            return;
+        self.try_add_field(name, field)
+            .unwrap_or_else(|e| panic!("{}", e));
+    }
+    fn try_add_field(
+        &mut self,
+        name: Option<String>,
+        field: Arc<Box<dyn SqlField>>,
+    ) -> Result<(), Error> {
+        if let Some(alias) = &name {
+            if self.strict_fields && self.fields.contains_key(&name) {
+                return Err(Error::DuplicateField {
+                    table: self.source_name(),
+                    field: alias.clone(),
+                });
+            }
         }
+        self.fields.insert(name, field);
+        Ok(())
     }
     fn get_where_conditions_mut(&mut self) -> &mut QueryConditions {
         &mut self.where_conditions
@@ -397,21 +807,99 @@ impl SqlQuery for Query {
     fn add_skip(&mut self, skip: Option<i64>) {
         self.skip_items = skip;
     }
+    fn add_comment(&mut self, key: &str, value: &str) {
+        self.comment.insert(key.to_string(), value.to_string());
+    }
     fn set_field_value(&mut self, field: &str, value: Value) {
+        self.set_field_expression(field, value.render_chunk());
+    }
+    fn set_field_expression(&mut self, field: &str, expression: Expression) {
+        self.try_set_field_expression(field, expression)
+            .unwrap_or_else(|e| panic!("{}", e));
+    }
+    fn try_set_field_value(&mut self, field: &str, value: Value) -> Result<(), Error> {
+        self.try_set_field_expression(field, value.render_chunk())
+    }
+    fn try_set_field_expression(
+        &mut self,
+        field: &str,
+        expression: Expression,
+    ) -> Result<(), Error> {
         match self.query_type {
             QueryType::Insert | QueryType::Update | QueryType::Replace => {
-                self.set_fields.insert(field.to_string(), value);
-            }
-            _ => {
-                panic!("Query should be \"Insert\", \"Update\" or \"Replace\" to set field value. Type is set to {:?}", self.query_type);
+                // additional inserted rows get the same value, just like the first one
+                for row in &mut self.values_rows {
+                    row.insert(field.to_string(), expression.clone());
+                }
+                self.set_fields.insert(field.to_string(), expression);
+                Ok(())
             }
+            _ => Err(Error::RenderError(format!(
+                "Query should be \"Insert\", \"Update\" or \"Replace\" to set field value. Type is set to {:?}",
+                self.query_type
+            ))),
         }
     }
+    fn get_set_field(&self, field: &str) -> Option<&Expression> {
+        self.set_fields.get(field)
+    }
+    fn get_source(&self) -> &QuerySource {
+        &self.table
+    }
+    fn get_returning(&self) -> &QueryReturning {
+        &self.returning
+    }
+    fn get_dialect(&self) -> &Arc<dyn Dialect> {
+        &self.dialect
+    }
+    fn get_type(&self) -> &QueryType {
+        &self.query_type
+    }
+    fn get_field_names(&self) -> Vec<String> {
+        self.fields.keys().flatten().cloned().collect()
+    }
+    fn get_skip(&self) -> Option<i64> {
+        self.skip_items
+    }
+    fn get_limit(&self) -> Option<i64> {
+        self.limit_items
+    }
+    fn get_filters(&self) -> Result<Vec<Filter>, Error> {
+        self.where_conditions
+            .get_conditions()
+            .iter()
+            .map(Filter::try_from_expression)
+            .collect()
+    }
+    fn get_order(&self) -> Result<Vec<(String, Direction)>, Error> {
+        self.order_by.iter().map(order_of).collect()
+    }
+    fn get_set_values(&self) -> Result<Map<String, Value>, Error> {
+        self.set_fields
+            .iter()
+            .map(|(field, expression)| {
+                let value = value_of(expression).ok_or_else(|| {
+                    Error::RenderError(format!(
+                        "Field {} is set to expression {}, not a value",
+                        field,
+                        expression.preview()
+                    ))
+                })?;
+                Ok((field.clone(), value))
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{expr, sql::Operations};
+    use crate::{
+        expr,
+        sql::{
+            dialect::{MssqlDialect, MySqlDialect},
+            Operations,
+        },
+    };
     use serde_json::json;
 
     use super::*;
@@ -450,6 +938,92 @@ mod tests {
         assert_eq!(params.len(), 0);
     }
 
+    #[test]
+    fn test_strict_fields() {
+        let query = Query::new()
+            .with_table("users", None)
+            .with_column_field("name")
+            .with_field("name".to_string(), expr_arc!("upper(name)"));
+        assert_eq!(query.preview(), "SELECT (upper(name)) AS name FROM users");
+
+        let mut query = Query::new()
+            .with_table("users", None)
+            .with_strict_fields()
+            .with_column_field("name");
+        let err = query
+            .try_add_field(
+                Some("name".to_string()),
+                Arc::new(Box::new(expr_arc!("upper(name)"))),
+            )
+            .unwrap_err();
+        assert!(matches!(&err, Error::DuplicateField { field, .. } if field == "name"));
+        assert_eq!(
+            err.to_string(),
+            "Query for 'users' already has a field 'name'"
+        );
+    }
+
+    #[test]
+    fn test_distinct_on() {
+        let query = Query::new()
+            .with_table("orders", None)
+            .with_column_field("client_id")
+            .with_column_field("total")
+            .with_distinct_on(vec![expr!("client_id")])
+            .with_order_by(expr!("created_at DESC"))
+            .with_order_by(expr!("client_id"));
+
+        assert_eq!(
+            query.preview(),
+            "SELECT DISTINCT ON (client_id) client_id, total FROM orders \
+            ORDER BY client_id, created_at DESC"
+        );
+    }
+
+    #[test]
+    fn test_comment() {
+        let query = Query::new()
+            .with_table("orders", None)
+            .with_condition(expr!("id = {}", 1))
+            .with_comment("route:/orders/{id} api:list_orders")
+            .with_comment("req:1234 */DROP");
+
+        assert_eq!(
+            query.render_chunk().sql(),
+            "/*%2A%2FDROP='',api='list_orders',req='1234',route='%2Forders%2F%7Bid%7D'*/ \
+            SELECT * FROM orders WHERE id = {}"
+        );
+        assert_eq!(query.render_chunk().params(), &vec![json!(1)]);
+    }
+
+    #[test]
+    fn test_lock() {
+        let query = Query::new()
+            .with_table("orders", None)
+            .with_condition(expr!("status = {}", "pending"))
+            .with_limit(1)
+            .with_lock(LockMode::ForUpdate {
+                skip_locked: true,
+                nowait: false,
+            });
+
+        assert_eq!(
+            query.render_chunk().sql(),
+            "SELECT * FROM orders WHERE status = {} LIMIT {}::int4 FOR UPDATE SKIP LOCKED"
+        );
+
+        let query = Query::new()
+            .with_table("orders", None)
+            .with_lock(LockMode::ForShare {
+                skip_locked: false,
+                nowait: true,
+            });
+        assert_eq!(
+            query.render_chunk().sql(),
+            "SELECT * FROM orders FOR SHARE NOWAIT"
+        );
+    }
+
     #[test]
     fn test_insert() {
         let (sql, params) = Query::new()
@@ -463,7 +1037,7 @@ mod tests {
 
         assert_eq!(
             sql,
-            "INSERT INTO users (name, surname, age) VALUES ({}, {}, {}) returning id"
+            "INSERT INTO users (name, surname, age) VALUES ({}, {}, {})"
         );
         assert_eq!(params.len(), 3);
         assert_eq!(params[0], json!("John"));
@@ -471,6 +1045,60 @@ mod tests {
         assert_eq!(params[2], json!(30));
     }
 
+    #[test]
+    fn test_insert_rows() {
+        let query = Query::new()
+            .with_table("users", None)
+            .with_type(QueryType::Insert)
+            .with_set_field("name", "John".into())
+            .with_set_field("age", 30.into())
+            .with_values_row(IndexMap::from([
+                ("age".to_string(), expr!("{}", 25)),
+                ("name".to_string(), expr!("{}", "Jane")),
+            ]));
+
+        assert_eq!(
+            query.render_chunk().split(),
+            (
+                "INSERT INTO users (name, age) VALUES ({}, {}), ({}, {})".to_string(),
+                vec![json!("John"), json!(30), json!("Jane"), json!(25)]
+            )
+        );
+
+        let query = query.with_values_row(IndexMap::from([("name".to_string(), expr!("1"))]));
+        assert!(query.render_insert().is_err());
+    }
+
+    #[test]
+    fn test_returning() {
+        let query = Query::new()
+            .with_table("users", None)
+            .with_type(QueryType::Insert)
+            .with_set_field("name", "John".into());
+
+        assert_eq!(
+            query
+                .clone()
+                .with_returning(&["id", "created_at"])
+                .preview(),
+            "INSERT INTO users (name) VALUES ('John') RETURNING id, created_at"
+        );
+        assert_eq!(
+            query.clone().with_returning_all().preview(),
+            "INSERT INTO users (name) VALUES ('John') RETURNING *"
+        );
+
+        let query = Query::new()
+            .with_table("users", None)
+            .with_type(QueryType::Delete)
+            .with_condition(expr!("id = {}", 1))
+            .with_returning(&["id"]);
+        assert_eq!(
+            query.preview(),
+            "DELETE FROM users WHERE id = 1 RETURNING id"
+        );
+    }
+
     #[test]
     fn test_update() {
         let (sql, params) = Query::new()
@@ -494,6 +1122,20 @@ mod tests {
         assert_eq!(params[3], json!(1));
     }
 
+    #[test]
+    fn test_update_with_expression() {
+        let (sql, params) = Query::new()
+            .with_table("product", None)
+            .with_type(QueryType::Update)
+            .with_set_field_expression("price", expr!("price * {}", 1.1))
+            .with_set_field("name", "Cake".into())
+            .render_chunk()
+            .split();
+
+        assert_eq!(sql, "UPDATE product SET price = price * {}, name = {}");
+        assert_eq!(params, vec![json!(1.1), json!("Cake")]);
+    }
+
     #[test]
     fn test_expression() {
         let (sql, params) = Query::new()
@@ -563,6 +1205,63 @@ mod tests {
         assert_eq!(params.len(), 0);
     }
 
+    #[test]
+    fn test_render_with_recursive() {
+        let base = Query::new()
+            .with_table("teams", None)
+            .with_column_field("id")
+            .with_condition(expr!("id = {}", 1));
+        let recursive = Query::new()
+            .with_table("teams", Some("t".to_string()))
+            .with_field("id".to_string(), expr!("t.id"))
+            .with_join(JoinQuery::new(
+                JoinType::Inner,
+                QuerySource::Table("tree".to_string(), None),
+                QueryConditions::on().with_condition(expr!("t.parent_id = tree.id")),
+            ));
+
+        let query = Query::new()
+            .with_recursive("tree", base, recursive)
+            .with_table("tree", None)
+            .with_column_field("id");
+
+        assert_eq!(
+            query.preview(),
+            "WITH RECURSIVE tree AS (SELECT id FROM teams WHERE id = 1 UNION ALL \
+            SELECT (t.id) AS id FROM teams AS t JOIN tree ON t.parent_id = tree.id) \
+            SELECT id FROM tree"
+        );
+    }
+
+    #[test]
+    fn test_union() {
+        let current = Query::new()
+            .with_table("orders", None)
+            .with_column_field("id")
+            .with_condition(expr!("total > {}", 100));
+        let archived = Query::new()
+            .with_table("orders_archive", None)
+            .with_column_field("id");
+
+        let query = current
+            .clone()
+            .union_all(archived.clone())
+            .with_order_by(expr!("id"))
+            .with_limit(10);
+        assert_eq!(
+            query.preview(),
+            "SELECT * FROM ((SELECT id FROM orders WHERE total > 100) UNION ALL \
+            (SELECT id FROM orders_archive)) AS u ORDER BY id LIMIT 10::int4"
+        );
+
+        let (sql, _) = current.except(archived).render_chunk().split();
+        assert_eq!(
+            sql,
+            "SELECT * FROM ((SELECT id FROM orders WHERE total > {}) EXCEPT \
+            (SELECT id FROM orders_archive)) AS u"
+        );
+    }
+
     #[test]
     fn test_group_and_order() {
         let query = Query::new()
@@ -656,4 +1355,169 @@ mod tests {
             "SELECT id, name, age FROM users OFFSET 10::int4 LIMIT 20::int4"
         );
     }
+
+    #[test]
+    fn test_dialect() {
+        let query = Query::new()
+            .with_dialect(Arc::new(MySqlDialect))
+            .with_table("users", None)
+            .with_column_field("name")
+            .with_condition(expr!("age > {}", 18))
+            .with_skip_and_limit(10, 20);
+
+        let rendered = query.render_chunk();
+        assert_eq!(
+            rendered.sql_final_for(query.get_dialect().as_ref()),
+            "SELECT name FROM users WHERE age > ? LIMIT ? OFFSET ?"
+        );
+
+        let insert = Query::new()
+            .with_dialect(Arc::new(MySqlDialect))
+            .with_table("product", None)
+            .with_type(QueryType::Insert)
+            .with_set_field("sku", "PIE-1".into())
+            .with_set_field("name", "Pie".into())
+            .with_upsert(&["sku"]);
+        assert_eq!(
+            insert.preview(),
+            "INSERT INTO product (sku, name) VALUES ('PIE-1', 'Pie') ON DUPLICATE KEY UPDATE name = VALUES(name)"
+        );
+
+        // MySQL can't return inserted rows
+        assert!(matches!(
+            insert.with_returning(&["id"]).try_render_chunk(),
+            Err(Error::RenderError(_))
+        ));
+
+        // only MySQL limits deleted rows
+        let delete = Query::new()
+            .with_table("product", None)
+            .with_type(QueryType::Delete)
+            .with_condition(expr!("stock = {}", 0))
+            .with_limit(100);
+        assert_eq!(delete.preview(), "DELETE FROM product WHERE stock = 0");
+        assert_eq!(
+            delete.with_dialect(Arc::new(MySqlDialect)).preview(),
+            "DELETE FROM product WHERE stock = 0 LIMIT 100"
+        );
+    }
+
+    #[test]
+    fn test_upsert() {
+        let query = Query::new()
+            .with_table("product", None)
+            .with_type(QueryType::Insert)
+            .with_set_field("sku", "PIE-1".into())
+            .with_set_field("name", "Pie".into())
+            .with_upsert(&["sku"])
+            .with_returning(&["id"]);
+        assert_eq!(
+            query.render_chunk().sql(),
+            "INSERT INTO product (sku, name) VALUES ({}, {}) ON CONFLICT (sku) DO UPDATE SET name = EXCLUDED.name RETURNING id"
+        );
+    }
+
+    #[test]
+    fn test_mssql_dialect() {
+        let query = Query::new()
+            .with_dialect(Arc::new(MssqlDialect))
+            .with_table("order", None)
+            .with_column_field("id")
+            .with_limit(10);
+        assert_eq!(query.preview(), "SELECT TOP (10) id FROM [order]");
+
+        let query = query.with_skip(20);
+        assert_eq!(
+            query.preview(),
+            "SELECT id FROM [order] ORDER BY (SELECT NULL) OFFSET 20 ROWS FETCH NEXT 10 ROWS ONLY"
+        );
+
+        let insert = Query::new()
+            .with_dialect(Arc::new(MssqlDialect))
+            .with_table("product", None)
+            .with_type(QueryType::Insert)
+            .with_set_field("name", "Pie".into())
+            .with_returning(&["id"]);
+        let rendered = insert.render_chunk();
+        assert_eq!(
+            rendered.sql_final_for(&MssqlDialect),
+            "INSERT INTO product (name) OUTPUT INSERTED.id VALUES (@P1)"
+        );
+
+        let delete = Query::new()
+            .with_dialect(Arc::new(MssqlDialect))
+            .with_table("product", None)
+            .with_type(QueryType::Delete)
+            .with_condition(expr!("id = {}", 1))
+            .with_returning_all();
+        assert_eq!(
+            delete.render_chunk().sql(),
+            "DELETE FROM product OUTPUT DELETED.* WHERE id = {}"
+        );
+    }
+
+    #[test]
+    fn test_count_and_exists() {
+        let query = Query::new()
+            .with_table("ord", None)
+            .with_column_field("id")
+            .with_condition(expr!("client_id = {}", 1));
+        assert_eq!(
+            query.get_count_query().preview(),
+            "SELECT (COUNT(*)) AS count FROM (SELECT id FROM ord WHERE client_id = 1) AS c"
+        );
+        assert_eq!(
+            query.get_exists_query().preview(),
+            "SELECT EXISTS (SELECT id FROM ord WHERE client_id = 1)"
+        );
+
+        let query = query.with_dialect(Arc::new(MssqlDialect));
+        assert_eq!(
+            query.get_exists_query().preview(),
+            "SELECT CASE WHEN EXISTS (SELECT id FROM ord WHERE client_id = 1) THEN 1 ELSE 0 END"
+        );
+    }
+
+    #[test]
+    fn test_quoted_identifiers() {
+        let query = Query::new()
+            .with_table("order", Some("o".to_string()))
+            .with_column_field("from")
+            .with_field("group".to_string(), expr!("1"));
+        assert_eq!(
+            query.preview(),
+            "SELECT \"from\", (1) AS \"group\" FROM \"order\" AS o"
+        );
+
+        let query = Query::new()
+            .with_dialect(Arc::new(MySqlDialect))
+            .with_table("order", None)
+            .with_type(QueryType::Update)
+            .with_set_field("desc", "x".into());
+        assert_eq!(query.render_chunk().sql(), "UPDATE `order` SET `desc` = {}");
+    }
+
+    #[test]
+    fn test_try_render() {
+        let insert = Query::new()
+            .with_type(QueryType::Insert)
+            .with_set_field("name", "John".into());
+        assert!(matches!(
+            insert.try_render_chunk(),
+            Err(Error::RenderError(_))
+        ));
+
+        // errors of nested queries are propagated too
+        let query = Query::new()
+            .with_source(QuerySource::Query(Arc::new(Box::new(insert)), None))
+            .with_column_field("name")
+            .with_condition(expr!("age > {}", 18));
+        assert!(query.try_render_chunk().is_err());
+
+        let query = Query::new().with_table("users", None);
+        assert_eq!(
+            query.try_render_chunk().unwrap().sql(),
+            "SELECT * FROM users"
+        );
+    }
 }
@@ -0,0 +1,386 @@
+//! JSON representation of [`Query`]. Clauses are stored as [`Expression`]s,
+//! fields are stored rendered together with their alias.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::sql::chunk::Chunk;
+use crate::sql::dialect::{
+    ClickHouseDialect, Dialect, DuckDbDialect, MssqlDialect, MySqlDialect, PostgresDialect,
+    SqliteDialect,
+};
+use crate::sql::Expression;
+use crate::traits::column::SqlField;
+
+use super::{JoinQuery, JoinType, LockMode, Query, QueryConditions, QueryReturning, QuerySource};
+use super::{QueryType, SqlQuery};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+struct QueryAst {
+    #[serde(rename = "type")]
+    query_type: QueryType,
+    dialect: String,
+    source: SourceAst,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    with: IndexMap<String, SourceAst>,
+    #[serde(skip_serializing_if = "is_false")]
+    recursive: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    distinct: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    distinct_on: Vec<Expression>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<FieldAst>,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    set: IndexMap<String, Expression>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    values_rows: Vec<IndexMap<String, Expression>>,
+    returning: QueryReturning,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    on_conflict: Option<Vec<String>>,
+    #[serde(rename = "where", skip_serializing_if = "Vec::is_empty")]
+    where_conditions: Vec<Expression>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    having: Vec<Expression>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    joins: Vec<JoinAst>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skip: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<i64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    group_by: Vec<Expression>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    order_by: Vec<Expression>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lock: Option<LockMode>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    comment: BTreeMap<String, String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl Default for QueryAst {
+    fn default() -> Self {
+        QueryAst::from(&Query::new())
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SourceAst {
+    #[default]
+    None,
+    Table {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        alias: Option<String>,
+    },
+    Query {
+        query: Box<QueryAst>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        alias: Option<String>,
+    },
+    Expression {
+        expression: Expression,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        alias: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FieldAst {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alias: Option<String>,
+    /// Field as it appears in SELECT, including the alias
+    expression: Expression,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JoinAst {
+    join_type: JoinType,
+    source: SourceAst,
+    on: Vec<Expression>,
+}
+
+/// Field of a deserialized query, rendered as it was when serialized.
+#[derive(Debug)]
+struct RenderedField(Expression);
+
+impl Chunk for RenderedField {
+    fn render_chunk(&self) -> Expression {
+        self.0.clone()
+    }
+}
+
+impl SqlField for RenderedField {
+    fn render_column(&self, _alias: Option<&str>) -> Expression {
+        self.0.clone()
+    }
+    fn calculated(&self) -> bool {
+        false
+    }
+}
+
+fn dialect_name(dialect: &dyn Dialect) -> String {
+    let name = format!("{:?}", dialect);
+    name.strip_suffix("Dialect").unwrap_or(&name).to_lowercase()
+}
+
+fn dialect_from_name(name: &str) -> Result<Arc<dyn Dialect>> {
+    Ok(match name {
+        "postgres" => Arc::new(PostgresDialect),
+        "sqlite" => Arc::new(SqliteDialect),
+        "mysql" => Arc::new(MySqlDialect),
+        "mssql" => Arc::new(MssqlDialect),
+        "duckdb" => Arc::new(DuckDbDialect),
+        "clickhouse" => Arc::new(ClickHouseDialect),
+        name => return Err(anyhow!("Unknown dialect {}", name)),
+    })
+}
+
+impl From<&QuerySource> for SourceAst {
+    fn from(source: &QuerySource) -> Self {
+        match source.clone() {
+            QuerySource::None => SourceAst::None,
+            QuerySource::Table(name, alias) => SourceAst::Table { name, alias },
+            QuerySource::Query(query, alias) => SourceAst::Query {
+                query: Box::new(QueryAst::from(query.as_ref().as_ref())),
+                alias,
+            },
+            QuerySource::Expression(expression, alias) => {
+                SourceAst::Expression { expression, alias }
+            }
+        }
+    }
+}
+
+impl TryFrom<SourceAst> for QuerySource {
+    type Error = anyhow::Error;
+
+    fn try_from(source: SourceAst) -> Result<Self> {
+        Ok(match source {
+            SourceAst::None => QuerySource::None,
+            SourceAst::Table { name, alias } => QuerySource::Table(name, alias),
+            SourceAst::Query { query, alias } => {
+                QuerySource::Query(Arc::new(Box::new(Query::try_from(*query)?)), alias)
+            }
+            SourceAst::Expression { expression, alias } => {
+                QuerySource::Expression(expression, alias)
+            }
+        })
+    }
+}
+
+impl From<&Query> for QueryAst {
+    fn from(query: &Query) -> Self {
+        QueryAst {
+            query_type: query.query_type.clone(),
+            dialect: dialect_name(query.dialect.as_ref()),
+            source: SourceAst::from(&query.table),
+            with: query
+                .with
+                .iter()
+                .map(|(alias, source)| (alias.clone(), SourceAst::from(source)))
+                .collect(),
+            recursive: query.recursive,
+            distinct: query.distinct,
+            distinct_on: query.distinct_on.clone(),
+            fields: query
+                .fields
+                .iter()
+                .map(|(alias, field)| FieldAst {
+                    alias: alias.clone(),
+                    expression: query.render_field(alias, field),
+                })
+                .collect(),
+            set: query.set_fields.clone(),
+            values_rows: query.values_rows.clone(),
+            returning: query.returning.clone(),
+            on_conflict: query.on_conflict.clone(),
+            where_conditions: query.where_conditions.get_conditions().clone(),
+            having: query.having_conditions.get_conditions().clone(),
+            joins: query
+                .joins
+                .iter()
+                .map(|join| JoinAst {
+                    join_type: join.join_type.clone(),
+                    source: SourceAst::from(&join.source),
+                    on: join.on_conditions.get_conditions().clone(),
+                })
+                .collect(),
+            skip: query.skip_items,
+            limit: query.limit_items,
+            group_by: query.group_by.clone(),
+            order_by: query.order_by.clone(),
+            lock: query.lock,
+            comment: query.comment.clone(),
+        }
+    }
+}
+
+impl TryFrom<QueryAst> for Query {
+    type Error = anyhow::Error;
+
+    fn try_from(ast: QueryAst) -> Result<Self> {
+        let mut query = Query::new()
+            .with_dialect(dialect_from_name(&ast.dialect)?)
+            .with_source(QuerySource::try_from(ast.source)?)
+            .with_type(ast.query_type);
+        for (alias, source) in ast.with {
+            query.add_with(alias, QuerySource::try_from(source)?);
+        }
+        query.recursive = ast.recursive;
+        query.distinct = ast.distinct;
+        query.distinct_on = ast.distinct_on;
+        for field in ast.fields {
+            query.add_field(
+                field.alias,
+                Arc::new(Box::new(RenderedField(field.expression))),
+            );
+        }
+        query.set_fields = ast.set;
+        query.values_rows = ast.values_rows;
+        query.returning = ast.returning;
+        query.on_conflict = ast.on_conflict;
+        for condition in ast.where_conditions {
+            query.where_conditions.add_condition(condition);
+        }
+        for condition in ast.having {
+            query.having_conditions.add_condition(condition);
+        }
+        for join in ast.joins {
+            let mut on_conditions = QueryConditions::on();
+            for condition in join.on {
+                on_conditions.add_condition(condition);
+            }
+            query.add_join(JoinQuery::new(
+                join.join_type,
+                QuerySource::try_from(join.source)?,
+                on_conditions,
+            ));
+        }
+        query.skip_items = ast.skip;
+        query.limit_items = ast.limit;
+        query.group_by = ast.group_by;
+        query.order_by = ast.order_by;
+        query.lock = ast.lock;
+        query.comment = ast.comment;
+        Ok(query)
+    }
+}
+
+impl Query {
+    /// Restores a query from its JSON representation, for queries (such as report
+    /// definitions) which were stored and are executed later:
+    ///
+    /// ```
+    /// let json = serde_json::to_value(&orders.get_select_query())?;
+    /// // {"type": "select", "dialect": "postgres", "source": {"table": {"name": "orders"}}, ...}
+    ///
+    /// let query = Query::from_ast(json)?;
+    /// let rows = postgres.query_raw(&query).await?;
+    /// ```
+    ///
+    /// Missing keys take their defaults, so `{"source": {"table": {"name": "orders"}}}`
+    /// is a valid `SELECT * FROM orders`. Fields are restored in their rendered form,
+    /// so they can't be referenced as columns.
+    pub fn from_ast(json: Value) -> Result<Query> {
+        let ast: QueryAst = serde_json::from_value(json)?;
+        Query::try_from(ast)
+    }
+
+    /// JSON representation of the query, same as `serde_json::to_value(&query)`.
+    pub fn to_ast(&self) -> Result<Value> {
+        Ok(serde_json::to_value(QueryAst::from(self))?)
+    }
+}
+
+impl Serialize for Query {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        QueryAst::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Query {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let ast = QueryAst::deserialize(deserializer)?;
+        Query::try_from(ast).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::expr;
+    use crate::sql::query::JoinType;
+    use crate::sql::Column;
+    use crate::sql::Operations;
+
+    #[test]
+    fn test_round_trip() {
+        let total = expr!("SUM(price * {})", 2);
+        let query = Query::new()
+            .with_dialect(Arc::new(MySqlDialect))
+            .with_table("orders", Some("o".to_string()))
+            .with_field(
+                "client".to_string(),
+                Arc::new(Column::new("name".to_string(), Some("c".to_string()))),
+            )
+            .with_field("total".to_string(), total)
+            .with_join(JoinQuery::new(
+                JoinType::Left,
+                QuerySource::Table("client".to_string(), Some("c".to_string())),
+                QueryConditions::on().with_condition(expr!("c.id = o.client_id")),
+            ))
+            .with_condition(
+                Column::new("status".to_string(), Some("o".to_string())).eq(&"paid".to_string()),
+            )
+            .with_group_by(expr!("c.name"))
+            .with_order_by(expr!("total DESC"))
+            .with_limit(10)
+            .with_comment("report:top_clients");
+
+        let json = serde_json::to_value(&query).unwrap();
+        assert_eq!(json["dialect"], json!("mysql"));
+        assert_eq!(
+            json["source"],
+            json!({"table": {"name": "orders", "alias": "o"}})
+        );
+        assert_eq!(
+            json["fields"][1],
+            json!({"alias": "total", "expression": {"sql": "(SUM(price * {})) AS total", "params": [2]}})
+        );
+
+        let restored = Query::from_ast(json.clone()).unwrap();
+        assert_eq!(restored.preview(), query.preview());
+        assert_eq!(restored.get_field_names(), vec!["client", "total"]);
+        assert_eq!(restored.to_ast().unwrap(), json);
+    }
+
+    #[test]
+    fn test_defaults() {
+        let query = Query::from_ast(json!({"source": {"table": {"name": "orders"}}})).unwrap();
+        assert_eq!(query.preview(), "SELECT * FROM orders");
+
+        let query = Query::from_ast(json!({
+            "type": "delete",
+            "source": {"table": {"name": "orders"}},
+            "where": [{"sql": "id = {}", "params": [1]}]
+        }))
+        .unwrap();
+        assert_eq!(query.preview(), "DELETE FROM orders WHERE id = 1");
+
+        assert!(Query::from_ast(json!({"dialect": "oracle"})).is_err());
+    }
+}
@@ -0,0 +1,315 @@
+use std::cmp::Ordering;
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::sql::query::Direction;
+use crate::sql::Expression;
+use crate::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterOperation {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    In,
+    NotIn,
+    Like,
+}
+
+impl FilterOperation {
+    // Longer operators go first, so that "NOT IN" is not recognized as "IN"
+    const OPERATORS: [(&'static str, FilterOperation); 13] = [
+        ("NOT IN", FilterOperation::NotIn),
+        ("IS NOT", FilterOperation::Ne),
+        ("LIKE", FilterOperation::Like),
+        ("IN", FilterOperation::In),
+        ("IS", FilterOperation::Eq),
+        (">=", FilterOperation::Gte),
+        ("<=", FilterOperation::Lte),
+        ("!=", FilterOperation::Ne),
+        ("<>", FilterOperation::Ne),
+        ("= ANY", FilterOperation::In),
+        ("=", FilterOperation::Eq),
+        (">", FilterOperation::Gt),
+        ("<", FilterOperation::Lt),
+    ];
+}
+
+/// Condition in form `field <operation> value`, recognized in a rendered query
+/// condition. Data sources, which can't execute SQL, use filters to send
+/// conditions to a remote API or to apply them in-process:
+///
+/// ```
+/// let filter = Filter::try_from_expression(&users.id().gt(5).render_chunk())?;
+/// // Filter { field: "id", operation: Gt, value: 5 }
+/// ```
+///
+/// For `IN` and `NOT IN` value is an array.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub field: String,
+    pub operation: FilterOperation,
+    pub value: Value,
+}
+
+impl Filter {
+    pub fn new(field: &str, operation: FilterOperation, value: Value) -> Filter {
+        Filter {
+            field: field.to_string(),
+            operation,
+            value,
+        }
+    }
+
+    /// Recognizes conditions like `(name = {})`, `(u.age > {})` or
+    /// `(id IN ({}, {}))`. Table prefix and quotes are removed from the field
+    /// name. Anything else, for example comparing two columns, results in error.
+    pub fn try_from_expression(condition: &Expression) -> Result<Filter, Error> {
+        let unsupported = || {
+            Error::RenderError(format!(
+                "Condition {} can't be used as a filter",
+                condition.preview()
+            ))
+        };
+
+        let mut sql = condition.sql().trim();
+        if sql.starts_with('(') && sql.ends_with(')') {
+            sql = &sql[1..sql.len() - 1];
+        }
+        let (field, rest) = sql.split_once(' ').ok_or_else(unsupported)?;
+        let field = field_name(field).ok_or_else(unsupported)?;
+
+        let rest = rest.trim_start();
+        let (operation, value) = FilterOperation::OPERATORS
+            .iter()
+            .find_map(|(operator, operation)| {
+                let value = rest.strip_prefix(operator)?;
+                (value.is_empty()
+                    || value.starts_with(' ')
+                    || !operator.starts_with(char::is_alphabetic))
+                .then(|| (*operation, value.trim()))
+            })
+            .ok_or_else(unsupported)?;
+
+        let params = condition.params();
+        let value = match operation {
+            // `= ANY ({})` with an array parameter
+            FilterOperation::In if matches!(params.as_slice(), [Value::Array(_)]) => {
+                if value != "({})" {
+                    return Err(unsupported());
+                }
+                params[0].clone()
+            }
+            FilterOperation::In | FilterOperation::NotIn if value == "(NULL)" => {
+                Value::Array(vec![])
+            }
+            FilterOperation::In | FilterOperation::NotIn => {
+                let placeholders = vec!["{}"; params.len()].join(", ");
+                if value != format!("({})", placeholders) {
+                    return Err(unsupported());
+                }
+                Value::Array(params.clone())
+            }
+            _ => value_of(&Expression::new(value.to_string(), params.clone()))
+                .ok_or_else(unsupported)?,
+        };
+
+        Ok(Filter::new(field, operation, value))
+    }
+
+    /// Checks the filter against a row, for data sources which filter rows
+    /// in-process. Like in SQL, comparing with NULL never matches, except for
+    /// `IS NULL` and `IS NOT NULL`. Missing fields are treated as NULL.
+    pub fn matches(&self, row: &Map<String, Value>) -> bool {
+        let field = row.get(&self.field).unwrap_or(&Value::Null);
+        match (self.operation, &self.value) {
+            (FilterOperation::Eq, Value::Null) => field.is_null(),
+            (FilterOperation::Ne, Value::Null) => !field.is_null(),
+            (FilterOperation::In, Value::Array(values)) => values
+                .iter()
+                .any(|v| compare(field, v) == Some(Ordering::Equal)),
+            (FilterOperation::NotIn, Value::Array(values)) => {
+                !field.is_null()
+                    && values
+                        .iter()
+                        .all(|v| compare(field, v) != Some(Ordering::Equal))
+            }
+            (FilterOperation::Like, Value::String(pattern)) => match field {
+                Value::String(field) => Regex::new(&like_to_regex(pattern))
+                    .map(|regex| regex.is_match(field))
+                    .unwrap_or(false),
+                _ => false,
+            },
+            (operation, value) => match compare(field, value) {
+                Some(ordering) => match operation {
+                    FilterOperation::Eq => ordering == Ordering::Equal,
+                    FilterOperation::Ne => ordering != Ordering::Equal,
+                    FilterOperation::Gt => ordering == Ordering::Greater,
+                    FilterOperation::Gte => ordering != Ordering::Less,
+                    FilterOperation::Lt => ordering == Ordering::Less,
+                    FilterOperation::Lte => ordering != Ordering::Greater,
+                    _ => false,
+                },
+                None => false,
+            },
+        }
+    }
+}
+
+/// Compares values of the same kind. Numbers are compared as numbers, so `5` equals
+/// `5.0`. Returns None for NULL and for values which can't be compared.
+pub fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        },
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// Converts LIKE pattern into an anchored regular expression: `%` matches any
+/// number of characters and `_` matches a single one.
+pub fn like_to_regex(pattern: &str) -> String {
+    let regex = pattern
+        .split('%')
+        .map(|part| {
+            part.split('_')
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join(".")
+        })
+        .collect::<Vec<_>>()
+        .join(".*");
+    format!("^{}$", regex)
+}
+
+/// Recognizes ORDER BY expressions like `name` or `u.name DESC`, as created
+/// by [`Direction::order()`].
+pub fn order_of(expression: &Expression) -> Result<(String, Direction), Error> {
+    let unsupported = || {
+        Error::RenderError(format!(
+            "Order {} can't be applied to a field",
+            expression.preview()
+        ))
+    };
+    let (field, direction) = match expression.sql().trim().rsplit_once(' ') {
+        Some((field, direction)) if direction.eq_ignore_ascii_case("DESC") => {
+            (field, Direction::Desc)
+        }
+        Some((field, direction)) if direction.eq_ignore_ascii_case("ASC") => {
+            (field, Direction::Asc)
+        }
+        _ => (expression.sql().trim(), Direction::Asc),
+    };
+    let field = field_name(field.trim()).ok_or_else(unsupported)?;
+    Ok((field.to_string(), direction))
+}
+
+/// Removes table prefix and quotes from a field name, rejecting anything else
+fn field_name(field: &str) -> Option<&str> {
+    let field = field.rsplit('.').next().unwrap_or(field);
+    let field = field.trim_matches(|c| matches!(c, '"' | '`' | '[' | ']'));
+    (!field.is_empty() && field.chars().all(|c| c.is_alphanumeric() || c == '_')).then_some(field)
+}
+
+/// Returns value of an expression, which holds a single parameter, possibly
+/// with a type cast, like `{}` or `{}::int4`. Also recognizes `NULL`.
+pub fn value_of(expression: &Expression) -> Option<Value> {
+    match (expression.sql().as_str(), expression.params().as_slice()) {
+        ("{}", [param]) => Some(param.clone()),
+        ("NULL", []) => Some(Value::Null),
+        (sql, [param]) => {
+            let cast = sql.strip_prefix("{}::")?;
+            (!cast.contains("{}")).then(|| param.clone())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::expr;
+    use crate::prelude::{Chunk, Column, Operations};
+
+    #[test]
+    fn test_filter() {
+        let id = Arc::new(Column::new("id".to_string(), Some("u".to_string())));
+        let name = Arc::new(Column::new("user".to_string(), None));
+
+        assert_eq!(
+            Filter::try_from_expression(&id.gt(5).render_chunk()).unwrap(),
+            Filter::new("id", FilterOperation::Gt, json!(5))
+        );
+        assert_eq!(
+            Filter::try_from_expression(&name.eq(&"John".to_string()).render_chunk()).unwrap(),
+            Filter::new("user", FilterOperation::Eq, json!("John"))
+        );
+        assert_eq!(
+            Filter::try_from_expression(&id.in_expr(&expr!("{}, {}", 1, 2)).render_chunk())
+                .unwrap(),
+            Filter::new("id", FilterOperation::In, json!([1, 2]))
+        );
+        assert_eq!(
+            Filter::try_from_expression(&expr!("age >= {}::int4", 18)).unwrap(),
+            Filter::new("age", FilterOperation::Gte, json!(18))
+        );
+        assert_eq!(
+            Filter::try_from_expression(&expr!("(deleted_at IS NULL)")).unwrap(),
+            Filter::new("deleted_at", FilterOperation::Eq, Value::Null)
+        );
+        assert_eq!(
+            Filter::try_from_expression(&expr!("name NOT IN ({})", "x")).unwrap(),
+            Filter::new("name", FilterOperation::NotIn, json!(["x"]))
+        );
+        assert_eq!(
+            Filter::try_from_expression(&id.in_values(vec![json!(1), json!(2)]).render_chunk())
+                .unwrap(),
+            Filter::new("id", FilterOperation::In, json!([1, 2]))
+        );
+        assert!(Filter::try_from_expression(&expr!("(a = b)")).is_err());
+        assert!(Filter::try_from_expression(&expr!("(a = {} + {})", 1, 2)).is_err());
+        assert!(Filter::try_from_expression(&expr!("EXISTS (SELECT 1)")).is_err());
+    }
+
+    #[test]
+    fn test_order() {
+        let name = Arc::new(Column::new("name".to_string(), Some("u".to_string())));
+
+        assert_eq!(
+            order_of(&Direction::Desc.order(&name)).unwrap(),
+            ("name".to_string(), Direction::Desc)
+        );
+        assert_eq!(
+            order_of(&expr!("\"user\"")).unwrap(),
+            ("user".to_string(), Direction::Asc)
+        );
+        assert!(order_of(&expr!("price * 2")).is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let row = json!({"name": "Apple Pie", "price": 12, "deleted_at": null});
+        let row = row.as_object().unwrap();
+
+        assert!(Filter::new("price", FilterOperation::Gt, json!(10.5)).matches(row));
+        assert!(Filter::new("price", FilterOperation::In, json!([11, 12])).matches(row));
+        assert!(Filter::new("name", FilterOperation::Like, json!("Apple%")).matches(row));
+        assert!(Filter::new("deleted_at", FilterOperation::Eq, Value::Null).matches(row));
+        assert!(!Filter::new("deleted_at", FilterOperation::Ne, json!(1)).matches(row));
+        assert!(!Filter::new("missing", FilterOperation::NotIn, json!([1])).matches(row));
+        assert!(!Filter::new("name", FilterOperation::Eq, json!(12)).matches(row));
+        assert_eq!(like_to_regex("a.b_%"), "^a\\.b..*$");
+    }
+}
@@ -1,10 +1,20 @@
 use std::sync::Arc;
 
-use crate::{expr, expr_arc, prelude::Expression, prelude::ExpressionArc, sql::chunk::Chunk};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    expr, expr_arc,
+    prelude::Expression,
+    prelude::ExpressionArc,
+    sql::chunk::Chunk,
+    sql::dialect::{Dialect, PostgresDialect},
+    Error,
+};
 
 use super::Query;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum QueryType {
     Select,
     Insert,
@@ -14,6 +24,53 @@ pub enum QueryType {
     Expression(Expression),
 }
 
+/// Controls what an INSERT, UPDATE or DELETE query returns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryReturning {
+    None,
+    All,
+    Fields(Vec<String>),
+}
+impl QueryReturning {
+    pub fn render_for(&self, dialect: &dyn Dialect) -> Expression {
+        match self {
+            QueryReturning::None => Expression::empty(),
+            QueryReturning::All => expr!(" RETURNING *"),
+            QueryReturning::Fields(fields) => expr!(format!(
+                " RETURNING {}",
+                fields
+                    .iter()
+                    .map(|f| dialect.quote_identifier(f))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+
+    /// Renders SQL Server `OUTPUT INSERTED.id` clause. `pseudo_table` is either
+    /// `INSERTED` or `DELETED`.
+    pub fn render_output(&self, dialect: &dyn Dialect, pseudo_table: &str) -> String {
+        match self {
+            QueryReturning::None => String::new(),
+            QueryReturning::All => format!(" OUTPUT {}.*", pseudo_table),
+            QueryReturning::Fields(fields) => format!(
+                " OUTPUT {}",
+                fields
+                    .iter()
+                    .map(|f| format!("{}.{}", pseudo_table, dialect.quote_identifier(f)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+impl Chunk for QueryReturning {
+    fn render_chunk(&self) -> Expression {
+        self.render_for(&PostgresDialect)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum QuerySource {
     None,
@@ -23,35 +80,110 @@ pub enum QuerySource {
 }
 impl QuerySource {
     pub fn render_prefix(&self, prefix: &str) -> Expression {
-        match self {
+        self.try_render_prefix(prefix)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_render_prefix(&self, prefix: &str) -> Result<Expression, Error> {
+        self.try_render_prefix_for(prefix, &PostgresDialect)
+    }
+
+    /// Renders the source, quoting table name and alias for the dialect
+    pub fn try_render_prefix_for(
+        &self,
+        prefix: &str,
+        dialect: &dyn Dialect,
+    ) -> Result<Expression, Error> {
+        Ok(match self {
             QuerySource::None => Expression::empty(),
             QuerySource::Query(query, None) => {
-                expr_arc!(format!("{}({{}})", prefix), query.render_chunk()).render_chunk()
+                expr_arc!(format!("{}({{}})", prefix), query.try_render_chunk()?).render_chunk()
             }
             QuerySource::Query(query, Some(alias)) => expr_arc!(
-                format!("{}({{}}) AS {}", prefix, alias),
-                query.render_chunk()
+                format!("{}({{}}) AS {}", prefix, dialect.quote_identifier(alias)),
+                query.try_render_chunk()?
             )
             .render_chunk(),
-            QuerySource::Table(table, None) => expr!(format!("{}{}", prefix, table)),
-            QuerySource::Table(table, Some(alias)) => {
-                expr!(format!("{}{} AS {}", prefix, table, alias))
+            QuerySource::Table(table, None) => {
+                expr!(format!("{}{}", prefix, dialect.quote_identifier(table)))
             }
+            QuerySource::Table(table, Some(alias)) => expr!(format!(
+                "{}{} AS {}",
+                prefix,
+                dialect.quote_identifier(table),
+                dialect.quote_identifier(alias)
+            )),
             QuerySource::Expression(expression, None) => {
                 expr_arc!(format!("{}{{}}", prefix), expression.render_chunk()).render_chunk()
             }
             QuerySource::Expression(expression, Some(alias)) => expr_arc!(
-                format!("{}{{}} AS {}", prefix, alias),
+                format!("{}{{}} AS {}", prefix, dialect.quote_identifier(alias)),
                 expression.render_chunk()
             )
             .render_chunk(),
-        }
+        })
     }
 }
 impl Chunk for QuerySource {
     fn render_chunk(&self) -> Expression {
         self.render_prefix("FROM ")
     }
+
+    fn try_render_chunk(&self) -> Result<Expression, Error> {
+        self.try_render_prefix("FROM ")
+    }
+}
+
+/// Sort direction used with `with_order_by()` of [`Table`] and [`AssociatedQuery`]
+///
+/// [`Table`]: crate::sql::Table
+/// [`AssociatedQuery`]: crate::prelude::AssociatedQuery
+#[derive(Debug, Clone, PartialEq)]
+pub enum Direction {
+    Asc,
+    Desc,
+}
+impl Direction {
+    /// Render expression to be used in ORDER BY clause, e.g. `name DESC`
+    pub fn order(&self, column: &impl Chunk) -> Expression {
+        let direction = match self {
+            Direction::Asc => "ASC",
+            Direction::Desc => "DESC",
+        };
+        expr_arc!(format!("{{}} {}", direction), column.render_chunk()).render_chunk()
+    }
+}
+
+/// Row-level lock, acquired on the selected rows until the end of transaction.
+/// With `skip_locked` rows locked by someone else are skipped, with `nowait` the
+/// query fails instead of waiting for them.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockMode {
+    ForUpdate { skip_locked: bool, nowait: bool },
+    ForShare { skip_locked: bool, nowait: bool },
+}
+impl Chunk for LockMode {
+    fn render_chunk(&self) -> Expression {
+        let (mode, skip_locked, nowait) = match self {
+            LockMode::ForUpdate {
+                skip_locked,
+                nowait,
+            } => ("UPDATE", skip_locked, nowait),
+            LockMode::ForShare {
+                skip_locked,
+                nowait,
+            } => ("SHARE", skip_locked, nowait),
+        };
+        let mut sql = format!(" FOR {}", mode);
+        if *skip_locked {
+            sql.push_str(" SKIP LOCKED");
+        }
+        if *nowait {
+            sql.push_str(" NOWAIT");
+        }
+        Expression::new(sql, vec![])
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -92,6 +224,9 @@ impl QueryConditions {
         self.add_condition(condition);
         self
     }
+    pub fn get_conditions(&self) -> &Vec<Expression> {
+        &self.conditions
+    }
 }
 impl Chunk for QueryConditions {
     fn render_chunk(&self) -> Expression {
@@ -108,7 +243,8 @@ impl Chunk for QueryConditions {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum JoinType {
     Inner,
     Left,
@@ -118,9 +254,9 @@ pub enum JoinType {
 
 #[derive(Debug, Clone)]
 pub struct JoinQuery {
-    join_type: JoinType,
-    source: QuerySource,
-    on_conditions: QueryConditions,
+    pub(super) join_type: JoinType,
+    pub(super) source: QuerySource,
+    pub(super) on_conditions: QueryConditions,
 }
 impl JoinQuery {
     pub fn new(
@@ -135,17 +271,27 @@ impl JoinQuery {
         }
     }
 }
-impl Chunk for JoinQuery {
-    fn render_chunk(&self) -> Expression {
+impl JoinQuery {
+    pub fn try_render_for(&self, dialect: &dyn Dialect) -> Result<Expression, Error> {
         let join_type = match self.join_type {
             JoinType::Inner => "JOIN ",
             JoinType::Left => "LEFT JOIN ",
             JoinType::Right => "RIGHT JOIN ",
             JoinType::Full => "FULL JOIN ",
         };
-        let source = self.source.render_prefix(join_type);
+        let source = self.source.try_render_prefix_for(join_type, dialect)?;
         let on_conditions = self.on_conditions.render_chunk();
-        expr_arc!(" {} {}", source, on_conditions).render_chunk()
+        Ok(expr_arc!(" {} {}", source, on_conditions).render_chunk())
+    }
+}
+impl Chunk for JoinQuery {
+    fn render_chunk(&self) -> Expression {
+        self.try_render_for(&PostgresDialect)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render_chunk(&self) -> Result<Expression, Error> {
+        self.try_render_for(&PostgresDialect)
     }
 }
 
@@ -165,7 +311,7 @@ mod tests {
         let query = QuerySource::Table("user".to_string(), None);
         let result = query.render_chunk().split();
 
-        assert_eq!(result.0, "FROM user");
+        assert_eq!(result.0, "FROM \"user\"");
         assert_eq!(result.1.len(), 0);
     }
 
@@ -226,7 +372,7 @@ mod tests {
         };
         let result = join_query.render_chunk().split();
 
-        assert_eq!(result.0, " JOIN user ON user.id = address.user_id");
+        assert_eq!(result.0, " JOIN \"user\" ON user.id = address.user_id");
         assert_eq!(result.1.len(), 0);
     }
 
@@ -242,7 +388,7 @@ mod tests {
         };
         let result = join_query.render_chunk().split();
 
-        assert_eq!(result.0, " JOIN user AS u ON u.id = address.user_id");
+        assert_eq!(result.0, " JOIN \"user\" AS u ON u.id = address.user_id");
         assert_eq!(result.1.len(), 0);
     }
 }
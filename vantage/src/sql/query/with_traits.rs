@@ -1,21 +1,39 @@
 use std::sync::Arc;
 
-use serde_json::Value;
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
 
 use crate::prelude::*;
+use crate::Error;
 
-use super::{QueryConditions, QuerySource, QueryType};
+use super::{Direction, Filter, LockMode, QueryConditions, QueryReturning, QuerySource, QueryType};
 
 /// Implementation of object-safe Query. All the methods
 /// in form "query.with_condition()" are implemented
 /// in Query struct instead
 pub trait SqlQuery {
     fn set_distinct(&mut self, distinct: bool);
+    fn add_distinct_on(&mut self, expression: Expression);
+    fn set_lock(&mut self, lock: Option<LockMode>);
+    fn add_values_row(&mut self, row: IndexMap<String, Expression>);
     fn set_table(&mut self, table: &str, alias: Option<String>);
     fn add_with(&mut self, alias: String, subquery: QuerySource);
     fn set_source(&mut self, source: QuerySource);
     fn set_type(&mut self, query_type: QueryType);
+    fn set_returning(&mut self, returning: QueryReturning);
+    fn set_upsert(&mut self, conflict_fields: Option<Vec<String>>);
+    fn set_dialect(&mut self, dialect: Arc<dyn Dialect>);
+    /// Panics on duplicate field in strict mode, see [`try_add_field()`].
+    ///
+    /// [`try_add_field()`]: SqlQuery::try_add_field()
     fn add_field(&mut self, name: Option<String>, column: Arc<Box<dyn SqlField>>);
+    /// Returns [`Error::DuplicateField`] if the alias is already used and
+    /// [`Query::with_strict_fields()`] was called.
+    fn try_add_field(
+        &mut self,
+        name: Option<String>,
+        column: Arc<Box<dyn SqlField>>,
+    ) -> Result<(), Error>;
     fn get_where_conditions_mut(&mut self) -> &mut QueryConditions;
     fn get_having_conditions_mut(&mut self) -> &mut QueryConditions;
     fn add_join(&mut self, join: JoinQuery);
@@ -23,5 +41,33 @@ pub trait SqlQuery {
     fn add_order_by(&mut self, order_by: Expression);
     fn add_limit(&mut self, limit: Option<i64>);
     fn add_skip(&mut self, skip: Option<i64>);
+    /// Adds a key-value pair to the leading comment, see [`Query::with_comment()`].
+    fn add_comment(&mut self, key: &str, value: &str);
+    /// Panics if the query is not INSERT, UPDATE or REPLACE. See [`try_set_field_value()`].
+    ///
+    /// [`try_set_field_value()`]: SqlQuery::try_set_field_value()
     fn set_field_value(&mut self, field: &str, value: Value);
+    fn set_field_expression(&mut self, field: &str, expression: Expression);
+    fn try_set_field_value(&mut self, field: &str, value: Value) -> Result<(), Error>;
+    fn try_set_field_expression(
+        &mut self,
+        field: &str,
+        expression: Expression,
+    ) -> Result<(), Error>;
+    fn get_set_field(&self, field: &str) -> Option<&Expression>;
+    fn get_source(&self) -> &QuerySource;
+    fn get_returning(&self) -> &QueryReturning;
+    fn get_dialect(&self) -> &Arc<dyn Dialect>;
+    fn get_type(&self) -> &QueryType;
+    fn get_field_names(&self) -> Vec<String>;
+    fn get_skip(&self) -> Option<i64>;
+    fn get_limit(&self) -> Option<i64>;
+    /// Where conditions as [`Filter`]s, for data sources which can't execute SQL.
+    /// Fails if any of the conditions can't be represented as a filter.
+    fn get_filters(&self) -> Result<Vec<Filter>, Error>;
+    /// ORDER BY as field names with direction. Fails for ordering by expressions.
+    fn get_order(&self) -> Result<Vec<(String, Direction)>, Error>;
+    /// Values of set fields of INSERT or UPDATE query. Fails if a field is set to
+    /// an expression other than a value.
+    fn get_set_values(&self) -> Result<Map<String, Value>, Error>;
 }
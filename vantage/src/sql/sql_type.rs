@@ -0,0 +1,140 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// Type of a [`Column`]. Columns without a type are sent to the database as-is,
+/// while typed columns are validated before insert/update and are explicitly
+/// cast in the query:
+///
+/// ```
+/// let products = Table::new("product", postgres())
+///     .with_id_column("id")
+///     .with_typed_column("price", SqlType::Int8);
+///
+/// // INSERT INTO product (price) VALUES ({}::int8)
+/// ```
+///
+/// [`Column`]: crate::sql::Column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlType {
+    Int2,
+    Int4,
+    Int8,
+    Numeric,
+    Text,
+    Bool,
+    Timestamp,
+    Uuid,
+    Json,
+    Jsonb,
+    Bytea,
+}
+
+impl SqlType {
+    /// Name of the type, as used in a cast (`{}::int8`)
+    pub fn name(&self) -> &'static str {
+        match self {
+            SqlType::Int2 => "int2",
+            SqlType::Int4 => "int4",
+            SqlType::Int8 => "int8",
+            SqlType::Numeric => "numeric",
+            SqlType::Text => "text",
+            SqlType::Bool => "bool",
+            SqlType::Timestamp => "timestamp",
+            SqlType::Uuid => "uuid",
+            SqlType::Json => "json",
+            SqlType::Jsonb => "jsonb",
+            SqlType::Bytea => "bytea",
+        }
+    }
+
+    /// Map type name reported by the database (either short `int8` or long
+    /// `bigint` form, as used by `information_schema`) into SqlType.
+    pub fn from_name(name: &str) -> Option<SqlType> {
+        Some(match name {
+            "int2" | "smallint" => SqlType::Int2,
+            "int4" | "integer" | "serial" => SqlType::Int4,
+            "int8" | "bigint" | "bigserial" => SqlType::Int8,
+            "numeric" | "decimal" => SqlType::Numeric,
+            "text" | "varchar" | "character varying" | "bpchar" | "character" => SqlType::Text,
+            "bool" | "boolean" => SqlType::Bool,
+            "timestamp" | "timestamp without time zone" => SqlType::Timestamp,
+            "uuid" => SqlType::Uuid,
+            "json" => SqlType::Json,
+            "jsonb" => SqlType::Jsonb,
+            "bytea" => SqlType::Bytea,
+            _ => return None,
+        })
+    }
+
+    /// Check that the value can be stored in a column of this type. NULL is
+    /// always accepted.
+    pub fn validate(&self, value: &Value) -> Result<()> {
+        let valid = match (self, value) {
+            (_, Value::Null) => true,
+            (SqlType::Int2, Value::Number(n)) => {
+                n.as_i64().is_some_and(|n| i16::try_from(n).is_ok())
+            }
+            (SqlType::Int4, Value::Number(n)) => {
+                n.as_i64().is_some_and(|n| i32::try_from(n).is_ok())
+            }
+            (SqlType::Int8, Value::Number(n)) => n.as_i64().is_some(),
+            (SqlType::Numeric, Value::Number(_)) => true,
+            (SqlType::Numeric, Value::String(s)) => s.parse::<f64>().is_ok(),
+            (SqlType::Text, Value::String(_)) => true,
+            (SqlType::Bool, Value::Bool(_)) => true,
+            (SqlType::Timestamp, Value::String(_)) => true,
+            (SqlType::Uuid, Value::String(s)) => {
+                s.len() == 36
+                    && s.chars()
+                        .enumerate()
+                        .all(|(i, c)| matches!(i, 8 | 13 | 18 | 23) == (c == '-'))
+                    && s.chars().all(|c| c == '-' || c.is_ascii_hexdigit())
+            }
+            (SqlType::Json | SqlType::Jsonb, _) => true,
+            (SqlType::Bytea, Value::String(_)) => true,
+            (SqlType::Bytea, Value::Array(a)) => a
+                .iter()
+                .all(|v| v.as_u64().is_some_and(|b| u8::try_from(b).is_ok())),
+            _ => false,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Value {} is not valid for type {}",
+                value,
+                self.name()
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(SqlType::Int2.validate(&json!(100)).is_ok());
+        assert!(SqlType::Int2.validate(&json!(100000)).is_err());
+        assert!(SqlType::Int8.validate(&json!(1.5)).is_err());
+        assert!(SqlType::Numeric.validate(&json!("1.50")).is_ok());
+        assert!(SqlType::Text.validate(&json!(1)).is_err());
+        assert!(SqlType::Bool.validate(&Value::Null).is_ok());
+        assert!(SqlType::Uuid
+            .validate(&json!("a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11"))
+            .is_ok());
+        assert!(SqlType::Uuid.validate(&json!("a0eebc99")).is_err());
+        assert!(SqlType::Bytea.validate(&json!([0, 255])).is_ok());
+        assert!(SqlType::Bytea.validate(&json!([256])).is_err());
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(SqlType::from_name("bigint"), Some(SqlType::Int8));
+        assert_eq!(SqlType::from_name("character varying"), Some(SqlType::Text));
+        assert_eq!(SqlType::from_name("point"), None);
+    }
+}
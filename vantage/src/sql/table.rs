@@ -23,20 +23,40 @@
 //! [`sum()`]: Table::sum()
 
 use std::any::{type_name, Any};
+use std::borrow::Cow;
 use std::fmt::{Debug, Display};
 use std::ops::Deref;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 mod column;
+mod column_def;
+#[cfg(any(feature = "graphql", feature = "openapi"))]
+mod entity_fields;
+mod entry;
 mod join;
+mod operation;
+mod record;
+mod schema;
+mod validation;
 
 pub use column::Column;
-pub use extensions::{Hooks, SoftDelete, TableExtension};
+pub use column_def::ColumnDef;
+pub use entry::Entry;
+pub use extensions::{
+    AuditLog, Hooks, OptimisticLock, SoftDelete, SoftDeleteScope, StaleRecord, TableExtension,
+    TenantScope,
+};
 pub use join::Join;
+pub use operation::Operation;
+pub use record::Record;
+pub use schema::{ColumnSchema, ForeignKeySchema, TableSchema};
+pub use validation::{Rules, ValidationErrors, Validator, Validators};
 
 use crate::expr_arc;
 use crate::lazy_expression::LazyExpression;
 use crate::prelude::{AssociatedQuery, Expression};
+use crate::sql::aggregate::{self, Aggregate};
+use crate::sql::query::{Direction, QuerySource};
 use crate::sql::Condition;
 use crate::sql::ExpressionArc;
 use crate::sql::Query;
@@ -45,6 +65,7 @@ use crate::traits::entity::{EmptyEntity, Entity};
 use crate::uniqid::UniqueIdVendor;
 use anyhow::Result;
 use indexmap::IndexMap;
+use reference::polymorphic::PolymorphicRefs;
 use reference::RelatedSqlTable;
 use serde_json::{Map, Value};
 
@@ -72,6 +93,9 @@ pub trait AnyTable: Any + Send + Sync {
 
     fn add_condition(&mut self, condition: Condition);
     fn hooks(&self) -> &Hooks;
+
+    /// Traverses a reference, same as [`Table::get_ref()`]
+    fn get_ref(&self, ref_name: &str) -> Result<Box<dyn SqlTable>>;
 }
 
 /// When defining references between tables, RelatedTable represents
@@ -121,29 +145,60 @@ pub struct Table<T: DataSource, E: Entity> {
 
     table_name: String,
     table_alias: Option<String>,
+    source_query: Option<Box<Query>>,
+    read_only: bool,
+    hidden_columns: Vec<String>,
     id_column: Option<String>,
     title_column: Option<String>,
 
     conditions: Vec<Condition>,
+    order_by: Vec<Expression>,
+    keyset: Vec<String>,
+    preload: Vec<String>,
     columns: IndexMap<String, Arc<Column>>,
     joins: IndexMap<String, Arc<Join<T>>>,
-    lazy_expressions: IndexMap<String, LazyExpression<T, E>>,
+    lazy_expressions: IndexMap<String, LazyExpression<T>>,
+    include_expressions: bool,
     refs: IndexMap<String, Arc<Box<dyn RelatedSqlTable>>>,
-    table_aliases: Arc<Mutex<UniqueIdVendor>>,
+    polymorphic_refs: PolymorphicRefs,
 
     hooks: Hooks,
+    validators: Validators<E>,
 }
 
 mod with_columns;
-pub use with_columns::TableWithColumns;
+pub use with_columns::{ColumnRef, TableWithColumns};
 pub use with_queries::TableWithQueries;
 
 use super::Chunk;
 
+mod with_aggregates;
+#[cfg(feature = "postgres")]
+mod with_copy;
+#[cfg(feature = "postgres")]
+mod with_watch;
+#[cfg(feature = "postgres")]
+pub use with_watch::{ChangeEvent, ChangeKind};
+mod with_views;
+pub use with_views::{MaterializedView, View};
+mod with_filter;
+#[cfg(feature = "graphql")]
+mod with_graphql;
+pub use with_aggregates::GroupedTable;
+#[cfg(feature = "graphql")]
+pub use with_graphql::{GraphQlField, GraphQlFieldKind, GraphQlSchema, GraphQlType};
+#[cfg(feature = "openapi")]
+mod with_openapi;
+#[cfg(feature = "openapi")]
+pub use with_openapi::OpenApi;
 mod with_joins;
+mod with_keyset;
+pub use with_keyset::Cursor;
+mod with_preload;
 mod with_queries;
 
 mod reference;
+pub use reference::SubqueryStrategy;
 mod with_refs;
 
 mod with_updates;
@@ -164,19 +219,25 @@ impl<T: DataSource + Clone, E: Entity> Clone for Table<T, E> {
 
             table_name: self.table_name.clone(),
             table_alias: self.table_alias.clone(),
+            source_query: self.source_query.clone(),
+            read_only: self.read_only,
+            hidden_columns: self.hidden_columns.clone(),
             id_column: self.id_column.clone(),
             title_column: self.title_column.clone(),
 
             conditions: self.conditions.clone(),
+            order_by: self.order_by.clone(),
+            keyset: self.keyset.clone(),
+            preload: self.preload.clone(),
             columns: self.columns.clone(),
             joins: self.joins.clone(),
             lazy_expressions: self.lazy_expressions.clone(),
+            include_expressions: self.include_expressions,
             refs: self.refs.clone(),
-
-            // Perform a deep clone of the UniqueIdVendor
-            table_aliases: Arc::new(Mutex::new((*self.table_aliases.lock().unwrap()).clone())),
+            polymorphic_refs: self.polymorphic_refs.clone(),
 
             hooks: self.hooks.clone(),
+            validators: self.validators.clone(),
         }
     }
 }
@@ -207,6 +268,9 @@ impl<T: DataSource, E: Entity> AnyTable for Table<T, E> {
     fn hooks(&self) -> &Hooks {
         &self.hooks
     }
+    fn get_ref(&self, ref_name: &str) -> Result<Box<dyn SqlTable>> {
+        Table::get_ref(self, ref_name)
+    }
 }
 
 impl<T: DataSource, E: Entity> RelatedTable<T> for Table<T, E> {
@@ -218,6 +282,9 @@ impl<T: DataSource, E: Entity> RelatedTable<T> for Table<T, E> {
     // TODO: debug why this overwrites the previous columns
     fn add_columns_into_query(&self, mut query: Query, alias_prefix: Option<&str>) -> Query {
         for (column_key, column_val) in &self.columns {
+            if self.hidden_columns.contains(column_key) {
+                continue;
+            }
             let column_val = if let Some(alias_prefix) = &alias_prefix {
                 let alias = format!("{}_{}", alias_prefix, column_key);
                 let mut column_val = column_val.deref().clone();
@@ -235,6 +302,22 @@ impl<T: DataSource, E: Entity> RelatedTable<T> for Table<T, E> {
             );
         }
 
+        for (name, lazy_expression) in &self.lazy_expressions {
+            if self.hidden_columns.contains(name) {
+                continue;
+            }
+            let expression = match lazy_expression {
+                LazyExpression::Computed(expression) => expression,
+                LazyExpression::BeforeQuery(expression) if self.include_expressions => expression,
+                _ => continue,
+            };
+            let name = match alias_prefix {
+                Some(alias_prefix) => format!("{}_{}", alias_prefix, name),
+                None => name.clone(),
+            };
+            query = query.with_field(name, expression(&self.as_entity()));
+        }
+
         for (alias, join) in &self.joins {
             query = join.add_columns_into_query(query, Some(alias));
         }
@@ -246,11 +329,7 @@ impl<T: DataSource, E: Entity> RelatedTable<T> for Table<T, E> {
         self.table_alias.as_ref()
     }
     fn set_alias(&mut self, alias: &str) {
-        if let Some(alias) = &self.table_alias {
-            self.table_aliases.lock().unwrap().dont_avoid(alias);
-        }
         self.table_alias = Some(alias.to_string());
-        self.table_aliases.lock().unwrap().avoid(alias);
         for column in self.columns.values_mut() {
             let mut new_column = column.deref().deref().clone();
             new_column.set_table_alias(alias.to_string());
@@ -282,17 +361,25 @@ impl<T: DataSource, E: Entity> Table<T, E> {
 
             table_name: table_name.to_string(),
             table_alias: None,
+            source_query: None,
+            read_only: false,
+            hidden_columns: Vec::new(),
             id_column: None,
             title_column: None,
 
             conditions: Vec::new(),
+            order_by: Vec::new(),
+            keyset: Vec::new(),
+            preload: Vec::new(),
             columns: IndexMap::new(),
             joins: IndexMap::new(),
             lazy_expressions: IndexMap::new(),
+            include_expressions: false,
             refs: IndexMap::new(),
-            table_aliases: Arc::new(Mutex::new(UniqueIdVendor::new())),
+            polymorphic_refs: IndexMap::new(),
 
             hooks: Hooks::new(),
+            validators: Validators::new(),
         }
     }
 }
@@ -305,19 +392,53 @@ impl<T: DataSource> Table<T, EmptyEntity> {
 
             table_name: table_name.to_string(),
             table_alias: None,
+            source_query: None,
+            read_only: false,
+            hidden_columns: Vec::new(),
             id_column: None,
             title_column: None,
 
             conditions: Vec::new(),
+            order_by: Vec::new(),
+            keyset: Vec::new(),
+            preload: Vec::new(),
             columns: IndexMap::new(),
             joins: IndexMap::new(),
             lazy_expressions: IndexMap::new(),
+            include_expressions: false,
             refs: IndexMap::new(),
-            table_aliases: Arc::new(Mutex::new(UniqueIdVendor::new())),
+            polymorphic_refs: IndexMap::new(),
 
             hooks: Hooks::new(),
+            validators: Validators::new(),
         }
     }
+
+    /// Table, which selects from a subquery instead of a physical table. Use it for
+    /// read models, which still need conditions, references and entity typing:
+    ///
+    /// ```
+    /// let totals = Query::new()
+    ///     .with_table("ord", None)
+    ///     .with_column_field("client_id")
+    ///     .with_field("total".to_string(), expr!("SUM(total)"))
+    ///     .with_group_by(expr!("client_id"));
+    ///
+    /// let big_spenders = Table::from_query("client_totals", totals, postgres())
+    ///     .with_column("client_id")
+    ///     .with_column("total")
+    ///     .with_condition(expr!("total > {}", 1000));
+    /// // SELECT client_id, total FROM (SELECT ..) AS client_totals WHERE (total > 1000)
+    /// ```
+    ///
+    /// The name is used as an alias of the subquery. Records can't be inserted,
+    /// updated or deleted.
+    pub fn from_query(name: &str, query: Query, data_source: T) -> Table<T, EmptyEntity> {
+        let mut table = Table::new(name, data_source);
+        table.source_query = Some(Box::new(query));
+        table.read_only = true;
+        table
+    }
 }
 
 impl<T: DataSource, E: Entity> Table<T, E> {
@@ -338,6 +459,18 @@ impl<T: DataSource, E: Entity> Table<T, E> {
         self
     }
 
+    /// Borrows the table as a table of another entity, converting it only if the
+    /// entity is different.
+    pub(crate) fn as_entity<E2: Entity>(&self) -> Cow<'_, Table<T, E2>> {
+        match (self as &dyn Any).downcast_ref::<Table<T, E2>>() {
+            Some(table) => Cow::Borrowed(table),
+            None => Cow::Owned(self.clone().into_entity()),
+        }
+    }
+
+    /// Converts table into a table of another entity. Columns, conditions, joins,
+    /// references and expressions are kept, but validators are specific to the
+    /// entity and are dropped.
     pub fn into_entity<E2: Entity>(self) -> Table<T, E2> {
         Table {
             data_source: self.data_source,
@@ -345,19 +478,25 @@ impl<T: DataSource, E: Entity> Table<T, E> {
 
             table_name: self.table_name,
             table_alias: self.table_alias,
+            source_query: self.source_query,
+            read_only: self.read_only,
+            hidden_columns: self.hidden_columns,
             id_column: self.id_column,
             title_column: self.title_column,
 
             conditions: self.conditions,
+            order_by: self.order_by,
+            keyset: self.keyset,
+            preload: self.preload,
             columns: self.columns,
             joins: self.joins,
-            lazy_expressions: IndexMap::new(), // TODO: cast proprely
-            refs: IndexMap::new(),             // TODO: cast proprely
-
-            // Perform a deep clone of the UniqueIdVendor
-            table_aliases: Arc::new(Mutex::new((*self.table_aliases.lock().unwrap()).clone())),
+            lazy_expressions: self.lazy_expressions,
+            include_expressions: self.include_expressions,
+            refs: self.refs,
+            polymorphic_refs: self.polymorphic_refs,
 
             hooks: self.hooks,
+            validators: Validators::new(), // validators are specific to the entity
         }
     }
 
@@ -366,6 +505,43 @@ impl<T: DataSource, E: Entity> Table<T, E> {
         self
     }
 
+    /// Source for the queries of this table: either the table itself or a subquery,
+    /// see [`Table::from_query()`]. Subquery always has an alias, defaulting to the
+    /// table name.
+    pub(crate) fn query_source(&self, alias: Option<String>) -> QuerySource {
+        match &self.source_query {
+            Some(query) => QuerySource::Query(
+                Arc::new(query.clone()),
+                Some(alias.unwrap_or_else(|| self.table_name.clone())),
+            ),
+            None => QuerySource::Table(self.table_name.clone(), alias),
+        }
+    }
+
+    /// Fails for read-only tables, such as views or tables, which select from a
+    /// subquery.
+    pub(crate) fn ensure_writable(&self) -> Result<()> {
+        if self.read_only {
+            return Err(anyhow::anyhow!("Table {} is read-only", self));
+        }
+        Ok(())
+    }
+
+    /// Aliases taken by this table and its joins. New aliases are picked from
+    /// what's left, so they only depend on table names and the order of joins,
+    /// and equivalent tables always render the same SQL.
+    pub(crate) fn used_aliases(&self) -> UniqueIdVendor {
+        let mut aliases = UniqueIdVendor::new();
+        if let Some(alias) = &self.table_alias {
+            aliases.avoid(alias);
+        }
+        for (alias, join) in &self.joins {
+            aliases.avoid(alias);
+            aliases.merge(join.table().used_aliases());
+        }
+        aliases
+    }
+
     /// Add a condition to the table, limiting what records
     /// the DataSet will represent
     pub fn add_condition(&mut self, condition: Condition) {
@@ -378,6 +554,25 @@ impl<T: DataSource, E: Entity> Table<T, E> {
         self
     }
 
+    /// Sort records of the table. If you call this method multiple times, the
+    /// latest order will take precedence, and earlier ones would be used for
+    /// breaking ties:
+    ///
+    /// ```
+    /// let products = Product::table()
+    ///     .with_order_by(Product::table().name(), Direction::Asc)
+    ///     .with_order_by(Product::table().price(), Direction::Desc);
+    /// // ORDER BY price DESC, name ASC
+    /// ```
+    pub fn add_order_by(&mut self, column: impl Chunk, direction: Direction) {
+        self.order_by.push(direction.order(&column));
+    }
+
+    pub fn with_order_by(mut self, column: impl Chunk, direction: Direction) -> Self {
+        self.add_order_by(column, direction);
+        self
+    }
+
     // ---- Expressions ----
     //  BeforeQuery(Arc<Box<dyn Fn(&Query) -> Expression>>),
     pub fn add_expression(
@@ -387,7 +582,9 @@ impl<T: DataSource, E: Entity> Table<T, E> {
     ) {
         self.lazy_expressions.insert(
             name.to_string(),
-            LazyExpression::BeforeQuery(Arc::new(Box::new(expression))),
+            LazyExpression::BeforeQuery(Arc::new(Box::new(
+                move |table: &Table<T, EmptyEntity>| expression(&table.as_entity()),
+            ))),
         );
     }
 
@@ -400,6 +597,61 @@ impl<T: DataSource, E: Entity> Table<T, E> {
         self
     }
 
+    /// Adds a column calculated from an SQL expression. Unlike [`with_expression()`],
+    /// computed columns are selected by [`get_select_query()`] just like physical
+    /// columns. They are never written:
+    ///
+    /// ```
+    /// let items = LineItem::table()
+    ///     .with_computed_column("total", |t| t.price().mul(t.quantity()));
+    /// // SELECT id, price, quantity, (price * quantity) AS total FROM line_item
+    /// ```
+    ///
+    /// [`with_expression()`]: Table::with_expression()
+    /// [`get_select_query()`]: TableWithQueries::get_select_query()
+    pub fn with_computed_column(
+        mut self,
+        name: &str,
+        expression: impl Fn(&Table<T, E>) -> Expression + 'static + Sync + Send,
+    ) -> Self {
+        self.add_computed_column(name, expression);
+        self
+    }
+
+    pub fn add_computed_column(
+        &mut self,
+        name: &str,
+        expression: impl Fn(&Table<T, E>) -> Expression + 'static + Sync + Send,
+    ) {
+        self.lazy_expressions.insert(
+            name.to_string(),
+            LazyExpression::Computed(Arc::new(Box::new(move |table: &Table<T, EmptyEntity>| {
+                expression(&table.as_entity())
+            }))),
+        );
+    }
+
+    /// Select all expressions, including imported fields, by [`get_select_query()`]
+    /// and not only those, requested by the entity struct:
+    ///
+    /// ```
+    /// let orders = Order::table()
+    ///     .with_imported_fields("client", &["name"])
+    ///     .with_expressions_included();
+    /// let rows = orders.get_all_data().await?;
+    /// // [{"id": 1, "client_id": 2, "client_name": "Doc Brown"}, ..]
+    /// ```
+    ///
+    /// [`get_select_query()`]: TableWithQueries::get_select_query()
+    pub fn with_expressions_included(mut self) -> Self {
+        self.set_expressions_included(true);
+        self
+    }
+
+    pub fn set_expressions_included(&mut self, include: bool) {
+        self.include_expressions = include;
+    }
+
     pub fn with_extension(mut self, extension: impl TableExtension + 'static) -> Self {
         extension.init(&mut self);
         self.hooks.add_hook(Box::new(extension));
@@ -407,8 +659,38 @@ impl<T: DataSource, E: Entity> Table<T, E> {
         self
     }
 
+    /// Add a validator, which checks records before they are inserted or saved.
+    /// See [`Rules`] for common validation rules.
+    pub fn with_validator(mut self, validator: impl Validator<E> + 'static) -> Self {
+        self.add_validator(validator);
+        self
+    }
+
+    pub fn add_validator(&mut self, validator: impl Validator<E> + 'static) {
+        self.validators.add_validator(validator);
+    }
+
+    /// Check record with the table validators and extensions. Validator failures
+    /// are returned as [`ValidationErrors`].
+    /// Non-nullable columns without a default are checked for NULL values as well.
+    pub fn validate(&self, record: &E) -> Result<()> {
+        let value = serde_json::to_value(record)?;
+        let mut errors = self.validators.validate(record).err().unwrap_or_default();
+        for (field, column) in &self.columns {
+            let nullable =
+                column.is_nullable() || column.is_generated() || column.default_value().is_some();
+            if !nullable && value.get(field).is_some_and(Value::is_null) {
+                errors.add(field, "can't be null");
+            }
+        }
+        errors.into_result()?;
+        self.hooks.validate(self, &value)
+    }
+
     pub async fn get_all_data(&self) -> Result<Vec<Map<String, Value>>> {
-        self.data_source.query_fetch(&self.get_select_query()).await
+        self.data_source
+            .query_fetch(&self.try_get_select_query()?)
+            .await
     }
 
     pub fn sum<C>(&self, column: C) -> AssociatedQuery<T, EmptyEntity>
@@ -422,12 +704,67 @@ impl<T: DataSource, E: Entity> Table<T, E> {
         AssociatedQuery::new(query, self.data_source.clone())
     }
 
+    /// Same as [`try_count()`], but panics if an extension fails.
+    ///
+    /// [`try_count()`]: Table::try_count()
     pub fn count(&self) -> AssociatedQuery<T, EmptyEntity> {
+        self.try_count().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Returns query, counting records in the table.
+    pub fn try_count(&self) -> Result<AssociatedQuery<T, EmptyEntity>> {
         let mut query = self
             .get_empty_query()
             .with_field("count".to_string(), expr_arc!("COUNT(*)"));
-        self.hooks().before_select_query(self, &mut query).unwrap();
-        AssociatedQuery::new(query, self.data_source.clone())
+        self.hooks().before_select_query(self, &mut query)?;
+        Ok(AssociatedQuery::new(query, self.data_source.clone()))
+    }
+
+    /// Same as [`try_aggregate_query()`], but panics if an extension fails.
+    ///
+    /// [`try_aggregate_query()`]: Table::try_aggregate_query()
+    pub fn aggregate_query(&self, aggregate: Aggregate) -> AssociatedQuery<T, EmptyEntity> {
+        self.try_aggregate_query(aggregate)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Returns query, calculating a single aggregate value over all records
+    /// in the table.
+    pub fn try_aggregate_query(
+        &self,
+        aggregate: Aggregate,
+    ) -> Result<AssociatedQuery<T, EmptyEntity>> {
+        let mut query = self.get_empty_query().with_field(
+            aggregate.alias().to_string(),
+            aggregate.expression().clone(),
+        );
+        self.hooks().before_select_query(self, &mut query)?;
+        Ok(AssociatedQuery::new(query, self.data_source.clone()))
+    }
+
+    pub fn min(&self, column: impl Chunk) -> AssociatedQuery<T, EmptyEntity> {
+        self.aggregate_query(aggregate::min(column))
+    }
+
+    pub fn max(&self, column: impl Chunk) -> AssociatedQuery<T, EmptyEntity> {
+        self.aggregate_query(aggregate::max(column))
+    }
+
+    pub fn avg(&self, column: impl Chunk) -> AssociatedQuery<T, EmptyEntity> {
+        self.aggregate_query(aggregate::avg(column))
+    }
+
+    pub fn count_distinct(&self, column: impl Chunk) -> AssociatedQuery<T, EmptyEntity> {
+        self.aggregate_query(aggregate::count_distinct(column))
+    }
+
+    /// Concatenates values of a column, separated by `separator`.
+    pub fn string_agg(
+        &self,
+        column: impl Chunk,
+        separator: &str,
+    ) -> AssociatedQuery<T, EmptyEntity> {
+        self.aggregate_query(aggregate::string_agg(column, separator))
     }
 }
 
@@ -467,6 +804,8 @@ mod tests {
 
     use super::*;
     use crate::{
+        dataset::WritableDataSet,
+        expr,
         mocks::datasource::MockDataSource,
         prelude::{Chunk, Operations},
     };
@@ -527,6 +866,66 @@ mod tests {
         assert_eq!(result.unwrap(), *data_source.data());
     }
 
+    #[test]
+    fn test_order_by() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let products = Table::new("product", db)
+            .with_column("name")
+            .with_column("price");
+        let products = products
+            .clone()
+            .with_order_by(products.get_column("name").unwrap(), Direction::Asc)
+            .with_order_by(products.get_column("price").unwrap(), Direction::Desc);
+
+        assert_eq!(
+            products.get_select_query().preview(),
+            "SELECT name, price FROM product ORDER BY price DESC, name ASC"
+        );
+        assert_eq!(
+            products.count().preview(),
+            "SELECT (COUNT(*)) AS count FROM product"
+        );
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let products = Table::new("product", db)
+            .with_column("name")
+            .with_column("price")
+            .with_column("bakery_id");
+        let price = products.get_column("price").unwrap();
+
+        assert_eq!(
+            products.min(price.clone()).preview(),
+            "SELECT (MIN(price)) AS min FROM product"
+        );
+        assert_eq!(
+            products.max(price.clone()).preview(),
+            "SELECT (MAX(price)) AS max FROM product"
+        );
+        assert_eq!(
+            products.avg(price).preview(),
+            "SELECT (AVG(price)) AS avg FROM product"
+        );
+        assert_eq!(
+            products
+                .count_distinct(products.get_column("bakery_id").unwrap())
+                .preview(),
+            "SELECT (COUNT(DISTINCT bakery_id)) AS count_distinct FROM product"
+        );
+        assert_eq!(
+            products
+                .string_agg(products.get_column("name").unwrap(), ", ")
+                .preview(),
+            "SELECT (STRING_AGG(name, ', ')) AS string_agg FROM product"
+        );
+    }
+
     #[test]
     fn test_vip_client() {
         let data =
@@ -546,4 +945,76 @@ mod tests {
             "SELECT (SUM(total_spent)) AS sum FROM client WHERE (is_vip = {})".to_owned()
         );
     }
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Default)]
+    struct Client {
+        name: String,
+    }
+    impl Entity for Client {}
+
+    #[test]
+    fn test_into_entity() {
+        let db = MockDataSource::new(&json!([]));
+        let orders = Table::new("ord", db.clone())
+            .with_id_column("id")
+            .with_column("client_id");
+        let clients = Table::new("client", db)
+            .with_id_column("id")
+            .with_column("name")
+            .with_many("orders", "client_id", move || Box::new(orders.clone()))
+            .with_expression("order_count", |t| {
+                t.get_subquery_as::<EmptyEntity>("orders")
+                    .unwrap()
+                    .count()
+                    .render_chunk()
+            });
+
+        let clients: Table<MockDataSource, Client> = clients.into_entity();
+        let clients = clients.with_expression("name_caps", |t: &Table<_, Client>| {
+            t.get_column("name").unwrap().upper()
+        });
+        let expected = "SELECT name, (SELECT (COUNT(*)) AS count FROM ord WHERE (ord.client_id = client.id)) AS order_count, (UPPER(name)) AS name_caps FROM client";
+        assert_eq!(
+            clients
+                .get_select_query_for_field_names(&["name", "order_count", "name_caps"])
+                .preview(),
+            expected
+        );
+        assert!(clients.get_ref("orders").is_ok());
+
+        // and back, keeping the expression defined for Client
+        let clients: Table<MockDataSource, EmptyEntity> = clients.into_entity();
+        assert_eq!(
+            clients
+                .get_select_query_for_field_names(&["name", "order_count", "name_caps"])
+                .preview(),
+            expected
+        );
+        assert!(clients.get_ref("orders").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_from_query() {
+        let db = MockDataSource::new(&json!([]));
+        let totals = Query::new()
+            .with_table("ord", None)
+            .with_column_field("client_id")
+            .with_field("total".to_string(), expr!("SUM(total)"))
+            .with_group_by(expr!("client_id"));
+
+        let table = Table::from_query("client_totals", totals, db)
+            .with_id_column("client_id")
+            .with_column("total");
+        let total = table.get_column("total").unwrap();
+        let table = table.with_condition(total.gt(1000));
+
+        assert_eq!(
+            table.get_select_query().preview(),
+            "SELECT client_id, total FROM (SELECT client_id, (SUM(total)) AS total FROM ord GROUP BY client_id) AS client_totals WHERE (total > 1000)"
+        );
+        assert_eq!(table.count().preview(), "SELECT (COUNT(*)) AS count FROM (SELECT client_id, (SUM(total)) AS total FROM ord GROUP BY client_id) AS client_totals WHERE (total > 1000)");
+
+        assert!(table.get_insert_query(json!({"total": 1})).is_err());
+        assert!(table.delete().await.is_err());
+    }
 }
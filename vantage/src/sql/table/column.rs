@@ -1,37 +1,83 @@
 use std::sync::Arc;
 
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+
 use crate::expr;
 use crate::sql::chunk::Chunk;
+use crate::sql::dialect::{Dialect, PostgresDialect};
 use crate::sql::Condition;
 use crate::sql::Expression;
 use crate::sql::Operations;
-use crate::sql::WrapArc;
+use crate::sql::SqlType;
 use crate::traits::column::SqlField;
 
 #[derive(Debug, Clone)]
 pub struct Column {
     name: String,
+    /// Table, which the column belongs to, set when the column is added
+    table_name: Option<String>,
     table_alias: Option<String>,
     column_alias: Option<String>,
+    sql_type: Option<SqlType>,
+    nullable: bool,
+    default: Option<Expression>,
+    generated: bool,
+    dialect: Arc<dyn Dialect>,
 }
 
 impl Column {
     pub fn new(name: String, table_alias: Option<String>) -> Column {
         Column {
             name,
+            table_name: None,
             table_alias,
             column_alias: None,
+            sql_type: None,
+            nullable: true,
+            default: None,
+            generated: false,
+            dialect: Arc::new(PostgresDialect),
         }
     }
+    pub fn with_type(mut self, sql_type: SqlType) -> Column {
+        self.sql_type = Some(sql_type);
+        self
+    }
+    pub fn with_nullable(mut self, nullable: bool) -> Column {
+        self.nullable = nullable;
+        self
+    }
+    pub fn with_default(mut self, default: Expression) -> Column {
+        self.default = Some(default);
+        self
+    }
+    pub fn with_generated(mut self, generated: bool) -> Column {
+        self.generated = generated;
+        self
+    }
     pub fn name(&self) -> String {
         self.name.clone()
     }
     fn name_with_table(&self) -> String {
+        let name = self.dialect.quote_identifier(&self.name);
         match &self.table_alias {
-            Some(table_alias) => format!("{}.{}", table_alias, self.name),
-            None => self.name.clone(),
+            Some(table_alias) => format!("{}.{}", self.dialect.quote_identifier(table_alias), name),
+            None => name,
         }
     }
+    /// Dialect for quoting the column name, set by the [`Table`] when the column is added.
+    ///
+    /// [`Table`]: crate::sql::Table
+    pub fn set_dialect(&mut self, dialect: Arc<dyn Dialect>) {
+        self.dialect = dialect;
+    }
+    /// Table of the column, set by the [`Table`] when the column is added.
+    ///
+    /// [`Table`]: crate::sql::Table
+    pub fn set_table_name(&mut self, table_name: String) {
+        self.table_name = Some(table_name);
+    }
     pub fn set_table_alias(&mut self, alias: String) {
         self.table_alias = Some(alias);
     }
@@ -42,6 +88,72 @@ impl Column {
     pub fn get_column_alias(&self) -> Option<String> {
         self.column_alias.clone()
     }
+
+    pub fn table_alias(&self) -> Option<&str> {
+        self.table_alias.as_deref()
+    }
+
+    /// Columns belong to the same table, if both the table and its alias match.
+    /// Unaliased columns of different tables are told apart by the table name.
+    pub fn same_table(&self, other: &Column) -> bool {
+        self.table_name == other.table_name && self.table_alias == other.table_alias
+    }
+
+    /// Condition comparing two columns, rendered with aliases of their tables:
+    ///
+    /// ```
+    /// let condition = users.role_id().eq_col(roles.id());
+    /// // (u.role_id = r.id)
+    /// ```
+    pub fn eq_col(self: &Arc<Self>, other: Arc<Column>) -> Condition {
+        Condition::from_columns(self.clone(), "=", other)
+    }
+
+    pub fn ne_col(self: &Arc<Self>, other: Arc<Column>) -> Condition {
+        Condition::from_columns(self.clone(), "!=", other)
+    }
+
+    pub fn gt_col(self: &Arc<Self>, other: Arc<Column>) -> Condition {
+        Condition::from_columns(self.clone(), ">", other)
+    }
+
+    pub fn lt_col(self: &Arc<Self>, other: Arc<Column>) -> Condition {
+        Condition::from_columns(self.clone(), "<", other)
+    }
+
+    pub fn sql_type(&self) -> Option<SqlType> {
+        self.sql_type
+    }
+
+    pub fn is_nullable(&self) -> bool {
+        self.nullable
+    }
+
+    pub fn default_value(&self) -> Option<&Expression> {
+        self.default.as_ref()
+    }
+
+    /// Generated columns are calculated by the database and are never written.
+    pub fn is_generated(&self) -> bool {
+        self.generated
+    }
+
+    /// Render value for storing in this column. Values of a typed column
+    /// are validated and cast explicitly (`{}::int8`).
+    ///
+    /// NULL in a non-nullable column with a default is replaced by the default.
+    pub fn render_value(&self, value: Value) -> Result<Expression> {
+        if let (Value::Null, false, Some(default)) = (&value, self.nullable, &self.default) {
+            return Ok(default.clone());
+        }
+        let Some(sql_type) = self.sql_type else {
+            return Ok(Expression::new("{}".to_string(), vec![value]));
+        };
+        sql_type
+            .validate(&value)
+            .with_context(|| anyhow!("Invalid value for column {}", self.name))?;
+        Ok(Expression::as_type(value, sql_type.name()))
+    }
 }
 
 impl Chunk for Column {
@@ -49,11 +161,19 @@ impl Chunk for Column {
         Arc::new(self.clone()).render_chunk()
     }
 }
-impl Operations for Column {}
+impl Operations for Column {
+    fn in_values(&self, values: Vec<Value>) -> Condition {
+        self.dialect.render_in_values(self.render_chunk(), values)
+    }
+}
 
 impl Operations for Arc<Column> {
-    fn eq(&self, other: &impl Chunk) -> Condition {
-        Condition::from_field(self.clone(), "=", WrapArc::wrap_arc(other.render_chunk()))
+    fn condition(&self, operation: &str, value: Arc<Box<dyn Chunk>>) -> Condition {
+        Condition::from_field(self.clone(), operation, value)
+    }
+
+    fn in_values(&self, values: Vec<Value>) -> Condition {
+        self.dialect.render_in_values(self.render_chunk(), values)
     }
 
     // fn add(&self, other: impl SqlChunk) -> Expression {
@@ -78,7 +198,11 @@ impl SqlField for Arc<Column> {
         let alias = alias.or(self.column_alias.as_deref());
 
         if let Some(alias) = alias {
-            expr!(format!("{} AS {}", self.name_with_table(), alias))
+            expr!(format!(
+                "{} AS {}",
+                self.name_with_table(),
+                self.dialect.quote_identifier(alias)
+            ))
         } else {
             expr!(format!("{}", self.name_with_table()))
         }
@@ -110,6 +234,20 @@ mod tests {
         assert_eq!(params.len(), 0);
     }
 
+    #[test]
+    fn test_quoting() {
+        let field = Arc::new(Column::new("from".to_string(), Some("o".to_string())));
+        assert_eq!(field.render_chunk().sql(), "o.\"from\"");
+        assert_eq!(
+            field.render_column(Some("order")).sql(),
+            "o.\"from\" AS \"order\""
+        );
+
+        let mut field = Column::new("from".to_string(), None);
+        field.set_dialect(Arc::new(crate::sql::dialect::MySqlDialect));
+        assert_eq!(field.render_chunk().sql(), "`from`");
+    }
+
     #[test]
     fn test_eq() {
         let field = Arc::new(Column::new("id".to_string(), None));
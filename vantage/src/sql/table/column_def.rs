@@ -0,0 +1,112 @@
+use serde_json::Value;
+
+use crate::sql::{Expression, SqlType};
+
+use super::Column;
+
+/// Definition of a column with metadata, for [`Table::with_column_def()`]:
+///
+/// ```
+/// let items = Table::new("line_item", postgres())
+///     .with_column_def(ColumnDef::new("id").bigint().generated())
+///     .with_column_def(ColumnDef::new("qty").int().not_null().default(0))
+///     .with_column_def(ColumnDef::new("created_at").timestamp().default_expr(expr!("now()")));
+/// ```
+///
+/// Generated columns are never written by inserts and updates. NULL in a non-nullable
+/// column is replaced with the default, if the column has one, otherwise it is rejected
+/// by [`Table::validate()`].
+///
+/// [`Table::with_column_def()`]: super::Table::with_column_def()
+/// [`Table::validate()`]: super::Table::validate()
+#[derive(Debug, Clone)]
+pub struct ColumnDef {
+    name: String,
+    sql_type: Option<SqlType>,
+    nullable: bool,
+    default: Option<Expression>,
+    generated: bool,
+}
+
+impl ColumnDef {
+    pub fn new(name: &str) -> ColumnDef {
+        ColumnDef {
+            name: name.to_string(),
+            sql_type: None,
+            nullable: true,
+            default: None,
+            generated: false,
+        }
+    }
+
+    pub fn of_type(mut self, sql_type: SqlType) -> Self {
+        self.sql_type = Some(sql_type);
+        self
+    }
+
+    pub fn int(self) -> Self {
+        self.of_type(SqlType::Int4)
+    }
+
+    pub fn bigint(self) -> Self {
+        self.of_type(SqlType::Int8)
+    }
+
+    pub fn numeric(self) -> Self {
+        self.of_type(SqlType::Numeric)
+    }
+
+    pub fn text(self) -> Self {
+        self.of_type(SqlType::Text)
+    }
+
+    pub fn bool(self) -> Self {
+        self.of_type(SqlType::Bool)
+    }
+
+    pub fn timestamp(self) -> Self {
+        self.of_type(SqlType::Timestamp)
+    }
+
+    pub fn not_null(mut self) -> Self {
+        self.nullable = false;
+        self
+    }
+
+    /// Value used instead of NULL in a non-nullable column
+    pub fn default(self, value: impl Into<Value>) -> Self {
+        self.default_expr(Expression::new("{}".to_string(), vec![value.into()]))
+    }
+
+    /// Same as [`default()`], but with an SQL expression, such as `now()`
+    ///
+    /// [`default()`]: ColumnDef::default()
+    pub fn default_expr(mut self, expression: Expression) -> Self {
+        self.default = Some(expression);
+        self
+    }
+
+    /// Column is calculated by the database, for example an identity or
+    /// `GENERATED ALWAYS AS (..)` column
+    pub fn generated(mut self) -> Self {
+        self.generated = true;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn into_column(self, table_alias: Option<String>) -> Column {
+        let mut column = Column::new(self.name, table_alias)
+            .with_nullable(self.nullable)
+            .with_generated(self.generated);
+        if let Some(sql_type) = self.sql_type {
+            column = column.with_type(sql_type);
+        }
+        if let Some(default) = self.default {
+            column = column.with_default(default);
+        }
+        column
+    }
+}
@@ -0,0 +1,69 @@
+use serde_json::Value;
+
+use crate::sql::sql_type::SqlType;
+use crate::sql::table::Table;
+use crate::traits::datasource::DataSource;
+use crate::traits::entity::Entity;
+
+/// Type of an entity field, as told by the column type or, if the column has
+/// no type, by the default value of the field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FieldType {
+    Integer,
+    Float,
+    Boolean,
+    String,
+    Uuid,
+    Timestamp,
+    Json,
+}
+
+/// Field of the entity, used for describing the table in API schemas.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct EntityField {
+    pub name: String,
+    pub field_type: FieldType,
+    /// Field is an `Option`
+    pub nullable: bool,
+    pub is_id: bool,
+    /// Field is calculated - an expression, an imported field or a generated column
+    pub read_only: bool,
+}
+
+impl<T: DataSource, E: Entity> Table<T, E> {
+    /// Fields of the entity, except for the preloaded references.
+    pub(crate) fn entity_fields(&self) -> Vec<EntityField> {
+        let Ok(Value::Object(sample)) = serde_json::to_value(E::default()) else {
+            panic!("Entity must serialize into a struct");
+        };
+
+        sample
+            .into_iter()
+            .filter(|(name, _)| !self.refs.contains_key(name))
+            .map(|(name, value)| {
+                let column = self.columns.get(&name);
+                let field_type = match (column.and_then(|c| c.sql_type()), &value) {
+                    (Some(SqlType::Int2 | SqlType::Int4 | SqlType::Int8), _) => FieldType::Integer,
+                    (Some(SqlType::Numeric), _) => FieldType::Float,
+                    (Some(SqlType::Bool), _) => FieldType::Boolean,
+                    (Some(SqlType::Uuid), _) => FieldType::Uuid,
+                    (Some(SqlType::Timestamp), _) => FieldType::Timestamp,
+                    (Some(SqlType::Json | SqlType::Jsonb), _) => FieldType::Json,
+                    (Some(SqlType::Text | SqlType::Bytea), _) => FieldType::String,
+                    (None, Value::Bool(_)) => FieldType::Boolean,
+                    (None, Value::Number(n)) if n.is_i64() || n.is_u64() => FieldType::Integer,
+                    (None, Value::Number(_)) => FieldType::Float,
+                    (None, Value::Object(_) | Value::Array(_)) => FieldType::Json,
+                    (None, _) => FieldType::String,
+                };
+                EntityField {
+                    is_id: self.id_column.as_deref() == Some(name.as_str()),
+                    read_only: column.is_none_or(|c| c.is_generated()),
+                    nullable: value.is_null(),
+                    field_type,
+                    name,
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,162 @@
+use std::ops::Deref;
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::dataset::{ReadableDataSet, WritableDataSet};
+use crate::traits::datasource::{DataSource, ExecResult};
+use crate::traits::entity::Entity;
+use crate::Error;
+
+use super::{Record, Table};
+
+/// The one record of a [`Table`] with a given id. Entry dereferences into the table,
+/// scoped to that record, so references stay scoped to it too:
+///
+/// ```
+/// let order = Order::table().entry(12.into());
+///
+/// let data = order.get().await?;              // fails with Error::NotFound
+/// order.patch(json!({"qty": 3})).await?;      // UPDATE ord SET qty = {} WHERE (id = {})
+/// let lines = order.ref_line_items().get().await?;
+/// order.delete().await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Entry<T: DataSource, E: Entity> {
+    table: Table<T, E>,
+    id: Value,
+}
+
+impl<T: DataSource, E: Entity> Table<T, E> {
+    /// Returns [`Entry`] for the record with `id`. No query is executed.
+    pub fn entry(&self, id: Value) -> Entry<T, E> {
+        Entry {
+            table: self.clone().with_id(id.clone()),
+            id,
+        }
+    }
+}
+
+impl<T: DataSource, E: Entity> Entry<T, E> {
+    pub fn id(&self) -> &Value {
+        &self.id
+    }
+
+    /// Returns table, scoped to this record only.
+    pub fn table(&self) -> &Table<T, E> {
+        &self.table
+    }
+
+    /// Fetches the record. Returns [`Error::NotFound`] if it does not exist.
+    pub async fn get(&self) -> Result<E> {
+        match self.try_get().await? {
+            Some(entity) => Ok(entity),
+            None => Err(self.not_found().into()),
+        }
+    }
+
+    /// Fetches the record, if it exists.
+    pub async fn try_get(&self) -> Result<Option<E>> {
+        self.table.get_some().await
+    }
+
+    /// Same as [`Table::load()`] - fetches the record, so that it can be modified
+    /// and saved.
+    pub async fn load(&self) -> Result<Record<T, E>> {
+        self.table.load(self.id.clone()).await
+    }
+
+    /// Updates fields present in `values`, without fetching the record. Id field
+    /// can't be changed.
+    pub async fn patch<V: Serialize + Clone>(&self, values: V) -> Result<ExecResult> {
+        WritableDataSet::update_with::<(), V>(&self.table, values).await
+    }
+
+    /// Deletes the record. `rows_affected` of the result is 0 if it didn't exist.
+    pub async fn delete(&self) -> Result<ExecResult> {
+        WritableDataSet::delete(&self.table).await
+    }
+
+    /// Returns true if the record exists.
+    pub async fn exists(&self) -> Result<bool> {
+        ReadableDataSet::exists(&self.table).await
+    }
+
+    pub(crate) fn not_found(&self) -> Error {
+        Error::NotFound {
+            table: self.table.table_name.clone(),
+            id: self.id.clone(),
+        }
+    }
+}
+
+impl<T: DataSource, E: Entity> Deref for Entry<T, E> {
+    type Target = Table<T, E>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use crate::prelude::*;
+
+    #[derive(Serialize, Deserialize, Clone, Default, Debug)]
+    struct Product {
+        id: i64,
+        name: String,
+        price: i64,
+    }
+    impl Entity for Product {}
+
+    fn products() -> (MemoryDataSource, Table<MemoryDataSource, Product>) {
+        let memory = MemoryDataSource::new().with_table(
+            "product",
+            vec![
+                json!({"id": 1, "name": "Cake", "price": 12})
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+                json!({"id": 2, "name": "Pie", "price": 8})
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+            ],
+        );
+        let table = Table::new_with_entity("product", memory.clone())
+            .with_id_column("id")
+            .with_column("name")
+            .with_column("price");
+        (memory, table)
+    }
+
+    #[tokio::test]
+    async fn test_entry() {
+        let (memory, products) = products();
+        let pie = products.entry(2.into());
+
+        assert_eq!(pie.get().await.unwrap().name, "Pie");
+        assert_eq!(
+            pie.get_select_query().preview(),
+            "SELECT id, name, price FROM product WHERE (id = 2)"
+        );
+
+        pie.patch(json!({"price": 9})).await.unwrap();
+        assert_eq!(pie.get().await.unwrap().price, 9);
+        assert!(pie.patch(json!({"id": 3})).await.is_err());
+
+        pie.delete().await.unwrap();
+        assert_eq!(memory.rows("product").unwrap().len(), 1);
+        assert_eq!(
+            pie.get().await.unwrap_err().to_string(),
+            "Record with id=2 not found in 'product'"
+        );
+        assert!(pie.try_get().await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,252 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use crate::{
+    expr_arc,
+    prelude::SqlTable,
+    sql::{
+        query::{QueryReturning, QuerySource, QueryType, SqlQuery},
+        Chunk, ExpressionArc, Query,
+    },
+};
+
+use super::TableExtension;
+
+pub type ActorFx = dyn Fn() -> Value + Send + Sync;
+
+/// Records all changes of the table into an audit table. Each inserted, updated
+/// or deleted record produces a row in the audit table:
+///
+/// ```sql
+/// CREATE TABLE audit_log (
+///     id serial PRIMARY KEY,
+///     table_name text NOT NULL,
+///     operation text NOT NULL,       -- insert, update or delete
+///     old_values jsonb,
+///     new_values jsonb,
+///     actor jsonb,
+///     created_at timestamptz NOT NULL
+/// );
+/// ```
+///
+/// Changes are recorded by the same query, which modifies the table, so both
+/// succeed or fail together:
+///
+/// ```
+/// let clients = Client::table()
+///     .with_extension(AuditLog::new("audit_log").with_actor(|| json!(current_user_id())));
+/// ```
+///
+/// Add this extension after the other extensions, so that it records the final
+/// version of the query. Only Postgres is supported, other dialects fail the query.
+#[derive(Clone)]
+pub struct AuditLog {
+    audit_table: String,
+    actor: Arc<Box<ActorFx>>,
+}
+
+impl AuditLog {
+    pub fn new(audit_table: &str) -> Self {
+        AuditLog {
+            audit_table: audit_table.to_string(),
+            actor: Arc::new(Box::new(|| Value::Null)),
+        }
+    }
+
+    /// Callback, returning who is making the change, such as id of the current user.
+    pub fn with_actor(mut self, actor: impl Fn() -> Value + Send + Sync + 'static) -> Self {
+        self.actor = Arc::new(Box::new(actor));
+        self
+    }
+
+    /// Turns query into a statement, which also inserts changed rows into
+    /// the audit table:
+    ///
+    /// WITH changed AS (UPDATE .. RETURNING *), audit AS (INSERT INTO audit_log ..)
+    /// SELECT .. FROM changed
+    fn wrap(&self, table: &dyn SqlTable, query: &mut Query, operation: &str) -> Result<()> {
+        let QuerySource::Table(table_name, _) = query.get_source().clone() else {
+            return Err(anyhow!("AuditLog can only record changes of a table"));
+        };
+        let dialect = query.get_dialect().clone();
+        if !dialect.supports_writable_cte() {
+            return Err(anyhow!(
+                "AuditLog requires Postgres, {:?} can't record changes of {}",
+                dialect,
+                table_name
+            ));
+        }
+
+        let mut result = Query::new();
+        let (old_values, new_values, join) = match operation {
+            "insert" => ("NULL", "to_jsonb(changed)", String::new()),
+            "update" => {
+                // rows are selected before they are changed by the update
+                result = result.with_with("old", query.clone().with_type(QueryType::Select));
+                let id = dialect.quote_identifier(&table.id().name());
+                (
+                    "to_jsonb(old)",
+                    "to_jsonb(changed)",
+                    format!(" LEFT JOIN old ON old.{} = changed.{}", id, id),
+                )
+            }
+            _ => ("to_jsonb(changed)", "NULL", String::new()),
+        };
+
+        let audit = expr_arc!(
+            format!(
+                "INSERT INTO {} (table_name, operation, old_values, new_values, actor, created_at) \
+                SELECT {{}}, {{}}, {}, {}, {{}}, now() FROM changed{}",
+                dialect.quote_identifier(&self.audit_table),
+                old_values,
+                new_values,
+                join
+            ),
+            table_name,
+            operation.to_string(),
+            (self.actor)()
+        )
+        .render_chunk();
+
+        let returning = query.get_returning().clone();
+        let mut changed = query.clone();
+        changed.set_returning(QueryReturning::All);
+
+        result = result
+            .with_with("changed", changed)
+            .with_with(
+                "audit",
+                Query::new().with_type(QueryType::Expression(audit)),
+            )
+            .with_table("changed", None);
+        if let QueryReturning::Fields(fields) = returning {
+            for field in fields {
+                result = result.with_column_field(&field);
+            }
+        }
+
+        *query = result;
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for AuditLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuditLog")
+            .field("audit_table", &self.audit_table)
+            .finish()
+    }
+}
+
+impl TableExtension for AuditLog {
+    fn before_insert_query(&self, table: &dyn SqlTable, query: &mut Query) -> Result<()> {
+        self.wrap(table, query, "insert")
+    }
+    fn before_update_query(&self, table: &dyn SqlTable, query: &mut Query) -> Result<()> {
+        self.wrap(table, query, "update")
+    }
+    fn before_delete_query(&self, table: &dyn SqlTable, query: &mut Query) -> Result<()> {
+        self.wrap(table, query, "delete")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        dataset::WritableDataSet,
+        mocks::datasource::MockDataSource,
+        prelude::{Operations, TableWithColumns},
+        sql::{dialect::MssqlDialect, Table},
+    };
+
+    fn clients() -> Table<MockDataSource, crate::prelude::EmptyEntity> {
+        clients_with(MockDataSource::new(&json!([])))
+    }
+
+    fn clients_with(db: MockDataSource) -> Table<MockDataSource, crate::prelude::EmptyEntity> {
+        Table::new("client", db)
+            .with_id_column("id")
+            .with_column("name")
+            .with_extension(AuditLog::new("audit_log").with_actor(|| json!("admin")))
+    }
+
+    #[test]
+    fn test_insert() {
+        let query = clients().get_insert_query(json!({"name": "John"})).unwrap();
+
+        assert_eq!(
+            query.preview(),
+            "WITH changed AS (INSERT INTO client (name) VALUES ('John') RETURNING *), \
+            audit AS (INSERT INTO audit_log (table_name, operation, old_values, new_values, actor, created_at) \
+            SELECT 'client', 'insert', NULL, to_jsonb(changed), 'admin', now() FROM changed) \
+            SELECT id FROM changed"
+        );
+    }
+
+    #[test]
+    fn test_quoting_and_dialect() {
+        let clients = Table::new("client", MockDataSource::new(&json!([])))
+            .with_id_column("user id")
+            .with_column("name")
+            .with_extension(AuditLog::new("audit-log"));
+        let query = clients
+            .get_update_query(json!({"name": "Doc"}))
+            .unwrap()
+            .preview();
+        assert!(query.contains("INSERT INTO \"audit-log\" ("));
+        assert!(query.contains("LEFT JOIN old ON old.\"user id\" = changed.\"user id\""));
+
+        let clients = clients_with(MockDataSource::new(&json!([])).with_dialect(MssqlDialect));
+        assert!(clients.get_insert_query(json!({"name": "John"})).is_err());
+    }
+
+    #[test]
+    fn test_update() {
+        let clients = clients();
+        let query = clients
+            .clone()
+            .with_condition(clients.id().eq(&1))
+            .get_update_query(json!({"name": "Doc"}))
+            .unwrap();
+
+        assert_eq!(
+            query.preview(),
+            "WITH old AS (SELECT * FROM client WHERE (id = 1)), \
+            changed AS (UPDATE client SET name = 'Doc' WHERE (id = 1) RETURNING *), \
+            audit AS (INSERT INTO audit_log (table_name, operation, old_values, new_values, actor, created_at) \
+            SELECT 'client', 'update', to_jsonb(old), to_jsonb(changed), 'admin', now() \
+            FROM changed LEFT JOIN old ON old.id = changed.id) \
+            SELECT * FROM changed"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete() {
+        let db = MockDataSource::new(&json!([]));
+        let clients = clients_with(db.clone());
+        clients
+            .clone()
+            .with_condition(clients.id().eq(&1))
+            .delete()
+            .await
+            .unwrap();
+
+        let calls = db.calls();
+        assert_eq!(
+            calls[0].sql,
+            "WITH changed AS (DELETE FROM client WHERE (id = {}) RETURNING *), \
+            audit AS (INSERT INTO audit_log (table_name, operation, old_values, new_values, actor, created_at) \
+            SELECT {}, {}, to_jsonb(changed), NULL, {}, now() FROM changed) \
+            SELECT * FROM changed"
+        );
+        assert_eq!(
+            calls[0].params,
+            vec![json!(1), json!("client"), json!("delete"), json!("admin")]
+        );
+    }
+}
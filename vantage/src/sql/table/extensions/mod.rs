@@ -7,20 +7,90 @@
 use std::sync::Arc;
 
 use anyhow::Result;
-pub use soft_delete::SoftDelete;
+pub use audit_log::AuditLog;
+pub use optimistic_lock::{OptimisticLock, StaleRecord};
+use serde_json::Value;
+pub use soft_delete::{SoftDelete, SoftDeleteScope};
+pub use tenant_scope::TenantScope;
 
 use crate::sql::Query;
+use crate::traits::datasource::ExecResult;
 
 use super::SqlTable;
 
+/// Extension hooks are called by the [`Table`] in the following order:
+///
+///  - insert: [`validate()`], [`before_insert_query()`], [`after_insert()`]
+///  - update: [`validate()`] (when saving a [`Record`]), [`before_update_query()`],
+///    [`after_update_query()`]
+///  - delete: [`before_delete_query()`], [`after_delete()`]
+///
+/// Returning an error from any of the hooks aborts the operation.
+///
+/// [`Table`]: crate::sql::Table
+/// [`Record`]: crate::sql::table::Record
+/// [`validate()`]: TableExtension::validate()
+/// [`before_insert_query()`]: TableExtension::before_insert_query()
+/// [`after_insert()`]: TableExtension::after_insert()
+/// [`before_update_query()`]: TableExtension::before_update_query()
+/// [`after_update_query()`]: TableExtension::after_update_query()
+/// [`before_delete_query()`]: TableExtension::before_delete_query()
+/// [`after_delete()`]: TableExtension::after_delete()
 pub trait TableExtension: std::fmt::Debug + Send + Sync {
     fn init(&self, _table: &mut dyn SqlTable) {}
+    /// Called with a serialized record before it is inserted or saved.
+    fn validate(&self, _table: &dyn SqlTable, _record: &Value) -> Result<()> {
+        Ok(())
+    }
     fn before_select_query(&self, _table: &dyn SqlTable, _query: &mut Query) -> Result<()> {
         Ok(())
     }
+    fn before_insert_query(&self, _table: &dyn SqlTable, _query: &mut Query) -> Result<()> {
+        Ok(())
+    }
+    fn before_update_query(&self, _table: &dyn SqlTable, _query: &mut Query) -> Result<()> {
+        Ok(())
+    }
     fn before_delete_query(&self, _table: &dyn SqlTable, _query: &mut Query) -> Result<()> {
         Ok(())
     }
+    /// Called after a record is inserted, with its id (if the table has id column).
+    fn after_insert(&self, _table: &dyn SqlTable, _id: Option<&Value>) -> Result<()> {
+        Ok(())
+    }
+    /// Called after DELETE query is executed.
+    fn after_delete(&self, _table: &dyn SqlTable, _query: &Query) -> Result<()> {
+        Ok(())
+    }
+    /// Fields, which [`Record::save()`] should always include into UPDATE with
+    /// the value they were loaded with, even if unchanged.
+    ///
+    /// [`Record::save()`]: crate::sql::table::Record::save()
+    fn tracked_fields(&self) -> Vec<String> {
+        vec![]
+    }
+    /// Called after UPDATE query is executed, with number of updated rows and
+    /// the rows it has returned.
+    fn after_update_query(
+        &self,
+        _table: &dyn SqlTable,
+        _query: &Query,
+        _result: &ExecResult,
+    ) -> Result<()> {
+        Ok(())
+    }
+    /// Called by [`Table::restore()`] with an UPDATE query, which extension should
+    /// complete. Returns `true` if the extension has handled the query.
+    ///
+    /// [`Table::restore()`]: crate::sql::Table::restore()
+    fn before_restore_query(&self, _table: &dyn SqlTable, _query: &mut Query) -> Result<bool> {
+        Ok(false)
+    }
+    /// Returns copy of the extension, which uses a different scope for deleted
+    /// records. Extensions, which don't deal with deleted records, return `None`.
+    fn with_soft_delete_scope(&self, _scope: SoftDeleteScope) -> Option<Box<dyn TableExtension>> {
+        None
+    }
 }
 
 #[derive(Default)]
@@ -38,16 +108,88 @@ impl Hooks {
 
     pub fn before_select_query(&self, table: &dyn SqlTable, query: &mut Query) -> Result<()> {
         for hook in self.hooks.iter() {
-            hook.before_select_query(table, query).unwrap();
+            hook.before_select_query(table, query)?;
+        }
+        Ok(())
+    }
+    pub fn validate(&self, table: &dyn SqlTable, record: &Value) -> Result<()> {
+        for hook in self.hooks.iter() {
+            hook.validate(table, record)?;
+        }
+        Ok(())
+    }
+    pub fn before_insert_query(&self, table: &dyn SqlTable, query: &mut Query) -> Result<()> {
+        for hook in self.hooks.iter() {
+            hook.before_insert_query(table, query)?;
+        }
+        Ok(())
+    }
+    pub fn before_update_query(&self, table: &dyn SqlTable, query: &mut Query) -> Result<()> {
+        for hook in self.hooks.iter() {
+            hook.before_update_query(table, query)?;
         }
         Ok(())
     }
     pub fn before_delete_query(&self, table: &dyn SqlTable, query: &mut Query) -> Result<()> {
         for hook in self.hooks.iter() {
-            hook.before_delete_query(table, query).unwrap();
+            hook.before_delete_query(table, query)?;
+        }
+        Ok(())
+    }
+    pub fn after_insert(&self, table: &dyn SqlTable, id: Option<&Value>) -> Result<()> {
+        for hook in self.hooks.iter() {
+            hook.after_insert(table, id)?;
+        }
+        Ok(())
+    }
+    pub fn after_delete(&self, table: &dyn SqlTable, query: &Query) -> Result<()> {
+        for hook in self.hooks.iter() {
+            hook.after_delete(table, query)?;
         }
         Ok(())
     }
+    pub fn tracked_fields(&self) -> Vec<String> {
+        self.hooks
+            .iter()
+            .flat_map(|hook| hook.tracked_fields())
+            .collect()
+    }
+    pub fn after_update_query(
+        &self,
+        table: &dyn SqlTable,
+        query: &Query,
+        result: &ExecResult,
+    ) -> Result<()> {
+        for hook in self.hooks.iter() {
+            hook.after_update_query(table, query, result)?;
+        }
+        Ok(())
+    }
+    pub fn before_restore_query(&self, table: &dyn SqlTable, query: &mut Query) -> Result<bool> {
+        let mut handled = false;
+        for hook in self.hooks.iter() {
+            handled |= hook.before_restore_query(table, query)?;
+        }
+        Ok(handled)
+    }
+
+    /// Replace extensions with their copies using a different scope for deleted
+    /// records. Returns `None` if none of the extensions deal with deleted records.
+    pub fn with_soft_delete_scope(&self, scope: SoftDeleteScope) -> Option<Hooks> {
+        let mut found = false;
+        let hooks = self
+            .hooks
+            .iter()
+            .map(|hook| match hook.with_soft_delete_scope(scope) {
+                Some(hook) => {
+                    found = true;
+                    Arc::new(hook)
+                }
+                None => hook.clone(),
+            })
+            .collect();
+        found.then_some(Hooks { hooks })
+    }
 }
 
 // implement Debug for Hooks
@@ -65,4 +207,93 @@ impl Clone for Hooks {
     }
 }
 
+mod audit_log;
+mod optimistic_lock;
 mod soft_delete;
+mod tenant_scope;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use anyhow::anyhow;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        dataset::WritableDataSet, mocks::datasource::MockDataSource, prelude::Entity, sql::Table,
+    };
+
+    #[derive(Serialize, Deserialize, Clone, Default, Debug)]
+    struct User {
+        name: String,
+    }
+    impl Entity for User {}
+
+    #[derive(Debug, Default)]
+    struct Recorder {
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Recorder {
+        fn record(&self, call: &str) -> Result<()> {
+            self.calls.lock().unwrap().push(call.to_string());
+            Ok(())
+        }
+    }
+
+    impl TableExtension for Recorder {
+        fn validate(&self, _table: &dyn SqlTable, record: &Value) -> Result<()> {
+            if record["name"] == "" {
+                return Err(anyhow!("name is required"));
+            }
+            self.record("validate")
+        }
+        fn before_insert_query(&self, _table: &dyn SqlTable, _query: &mut Query) -> Result<()> {
+            self.record("before_insert_query")
+        }
+        fn after_insert(&self, _table: &dyn SqlTable, _id: Option<&Value>) -> Result<()> {
+            self.record("after_insert")
+        }
+        fn before_delete_query(&self, _table: &dyn SqlTable, _query: &mut Query) -> Result<()> {
+            self.record("before_delete_query")
+        }
+        fn after_delete(&self, _table: &dyn SqlTable, _query: &Query) -> Result<()> {
+            self.record("after_delete")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_hooks() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let users: Table<MockDataSource, User> =
+            Table::new_with_entity("users", MockDataSource::new(&json!([])))
+                .with_column("name")
+                .with_extension(Recorder {
+                    calls: calls.clone(),
+                });
+
+        users.insert(User::default()).await.unwrap_err();
+        assert!(calls.lock().unwrap().is_empty());
+
+        users
+            .insert(User {
+                name: "John".to_string(),
+            })
+            .await
+            .unwrap();
+        users.delete().await.unwrap();
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                "validate",
+                "before_insert_query",
+                "after_insert",
+                "before_delete_query",
+                "after_delete"
+            ]
+        );
+    }
+}
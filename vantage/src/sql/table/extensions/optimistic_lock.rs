@@ -0,0 +1,172 @@
+use anyhow::Result;
+
+use crate::{
+    expr_arc,
+    prelude::SqlTable,
+    sql::{
+        query::{QueryReturning, SqlQuery},
+        Chunk, Column, ExpressionArc, Operations, Query,
+    },
+    traits::datasource::ExecResult,
+};
+
+use super::TableExtension;
+
+/// Error returned when saving a record, which was changed by someone else
+/// since it was loaded. Use `downcast_ref()` to detect it:
+///
+/// ```
+/// if let Some(stale) = err.downcast_ref::<StaleRecord>() {
+///     // reload the record and ask user to re-apply the changes
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaleRecord;
+
+impl std::fmt::Display for StaleRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Record was modified by someone else, reload it and try again"
+        )
+    }
+}
+
+impl std::error::Error for StaleRecord {}
+
+/// Prevents concurrent edits from overwriting each other. Each record holds a
+/// version number, which is incremented on every update. Update only succeeds if
+/// the version is still the same as when the record was loaded:
+///
+/// ```
+/// let clients = Client::table().with_extension(OptimisticLock::new("version"));
+/// let mut client = clients.load(1.into()).await?;
+/// client.name = "Doc Brown".to_string();
+/// client.save().await?;
+/// // UPDATE client SET name = {}, version = version + 1
+/// //     WHERE (id = {}) AND (version = {}) RETURNING version
+/// ```
+///
+/// The entity must have the version field. If no record was updated, [`StaleRecord`]
+/// error is returned.
+#[derive(Debug, Clone)]
+pub struct OptimisticLock {
+    version_field: String,
+}
+
+impl OptimisticLock {
+    pub fn new(version_field: &str) -> Self {
+        OptimisticLock {
+            version_field: version_field.to_string(),
+        }
+    }
+}
+
+impl TableExtension for OptimisticLock {
+    fn init(&self, table: &mut dyn SqlTable) {
+        if table.get_column(&self.version_field).is_none() {
+            table.add_column(
+                self.version_field.clone(),
+                Column::new(self.version_field.clone(), None),
+            );
+        }
+    }
+
+    /// Replace version with an increment and only update the version we've loaded
+    fn before_update_query(&self, table: &dyn SqlTable, query: &mut Query) -> Result<()> {
+        let Some(version) = query.get_set_field(&self.version_field).cloned() else {
+            return Ok(());
+        };
+        let column = table.get_column(&self.version_field).unwrap();
+
+        query
+            .get_where_conditions_mut()
+            .add_condition(column.eq(&version).render_chunk());
+        query.set_field_expression(
+            &self.version_field,
+            expr_arc!("{} + 1", column).render_chunk(),
+        );
+        query.set_returning(QueryReturning::Fields(vec![self.version_field.clone()]));
+        Ok(())
+    }
+
+    fn tracked_fields(&self) -> Vec<String> {
+        vec![self.version_field.clone()]
+    }
+
+    fn after_update_query(
+        &self,
+        _table: &dyn SqlTable,
+        query: &Query,
+        result: &ExecResult,
+    ) -> Result<()> {
+        if query.get_set_field(&self.version_field).is_some() && result.rows_affected == 0 {
+            return Err(StaleRecord.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        mocks::datasource::MockDataSource,
+        prelude::{EmptyEntity, Entity},
+        sql::{table::AnyTable, Table},
+    };
+
+    #[derive(Serialize, Deserialize, Clone, Default, Debug)]
+    struct Client {
+        name: String,
+        version: i64,
+    }
+    impl Entity for Client {}
+
+    fn clients() -> Table<MockDataSource, Client> {
+        let data = json!([{ "id": 1, "name": "John", "version": 3 }]);
+        Table::new_with_entity("client", MockDataSource::new(&data))
+            .with_id_column("id")
+            .with_column("name")
+            .with_extension(OptimisticLock::new("version"))
+    }
+
+    #[test]
+    fn test_update_query() {
+        let query = clients()
+            .with_id(1.into())
+            .get_update_query(json!({"name": "Doc Brown", "version": 3}))
+            .unwrap();
+
+        assert_eq!(
+            query.preview(),
+            "UPDATE client SET name = 'Doc Brown', version = version + 1 \
+            WHERE (id = 1) AND (version = 3) RETURNING version"
+        );
+
+        let table: Table<MockDataSource, EmptyEntity> =
+            Table::new("client", MockDataSource::new(&json!([])))
+                .with_extension(OptimisticLock::new("version"));
+        assert!(AnyTable::get_column(&table, "version").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_stale_record() {
+        // mock data source never returns updated rows
+        let mut client = clients().load(1.into()).await.unwrap();
+        client.name = "Doc Brown".to_string();
+
+        let query = client.get_save_query().unwrap().unwrap();
+        assert_eq!(
+            query.preview(),
+            "UPDATE client SET name = 'Doc Brown', version = version + 1 \
+            WHERE (id = 1) AND (version = 3) RETURNING version"
+        );
+
+        let err = client.save().await.unwrap_err();
+        assert_eq!(err.downcast_ref::<StaleRecord>(), Some(&StaleRecord));
+    }
+}
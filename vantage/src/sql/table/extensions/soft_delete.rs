@@ -1,29 +1,58 @@
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use serde_json::json;
 
 use crate::{
-    prelude::SqlTable,
-    sql::{query::SqlQuery, Chunk, Column, Operations, Query},
+    prelude::{SqlTable, TableWithQueries},
+    sql::{
+        query::{QueryType, SqlQuery},
+        Chunk, Column, Operations, Query, Table,
+    },
+    traits::{datasource::DataSource, entity::Entity},
 };
 
 use super::TableExtension;
 
-#[derive(Debug)]
+/// Which records are visible in a table with [`SoftDelete`] extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SoftDeleteScope {
+    /// Only records which are not deleted
+    #[default]
+    WithoutTrashed,
+    /// Both deleted and not deleted records
+    WithTrashed,
+    /// Only deleted records
+    OnlyTrashed,
+}
+
+#[derive(Debug, Clone)]
 pub struct SoftDelete {
     soft_delete_field: String,
+    scope: SoftDeleteScope,
 }
 
 impl SoftDelete {
     pub fn new(soft_delete_field: &str) -> Self {
         SoftDelete {
             soft_delete_field: soft_delete_field.to_string(),
+            scope: SoftDeleteScope::default(),
         }
     }
     fn is_deleted(&self, table: &dyn SqlTable) -> Arc<Column> {
         table.get_column(&self.soft_delete_field).unwrap()
     }
+    /// Limit query to records visible in the current scope
+    fn add_scope_condition(&self, table: &dyn SqlTable, query: &mut Query) {
+        let is_deleted = match self.scope {
+            SoftDeleteScope::WithoutTrashed => false,
+            SoftDeleteScope::OnlyTrashed => true,
+            SoftDeleteScope::WithTrashed => return,
+        };
+        query
+            .get_where_conditions_mut()
+            .add_condition(self.is_deleted(table).eq(&is_deleted).render_chunk());
+    }
 }
 
 impl TableExtension for SoftDelete {
@@ -36,9 +65,12 @@ impl TableExtension for SoftDelete {
 
     /// When selecting records, exclude deleted records
     fn before_select_query(&self, table: &dyn SqlTable, query: &mut Query) -> Result<()> {
-        query
-            .get_where_conditions_mut()
-            .add_condition(self.is_deleted(table).eq(&false).render_chunk());
+        self.add_scope_condition(table, query);
+        Ok(())
+    }
+    /// When updating records, leave deleted records untouched
+    fn before_update_query(&self, table: &dyn SqlTable, query: &mut Query) -> Result<()> {
+        self.add_scope_condition(table, query);
         Ok(())
     }
     /// When deleting records, mark them as deleted instead
@@ -47,6 +79,71 @@ impl TableExtension for SoftDelete {
         query.set_field_value(&self.soft_delete_field, json!(true));
         Ok(())
     }
+    /// Restore deleted records by clearing the flag. Deleted records are picked
+    /// by [`before_update_query()`](TableExtension::before_update_query), as
+    /// [`Table::restore()`] uses [`OnlyTrashed`](SoftDeleteScope::OnlyTrashed) scope.
+    fn before_restore_query(&self, _table: &dyn SqlTable, query: &mut Query) -> Result<bool> {
+        query.set_field_value(&self.soft_delete_field, json!(false));
+        Ok(true)
+    }
+    fn with_soft_delete_scope(&self, scope: SoftDeleteScope) -> Option<Box<dyn TableExtension>> {
+        Some(Box::new(SoftDelete {
+            scope,
+            ..self.clone()
+        }))
+    }
+}
+
+impl<T: DataSource, E: Entity> Table<T, E> {
+    /// Include deleted records, hidden by the [`SoftDelete`] extension.
+    ///
+    /// Fails if table has no [`SoftDelete`] extension.
+    pub fn with_trashed(&self) -> Result<Self> {
+        self.with_soft_delete_scope(SoftDeleteScope::WithTrashed)
+    }
+
+    /// Only show records deleted through the [`SoftDelete`] extension:
+    ///
+    /// ```
+    /// let deleted_orders = Order::table().only_trashed()?;
+    /// ```
+    ///
+    /// Fails if table has no [`SoftDelete`] extension.
+    pub fn only_trashed(&self) -> Result<Self> {
+        self.with_soft_delete_scope(SoftDeleteScope::OnlyTrashed)
+    }
+
+    fn with_soft_delete_scope(&self, scope: SoftDeleteScope) -> Result<Self> {
+        let mut table = self.clone();
+        table.hooks = self
+            .hooks
+            .with_soft_delete_scope(scope)
+            .ok_or_else(|| anyhow!("Table {} has no SoftDelete extension", self))?;
+        Ok(table)
+    }
+
+    /// Undelete records of the table, which were deleted through the
+    /// [`SoftDelete`] extension:
+    ///
+    /// ```
+    /// Order::table().with_id(order_id).restore().await?;
+    /// ```
+    ///
+    /// Records are updated like with any other UPDATE, so conditions of the table
+    /// and other extensions (such as [`TenantScope`]) apply.
+    ///
+    /// [`TenantScope`]: super::TenantScope
+    pub async fn restore(&self) -> Result<()> {
+        self.ensure_writable()?;
+        let trashed = self.only_trashed()?;
+        let mut query = trashed.get_empty_query().with_type(QueryType::Update);
+        if !trashed.hooks.before_restore_query(&trashed, &mut query)? {
+            return Err(anyhow!("Table {} has no SoftDelete extension", self));
+        }
+        trashed.hooks.before_update_query(&trashed, &mut query)?;
+        let result = self.data_source.query_exec(&query).await?;
+        trashed.hooks.after_update_query(&trashed, &query, &result)
+    }
 }
 
 #[cfg(test)]
@@ -56,7 +153,10 @@ mod tests {
     use crate::{
         mocks::datasource::MockDataSource,
         prelude::{AnyTable, Chunk, Operations, TableWithQueries},
-        sql::{table::extensions::Hooks, Table},
+        sql::{
+            table::extensions::{Hooks, TenantScope},
+            Table,
+        },
     };
 
     #[tokio::test]
@@ -108,4 +208,53 @@ mod tests {
         );
         assert_eq!(query.1[0], json!(false));
     }
+
+    #[test]
+    fn test_scopes() {
+        let data = json!([]);
+        let table = Table::new("users", MockDataSource::new(&data))
+            .with_column("name")
+            .with_extension(SoftDelete::new("is_deleted"));
+
+        assert_eq!(
+            table.with_trashed().unwrap().get_select_query().preview(),
+            "SELECT name, is_deleted FROM users"
+        );
+        assert_eq!(
+            table.only_trashed().unwrap().get_select_query().preview(),
+            "SELECT name, is_deleted FROM users WHERE (is_deleted = true)"
+        );
+        assert_eq!(
+            table.get_select_query().preview(),
+            "SELECT name, is_deleted FROM users WHERE (is_deleted = false)"
+        );
+
+        let table = Table::new("users", MockDataSource::new(&data)).with_column("name");
+        assert!(table.with_trashed().is_err());
+        assert!(table.only_trashed().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restore() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+        let table = Table::new("users", db.clone())
+            .with_column("name")
+            .with_extension(SoftDelete::new("is_deleted"))
+            .with_extension(TenantScope::new("tenant_id", || json!(7)));
+
+        table.restore().await.unwrap();
+        assert_eq!(
+            db.calls()[0].sql,
+            "UPDATE users SET is_deleted = {} WHERE (is_deleted = {}) AND (tenant_id = {})"
+        );
+
+        // views can't be restored through
+        let view = table.into_view();
+        assert!(view.table().restore().await.is_err());
+        assert_eq!(db.calls().len(), 1);
+
+        let table = Table::new("users", MockDataSource::new(&data)).with_column("name");
+        assert!(table.restore().await.is_err());
+    }
 }
@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use tokio::task::LocalKey;
+
+use crate::{
+    prelude::SqlTable,
+    sql::{query::SqlQuery, Chunk, Column, Operations, Query},
+};
+
+use super::TableExtension;
+
+pub type TenantFx = dyn Fn() -> Result<Value> + Send + Sync;
+
+/// Limits table to the records of the current tenant. Condition is added to all
+/// select, update and delete queries, and the tenant is set for inserted records.
+///
+/// The current tenant is returned by a closure:
+///
+/// ```
+/// let clients = Client::table()
+///     .with_extension(TenantScope::new("tenant_id", || json!(config.tenant_id)));
+/// // SELECT .. FROM client WHERE (tenant_id = 42)
+/// ```
+///
+/// or is taken from a task-local variable, which is set for each request:
+///
+/// ```
+/// tokio::task_local! {
+///     pub static TENANT: Value;
+/// }
+///
+/// let clients = Client::table().with_extension(TenantScope::task_local("tenant_id", &TENANT));
+/// TENANT.scope(json!(42), async move { clients.get().await }).await?;
+/// ```
+#[derive(Clone)]
+pub struct TenantScope {
+    tenant_field: String,
+    tenant: Arc<Box<TenantFx>>,
+}
+
+impl TenantScope {
+    pub fn new(tenant_field: &str, tenant: impl Fn() -> Value + Send + Sync + 'static) -> Self {
+        TenantScope {
+            tenant_field: tenant_field.to_string(),
+            tenant: Arc::new(Box::new(move || Ok(tenant()))),
+        }
+    }
+
+    /// Read the tenant from a task-local variable. Queries fail if it is not set.
+    pub fn task_local(tenant_field: &str, key: &'static LocalKey<Value>) -> Self {
+        TenantScope {
+            tenant_field: tenant_field.to_string(),
+            tenant: Arc::new(Box::new(move || {
+                key.try_with(|tenant| tenant.clone())
+                    .map_err(|_| anyhow!("Current tenant is not set"))
+            })),
+        }
+    }
+
+    fn tenant_column(&self, table: &dyn SqlTable) -> Arc<Column> {
+        table.get_column(&self.tenant_field).unwrap()
+    }
+
+    fn add_tenant_condition(&self, table: &dyn SqlTable, query: &mut Query) -> Result<()> {
+        let tenant = (self.tenant)()?;
+        query
+            .get_where_conditions_mut()
+            .add_condition(self.tenant_column(table).eq(&tenant).render_chunk());
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for TenantScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TenantScope")
+            .field("tenant_field", &self.tenant_field)
+            .finish()
+    }
+}
+
+impl TableExtension for TenantScope {
+    fn init(&self, table: &mut dyn SqlTable) {
+        if table.get_column(&self.tenant_field).is_none() {
+            table.add_column(
+                self.tenant_field.clone(),
+                Column::new(self.tenant_field.clone(), None),
+            );
+        }
+    }
+
+    fn before_select_query(&self, table: &dyn SqlTable, query: &mut Query) -> Result<()> {
+        self.add_tenant_condition(table, query)
+    }
+    fn before_insert_query(&self, _table: &dyn SqlTable, query: &mut Query) -> Result<()> {
+        query.set_field_value(&self.tenant_field, (self.tenant)()?);
+        Ok(())
+    }
+    fn before_update_query(&self, table: &dyn SqlTable, query: &mut Query) -> Result<()> {
+        self.add_tenant_condition(table, query)
+    }
+    fn before_delete_query(&self, table: &dyn SqlTable, query: &mut Query) -> Result<()> {
+        self.add_tenant_condition(table, query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        dataset::ReadableDataSet,
+        mocks::datasource::MockDataSource,
+        prelude::{EmptyEntity, TableWithQueries},
+        sql::Table,
+    };
+
+    tokio::task_local! {
+        static TENANT: Value;
+    }
+
+    fn clients(scope: TenantScope) -> Table<MockDataSource, EmptyEntity> {
+        Table::new("client", MockDataSource::new(&json!([])))
+            .with_id_column("id")
+            .with_column("name")
+            .with_extension(scope)
+    }
+
+    #[test]
+    fn test_tenant_scope() {
+        let clients = clients(TenantScope::new("tenant_id", || json!(42)));
+
+        assert_eq!(
+            clients.get_select_query().preview(),
+            "SELECT id, name, tenant_id FROM client WHERE (tenant_id = 42)"
+        );
+        assert_eq!(
+            clients
+                .get_update_query(json!({"name": "John"}))
+                .unwrap()
+                .preview(),
+            "UPDATE client SET name = 'John' WHERE (tenant_id = 42)"
+        );
+        assert_eq!(
+            clients
+                .get_insert_batch_query(&[json!({"name": "John"}), json!({"name": "Jane"})])
+                .unwrap()
+                .preview(),
+            "INSERT INTO client (name, tenant_id) VALUES ('John', 42), ('Jane', 42) RETURNING id"
+        );
+        assert_eq!(
+            clients
+                .get_insert_batch_query(&[
+                    json!({"name": "John"}),
+                    json!({"name": "Jane", "tenant_id": 13})
+                ])
+                .unwrap()
+                .preview(),
+            "INSERT INTO client (name, tenant_id) VALUES ('John', 42), ('Jane', 42) RETURNING id"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_task_local() {
+        let clients = clients(TenantScope::task_local("tenant_id", &TENANT));
+
+        let query = TENANT
+            .scope(json!(7), async { clients.get_update_query(json!({})) })
+            .await
+            .unwrap();
+        assert_eq!(query.preview(), "UPDATE client SET  WHERE (tenant_id = 7)");
+
+        assert!(clients.get_update_query(json!({})).is_err());
+        assert!(clients.try_get_select_query().is_err());
+        assert!(clients.get_all_untyped().await.is_err());
+        assert!(ReadableDataSet::count(&clients).await.is_err());
+        assert!(clients.get().await.is_err());
+    }
+}
@@ -17,6 +17,7 @@ pub struct Join<T: DataSource> {
     // table: Table<T, E>,
     table: Table<T, EmptyEntity>,
     join_query: JoinQuery,
+    keys: Option<(String, String)>,
 }
 
 // impl<T: DataSource> Join<T> {
@@ -34,15 +35,24 @@ impl<T: DataSource> std::fmt::Debug for Join<T> {
             .field("table", &self.table.get_table_name())
             .field("fields", &self.table.get_columns())
             .field("join_query", &self.join_query)
+            .field("keys", &self.keys)
             .finish()
     }
 }
 
 impl<T: DataSource> Join<T> {
-    pub fn new(table: Table<T, EmptyEntity>, join_query: JoinQuery) -> Self {
+    pub fn new(
+        table: Table<T, EmptyEntity>,
+        join_query: JoinQuery,
+        keys: Option<(String, String)>,
+    ) -> Self {
         // Related table should have alias
 
-        Join { table, join_query }
+        Join {
+            table,
+            join_query,
+            keys,
+        }
     }
     pub fn alias(&self) -> &str {
         self.table.get_alias().unwrap()
@@ -50,6 +60,13 @@ impl<T: DataSource> Join<T> {
     pub fn join_query(&self) -> &JoinQuery {
         &self.join_query
     }
+    /// Columns of the primary and the joined table, which are equal in the join
+    /// condition. None if tables are joined on a custom condition.
+    pub fn keys(&self) -> Option<(&str, &str)> {
+        self.keys
+            .as_ref()
+            .map(|(ours, theirs)| (ours.as_str(), theirs.as_str()))
+    }
     pub fn table(&self) -> &Table<T, EmptyEntity> {
         &self.table
     }
@@ -0,0 +1,28 @@
+/// Operation of a REST API for records of a table. Used by
+/// `vantage::web::CrudRouter` and `OpenApi`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// `GET /orders?page=0&per_page=10&sort=-id`
+    List,
+    /// `GET /orders/{id}`
+    Get,
+    /// `POST /orders`
+    Create,
+    /// `PATCH /orders/{id}`
+    Update,
+    /// `DELETE /orders/{id}`
+    Delete,
+}
+
+impl Operation {
+    /// Operations, which don't modify records
+    pub const READ: [Operation; 2] = [Operation::List, Operation::Get];
+
+    pub const ALL: [Operation; 5] = [
+        Operation::List,
+        Operation::Get,
+        Operation::Create,
+        Operation::Update,
+        Operation::Delete,
+    ];
+}
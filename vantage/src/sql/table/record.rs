@@ -0,0 +1,299 @@
+use std::ops::{Deref, DerefMut};
+
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+
+use crate::dataset::hydrate::from_row;
+use crate::sql::Query;
+use crate::traits::datasource::{DataSource, ExecResult};
+use crate::traits::entity::Entity;
+use crate::Error;
+
+use super::{Table, TableWithColumns};
+
+/// A single record loaded from a [`Table`], which remembers the values it was loaded
+/// with. Record dereferences into the entity, so you can modify it directly and then
+/// call [`save()`] to store only the changed columns:
+///
+/// ```
+/// let mut client = Client::table().load(1.into()).await?;
+/// client.name = "Doc Brown".to_string();
+/// client.save().await?;   // UPDATE client SET name = {} WHERE (id = {})
+/// ```
+///
+/// [`save()`]: Record::save()
+#[derive(Debug, Clone)]
+pub struct Record<T: DataSource, E: Entity> {
+    table: Table<T, E>,
+    original: Map<String, Value>,
+    entity: E,
+}
+
+impl<T: DataSource, E: Entity> Record<T, E> {
+    /// Wraps entity into a record. Table should already be scoped to the record
+    /// (e.g. with [`Table::with_id()`]), as it will be used for updating.
+    pub fn new(table: Table<T, E>, entity: E) -> Result<Self> {
+        let original = Self::entity_to_map(&entity)?;
+        Ok(Record {
+            table,
+            original,
+            entity,
+        })
+    }
+
+    fn entity_to_map(entity: &E) -> Result<Map<String, Value>> {
+        let Value::Object(map) = serde_json::to_value(entity)? else {
+            return Err(anyhow!("Entity must serialize into a struct"));
+        };
+        Ok(map)
+    }
+
+    /// Returns names of the table columns, that were modified since the record
+    /// was loaded or last saved. Id column is never reported as dirty.
+    pub fn dirty_fields(&self) -> Result<Vec<String>> {
+        let current = Self::entity_to_map(&self.entity)?;
+        let id_column = self.table.id_column.clone().unwrap_or("id".to_string());
+
+        Ok(current
+            .into_iter()
+            .filter(|(field, value)| {
+                field != &id_column
+                    && self.table.columns().contains_key(field)
+                    && self.original.get(field) != Some(value)
+            })
+            .map(|(field, _)| field)
+            .collect())
+    }
+
+    pub fn is_dirty(&self) -> Result<bool> {
+        Ok(!self.dirty_fields()?.is_empty())
+    }
+
+    /// Builds an UPDATE query containing only the modified columns. Returns `None`
+    /// if nothing has changed.
+    pub fn get_save_query(&self) -> Result<Option<Query>> {
+        let dirty_fields = self.dirty_fields()?;
+        if dirty_fields.is_empty() {
+            return Ok(None);
+        }
+
+        let current = Self::entity_to_map(&self.entity)?;
+        let mut changes = current
+            .into_iter()
+            .filter(|(field, _)| dirty_fields.contains(field))
+            .collect::<Map<String, Value>>();
+
+        // extensions may require some fields with their loaded values
+        for field in self.table.hooks.tracked_fields() {
+            if let Some(value) = self.original.get(&field) {
+                changes.insert(field, value.clone());
+            }
+        }
+
+        Ok(Some(self.table.get_update_query(changes)?))
+    }
+
+    /// Stores modified columns in the database. If nothing was changed,
+    /// no query is executed.
+    pub async fn save(&mut self) -> Result<ExecResult> {
+        let Some(query) = self.get_save_query()? else {
+            return Ok(ExecResult::default());
+        };
+        self.table.validate(&self.entity)?;
+        let result = self.table.data_source.query_exec(&query).await?;
+        self.table
+            .hooks
+            .after_update_query(&self.table, &query, &result)?;
+
+        // Update entity with the values generated by the database
+        if let Some(returned) = result.row() {
+            let mut current = Self::entity_to_map(&self.entity)?;
+            for (field, value) in returned.clone() {
+                if current.contains_key(&field) {
+                    current.insert(field, value);
+                }
+            }
+            self.entity = serde_json::from_value(Value::Object(current))?;
+        }
+        self.original = Self::entity_to_map(&self.entity)?;
+        Ok(result)
+    }
+
+    /// Returns table, that is scoped to this record only.
+    pub fn table(&self) -> &Table<T, E> {
+        &self.table
+    }
+
+    /// Discards the tracking and returns the entity.
+    pub fn into_entity(self) -> E {
+        self.entity
+    }
+}
+
+impl<T: DataSource, E: Entity> Deref for Record<T, E> {
+    type Target = E;
+
+    fn deref(&self) -> &Self::Target {
+        &self.entity
+    }
+}
+
+impl<T: DataSource, E: Entity> DerefMut for Record<T, E> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.entity
+    }
+}
+
+impl<T: DataSource, E: Entity> Table<T, E> {
+    /// Loads a single record by its id. The returned [`Record`] tracks changes
+    /// and can be saved back with [`Record::save()`]. Use [`Record::into_entity()`]
+    /// if you only need the entity. If there is no such record,
+    /// [`Error::NotFound`] is returned:
+    ///
+    /// ```
+    /// let client = Client::table().load(1.into()).await?.into_entity();
+    /// ```
+    pub async fn load(&self, id: Value) -> Result<Record<T, E>> {
+        match self.try_load(id.clone()).await? {
+            Some(record) => Ok(record),
+            None => Err(Error::NotFound {
+                table: self.table_name.clone(),
+                id,
+            }
+            .into()),
+        }
+    }
+
+    /// Same as [`load()`], but returns `None` if there is no record with such id.
+    /// The record is fetched the same way as with [`get_some_as()`], so nested
+    /// structs and preloaded references are filled in and fields of the entity,
+    /// which can't be selected, result in [`Error::UnmatchedFields`].
+    ///
+    /// [`load()`]: Table::load()
+    /// [`get_some_as()`]: crate::dataset::ReadableDataSet::get_some_as()
+    pub async fn try_load(&self, id: Value) -> Result<Option<Record<T, E>>> {
+        let table = self.clone().with_id(id);
+        let query = table
+            .try_get_select_query_for_struct(E::default())?
+            .with_limit(1);
+        let Some(row) = table.fetch_hydrated(query).await?.into_iter().next() else {
+            return Ok(None);
+        };
+        let entity = from_row(row, 0)?;
+        Ok(Some(Record::new(table, entity)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{mocks::datasource::MockDataSource, prelude::Chunk};
+
+    #[derive(Serialize, Deserialize, Clone, Default, Debug)]
+    struct User {
+        id: i64,
+        name: String,
+        surname: String,
+    }
+    impl Entity for User {}
+
+    fn user_table() -> Table<MockDataSource, User> {
+        let data = json!([{ "id": 1, "name": "John", "surname": "Doe"}]);
+        Table::new_with_entity("users", MockDataSource::new(&data))
+            .with_id_column("id")
+            .with_column("name")
+            .with_column("surname")
+    }
+
+    #[tokio::test]
+    async fn test_load_and_save() {
+        let mut user = user_table().load(json!(1)).await.unwrap();
+
+        assert_eq!(user.name, "John");
+        assert!(!user.is_dirty().unwrap());
+        assert!(user.get_save_query().unwrap().is_none());
+
+        user.name = "Jane".to_string();
+
+        assert_eq!(user.dirty_fields().unwrap(), vec!["name".to_string()]);
+
+        let query = user
+            .get_save_query()
+            .unwrap()
+            .unwrap()
+            .render_chunk()
+            .split();
+        assert_eq!(query.0, "UPDATE users SET name = {} WHERE (id = {})");
+        assert_eq!(query.1, vec![json!("Jane"), json!(1)]);
+
+        user.save().await.unwrap();
+        assert!(!user.is_dirty().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_load_nested() {
+        #[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+        struct Inventory {
+            stock: i64,
+        }
+        #[derive(Serialize, Deserialize, Clone, Default, Debug)]
+        struct Product {
+            name: String,
+            inventory: Option<Inventory>,
+        }
+        impl Entity for Product {}
+
+        let db = MockDataSource::new(&json!([{ "name": "Cake", "i_stock": 5 }]));
+        let products = Table::<_, Product>::new_with_entity("product", db.clone())
+            .with_alias("p")
+            .with_id_column("id")
+            .with_column("name")
+            .with_join::<Product, _>(
+                Table::new("inventory", db.clone())
+                    .with_alias("i")
+                    .with_id_column("product_id")
+                    .with_column("stock"),
+                "id",
+            );
+
+        let product = products.load(json!(1)).await.unwrap();
+        assert_eq!(product.inventory, Some(Inventory { stock: 5 }));
+        assert_eq!(
+            db.calls()[0].sql,
+            "SELECT p.name, i.product_id AS i_product_id, i.stock AS i_stock \
+            FROM product AS p LEFT JOIN inventory AS i ON (p.id = i.product_id) \
+            WHERE (p.id = {}) LIMIT {}::int4"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_unmatched() {
+        let table = Table::new_with_entity("users", MockDataSource::new(&json!([])))
+            .with_id_column("id")
+            .with_column("name");
+        let user: Result<Record<_, User>> = table.load(json!(1)).await;
+        assert!(matches!(
+            user.unwrap_err().downcast_ref::<Error>(),
+            Some(Error::UnmatchedFields { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_load_missing() {
+        let table = Table::new_with_entity("users", MockDataSource::new(&json!([])))
+            .with_id_column("id")
+            .with_column("name")
+            .with_column("surname");
+
+        let user: Result<Record<_, User>> = table.load(json!(1)).await;
+        assert_eq!(
+            user.unwrap_err().to_string(),
+            "Record with id=1 not found in 'users'"
+        );
+        assert!(table.try_load(json!(1)).await.unwrap().is_none());
+    }
+}
@@ -1,12 +1,15 @@
 use std::sync::Arc;
 
-use super::{RelatedSqlTable, RelatedTableFx};
-use crate::{prelude::SqlTable, sql::Operations};
+use super::{ReferenceKeys, RelatedSqlTable, RelatedTableFx, SubqueryStrategy};
+use crate::expr;
+use crate::prelude::SqlTable;
+use crate::sql::{Chunk, Condition, Expression, Operations};
 
 #[derive(Clone)]
 pub struct ReferenceMany {
     target_foreign_key: String,
     get_table: Arc<Box<RelatedTableFx>>,
+    strategy: SubqueryStrategy,
 }
 
 impl ReferenceMany {
@@ -17,14 +20,21 @@ impl ReferenceMany {
         ReferenceMany {
             target_foreign_key: foreign_key.to_string(),
             get_table: Arc::new(Box::new(get_table)),
+            strategy: SubqueryStrategy::default(),
         }
     }
+
+    pub fn with_strategy(mut self, strategy: SubqueryStrategy) -> ReferenceMany {
+        self.strategy = strategy;
+        self
+    }
 }
 
 impl std::fmt::Debug for ReferenceMany {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ReferenceMany")
             .field("foreign_key", &self.target_foreign_key)
+            .field("strategy", &self.strategy)
             .finish()
     }
 }
@@ -32,6 +42,20 @@ impl std::fmt::Debug for ReferenceMany {
 impl RelatedSqlTable for ReferenceMany {
     fn get_related_set(&self, table: &dyn SqlTable) -> Box<dyn SqlTable> {
         let mut target = (self.get_table)();
+        if self.strategy == SubqueryStrategy::Exists {
+            let matching = table
+                .get_select_query_for_field(Box::new(expr!("1")))
+                .with_where_condition(
+                    table
+                        .id_with_table_alias()
+                        .eq(&target
+                            .get_column_with_table_alias(&self.target_foreign_key)
+                            .unwrap())
+                        .render_chunk(),
+                );
+            target.add_condition(Condition::exists(&matching));
+            return target;
+        }
         let target_field = target.get_column(&self.target_foreign_key).unwrap();
         let id_set = table.get_select_query_for_field(Box::new(table.id()));
         target.add_condition(target_field.in_expr(&id_set));
@@ -46,6 +70,18 @@ impl RelatedSqlTable for ReferenceMany {
         target.add_condition(target_field.eq(&table.id_with_table_alias()));
         target
     }
+
+    fn with_strategy(&self, strategy: SubqueryStrategy) -> Option<Box<dyn RelatedSqlTable>> {
+        Some(Box::new(self.clone().with_strategy(strategy)))
+    }
+
+    fn get_keys(&self, table: &dyn SqlTable) -> Option<ReferenceKeys> {
+        Some(ReferenceKeys {
+            our_key: table.id().name(),
+            their_key: self.target_foreign_key.clone(),
+            many: true,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -82,6 +118,16 @@ mod tests {
             "SELECT id, user_id, order_ref FROM orders WHERE (user_id IN (SELECT id FROM users))"
         );
 
+        let reference = reference.with_strategy(SubqueryStrategy::Exists);
+        assert_eq!(
+            reference
+                .get_related_set(&users)
+                .get_select_query()
+                .preview(),
+            "SELECT id, user_id, order_ref FROM orders \
+            WHERE (EXISTS (SELECT (1) FROM users WHERE (users.id = orders.user_id)))"
+        );
+
         let target = reference.get_linked_set(&users);
 
         assert_eq!(
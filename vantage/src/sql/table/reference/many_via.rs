@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use super::{RelatedSqlTable, RelatedTableFx};
+use crate::prelude::{Column, SqlTable};
+use crate::sql::{Operations, Query};
+
+/// Many-to-many reference, which traverses a junction table. For
+/// `product_tag(product_id, tag_id)` the tags of a product are:
+///
+/// ```sql
+/// SELECT .. FROM tag WHERE (id IN (SELECT tag_id FROM product_tag WHERE (product_id IN (..))))
+/// ```
+#[derive(Clone)]
+pub struct ReferenceManyVia {
+    junction_table: String,
+    our_key: String,
+    their_key: String,
+    get_table: Arc<Box<RelatedTableFx>>,
+}
+
+impl ReferenceManyVia {
+    pub fn new(
+        junction_table: &str,
+        our_key: &str,
+        their_key: &str,
+        get_table: impl Fn() -> Box<dyn SqlTable> + Send + Sync + 'static,
+    ) -> ReferenceManyVia {
+        ReferenceManyVia {
+            junction_table: junction_table.to_string(),
+            our_key: our_key.to_string(),
+            their_key: their_key.to_string(),
+            get_table: Arc::new(Box::new(get_table)),
+        }
+    }
+
+    fn junction_query(&self) -> Query {
+        Query::new()
+            .with_table(&self.junction_table, None)
+            .with_column_field(&self.their_key)
+    }
+}
+
+impl std::fmt::Debug for ReferenceManyVia {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReferenceManyVia")
+            .field("junction_table", &self.junction_table)
+            .field("our_key", &self.our_key)
+            .field("their_key", &self.their_key)
+            .finish()
+    }
+}
+
+impl RelatedSqlTable for ReferenceManyVia {
+    fn get_related_set(&self, table: &dyn SqlTable) -> Box<dyn SqlTable> {
+        let mut target = (self.get_table)();
+        let id_set = table.get_select_query_for_field(Box::new(table.id()));
+        let our_key = Arc::new(Column::new(self.our_key.clone(), None));
+        let their_set = self
+            .junction_query()
+            .with_condition(our_key.in_expr(&id_set));
+        target.add_condition(target.id().in_expr(&their_set));
+        target
+    }
+
+    fn get_linked_set(&self, table: &dyn SqlTable) -> Box<dyn SqlTable> {
+        let mut target = (self.get_table)();
+        let our_key = Arc::new(Column::new(
+            self.our_key.clone(),
+            Some(self.junction_table.clone()),
+        ));
+        let their_set = self
+            .junction_query()
+            .with_condition(our_key.eq(&table.id_with_table_alias()));
+        target.add_condition(target.id_with_table_alias().in_expr(&their_set));
+        target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::mocks::datasource::MockDataSource;
+    use crate::sql::Table;
+
+    #[test]
+    fn test_related_reference() {
+        let data_source = MockDataSource::new(&json!([]));
+
+        let products = Table::new("product", data_source.clone())
+            .with_id_column("id")
+            .with_title_column("name");
+
+        let tags = Table::new("tag", data_source.clone())
+            .with_id_column("id")
+            .with_title_column("name");
+
+        let reference = ReferenceManyVia::new("product_tag", "product_id", "tag_id", move || {
+            Box::new(tags.clone())
+        });
+
+        let target = reference.get_related_set(&products);
+
+        assert_eq!(
+            target.get_select_query().preview(),
+            "SELECT id, name FROM tag WHERE (id IN (SELECT tag_id FROM product_tag \
+            WHERE (product_id IN (SELECT id FROM product))))"
+        );
+
+        let target = reference.get_linked_set(&products);
+
+        assert_eq!(
+            target.get_select_query().preview(),
+            "SELECT id, name FROM tag WHERE (tag.id IN (SELECT tag_id FROM product_tag \
+            WHERE (product_tag.product_id = product.id)))"
+        );
+    }
+}
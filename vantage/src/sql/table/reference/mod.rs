@@ -1,12 +1,49 @@
 pub mod many;
+pub mod many_via;
 pub mod one;
+pub mod polymorphic;
 
 use super::SqlTable;
 use std::fmt::Debug;
 
 pub type RelatedTableFx = dyn Fn() -> Box<dyn SqlTable> + Send + Sync + 'static;
 
+/// Columns linking records of two tables. Value of `our_key` in our record
+/// matches `their_key` of related records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferenceKeys {
+    pub our_key: String,
+    pub their_key: String,
+    pub many: bool,
+}
+
+/// How [`RelatedSqlTable::get_related_set()`] limits related records to the ones
+/// matching our table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubqueryStrategy {
+    /// `WHERE user_id IN (SELECT id FROM users)`
+    #[default]
+    In,
+    /// `WHERE EXISTS (SELECT 1 FROM users WHERE users.id = orders.user_id)`, which
+    /// Postgres often handles better when the subset is large.
+    Exists,
+}
+
 pub trait RelatedSqlTable: Debug + Send + Sync {
     fn get_related_set(&self, _table: &dyn SqlTable) -> Box<dyn SqlTable>;
     fn get_linked_set(&self, _table: &dyn SqlTable) -> Box<dyn SqlTable>;
+
+    /// Returns keys for matching related records, if the reference can be
+    /// preloaded (see [`Table::with_preload()`]).
+    ///
+    /// [`Table::with_preload()`]: crate::sql::Table::with_preload()
+    fn get_keys(&self, _table: &dyn SqlTable) -> Option<ReferenceKeys> {
+        None
+    }
+
+    /// Returns a copy of the reference using a different strategy, if the
+    /// reference supports it.
+    fn with_strategy(&self, _strategy: SubqueryStrategy) -> Option<Box<dyn RelatedSqlTable>> {
+        None
+    }
 }
@@ -1,12 +1,15 @@
 use std::sync::Arc;
 
-use super::{RelatedSqlTable, RelatedTableFx};
-use crate::{prelude::SqlTable, sql::Operations};
+use super::{ReferenceKeys, RelatedSqlTable, RelatedTableFx, SubqueryStrategy};
+use crate::expr;
+use crate::prelude::SqlTable;
+use crate::sql::{Chunk, Condition, Expression, Operations};
 
 #[derive(Clone)]
 pub struct ReferenceOne {
     our_foreign_key: String,
     get_table: Arc<Box<RelatedTableFx>>,
+    strategy: SubqueryStrategy,
 }
 
 impl ReferenceOne {
@@ -17,14 +20,21 @@ impl ReferenceOne {
         ReferenceOne {
             our_foreign_key: our_foreign_key.to_string(),
             get_table: Arc::new(Box::new(get_table)),
+            strategy: SubqueryStrategy::default(),
         }
     }
+
+    pub fn with_strategy(mut self, strategy: SubqueryStrategy) -> ReferenceOne {
+        self.strategy = strategy;
+        self
+    }
 }
 
 impl std::fmt::Debug for ReferenceOne {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ReferenceOne")
             .field("foreign_key", &self.our_foreign_key)
+            .field("strategy", &self.strategy)
             .finish()
     }
 }
@@ -32,6 +42,19 @@ impl std::fmt::Debug for ReferenceOne {
 impl RelatedSqlTable for ReferenceOne {
     fn get_related_set(&self, table: &dyn SqlTable) -> Box<dyn SqlTable> {
         let mut target = (self.get_table)();
+        if self.strategy == SubqueryStrategy::Exists {
+            let matching = table
+                .get_select_query_for_field(Box::new(expr!("1")))
+                .with_where_condition(
+                    table
+                        .get_column_with_table_alias(self.our_foreign_key.as_str())
+                        .unwrap()
+                        .eq(&target.id_with_table_alias())
+                        .render_chunk(),
+                );
+            target.add_condition(Condition::exists(&matching));
+            return target;
+        }
         let target_field = target.id();
         let id_set = table.get_select_query_for_field(Box::new(
             table.get_column(self.our_foreign_key.as_str()).unwrap(),
@@ -50,6 +73,18 @@ impl RelatedSqlTable for ReferenceOne {
         );
         target
     }
+
+    fn with_strategy(&self, strategy: SubqueryStrategy) -> Option<Box<dyn RelatedSqlTable>> {
+        Some(Box::new(self.clone().with_strategy(strategy)))
+    }
+
+    fn get_keys(&self, _table: &dyn SqlTable) -> Option<ReferenceKeys> {
+        Some(ReferenceKeys {
+            our_key: self.our_foreign_key.clone(),
+            their_key: (self.get_table)().id().name(),
+            many: false,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -84,6 +119,16 @@ mod tests {
             "SELECT id, name FROM roles WHERE (id IN (SELECT role_id FROM users))"
         );
 
+        let reference = reference.with_strategy(SubqueryStrategy::Exists);
+        assert_eq!(
+            reference
+                .get_related_set(&users)
+                .get_select_query()
+                .preview(),
+            "SELECT id, name FROM roles \
+            WHERE (EXISTS (SELECT (1) FROM users WHERE (users.role_id = roles.id)))"
+        );
+
         let target = reference.get_linked_set(&users);
 
         assert_eq!(
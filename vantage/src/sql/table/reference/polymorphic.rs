@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use indexmap::IndexMap;
+
+use crate::prelude::SqlTable;
+use crate::sql::Operations;
+
+pub type PolymorphicTableFx = fn() -> Box<dyn SqlTable>;
+
+/// Reference, where the related table is determined by a type column. For
+/// `notification(subject_type, subject_id)` a subject could be an order or an
+/// invoice:
+///
+/// ```sql
+/// SELECT .. FROM order WHERE (id IN (SELECT subject_id FROM notification WHERE (subject_type = 'order')))
+/// ```
+#[derive(Clone)]
+pub struct ReferencePolymorphic {
+    type_column: String,
+    id_column: String,
+    variants: IndexMap<String, PolymorphicTableFx>,
+}
+
+impl ReferencePolymorphic {
+    pub fn new(
+        type_column: &str,
+        id_column: &str,
+        variants: &[(&str, PolymorphicTableFx)],
+    ) -> ReferencePolymorphic {
+        ReferencePolymorphic {
+            type_column: type_column.to_string(),
+            id_column: id_column.to_string(),
+            variants: variants
+                .iter()
+                .map(|(name, get_table)| (name.to_string(), *get_table))
+                .collect(),
+        }
+    }
+
+    /// Returns values of the type column, which this reference recognizes.
+    pub fn variants(&self) -> Vec<&String> {
+        self.variants.keys().collect()
+    }
+
+    fn get_variant_table(&self, variant: &str) -> Result<Box<dyn SqlTable>> {
+        let get_table = self
+            .variants
+            .get(variant)
+            .ok_or_else(|| anyhow!("Unknown variant '{}' of polymorphic reference", variant))?;
+        Ok(get_table())
+    }
+
+    /// Returns records of the `variant` table, referenced by any of the records
+    /// of `table`, that have this variant.
+    pub fn get_related_set(
+        &self,
+        table: &dyn SqlTable,
+        variant: &str,
+    ) -> Result<Box<dyn SqlTable>> {
+        let mut target = self.get_variant_table(variant)?;
+        let type_column = table
+            .get_column(&self.type_column)
+            .ok_or_else(|| anyhow!("Type column '{}' not found", self.type_column))?;
+        let id_column = table
+            .get_column(&self.id_column)
+            .ok_or_else(|| anyhow!("Id column '{}' not found", self.id_column))?;
+
+        let id_set = table
+            .get_select_query_for_field(Box::new(id_column))
+            .with_condition(type_column.eq(&variant.to_string()));
+        target.add_condition(target.id().in_expr(&id_set));
+        Ok(target)
+    }
+
+    /// Returns `variant` table linked to the record of `table` for use in subqueries.
+    pub fn get_linked_set(&self, table: &dyn SqlTable, variant: &str) -> Result<Box<dyn SqlTable>> {
+        let mut target = self.get_variant_table(variant)?;
+        let type_column = table
+            .get_column_with_table_alias(&self.type_column)
+            .ok_or_else(|| anyhow!("Type column '{}' not found", self.type_column))?;
+        let id_column = table
+            .get_column_with_table_alias(&self.id_column)
+            .ok_or_else(|| anyhow!("Id column '{}' not found", self.id_column))?;
+
+        target.add_condition(target.id_with_table_alias().eq(&id_column));
+        target.add_condition(type_column.eq(&variant.to_string()));
+        Ok(target)
+    }
+}
+
+impl std::fmt::Debug for ReferencePolymorphic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReferencePolymorphic")
+            .field("type_column", &self.type_column)
+            .field("id_column", &self.id_column)
+            .field("variants", &self.variants())
+            .finish()
+    }
+}
+
+pub type PolymorphicRefs = IndexMap<String, Arc<ReferencePolymorphic>>;
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::mocks::datasource::MockDataSource;
+    use crate::sql::Table;
+
+    fn order() -> Box<dyn SqlTable> {
+        Box::new(
+            Table::new("orders", MockDataSource::new(&json!([])))
+                .with_id_column("id")
+                .with_column("total"),
+        )
+    }
+
+    fn invoice() -> Box<dyn SqlTable> {
+        Box::new(
+            Table::new("invoices", MockDataSource::new(&json!([])))
+                .with_id_column("id")
+                .with_column("amount"),
+        )
+    }
+
+    #[test]
+    fn test_polymorphic_reference() {
+        let notifications = Table::new("notifications", MockDataSource::new(&json!([])))
+            .with_id_column("id")
+            .with_column("subject_type")
+            .with_column("subject_id");
+
+        let reference = ReferencePolymorphic::new(
+            "subject_type",
+            "subject_id",
+            &[("order", order), ("invoice", invoice)],
+        );
+
+        let target = reference
+            .get_related_set(&notifications, "invoice")
+            .unwrap();
+        assert_eq!(
+            target.get_select_query().preview(),
+            "SELECT id, amount FROM invoices WHERE (id IN \
+            (SELECT subject_id FROM notifications WHERE (subject_type = 'invoice')))"
+        );
+
+        let target = reference.get_linked_set(&notifications, "order").unwrap();
+        assert_eq!(
+            target.get_select_query().preview(),
+            "SELECT id, total FROM orders WHERE (orders.id = notifications.subject_id) \
+            AND (notifications.subject_type = 'order')"
+        );
+
+        assert!(reference.get_related_set(&notifications, "refund").is_err());
+    }
+}
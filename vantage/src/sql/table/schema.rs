@@ -0,0 +1,129 @@
+use crate::sql::table::{Column, Table, TableWithColumns};
+use crate::sql::SqlType;
+use crate::traits::datasource::DataSource;
+use crate::traits::entity::EmptyEntity;
+
+/// Description of a physical table, as reported by the database. See
+/// [`Postgres::introspect_table()`].
+///
+/// [`Postgres::introspect_table()`]: crate::prelude::Postgres::introspect_table
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<ColumnSchema>,
+    pub primary_key: Vec<String>,
+    pub foreign_keys: Vec<ForeignKeySchema>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKeySchema {
+    pub column: String,
+    pub foreign_table: String,
+    pub foreign_column: String,
+}
+
+impl TableSchema {
+    pub fn new(name: &str) -> Self {
+        TableSchema {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn get_column(&self, name: &str) -> Option<&ColumnSchema> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    pub fn get_foreign_key(&self, column: &str) -> Option<&ForeignKeySchema> {
+        self.foreign_keys.iter().find(|fk| fk.column == column)
+    }
+}
+
+impl<T: DataSource> Table<T, EmptyEntity> {
+    /// Create a table with all the columns described by the schema. If the table has
+    /// a single-column primary key, it will be used as the id column. Columns with
+    /// a known [`SqlType`] will be typed.
+    ///
+    /// ```
+    /// let schema = postgres().introspect_table("product").await?;
+    /// let products = Table::from_introspection(&schema, postgres())
+    ///     .into_entity::<Product>();
+    /// ```
+    pub fn from_introspection(schema: &TableSchema, data_source: T) -> Self {
+        let id_column = match schema.primary_key.as_slice() {
+            [id] => Some(id.clone()),
+            _ => None,
+        };
+
+        let mut table = Table::new(&schema.name, data_source);
+        for column in &schema.columns {
+            if Some(&column.name) == id_column.as_ref() {
+                table.id_column = Some(column.name.clone());
+            }
+            let mut col = Column::new(column.name.clone(), None);
+            if let Some(sql_type) = SqlType::from_name(&column.data_type) {
+                col = col.with_type(sql_type);
+            }
+            table.add_column(column.name.clone(), col);
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{mocks::datasource::MockDataSource, prelude::TableWithQueries};
+
+    #[test]
+    fn test_from_introspection() {
+        let schema = TableSchema {
+            name: "product".to_string(),
+            columns: vec![
+                ColumnSchema {
+                    name: "id".to_string(),
+                    data_type: "integer".to_string(),
+                    nullable: false,
+                },
+                ColumnSchema {
+                    name: "name".to_string(),
+                    data_type: "text".to_string(),
+                    nullable: false,
+                },
+                ColumnSchema {
+                    name: "bakery_id".to_string(),
+                    data_type: "integer".to_string(),
+                    nullable: true,
+                },
+            ],
+            primary_key: vec!["id".to_string()],
+            foreign_keys: vec![ForeignKeySchema {
+                column: "bakery_id".to_string(),
+                foreign_table: "bakery".to_string(),
+                foreign_column: "id".to_string(),
+            }],
+        };
+
+        let products = Table::from_introspection(&schema, MockDataSource::new(&json!([])));
+
+        assert_eq!(
+            products.get_select_query().preview(),
+            "SELECT id, name, bakery_id FROM product"
+        );
+        assert_eq!(products.id().name(), "id");
+        assert_eq!(products.id().sql_type(), Some(SqlType::Int4));
+        assert_eq!(
+            schema.get_foreign_key("bakery_id").unwrap().foreign_table,
+            "bakery"
+        );
+    }
+}
@@ -0,0 +1,343 @@
+//! Validation of records before they are inserted or saved.
+//!
+//! Validators are added to a [`Table`] and are executed by [`insert()`],
+//! [`insert_batch()`] and [`Record::save()`]. If any of the validators fail, the
+//! operation is aborted with [`ValidationErrors`]:
+//!
+//! ```
+//! let products = Product::table().with_validator(
+//!     Rules::new()
+//!         .required("name")
+//!         .length("name", 1, 100)
+//!         .range("price", 0.0, 10000.0),
+//! );
+//!
+//! if let Err(e) = products.insert(product).await {
+//!     if let Some(errors) = e.downcast_ref::<ValidationErrors>() {
+//!         // {"name": ["is required"]}
+//!         return (StatusCode::UNPROCESSABLE_ENTITY, Json(errors.clone()));
+//!     }
+//! }
+//! ```
+//!
+//! [`Table`]: crate::sql::Table
+//! [`insert()`]: crate::dataset::WritableDataSet::insert()
+//! [`insert_batch()`]: crate::sql::Table::insert_batch()
+//! [`Record::save()`]: crate::sql::table::Record::save()
+
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Validation messages, grouped by the field name. Messages which don't belong
+/// to a field, such as failure to serialize the record, have an empty name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct ValidationErrors {
+    errors: IndexMap<String, Vec<String>>,
+}
+
+impl ValidationErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.errors
+            .entry(field.to_string())
+            .or_default()
+            .push(message.into());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Messages for a single field.
+    pub fn get(&self, field: &str) -> Option<&Vec<String>> {
+        self.errors.get(field)
+    }
+
+    pub fn errors(&self) -> &IndexMap<String, Vec<String>> {
+        &self.errors
+    }
+
+    /// Returns `Err(self)` if there are any errors.
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let messages = self
+            .errors
+            .iter()
+            .map(|(field, messages)| {
+                format!("{} {}", field, messages.join(", "))
+                    .trim_start()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        write!(f, "Validation failed: {}", messages.join("; "))
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// Validates a record, adding messages to `errors`. Implemented for closures:
+///
+/// ```
+/// let orders = Order::table().with_validator(|order: &Order, errors: &mut ValidationErrors| {
+///     if order.shipped && order.address.is_none() {
+///         errors.add("address", "is required for shipped orders");
+///     }
+/// });
+/// ```
+pub trait Validator<E>: Send + Sync {
+    fn validate(&self, record: &E, errors: &mut ValidationErrors);
+}
+
+impl<E, F> Validator<E> for F
+where
+    F: Fn(&E, &mut ValidationErrors) + Send + Sync,
+{
+    fn validate(&self, record: &E, errors: &mut ValidationErrors) {
+        self(record, errors)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Rule {
+    Required,
+    Length(usize, usize),
+    Range(f64, f64),
+    Regex(Regex),
+}
+
+/// Common validation rules, checked against the serialized record. Rules other
+/// than [`required()`] ignore missing and null values.
+///
+/// [`required()`]: Rules::required()
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    rules: Vec<(String, Rule)>,
+}
+
+impl Rules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Field must be present, not null and not an empty string.
+    pub fn required(mut self, field: &str) -> Self {
+        self.rules.push((field.to_string(), Rule::Required));
+        self
+    }
+
+    /// Length of a string field, in characters.
+    pub fn length(mut self, field: &str, min: usize, max: usize) -> Self {
+        self.rules.push((field.to_string(), Rule::Length(min, max)));
+        self
+    }
+
+    /// Numeric field must be within the range (inclusive).
+    pub fn range(mut self, field: &str, min: f64, max: f64) -> Self {
+        self.rules.push((field.to_string(), Rule::Range(min, max)));
+        self
+    }
+
+    /// String field must match the regular expression. Panics if the pattern
+    /// is invalid.
+    pub fn regex(mut self, field: &str, pattern: &str) -> Self {
+        let regex = Regex::new(pattern)
+            .unwrap_or_else(|e| panic!("Invalid pattern for field {}: {}", field, e));
+        self.rules.push((field.to_string(), Rule::Regex(regex)));
+        self
+    }
+
+    fn check(rule: &Rule, value: &Value) -> Option<String> {
+        match (rule, value) {
+            (Rule::Required, Value::Null) => Some("is required".to_string()),
+            (Rule::Required, Value::String(s)) if s.is_empty() => Some("is required".to_string()),
+            (Rule::Length(min, max), Value::String(s)) => {
+                let len = s.chars().count();
+                (len < *min || len > *max)
+                    .then(|| format!("must be between {} and {} characters long", min, max))
+            }
+            (Rule::Range(min, max), Value::Number(n)) => {
+                let n = n.as_f64().unwrap_or(f64::NAN);
+                (!(*min..=*max).contains(&n))
+                    .then(|| format!("must be between {} and {}", min, max))
+            }
+            (Rule::Regex(regex), Value::String(s)) => {
+                (!regex.is_match(s)).then(|| "has invalid format".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<E: Serialize> Validator<E> for Rules {
+    fn validate(&self, record: &E, errors: &mut ValidationErrors) {
+        let record = match serde_json::to_value(record) {
+            Ok(record) => record,
+            Err(e) => return errors.add("", format!("can't be serialized: {}", e)),
+        };
+        for (field, rule) in &self.rules {
+            let value = record.get(field).unwrap_or(&Value::Null);
+            if let Some(message) = Self::check(rule, value) {
+                errors.add(field, message);
+            }
+        }
+    }
+}
+
+/// Validators of a table.
+pub struct Validators<E> {
+    validators: Vec<Arc<dyn Validator<E>>>,
+}
+
+impl<E> Validators<E> {
+    pub fn new() -> Self {
+        Validators { validators: vec![] }
+    }
+
+    pub fn add_validator(&mut self, validator: impl Validator<E> + 'static) {
+        self.validators.push(Arc::new(validator));
+    }
+
+    /// Runs all validators, collecting their messages.
+    pub fn validate(&self, record: &E) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        for validator in &self.validators {
+            validator.validate(record, &mut errors);
+        }
+        errors.into_result()
+    }
+}
+
+impl<E> Default for Validators<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> Clone for Validators<E> {
+    fn clone(&self) -> Self {
+        Validators {
+            validators: self.validators.clone(),
+        }
+    }
+}
+
+impl<E> std::fmt::Debug for Validators<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Validators")
+            .field("count", &self.validators.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        dataset::WritableDataSet, mocks::datasource::MockDataSource, prelude::Entity, sql::Table,
+    };
+
+    #[derive(Serialize, Deserialize, Clone, Default, Debug)]
+    struct Product {
+        name: String,
+        price: Option<f64>,
+        sku: String,
+    }
+    impl Entity for Product {}
+
+    #[test]
+    fn test_rules() {
+        let rules = Rules::new()
+            .required("name")
+            .length("name", 1, 5)
+            .range("price", 0.0, 100.0)
+            .regex("sku", r"^[A-Z]+-\d+$");
+
+        let mut errors = ValidationErrors::new();
+        rules.validate(
+            &Product {
+                name: "".to_string(),
+                price: Some(120.0),
+                sku: "abc".to_string(),
+            },
+            &mut errors,
+        );
+        assert_eq!(
+            serde_json::to_value(&errors).unwrap(),
+            json!({
+                "name": ["is required", "must be between 1 and 5 characters long"],
+                "price": ["must be between 0 and 100"],
+                "sku": ["has invalid format"],
+            })
+        );
+
+        let mut errors = ValidationErrors::new();
+        rules.validate(
+            &Product {
+                name: "Pie".to_string(),
+                price: None,
+                sku: "PIE-1".to_string(),
+            },
+            &mut errors,
+        );
+        assert!(errors.is_empty());
+
+        let mut errors = ValidationErrors::new();
+        let record = std::collections::BTreeMap::from([((1, 2), 3)]);
+        Rules::new().required("name").validate(&record, &mut errors);
+        assert_eq!(errors.errors().len(), 1);
+        assert!(errors.get("").is_some());
+        assert_eq!(
+            errors.to_string(),
+            "Validation failed: can't be serialized: key must be a string"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_insert_validation() {
+        let products: Table<MockDataSource, Product> =
+            Table::new_with_entity("product", MockDataSource::new(&json!([])))
+                .with_column("name")
+                .with_column("price")
+                .with_validator(Rules::new().required("name"))
+                .with_validator(|product: &Product, errors: &mut ValidationErrors| {
+                    if product.price.is_none() {
+                        errors.add("price", "is required");
+                    }
+                });
+
+        let error = products.insert(Product::default()).await.unwrap_err();
+        let errors = error.downcast_ref::<ValidationErrors>().unwrap();
+        assert_eq!(
+            errors.get("name").unwrap(),
+            &vec!["is required".to_string()]
+        );
+        assert_eq!(
+            errors.get("price").unwrap(),
+            &vec!["is required".to_string()]
+        );
+        assert_eq!(
+            error.to_string(),
+            "Validation failed: name is required; price is required"
+        );
+    }
+}
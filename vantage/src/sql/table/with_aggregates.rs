@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::prelude::AssociatedQuery;
+use crate::sql::aggregate::Aggregate;
+use crate::sql::table::Table;
+use crate::sql::{Chunk, Condition};
+use crate::traits::datasource::DataSource;
+use crate::traits::entity::{EmptyEntity, Entity};
+
+use super::{AnyTable, Column, TableWithQueries};
+
+/// Records of a [`Table`] grouped by one or more columns, created with
+/// [`Table::group_by()`]. Use [`aggregate()`] to calculate values for each group:
+///
+/// ```
+/// #[derive(Deserialize)]
+/// struct ClientStats {
+///     client_id: i64,
+///     orders: i64,
+///     total: Decimal,
+/// }
+///
+/// let stats = orders
+///     .group_by(orders.client_id())
+///     .aggregate([count_all().as_("orders"), sum(orders.total()).as_("total")])
+///     .get_as::<ClientStats>()
+///     .await?;
+/// ```
+///
+/// [`aggregate()`]: GroupedTable::aggregate()
+#[derive(Debug, Clone)]
+pub struct GroupedTable<T: DataSource, E: Entity> {
+    table: Table<T, E>,
+    group_by: Vec<Arc<Column>>,
+    having: Vec<Condition>,
+}
+
+impl<T: DataSource, E: Entity> GroupedTable<T, E> {
+    /// Add another column to group by.
+    pub fn group_by(mut self, column: Arc<Column>) -> Self {
+        self.group_by.push(column);
+        self
+    }
+
+    /// Add a condition on the aggregated values, which is applied after grouping:
+    ///
+    /// ```
+    /// let busy_clients = orders
+    ///     .group_by(orders.client_id())
+    ///     .with_having(count_all().gt(10))
+    ///     .aggregate([count_all().as_("orders")]);
+    /// // SELECT client_id, COUNT(*) AS orders FROM ord GROUP BY client_id HAVING (COUNT(*) > 10)
+    /// ```
+    pub fn with_having(mut self, condition: Condition) -> Self {
+        self.add_having(condition);
+        self
+    }
+
+    pub fn add_having(&mut self, condition: Condition) {
+        self.having.push(condition);
+    }
+
+    /// Same as [`try_aggregate()`], but panics if an extension fails.
+    ///
+    /// [`try_aggregate()`]: GroupedTable::try_aggregate()
+    pub fn aggregate(
+        &self,
+        aggregates: impl IntoIterator<Item = Aggregate>,
+    ) -> AssociatedQuery<T, EmptyEntity> {
+        self.try_aggregate(aggregates)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Returns query, selecting grouped columns and the aggregates.
+    pub fn try_aggregate(
+        &self,
+        aggregates: impl IntoIterator<Item = Aggregate>,
+    ) -> Result<AssociatedQuery<T, EmptyEntity>> {
+        let mut query = self.table.get_empty_query();
+        for column in &self.group_by {
+            query = query
+                .with_field(column.name(), column.clone())
+                .with_group_by(column.render_chunk());
+        }
+        for aggregate in aggregates {
+            query = query.with_field(
+                aggregate.alias().to_string(),
+                aggregate.expression().clone(),
+            );
+        }
+        for condition in &self.having {
+            query = query.with_having_condition(condition.render_chunk());
+        }
+        self.table
+            .hooks()
+            .before_select_query(&self.table, &mut query)?;
+        Ok(AssociatedQuery::new(query, self.table.data_source.clone()))
+    }
+}
+
+impl<T: DataSource, E: Entity> Table<T, E> {
+    /// Group records by a column. See [`GroupedTable`].
+    pub fn group_by(&self, column: Arc<Column>) -> GroupedTable<T, E> {
+        GroupedTable {
+            table: self.clone(),
+            group_by: vec![column],
+            having: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use crate::{
+        mocks::datasource::MockDataSource,
+        prelude::*,
+        sql::aggregate::{count_all, sum},
+    };
+
+    #[test]
+    fn test_group_by() {
+        let orders = Table::new("orders", MockDataSource::new(&json!([])))
+            .with_id_column("id")
+            .with_column("client_id")
+            .with_column("status")
+            .with_column("total")
+            .with_extension(SoftDelete::new("is_deleted"));
+
+        let query = orders
+            .group_by(orders.get_column("client_id").unwrap())
+            .group_by(orders.get_column("status").unwrap())
+            .aggregate([
+                count_all().as_("orders"),
+                sum(orders.get_column("total").unwrap()),
+            ]);
+
+        assert_eq!(
+            query.preview(),
+            "SELECT client_id, status, (COUNT(*)) AS orders, (SUM(total)) AS sum FROM orders \
+            WHERE (is_deleted = false) GROUP BY client_id, status"
+        );
+    }
+
+    #[test]
+    fn test_having() {
+        let orders = Table::new("orders", MockDataSource::new(&json!([])))
+            .with_id_column("id")
+            .with_column("client_id");
+
+        let query = orders
+            .group_by(orders.get_column("client_id").unwrap())
+            .with_having(count_all().gt(10))
+            .aggregate([count_all().as_("orders")]);
+
+        assert_eq!(
+            query.preview(),
+            "SELECT client_id, (COUNT(*)) AS orders FROM orders \
+            GROUP BY client_id HAVING (COUNT(*) > 10)"
+        );
+    }
+}
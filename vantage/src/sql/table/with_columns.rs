@@ -1,16 +1,17 @@
-use anyhow::{anyhow, Context};
 use indexmap::IndexMap;
 use serde_json::Value;
 use std::ops::Deref;
 use std::sync::Arc;
 
-use super::{Column, RelatedTable};
+use super::{Column, ColumnDef, RelatedTable};
 use crate::lazy_expression::LazyExpression;
 use crate::prelude::Operations;
 use crate::sql::table::Table;
+use crate::sql::SqlType;
 use crate::traits::column::SqlField;
 use crate::traits::datasource::DataSource;
 use crate::traits::entity::Entity;
+use crate::Error;
 
 use super::AnyTable;
 
@@ -116,6 +117,7 @@ pub trait TableWithColumns: AnyTable {
     fn columns(&self) -> &IndexMap<String, Arc<Column>>;
     fn get_column_with_table_alias(&self, name: &str) -> Option<Arc<Column>>;
     fn id(&self) -> Arc<Column>;
+    fn try_id(&self) -> Result<Arc<Column>, Error>;
     fn id_with_table_alias(&self) -> Arc<Column>;
     fn search_for_field(&self, field_name: &str) -> Option<Box<dyn SqlField>>;
 }
@@ -126,7 +128,9 @@ impl<T: DataSource, E: Entity> TableWithColumns for Table<T, E> {
     /// Adds a new column to the table. Note, that Column may use an alias. Additional
     /// features may be added into [`Column`] in the future, so better use [`with_column()`]
     /// to keep your code portable.
-    fn add_column(&mut self, column_name: String, column: Column) {
+    fn add_column(&mut self, column_name: String, mut column: Column) {
+        column.set_dialect(self.data_source.dialect());
+        column.set_table_name(self.table_name.clone());
         self.columns.insert(column_name, Arc::new(column));
     }
 
@@ -146,17 +150,18 @@ impl<T: DataSource, E: Entity> TableWithColumns for Table<T, E> {
     }
 
     /// Returns the id column. If `with_id_column` was not called, will try to find
-    /// column called `"id"`. If not found, will panic.
+    /// column called `"id"`. If not found, will panic. See [`try_id()`].
+    ///
+    /// [`try_id()`]: Table::try_id()
     fn id(&self) -> Arc<Column> {
-        let id_column = if self.id_column.is_some() {
-            let x = self.id_column.clone().unwrap();
-            x.clone()
-        } else {
-            "id".to_string()
-        };
-        self.get_column(&id_column)
-            .with_context(|| anyhow!("Table '{}' has no field '{}'", &self, &id_column))
-            .unwrap()
+        self.try_id().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Returns the id column, or [`Error::MissingColumn`] if the table has none.
+    fn try_id(&self) -> Result<Arc<Column>, Error> {
+        let id_column = self.id_column.as_deref().unwrap_or("id");
+        self.get_column(id_column)
+            .ok_or_else(|| Error::missing_column(self, id_column))
     }
 
     fn id_with_table_alias(&self) -> Arc<Column> {
@@ -192,13 +197,9 @@ impl<T: DataSource, E: Entity> TableWithColumns for Table<T, E> {
 
         // maybe we have a lazy expression
         if let Some(lazy_expression) = self.lazy_expressions.get(field_name) {
-            return match lazy_expression {
-                LazyExpression::AfterQuery(_) => None,
-                LazyExpression::BeforeQuery(expr) => {
-                    let x = (expr)(self);
-                    Some(Box::new(x))
-                }
-            };
+            let (LazyExpression::BeforeQuery(expr) | LazyExpression::Computed(expr)) =
+                lazy_expression;
+            return Some(Box::new((expr)(&self.as_entity())));
         }
         None
     }
@@ -214,6 +215,30 @@ impl<T: DataSource, E: Entity> Table<T, E> {
         self
     }
 
+    /// Adds a column of a specific [`SqlType`]. Values of typed columns are validated
+    /// before insert or update, and are cast explicitly in the query.
+    ///
+    /// [`SqlType`]: crate::sql::SqlType
+    pub fn with_typed_column(mut self, column: &str, sql_type: SqlType) -> Self {
+        self.add_column(
+            column.to_string(),
+            Column::new(column.to_string(), self.table_alias.clone()).with_type(sql_type),
+        );
+        self
+    }
+
+    /// Adds a column with metadata, see [`ColumnDef`].
+    pub fn with_column_def(mut self, column: ColumnDef) -> Self {
+        self.add_column_def(column);
+        self
+    }
+
+    pub fn add_column_def(&mut self, column: ColumnDef) {
+        let name = column.name().to_string();
+        let column = column.into_column(self.table_alias.clone());
+        self.add_column(name, column);
+    }
+
     /// Adds a column that is also a title column. Title column will be
     /// used in the UI to represent the record.
     pub fn with_title_column(mut self, column: &str) -> Self {
@@ -221,6 +246,16 @@ impl<T: DataSource, E: Entity> Table<T, E> {
         self.with_column(column)
     }
 
+    /// Returns the title column. Panics if [`with_title_column()`] was not called.
+    ///
+    /// [`with_title_column()`]: Table::with_title_column()
+    pub fn title(&self) -> Arc<Column> {
+        self.title_column
+            .as_ref()
+            .and_then(|column| self.get_column(column))
+            .unwrap_or_else(|| panic!("Table '{}' has no title column", self))
+    }
+
     /// Adds a column that is also an id column. Id column is used
     /// by [`Table::id()`] and [`Table::with_id()`].
     pub fn with_id_column(mut self, column: &str) -> Self {
@@ -234,13 +269,119 @@ impl<T: DataSource, E: Entity> Table<T, E> {
         let f = self.id().eq(&id);
         self.with_condition(f)
     }
+
+    /// Returns a column referenced through an enum generated by [`table_columns!`],
+    /// so that a misspelled column is a compile error. Panics if the table does not
+    /// define the column, see [`try_col()`].
+    ///
+    /// [`table_columns!`]: crate::table_columns
+    /// [`try_col()`]: Table::try_col()
+    pub fn col(&self, column: impl ColumnRef) -> Arc<Column> {
+        self.try_col(column).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as [`Table::col()`], but returns [`Error::MissingColumn`] if the table
+    /// does not define the column, for example when the enum is shared by tables
+    /// with different columns.
+    pub fn try_col(&self, column: impl ColumnRef) -> Result<Arc<Column>, Error> {
+        let name = column.column_name();
+        self.get_column(name)
+            .ok_or_else(|| Error::missing_column(self, name))
+    }
+
+    /// Select only the listed columns and expressions, so that a query does not
+    /// fetch heavy columns, which are not needed:
+    ///
+    /// ```
+    /// let products = Product::table().select_only(&["id", "name"]);
+    /// let list = products.get_all_untyped().await?;
+    /// // SELECT id, name FROM product
+    /// ```
+    ///
+    /// Other columns can still be used in conditions. Struct fields, selected by
+    /// [`get()`](crate::dataset::ReadableDataSet::get), are not affected.
+    pub fn select_only(mut self, columns: &[&str]) -> Self {
+        for column in columns {
+            self.ensure_column(column);
+        }
+        self.hidden_columns = self
+            .columns
+            .keys()
+            .chain(self.lazy_expressions.keys())
+            .filter(|name| !columns.contains(&name.as_str()))
+            .cloned()
+            .collect();
+        self
+    }
+
+    /// Exclude a column or an expression from the selected fields, see
+    /// [`select_only()`](Table::select_only).
+    pub fn without_column(mut self, column: &str) -> Self {
+        self.ensure_column(column);
+        self.hidden_columns.push(column.to_string());
+        self
+    }
+
+    fn ensure_column(&self, name: &str) {
+        if !self.columns.contains_key(name) && !self.lazy_expressions.contains_key(name) {
+            panic!("Table '{}' has no column '{}'", self, name);
+        }
+    }
+}
+
+/// Column of a table known at compile time, see [`table_columns!`].
+///
+/// [`table_columns!`]: crate::table_columns
+pub trait ColumnRef {
+    fn column_name(&self) -> &'static str;
+}
+
+/// Defines an enum with a variant for every column of a table, to be used with
+/// [`Table::col()`] instead of string column names:
+///
+/// ```
+/// table_columns! {
+///     pub enum ProductCol {
+///         Name = "name",
+///         Price = "price",
+///     }
+/// }
+///
+/// let cheap = products.col(ProductCol::Price).lt(10);
+/// ```
+#[macro_export]
+macro_rules! table_columns {
+    (
+        $vis:vis enum $name:ident {
+            $($variant:ident = $column:literal),* $(,)?
+        }
+    ) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $($variant),*
+        }
+
+        impl $name {
+            /// All columns, in the order of declaration.
+            #[allow(dead_code)]
+            pub const ALL: &'static [$name] = &[$($name::$variant),*];
+        }
+
+        impl $crate::sql::table::ColumnRef for $name {
+            fn column_name(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $column),*
+                }
+            }
+        }
+    };
 }
 
 #[cfg(test)]
 mod tests {
     use serde_json::json;
 
-    use crate::{mocks::datasource::MockDataSource, prelude::*, sql::table::Table};
+    use crate::{mocks::datasource::MockDataSource, prelude::*, sql::table::Table, Error};
 
     #[test]
     fn test_get_column() {
@@ -255,6 +396,80 @@ mod tests {
         assert!(roles.get_column("name").is_some());
     }
 
+    table_columns! {
+        enum RoleCol {
+            Id = "id",
+            Name = "name",
+            Rank = "rank",
+        }
+    }
+
+    #[test]
+    fn test_col() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let roles = Table::new("roles", db.clone())
+            .with_column("id")
+            .with_column("name");
+
+        assert_eq!(RoleCol::ALL.len(), 3);
+        assert_eq!(
+            roles.col(RoleCol::Name).gt(10).render_chunk().preview(),
+            "(name > 10)"
+        );
+        assert_eq!(roles.col(RoleCol::Id).name(), "id");
+    }
+
+    #[test]
+    #[should_panic(expected = "has no field 'rank'")]
+    fn test_col_missing() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        Table::new("roles", db).with_column("id").col(RoleCol::Rank);
+    }
+
+    #[test]
+    fn test_try_col() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let roles = Table::new("roles", db).with_column("id");
+        assert_eq!(roles.try_col(RoleCol::Id).unwrap().name(), "id");
+        assert!(matches!(
+            roles.try_col(RoleCol::Rank),
+            Err(Error::MissingColumn { .. })
+        ));
+    }
+
+    #[test]
+    fn test_select_only() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let products = Table::new("product", db)
+            .with_id_column("id")
+            .with_column("name")
+            .with_column("photo");
+
+        assert_eq!(
+            products
+                .clone()
+                .select_only(&["id", "name"])
+                .get_select_query()
+                .preview(),
+            "SELECT id, name FROM product"
+        );
+        assert_eq!(
+            products
+                .without_column("photo")
+                .get_select_query()
+                .preview(),
+            "SELECT id, name FROM product"
+        );
+    }
+
     #[test]
     fn test_search_for_field() {
         let data = json!([]);
@@ -0,0 +1,144 @@
+use anyhow::{anyhow, Result};
+use futures::stream;
+use serde_json::{Map, Value};
+
+use crate::datasource::postgres::Postgres;
+use crate::sql::query::{QuerySource, QueryType, SqlQuery};
+use crate::sql::table::Table;
+use crate::traits::datasource::DataSource;
+use crate::traits::entity::Entity;
+
+impl<E: Entity> Table<Postgres, E> {
+    /// Bulk-load records with `COPY`, see [`Postgres::copy_in()`]. Records are
+    /// consumed lazily, so the iterator may produce millions of them:
+    ///
+    /// ```
+    /// let rows = csv_reader.deserialize::<Client>().filter_map(|r| r.ok());
+    /// let count = Client::table().copy_from_iter(rows).await?;
+    /// ```
+    ///
+    /// Records go through the same steps as with [`insert()`], so they are
+    /// validated and extensions can set their fields, such as the tenant. If a
+    /// record fails, nothing is copied. Extensions, which replace the INSERT query
+    /// (like [`AuditLog`]), can't be used with COPY and fail the whole load.
+    /// COPY does not return ids of the records, so `after_insert` hooks are not
+    /// called.
+    ///
+    /// [`insert()`]: crate::dataset::WritableDataSet::insert()
+    /// [`AuditLog`]: crate::sql::table::extensions::AuditLog
+    pub async fn copy_from_iter(&self, records: impl IntoIterator<Item = E>) -> Result<u64> {
+        self.ensure_writable()?;
+        let mut records = records.into_iter();
+        let Some(first) = records.next() else {
+            return Ok(0);
+        };
+        let first = self.copy_values(first)?;
+        let columns = first.keys().cloned().collect::<Vec<String>>();
+
+        let row_columns = columns.clone();
+        let rows = stream::iter(
+            std::iter::once(Ok(first))
+                .chain(records.map(|record| self.copy_values(record)))
+                .map(move |values| copy_row(&row_columns, values?)),
+        );
+
+        let columns = columns.iter().map(|c| c.as_str()).collect::<Vec<&str>>();
+        self.data_source
+            .copy_in(&self.table_name, &columns, rows)
+            .await
+    }
+}
+
+impl<T: DataSource, E: Entity> Table<T, E> {
+    /// Values, which [`insert()`] would set for the record.
+    ///
+    /// [`insert()`]: crate::dataset::WritableDataSet::insert()
+    fn copy_values(&self, record: E) -> Result<Map<String, Value>> {
+        self.validate(&record)?;
+        let query = self.get_insert_query(record)?;
+        match (query.get_type(), query.get_source()) {
+            (QueryType::Insert, QuerySource::Table(table, _)) if *table == self.table_name => {}
+            _ => {
+                return Err(anyhow!(
+                    "Extensions of table {} replace INSERT query, which COPY can't execute",
+                    self
+                ))
+            }
+        }
+        Ok(query.get_set_values()?)
+    }
+}
+
+/// Orders values by `columns`. All records must set the same fields.
+fn copy_row(columns: &[String], mut values: Map<String, Value>) -> Result<Vec<Value>> {
+    let row = columns
+        .iter()
+        .map(|name| values.remove(name).unwrap_or(Value::Null))
+        .collect();
+    match values.keys().next() {
+        Some(name) => Err(anyhow!(
+            "Field '{}' is not set for the first copied record",
+            name
+        )),
+        None => Ok(row),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::mocks::datasource::MockDataSource;
+    use crate::sql::table::extensions::{AuditLog, TenantScope};
+
+    #[derive(Serialize, Deserialize, Clone, Default, Debug)]
+    struct Client {
+        name: String,
+        tenant_id: i64,
+    }
+    impl Entity for Client {}
+
+    fn clients() -> Table<MockDataSource, Client> {
+        Table::new_with_entity("client", MockDataSource::new(&json!([])))
+            .with_id_column("id")
+            .with_column("name")
+            .with_extension(TenantScope::new("tenant_id", || json!(7)))
+    }
+
+    #[test]
+    fn test_copy_values() {
+        let client = Client {
+            name: "John".to_string(),
+            tenant_id: 3,
+        };
+
+        let values = clients().copy_values(client.clone()).unwrap();
+        assert_eq!(
+            Value::Object(values),
+            json!({"name": "John", "tenant_id": 7})
+        );
+
+        let audited = clients().with_extension(AuditLog::new("audit_log"));
+        assert!(audited.copy_values(client.clone()).is_err());
+
+        let view = clients().into_view();
+        assert!(view.table().copy_values(client).is_err());
+    }
+
+    #[test]
+    fn test_copy_row() {
+        let columns = vec!["name".to_string(), "tenant_id".to_string()];
+        let values = json!({"tenant_id": 7, "name": "John"});
+        let Value::Object(values) = values else {
+            unreachable!()
+        };
+
+        assert_eq!(
+            copy_row(&columns, values.clone()).unwrap(),
+            vec![json!("John"), json!(7)]
+        );
+        assert!(copy_row(&columns[..1], values).is_err());
+    }
+}
@@ -1,9 +1,12 @@
+use crate::dataset::hydrate::{from_row, from_rows, from_rows_lenient};
 use crate::dataset::ReadableDataSet;
 use crate::sql::table::Table;
 use crate::sql::Query;
 use crate::traits::datasource::DataSource;
 use crate::traits::entity::Entity;
+use crate::traits::from_sql_value::FromSqlValue;
 use anyhow::Result;
+use futures::{Stream, StreamExt, TryStreamExt};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::{Map, Value};
 
@@ -19,51 +22,67 @@ impl<T: DataSource, E: Entity> ReadableDataSet<E> for Table<T, E> {
     }
 
     async fn get_all_untyped(&self) -> Result<Vec<Map<String, Value>>> {
-        let query = self.select_query();
+        let query = self.try_get_select_query()?;
         self.data_source.query_fetch(&query).await
     }
 
     async fn get_row_untyped(&self) -> Result<Map<String, Value>> {
-        let query = self.select_query();
+        let query = self.try_get_select_query()?;
         self.data_source.query_row(&query).await
     }
 
     async fn get_col_untyped(&self) -> Result<Vec<Value>> {
-        let query = self.select_query();
+        let query = self.try_get_select_query()?;
         self.data_source.query_col(&query).await
     }
 
     async fn get_one_untyped(&self) -> Result<Value> {
-        let query = self.select_query();
+        let query = self.try_get_select_query()?;
         self.data_source.query_one(&query).await
     }
 
+    async fn count(&self) -> Result<i64> {
+        self.try_count()?.get_one_as().await
+    }
+
+    async fn exists(&self) -> Result<bool> {
+        let query = self.try_get_select_query()?.get_exists_query();
+        Ok(bool::from_sql_value(
+            self.data_source.query_one(&query).await?,
+        )?)
+    }
+
     async fn get(&self) -> Result<Vec<E>> {
-        let query = self.get_select_query_for_struct(E::default());
-        let data = self.data_source.query_fetch(&query).await?;
-        Ok(data
-            .into_iter()
-            .map(|row| serde_json::from_value(Value::Object(row)).unwrap())
-            .collect())
+        let query = self.try_get_select_query_for_struct(E::default())?;
+        Ok(from_rows(self.fetch_hydrated(query).await?)?)
+    }
+
+    fn get_stream(&self) -> impl Stream<Item = Result<E>> {
+        let query = self.try_get_select_query_for_struct(E::default());
+        let data_source = self.data_source.clone();
+        futures::stream::once(async move { data_source.query_stream(&query?).await })
+            .try_flatten()
+            .enumerate()
+            .map(|(index, row)| Ok(from_row(row?, index)?))
     }
 
     async fn get_as<T2: DeserializeOwned>(&self) -> Result<Vec<T2>> {
-        let data = self.get_all_untyped().await?;
-        Ok(data
-            .into_iter()
-            .map(|row| serde_json::from_value(Value::Object(row)).unwrap())
-            .collect())
+        Ok(from_rows(
+            self.fetch_hydrated(self.try_get_select_query()?).await?,
+        )?)
+    }
+
+    async fn get_as_lenient<T2: DeserializeOwned>(&self) -> Result<Vec<T2>> {
+        Ok(from_rows_lenient(
+            self.fetch_hydrated(self.try_get_select_query()?).await?,
+        ))
     }
 
     async fn get_some(&self) -> Result<Option<E>> {
-        let query = self.select_query();
-        let data = self.data_source.query_fetch(&query).await?;
-        if data.len() > 0 {
-            let row = data[0].clone();
-            let row = serde_json::from_value(Value::Object(row)).unwrap();
-            Ok(Some(row))
-        } else {
-            Ok(None)
+        let data = self.fetch_hydrated(self.try_get_select_query()?).await?;
+        match data.into_iter().next() {
+            Some(row) => Ok(Some(from_row(row, 0)?)),
+            None => Ok(None),
         }
     }
 
@@ -71,22 +90,55 @@ impl<T: DataSource, E: Entity> ReadableDataSet<E> for Table<T, E> {
     where
         T2: DeserializeOwned + Default + Serialize,
     {
-        let query = self.get_select_query_for_struct(T2::default());
-        let data = self.data_source.query_fetch(&query).await?;
-        if data.len() > 0 {
-            let row = data[0].clone();
-            let row = serde_json::from_value(Value::Object(row)).unwrap();
-            Ok(Some(row))
-        } else {
-            Ok(None)
+        let query = self.try_get_select_query_for_struct(T2::default())?;
+        let data = self.fetch_hydrated(query).await?;
+        match data.into_iter().next() {
+            Some(row) => Ok(Some(from_row(row, 0)?)),
+            None => Ok(None),
         }
     }
 }
 
-// impl<T: DataSource, E: Entity> Table<T, E> {
-// }
+impl<T: DataSource, E: Entity> Table<T, E> {
+    /// Fetch rows of the query with nested structs and preloaded references in
+    /// place. All typed getters go through here.
+    pub(super) async fn fetch_hydrated(&self, query: Query) -> Result<Vec<Map<String, Value>>> {
+        let query = self.add_preload_keys_into_query(query);
+        let mut data = self.data_source.query_fetch(&query).await?;
+        data.iter_mut().for_each(|row| self.hydrate_nested(row));
+        self.preload_into(&mut data).await?;
+        Ok(data)
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use futures::TryStreamExt;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::mocks::datasource::MockDataSource;
+
+    #[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+    struct User {
+        name: String,
+    }
+    impl Entity for User {}
+
+    #[tokio::test]
+    async fn test_get_stream() {
+        let data = json!([{ "name": "John" }, { "name": "Jane" }]);
+        let users: Table<MockDataSource, User> =
+            Table::new_with_entity("users", MockDataSource::new(&data)).with_column("name");
+
+        let stream = users.get_stream();
+        futures::pin_mut!(stream);
+
+        let mut names = vec![];
+        while let Some(user) = stream.try_next().await.unwrap() {
+            names.push(user.name);
+        }
+        assert_eq!(names, vec!["John".to_string(), "Jane".to_string()]);
+    }
 }
@@ -0,0 +1,232 @@
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::sql::table::{AnyTable, Column, Table};
+use crate::sql::{Condition, ConditionTree, Operations};
+use crate::traits::datasource::DataSource;
+use crate::traits::entity::Entity;
+use crate::Error;
+
+impl<T: DataSource, E: Entity> Table<T, E> {
+    /// Converts a JSON filter document into a condition, so that clients of an API
+    /// can filter records without sending SQL:
+    ///
+    /// ```
+    /// let filter = json!({"and": [
+    ///     {"price": {"gt": 10}},
+    ///     {"name": {"ilike": "%bread%"}},
+    /// ]});
+    /// let products = products.with_json_filter(&filter, &["name", "price"])?;
+    /// // WHERE ((price > {}) AND (name ILIKE {}))
+    /// ```
+    ///
+    /// Document is an object, where `and` and `or` contain a list of filters,
+    /// `not` contains a filter and any other key is a column name. Column is
+    /// compared with a value (`{"status": "paid"}`) or with an object of operators:
+    /// `eq`, `ne`, `gt`, `gte`, `lt`, `lte`, `like`, `ilike`, `in` (list of values),
+    /// `between` (list of two values) and `null` (true or false). Several keys in
+    /// an object must all match.
+    ///
+    /// Only columns listed in `allowed_columns` can be used. Anything else in
+    /// the document fails with [`Error::InvalidFilter`].
+    pub fn condition_from_json(
+        &self,
+        filter: &Value,
+        allowed_columns: &[&str],
+    ) -> Result<Condition, Error> {
+        Ok(self.parse_filter(filter, allowed_columns)?.into())
+    }
+
+    /// Adds condition from a JSON filter document, see [`Table::condition_from_json()`].
+    pub fn with_json_filter(
+        mut self,
+        filter: &Value,
+        allowed_columns: &[&str],
+    ) -> Result<Self, Error> {
+        let condition = self.condition_from_json(filter, allowed_columns)?;
+        self.add_condition(condition);
+        Ok(self)
+    }
+
+    fn parse_filter(
+        &self,
+        filter: &Value,
+        allowed_columns: &[&str],
+    ) -> Result<ConditionTree, Error> {
+        let Value::Object(filter) = filter else {
+            return Err(invalid_filter(format!(
+                "expected an object, got {}",
+                filter
+            )));
+        };
+        let mut items = Vec::new();
+        for (key, value) in filter {
+            items.push(match key.as_str() {
+                "and" => ConditionTree::all(self.parse_list(key, value, allowed_columns)?),
+                "or" => ConditionTree::any(self.parse_list(key, value, allowed_columns)?),
+                "not" => self.parse_filter(value, allowed_columns)?.not(),
+                field => self.parse_field(field, value, allowed_columns)?,
+            });
+        }
+        Ok(all(items))
+    }
+
+    fn parse_list(
+        &self,
+        key: &str,
+        value: &Value,
+        allowed_columns: &[&str],
+    ) -> Result<Vec<ConditionTree>, Error> {
+        let Value::Array(filters) = value else {
+            return Err(invalid_filter(format!(
+                "'{}' expects a list of filters",
+                key
+            )));
+        };
+        filters
+            .iter()
+            .map(|filter| self.parse_filter(filter, allowed_columns))
+            .collect()
+    }
+
+    fn parse_field(
+        &self,
+        field: &str,
+        value: &Value,
+        allowed_columns: &[&str],
+    ) -> Result<ConditionTree, Error> {
+        let column = allowed_columns
+            .contains(&field)
+            .then(|| self.get_column(field))
+            .flatten()
+            .ok_or_else(|| invalid_filter(format!("can't filter by '{}'", field)))?;
+
+        let operators = match value {
+            Value::Object(operators) => operators,
+            value => return Ok(compare(&column, field, "eq", value)?.into()),
+        };
+        if operators.is_empty() {
+            return Err(invalid_filter(format!("no operators for '{}'", field)));
+        }
+        let conditions = operators
+            .iter()
+            .map(|(operator, value)| Ok(compare(&column, field, operator, value)?.into()))
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(all(conditions))
+    }
+}
+
+fn compare(
+    column: &Arc<Column>,
+    field: &str,
+    operator: &str,
+    value: &Value,
+) -> Result<Condition, Error> {
+    let condition = match (operator, value) {
+        ("eq", Value::Null) | ("null", Value::Bool(true)) => column.is_null(),
+        ("ne", Value::Null) | ("null", Value::Bool(false)) => column.is_not_null(),
+        ("in", Value::Array(values)) if values.iter().all(is_scalar) => {
+            column.in_values(values.clone())
+        }
+        ("between", Value::Array(values)) if values.len() == 2 && values.iter().all(is_scalar) => {
+            column.between(values[0].clone(), values[1].clone())
+        }
+        (_, value) if !is_scalar(value) => {
+            return Err(invalid_filter(format!(
+                "unexpected value for '{}': {}",
+                field, value
+            )))
+        }
+        ("eq", value) => column.eq(value),
+        ("ne", value) => column.ne(value.clone()),
+        ("gt", value) => column.gt(value.clone()),
+        ("lt", value) => column.lt(value.clone()),
+        ("gte", value) => column.condition(">=", Arc::new(Box::new(value.clone()))),
+        ("lte", value) => column.condition("<=", Arc::new(Box::new(value.clone()))),
+        ("like", value) => column.like(value.clone()),
+        ("ilike", value) => column.ilike(value.clone()),
+        (operator, _) => {
+            return Err(invalid_filter(format!(
+                "unknown operator '{}' for '{}'",
+                operator, field
+            )))
+        }
+    };
+    Ok(condition)
+}
+
+/// Same as [`ConditionTree::all()`], without extra parentheses for a single item
+fn all(mut items: Vec<ConditionTree>) -> ConditionTree {
+    match items.len() {
+        1 => items.pop().unwrap(),
+        _ => ConditionTree::all(items),
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Object(_) | Value::Array(_) | Value::Null)
+}
+
+fn invalid_filter(message: String) -> Error {
+    Error::InvalidFilter(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::prelude::*;
+    use crate::Error;
+
+    fn products() -> Table<MockDataSource, EmptyEntity> {
+        Table::new("product", MockDataSource::new(&json!([])))
+            .with_id_column("id")
+            .with_column("name")
+            .with_column("price")
+            .with_column("deleted_at")
+    }
+
+    fn filter(filter: serde_json::Value) -> Result<String, Error> {
+        Ok(products()
+            .condition_from_json(&filter, &["name", "price", "deleted_at"])?
+            .render_chunk()
+            .preview())
+    }
+
+    #[test]
+    fn test_json_filter() {
+        assert_eq!(
+            filter(json!({"and": [{"price": {"gt": 10}}, {"name": {"ilike": "%bread%"}}]}))
+                .unwrap(),
+            "((price > 10) AND (name ILIKE '%bread%'))"
+        );
+        assert_eq!(
+            filter(json!({"or": [{"name": "Pie"}, {"price": {"gte": 5, "lte": 9}}]})).unwrap(),
+            "((name = 'Pie') OR ((price >= 5) AND (price <= 9)))"
+        );
+        assert_eq!(
+            filter(json!({"not": {"deleted_at": {"null": true}}, "price": {"between": [1, 2]}}))
+                .unwrap(),
+            "((NOT (deleted_at IS NULL)) AND (price BETWEEN 1 AND 2))"
+        );
+
+        assert_eq!(
+            filter(json!({"id": 1})).unwrap_err().to_string(),
+            "Invalid filter: can't filter by 'id'"
+        );
+        assert_eq!(
+            filter(json!({"price": {"gt": {"id": 1}}}))
+                .unwrap_err()
+                .to_string(),
+            "Invalid filter: unexpected value for 'price': {\"id\":1}"
+        );
+        assert_eq!(
+            filter(json!({"price": {"matches": 1}}))
+                .unwrap_err()
+                .to_string(),
+            "Invalid filter: unknown operator 'matches' for 'price'"
+        );
+        assert!(filter(json!({"and": {"price": 1}})).is_err());
+    }
+}
@@ -0,0 +1,667 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::dynamic::{Field, FieldFuture, FieldValue, InputValue, Object, Schema, TypeRef};
+use futures::future::{BoxFuture, FutureExt};
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+
+use crate::sql::query::{QuerySource, SqlQuery};
+use crate::sql::table::Table;
+use crate::sql::Chunk;
+use crate::traits::datasource::DataSource;
+use crate::traits::entity::Entity;
+use crate::Error;
+
+use super::entity_fields::FieldType;
+use super::reference::ReferenceKeys;
+use super::{SqlTable, TableWithColumns};
+
+/// # GraphQL schema
+///
+/// Describes tables as GraphQL object types and resolves queries against them, so
+/// that a GraphQL API can be built on top of the model without repeating the field
+/// lists. Enabled with `graphql` feature:
+///
+/// ```
+/// let schema = GraphQlSchema::new()
+///     .with_table("Client", &Client::table())
+///     .with_table("Order", &Order::table());
+///
+/// println!("{}", schema);
+/// // type Client {
+/// //   id: ID!
+/// //   name: String!
+/// //   orders: [Order!]!
+/// // }
+/// // ..
+/// // type Query {
+/// //   client(id: ID!): Client
+/// //   clientList(limit: Int, offset: Int): [Client!]!
+/// //   ..
+/// // }
+///
+/// let response = schema
+///     .build()?
+///     .execute("{ clientList(limit: 10) { name orders { id } } }")
+///     .await;
+/// ```
+///
+/// Fields follow the entity, so imported fields and expressions are included.
+/// Types come from the column type (see [`Table::with_typed_column()`]) or from
+/// the default value of the entity field. `Option` fields are nullable.
+/// References become fields of the related type: a list for [`Table::with_many()`]
+/// and a nullable object for [`Table::with_one()`]. References, which can't be
+/// matched by a key column (such as [`Table::with_many_via()`]), and references to
+/// tables, which were not added into the schema, are left out.
+///
+/// [`build()`] returns an executable schema. Reference fields are resolved in
+/// batches: related records of all the records in a response are fetched with a
+/// single query per reference instead of a query per record. Related records are
+/// fetched from the table added into the schema, so its conditions apply.
+///
+/// [`build()`]: GraphQlSchema::build()
+#[derive(Debug, Clone, Default)]
+pub struct GraphQlSchema {
+    types: IndexMap<String, GraphQlType>,
+    sources: IndexMap<String, Arc<dyn GraphQlSource>>,
+}
+
+/// GraphQL object type, describing records of a table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphQlType {
+    pub name: String,
+    pub fields: Vec<GraphQlField>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphQlField {
+    pub name: String,
+    pub kind: GraphQlFieldKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphQlFieldKind {
+    /// Built-in scalar type, such as `Int` or `String`
+    Scalar { type_name: String, required: bool },
+    /// Records of another table
+    Reference { table: String, many: bool },
+}
+
+impl GraphQlSchema {
+    pub fn new() -> GraphQlSchema {
+        GraphQlSchema::default()
+    }
+
+    /// Adds object type `name` for records of the table.
+    pub fn with_table<T: DataSource, E: Entity>(mut self, name: &str, table: &Table<T, E>) -> Self {
+        self.add_table(name, table);
+        self
+    }
+
+    pub fn add_table<T: DataSource, E: Entity>(&mut self, name: &str, table: &Table<T, E>) {
+        self.types
+            .insert(table.table_name.clone(), table.graphql_type(name));
+        self.sources
+            .insert(table.table_name.clone(), Arc::new(table.clone()));
+    }
+
+    /// Fields of the type, except for references to tables outside of the schema.
+    fn fields<'a>(&'a self, t: &'a GraphQlType) -> impl Iterator<Item = &'a GraphQlField> {
+        t.fields.iter().filter(|field| match &field.kind {
+            GraphQlFieldKind::Reference { table, .. } => self.types.contains_key(table),
+            GraphQlFieldKind::Scalar { .. } => true,
+        })
+    }
+
+    fn render_field(&self, field: &GraphQlField) -> String {
+        let type_name = match &field.kind {
+            GraphQlFieldKind::Scalar {
+                type_name,
+                required: true,
+            } => format!("{}!", type_name),
+            GraphQlFieldKind::Scalar { type_name, .. } => type_name.clone(),
+            GraphQlFieldKind::Reference { table, many: true } => {
+                format!("[{}!]!", self.types[table].name)
+            }
+            GraphQlFieldKind::Reference { table, .. } => self.types[table].name.clone(),
+        };
+        format!("{}: {}", field.name, type_name)
+    }
+
+    /// Builds an executable schema. The `Query` type has a field for fetching a
+    /// record by id and a field for fetching a page of records of every table.
+    pub fn build(&self) -> Result<Schema> {
+        let mut builder = Schema::build("Query", None, None);
+        let mut query = Object::new("Query");
+
+        for (table, t) in &self.types {
+            let source = &self.sources[table];
+            let mut object = Object::new(&t.name);
+            for field in self.fields(t) {
+                object = object.field(self.build_field(source, field)?);
+            }
+            builder = builder.register(object);
+
+            let name = lower_camel_case(&t.name);
+            let by_id = source.clone();
+            query = query.field(
+                Field::new(name.clone(), TypeRef::named(&t.name), move |ctx| {
+                    let source = by_id.clone();
+                    FieldFuture::new(async move {
+                        let id = match ctx.args.try_get("id")?.as_value() {
+                            async_graphql::Value::String(id) => id.clone(),
+                            id => id.to_string(),
+                        };
+                        let filter = source.id_filter(&id)?;
+                        let rows = source.fetch(Some(filter), Some(1), None).await?;
+                        Ok(rows.into_iter().next().map(FieldValue::owned_any))
+                    })
+                })
+                .argument(InputValue::new("id", TypeRef::named_nn(TypeRef::ID))),
+            );
+
+            let list = source.clone();
+            query = query.field(
+                Field::new(
+                    format!("{}List", name),
+                    TypeRef::named_nn_list_nn(&t.name),
+                    move |ctx| {
+                        let source = list.clone();
+                        FieldFuture::new(async move {
+                            let limit = ctx.args.get("limit").map(|v| v.i64()).transpose()?;
+                            let skip = ctx.args.get("offset").map(|v| v.i64()).transpose()?;
+                            let rows = source.fetch(None, limit, skip).await?;
+                            Ok(Some(FieldValue::list(
+                                rows.into_iter().map(FieldValue::owned_any),
+                            )))
+                        })
+                    },
+                )
+                .argument(InputValue::new("limit", TypeRef::named(TypeRef::INT)))
+                .argument(InputValue::new("offset", TypeRef::named(TypeRef::INT))),
+            );
+        }
+
+        let loader = RelatedLoader {
+            sources: self.sources.clone(),
+        };
+        builder
+            .register(query)
+            .data(DataLoader::new(loader, crate::runtime::spawn))
+            .finish()
+            .map_err(|e| anyhow!("Unable to build GraphQL schema: {}", e))
+    }
+
+    fn build_field(&self, source: &Arc<dyn GraphQlSource>, field: &GraphQlField) -> Result<Field> {
+        let name = field.name.clone();
+        match &field.kind {
+            GraphQlFieldKind::Scalar {
+                type_name,
+                required,
+            } => {
+                let ty = match required {
+                    true => TypeRef::named_nn(type_name),
+                    false => TypeRef::named(type_name),
+                };
+                let type_name = type_name.clone();
+                Ok(Field::new(&field.name, ty, move |ctx| {
+                    let (name, type_name) = (name.clone(), type_name.clone());
+                    FieldFuture::new(async move {
+                        let row = ctx.parent_value.try_downcast_ref::<Map<String, Value>>()?;
+                        Ok(scalar_value(&type_name, row.get(&name)))
+                    })
+                }))
+            }
+            GraphQlFieldKind::Reference { table, many } => {
+                let keys = source
+                    .reference_keys(&field.name)
+                    .ok_or_else(|| anyhow!("Reference '{}' can't be resolved", field.name))?;
+                let type_name = &self.types[table].name;
+                let ty = match many {
+                    true => TypeRef::named_nn_list_nn(type_name),
+                    false => TypeRef::named(type_name),
+                };
+                let (table, many) = (table.clone(), *many);
+                Ok(Field::new(&field.name, ty, move |ctx| {
+                    let (table, keys) = (table.clone(), keys.clone());
+                    FieldFuture::new(async move {
+                        let row = ctx.parent_value.try_downcast_ref::<Map<String, Value>>()?;
+                        let related = match row.get(&keys.our_key) {
+                            None | Some(Value::Null) => Vec::new(),
+                            Some(value) => ctx
+                                .data::<DataLoader<RelatedLoader>>()?
+                                .load_one(RelatedKey {
+                                    table,
+                                    column: keys.their_key,
+                                    value: value.to_string(),
+                                })
+                                .await?
+                                .unwrap_or_default(),
+                        };
+                        let mut related = related.into_iter().map(FieldValue::owned_any);
+                        Ok(match many {
+                            true => Some(FieldValue::list(related)),
+                            false => related.next(),
+                        })
+                    })
+                }))
+            }
+        }
+    }
+}
+
+impl Display for GraphQlSchema {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for t in self.types.values() {
+            writeln!(f, "type {} {{", t.name)?;
+            for field in self.fields(t) {
+                writeln!(f, "  {}", self.render_field(field))?;
+            }
+            writeln!(f, "}}\n")?;
+        }
+
+        writeln!(f, "type Query {{")?;
+        for t in self.types.values() {
+            let field = lower_camel_case(&t.name);
+            writeln!(f, "  {}(id: ID!): {}", field, t.name)?;
+            writeln!(
+                f,
+                "  {}List(limit: Int, offset: Int): [{}!]!",
+                field, t.name
+            )?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+/// Table, which fetches records for the resolvers.
+trait GraphQlSource: Display + Send + Sync {
+    /// Records, where `column` is one of the values, or all records if there is
+    /// no filter.
+    fn fetch(
+        &self,
+        filter: Option<(String, Vec<Value>)>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> BoxFuture<'static, Result<Vec<Map<String, Value>>>>;
+
+    /// Filter by the id column for an `ID` argument.
+    fn id_filter(&self, id: &str) -> Result<(String, Vec<Value>)>;
+
+    fn reference_keys(&self, relation: &str) -> Option<ReferenceKeys>;
+}
+
+impl Debug for dyn GraphQlSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl<T: DataSource, E: Entity> GraphQlSource for Table<T, E> {
+    /// Fetches fields of the entity and the keys of references, so that related
+    /// records can be matched.
+    fn fetch(
+        &self,
+        filter: Option<(String, Vec<Value>)>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> BoxFuture<'static, Result<Vec<Map<String, Value>>>> {
+        let mut table = self.clone();
+        async move {
+            if let Some((column, values)) = filter {
+                let field = table
+                    .search_for_field(&column)
+                    .ok_or_else(|| Error::missing_column(&table, &column))?;
+                let condition = table
+                    .data_source
+                    .dialect()
+                    .render_in_values(field.render_chunk(), values);
+                table.add_condition(condition);
+            }
+
+            let (mut query, _) = table.select_query_for_struct(E::default())?;
+            let keys = table
+                .refs
+                .values()
+                .filter_map(|reference| reference.get_keys(&table))
+                .map(|keys| keys.our_key);
+            for key in keys {
+                if let Some(field) = table.search_for_field(&key) {
+                    query.add_field(Some(key), Arc::new(field));
+                }
+            }
+            query.add_limit(limit);
+            query.add_skip(skip);
+            table.fetch_hydrated(query).await
+        }
+        .boxed()
+    }
+
+    fn id_filter(&self, id: &str) -> Result<(String, Vec<Value>)> {
+        let column = self
+            .id_column
+            .clone()
+            .ok_or_else(|| anyhow!("Table {} has no id column", self))?;
+        let numeric = self
+            .entity_fields()
+            .iter()
+            .any(|field| field.name == column && field.field_type == FieldType::Integer);
+        let value = match id.parse::<i64>() {
+            Ok(id) if numeric => id.into(),
+            _ => id.into(),
+        };
+        Ok((column, vec![value]))
+    }
+
+    fn reference_keys(&self, relation: &str) -> Option<ReferenceKeys> {
+        self.refs.get(relation)?.get_keys(self)
+    }
+}
+
+/// Records of `table`, which have the value in `column`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct RelatedKey {
+    table: String,
+    column: String,
+    /// Value as JSON
+    value: String,
+}
+
+/// Fetches related records for all keys of the same table and column with a
+/// single query.
+struct RelatedLoader {
+    sources: IndexMap<String, Arc<dyn GraphQlSource>>,
+}
+
+impl Loader<RelatedKey> for RelatedLoader {
+    type Value = Vec<Map<String, Value>>;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[RelatedKey],
+    ) -> Result<HashMap<RelatedKey, Self::Value>, Self::Error> {
+        let mut batches: IndexMap<(&str, &str), Vec<Value>> = IndexMap::new();
+        for key in keys {
+            batches
+                .entry((&key.table, &key.column))
+                .or_default()
+                .push(serde_json::from_str(&key.value)?);
+        }
+
+        let mut related: HashMap<RelatedKey, Self::Value> = HashMap::new();
+        for ((table, column), values) in batches {
+            let source = &self.sources[table];
+            let rows = source.fetch(Some((column.to_string(), values)), None, None);
+            for row in rows.await? {
+                let key = RelatedKey {
+                    table: table.to_string(),
+                    column: column.to_string(),
+                    value: row.get(column).unwrap_or(&Value::Null).to_string(),
+                };
+                related.entry(key).or_default().push(row);
+            }
+        }
+        Ok(related)
+    }
+}
+
+/// Converts a value of a record for a scalar field. Values, which have no
+/// matching GraphQL type, such as JSON objects, are converted into strings.
+fn scalar_value(type_name: &str, value: Option<&Value>) -> Option<async_graphql::Value> {
+    use async_graphql::Value as GraphQlValue;
+
+    match value? {
+        Value::Null => None,
+        Value::Bool(value) => Some(GraphQlValue::Boolean(*value)),
+        Value::Number(value) if type_name == "Int" || type_name == "Float" => {
+            Some(GraphQlValue::Number(value.clone()))
+        }
+        Value::String(value) => Some(GraphQlValue::String(value.clone())),
+        value => Some(GraphQlValue::String(value.to_string())),
+    }
+}
+
+impl<T: DataSource, E: Entity> Table<T, E> {
+    /// Describes records of the table as a GraphQL object type, see [`GraphQlSchema`].
+    pub fn graphql_type(&self, name: &str) -> GraphQlType {
+        let mut fields: Vec<GraphQlField> = self
+            .entity_fields()
+            .into_iter()
+            .map(|field| {
+                let type_name = match field.field_type {
+                    _ if field.is_id => "ID",
+                    FieldType::Integer => "Int",
+                    FieldType::Float => "Float",
+                    FieldType::Boolean => "Boolean",
+                    FieldType::Uuid => "ID",
+                    FieldType::String | FieldType::Timestamp | FieldType::Json => "String",
+                };
+                GraphQlField {
+                    name: field.name,
+                    kind: GraphQlFieldKind::Scalar {
+                        type_name: type_name.to_string(),
+                        required: !field.nullable,
+                    },
+                }
+            })
+            .collect();
+
+        for (relation, reference) in &self.refs {
+            let Some(keys) = reference.get_keys(self) else {
+                continue;
+            };
+            let target = reference.get_related_set(self);
+            let Some(table) = related_table_name(target.as_ref()) else {
+                continue;
+            };
+            fields.push(GraphQlField {
+                name: relation.clone(),
+                kind: GraphQlFieldKind::Reference {
+                    table,
+                    many: keys.many,
+                },
+            });
+        }
+
+        GraphQlType {
+            name: name.to_string(),
+            fields,
+        }
+    }
+}
+
+fn related_table_name(table: &dyn SqlTable) -> Option<String> {
+    match table.get_empty_query().get_source() {
+        QuerySource::Table(name, _) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// `LineItem` -> `lineItem`
+fn lower_camel_case(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use crate::prelude::*;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+    struct Client {
+        id: i64,
+        name: String,
+        is_vip: bool,
+    }
+    impl Entity for Client {}
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+    struct Order {
+        id: i64,
+        client_id: i64,
+        note: Option<String>,
+        total: f64,
+    }
+    impl Entity for Order {}
+
+    fn orders(db: &MockDataSource) -> Table<MockDataSource, Order> {
+        let clients_db = db.clone();
+        let line_items_db = db.clone();
+        Table::new_with_entity("ord", db.clone())
+            .with_id_column("id")
+            .with_column("client_id")
+            .with_column("note")
+            .with_typed_column("total", SqlType::Numeric)
+            .with_one("client", "client_id", move || {
+                Box::new(clients(&clients_db))
+            })
+            .with_many("line_items", "order_id", move || {
+                Box::new(
+                    Table::new("line_item", line_items_db.clone())
+                        .with_id_column("id")
+                        .with_column("order_id"),
+                )
+            })
+    }
+
+    fn clients(db: &MockDataSource) -> Table<MockDataSource, Client> {
+        let orders_db = db.clone();
+        Table::new_with_entity("client", db.clone())
+            .with_id_column("id")
+            .with_column("name")
+            .with_column("is_vip")
+            .with_many("orders", "client_id", move || Box::new(orders(&orders_db)))
+    }
+
+    fn schema(db: &MockDataSource) -> GraphQlSchema {
+        GraphQlSchema::new()
+            .with_table("Client", &clients(db))
+            .with_table("Order", &orders(db))
+    }
+
+    #[test]
+    fn test_graphql_schema() {
+        let schema = schema(&MockDataSource::new(&json!([])));
+
+        // line_item is not in the schema
+        assert_eq!(
+            schema.to_string(),
+            "type Client {
+  id: ID!
+  name: String!
+  is_vip: Boolean!
+  orders: [Order!]!
+}
+
+type Order {
+  id: ID!
+  client_id: Int!
+  note: String
+  total: Float!
+  client: Client
+}
+
+type Query {
+  client(id: ID!): Client
+  clientList(limit: Int, offset: Int): [Client!]!
+  order(id: ID!): Order
+  orderList(limit: Int, offset: Int): [Order!]!
+}
+"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_graphql_list() {
+        let db = MockDataSource::new(&json!([]))
+            .with_expectation(
+                QueryMatcher::select("client"),
+                &json!([
+                    { "id": 1, "name": "Alice", "is_vip": true },
+                    { "id": 2, "name": "Bob", "is_vip": false },
+                ]),
+            )
+            .with_expectation(
+                QueryMatcher::select("ord"),
+                &json!([
+                    { "id": 10, "client_id": 1, "note": null, "total": 5.5 },
+                    { "id": 11, "client_id": 1, "note": "urgent", "total": 3 },
+                ]),
+            );
+        let response = schema(&db)
+            .build()
+            .unwrap()
+            .execute("{ clientList(limit: 2, offset: 4) { name orders { id note } } }")
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(
+            response.data.into_json().unwrap(),
+            json!({ "clientList": [
+                { "name": "Alice", "orders": [
+                    { "id": "10", "note": null },
+                    { "id": "11", "note": "urgent" },
+                ] },
+                { "name": "Bob", "orders": [] },
+            ] })
+        );
+
+        // orders of both clients are fetched with a single query
+        let calls = db.calls();
+        assert_eq!(
+            calls.iter().map(|c| c.sql.as_str()).collect::<Vec<_>>(),
+            vec![
+                "SELECT id, name, is_vip FROM client OFFSET {}::int4 LIMIT {}::int4",
+                "SELECT id, client_id, note, total FROM ord WHERE (client_id = ANY ({}))",
+            ]
+        );
+        let mut client_ids = calls[1].params[0].as_array().unwrap().clone();
+        client_ids.sort_by_key(|id| id.as_i64());
+        assert_eq!(client_ids, vec![json!(1), json!(2)]);
+        db.verify();
+    }
+
+    #[tokio::test]
+    async fn test_graphql_get() {
+        let db = MockDataSource::new(&json!([]))
+            .with_expectation(
+                QueryMatcher::select("ord"),
+                &json!([{ "id": 10, "client_id": 2, "note": null, "total": 5.5 }]),
+            )
+            .with_expectation(
+                QueryMatcher::select("client"),
+                &json!([{ "id": 2, "name": "Bob", "is_vip": false }]),
+            );
+        let response = schema(&db)
+            .build()
+            .unwrap()
+            .execute(r#"{ order(id: "10") { total client { name is_vip } } }"#)
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(
+            response.data.into_json().unwrap(),
+            json!({ "order": { "total": 5.5, "client": { "name": "Bob", "is_vip": false } } })
+        );
+
+        let calls = db.calls();
+        assert_eq!(
+            calls[0].sql,
+            "SELECT id, client_id, note, total FROM ord WHERE (id = ANY ({})) LIMIT {}::int4"
+        );
+        assert_eq!(calls[0].params, vec![json!([10]), json!(1)]);
+        assert_eq!(calls[1].params, vec![json!([2])]);
+    }
+}
@@ -1,15 +1,18 @@
-use anyhow::anyhow;
-use std::ptr::eq;
 use std::sync::Arc;
 
-use super::{Join, TableWithColumns};
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::{Column, Join, TableWithColumns};
 use crate::prelude::Chunk;
 use crate::sql::query::{JoinQuery, JoinType, QueryConditions};
 use crate::sql::table::Table;
-use crate::sql::Operations;
+use crate::sql::Condition;
 use crate::traits::datasource::DataSource;
 use crate::traits::entity::Entity;
 use crate::uniqid::UniqueIdVendor;
+use crate::Error;
 
 use super::{AnyTable, RelatedTable};
 
@@ -36,9 +39,9 @@ use super::{AnyTable, RelatedTable};
 /// let product_inventory = product
 ///     .with_join(
 ///         Table::new("inventory", db)
-///             .with_field("product_id")
+///             .with_id_field("product_id")
 ///             .with_field("qty"),
-///         "product_id"
+///         "id"
 ///     );
 ///
 /// product_inventory.insert(
@@ -56,6 +59,14 @@ use super::{AnyTable, RelatedTable};
 /// record in the `inventory` table. So at the end of the example, you should have
 /// 11 records in the `product` table and 6 records in the `inventory` table.
 ///
+/// Fields of the record are split between the tables. A field of the joined table
+/// can be named after its column (`qty`), prefixed with the join alias (`i_qty`) or
+/// placed in a nested struct named after the table (`inventory: { qty }`). Since
+/// `inventory.product_id` references `product.id`, the product is inserted first
+/// and its id is used for the inventory record. When our table holds the foreign
+/// key instead (`user.role_id` referencing `role.id`), the joined record is inserted
+/// first. All the inserts are executed in a single transaction.
+///
 /// When table is building query, it will use a `LEFT JOIN` by default. The table
 /// you are joining with, can have some conditions defined, and those conditions
 /// will not impact the number of records in your DataSet as they will be applied
@@ -81,7 +92,10 @@ use super::{AnyTable, RelatedTable};
 /// In this case the resulting DataSet will be affected as the new condition will be under `WHERE` clause
 /// of the main query.
 ///
-/// TODO: Actually implement this!!
+/// ## Join types
+/// Use [`Table::with_join_type()`] for an `INNER JOIN`, which only keeps records that have a
+/// matching record in the joined table. If the joined table references our id, but its foreign
+/// key is not its id column, use [`Table::with_reverse_join()`].
 ///
 /// ## Limitations
 /// Other types of joins are likely to affect number of records in the set or will make it impossible
@@ -91,7 +105,7 @@ use super::{AnyTable, RelatedTable};
 ///     the same type. Adding new product could result in type duplicates if not handled properly.
 ///  - `product.id` referencing `product_details.id` may result in some ambiguity depending on the
 ///     implementation.
-///  - not using `LEFT JOIN` may result some original records become nullable.
+///  - `RIGHT JOIN` or `FULL JOIN` may result some original records become nullable.
 ///  - joining a subquery rather than a table can be handy, but will not work consistently with
 ///    records being modified.
 ///
@@ -188,7 +202,7 @@ impl<T: DataSource, E: Entity> Table<T, E> {
 
     pub fn add_join<E2: Entity>(
         &mut self,
-        mut their_table: Table<T, E2>,
+        their_table: Table<T, E2>,
         our_foreign_id: &str,
     ) -> Arc<Join<T>> {
         //! Combine two tables with 1 to 1 relationship into a single table.
@@ -196,62 +210,192 @@ impl<T: DataSource, E: Entity> Table<T, E> {
         //! Left-Joins their_table table and return self. Assuming their_table has set id field,
         //! but we still have to specify foreign key in our own table. For more complex
         //! joins use `join_table` method.
-        //! before joining, make sure there are no alias clashes
-        if eq(&*self.table_aliases, &*their_table.table_aliases) {
-            panic!(
-                "Tables are already joined: {}, {}",
-                self.table_name, their_table.table_name
-            )
+        //!
+        //! Panics if tables can't be joined, see [`Table::try_add_join()`].
+        self.try_add_join(their_table, our_foreign_id)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as [`Table::add_join()`], but returns [`Error::AliasConflict`] if aliases of
+    /// the tables clash, or [`Error::MissingColumn`] if either table lacks the join column.
+    pub fn try_add_join<E2: Entity>(
+        &mut self,
+        their_table: Table<T, E2>,
+        our_foreign_id: &str,
+    ) -> Result<Arc<Join<T>>, Error> {
+        self.try_add_join_by_keys(their_table, Some(our_foreign_id), None, JoinType::Left)
+    }
+
+    /// Same as [`Table::with_join()`], but with a different type of join. An inner
+    /// join will only keep records, which have a matching record in their table:
+    ///
+    /// ```
+    /// let products_in_stock = products.with_join_type::<ProductInventory, _>(
+    ///     inventory, "id", JoinType::Inner
+    /// );
+    /// // SELECT .. FROM product AS p JOIN inventory AS i ON (p.id = i.product_id)
+    /// ```
+    pub fn with_join_type<E3: Entity, E2: Entity>(
+        mut self,
+        their_table: Table<T, E2>,
+        our_foreign_id: &str,
+        join_type: JoinType,
+    ) -> Table<T, E3> {
+        self.try_add_join_type(their_table, our_foreign_id, join_type)
+            .unwrap_or_else(|e| panic!("{}", e));
+        self.into_entity::<E3>()
+    }
+
+    /// Same as [`Table::try_add_join()`], but with a different type of join.
+    pub fn try_add_join_type<E2: Entity>(
+        &mut self,
+        their_table: Table<T, E2>,
+        our_foreign_id: &str,
+        join_type: JoinType,
+    ) -> Result<Arc<Join<T>>, Error> {
+        self.try_add_join_by_keys(their_table, Some(our_foreign_id), None, join_type)
+    }
+
+    /// Joins a table, which references our id with `their_foreign_id`, so there is
+    /// no need to declare it as an id column of their table:
+    ///
+    /// ```
+    /// let users = users.with_reverse_join::<UserProfile, _>(profiles, "user_id");
+    /// // SELECT .. FROM users AS u LEFT JOIN profiles AS p ON (u.id = p.user_id)
+    /// ```
+    pub fn with_reverse_join<E3: Entity, E2: Entity>(
+        mut self,
+        their_table: Table<T, E2>,
+        their_foreign_id: &str,
+    ) -> Table<T, E3> {
+        self.try_add_reverse_join(their_table, their_foreign_id)
+            .unwrap_or_else(|e| panic!("{}", e));
+        self.into_entity::<E3>()
+    }
+
+    /// Same as [`Table::with_reverse_join()`], but returns an [`Error`] instead of
+    /// panicking, see [`Table::try_add_join()`].
+    pub fn try_add_reverse_join<E2: Entity>(
+        &mut self,
+        their_table: Table<T, E2>,
+        their_foreign_id: &str,
+    ) -> Result<Arc<Join<T>>, Error> {
+        self.try_add_join_by_keys(their_table, None, Some(their_foreign_id), JoinType::Left)
+    }
+
+    /// Joins a table on an arbitrary condition, such as a composite key. Callback
+    /// receives both tables with their aliases already set:
+    ///
+    /// ```
+    /// let prices = products.with_join_on::<ProductPrice, _>(prices, |ours, theirs| {
+    ///     ours.col(KeyCol::Sku)
+    ///         .eq_col(theirs.col(KeyCol::Sku))
+    ///         .and(ours.col(KeyCol::Region).eq_col(theirs.col(KeyCol::Region)))
+    /// });
+    /// // SELECT .. FROM product AS pr LEFT JOIN price AS p
+    /// //     ON ((pr.sku = p.sku) AND (pr.region = p.region))
+    /// ```
+    ///
+    /// Records can't be inserted into a table joined like this, as it's not known
+    /// which columns need to be copied into the joined record.
+    pub fn with_join_on<E3: Entity, E2: Entity>(
+        mut self,
+        their_table: Table<T, E2>,
+        on: impl FnOnce(&Self, &Table<T, E2>) -> Condition,
+    ) -> Table<T, E3> {
+        self.try_add_join_on(their_table, JoinType::Left, on)
+            .unwrap_or_else(|e| panic!("{}", e));
+        self.into_entity::<E3>()
+    }
+
+    /// Same as [`Table::with_join_on()`], but with a join type and returns
+    /// [`Error::AliasConflict`] if aliases of the tables clash.
+    pub fn try_add_join_on<E2: Entity>(
+        &mut self,
+        their_table: Table<T, E2>,
+        join_type: JoinType,
+        on: impl FnOnce(&Self, &Table<T, E2>) -> Condition,
+    ) -> Result<Arc<Join<T>>, Error> {
+        self.try_add_join_with(their_table, join_type, None, on)
+    }
+
+    /// Joins their table on `our_column = their_column`. If column is not
+    /// specified, id column of the table is used.
+    fn try_add_join_by_keys<E2: Entity>(
+        &mut self,
+        their_table: Table<T, E2>,
+        our_column: Option<&str>,
+        their_column: Option<&str>,
+        join_type: JoinType,
+    ) -> Result<Arc<Join<T>>, Error> {
+        self.check_join_aliases(&their_table)?;
+        let our_column = match our_column {
+            Some(column) => column.to_string(),
+            None => self.try_id()?.name(),
+        };
+        if self.get_column(&our_column).is_none() {
+            return Err(Error::missing_column(&self, &our_column));
+        }
+        let their_column = match their_column {
+            Some(column) => column.to_string(),
+            None => their_table.try_id()?.name(),
+        };
+        if their_table.get_column(&their_column).is_none() {
+            return Err(Error::missing_column(&their_table, &their_column));
         }
 
-        if their_table
-            .table_aliases
-            .lock()
-            .unwrap()
-            .has_conflict(&self.table_aliases.lock().unwrap())
-        {
-            panic!(
-                "Table alias conflict while joining: {}, {}",
-                self.table_name, their_table.table_name
-            )
+        let keys = (our_column.clone(), their_column.clone());
+        self.try_add_join_with(their_table, join_type, Some(keys), |ours, theirs| {
+            ours.get_column(&our_column)
+                .unwrap()
+                .eq_col(theirs.get_column(&their_column).unwrap())
+        })
+    }
+
+    fn check_join_aliases<E2: Entity>(&self, their_table: &Table<T, E2>) -> Result<(), Error> {
+        let conflicts = their_table.used_aliases().conflicts(&self.used_aliases());
+        if !conflicts.is_empty() {
+            return Err(Error::AliasConflict {
+                table: self.table_name.clone(),
+                other: their_table.table_name.clone(),
+                aliases: conflicts,
+            });
         }
+        Ok(())
+    }
 
-        self.table_aliases
-            .lock()
-            .unwrap()
-            .merge(their_table.table_aliases.lock().unwrap().to_owned());
+    /// Gives aliases to both tables and joins their table on the condition returned
+    /// by `on`. Conditions of their table are moved into the ON clause.
+    fn try_add_join_with<E2: Entity>(
+        &mut self,
+        mut their_table: Table<T, E2>,
+        join_type: JoinType,
+        keys: Option<(String, String)>,
+        on: impl FnOnce(&Self, &Table<T, E2>) -> Condition,
+    ) -> Result<Arc<Join<T>>, Error> {
+        // before joining, make sure there are no alias clashes
+        self.check_join_aliases(&their_table)?;
+        let mut aliases = self.used_aliases();
+        aliases.merge(their_table.used_aliases());
 
         // Get information about their_table
         let their_table_name = their_table.table_name.clone();
         if their_table.table_alias.is_none() {
-            let their_table_alias = self
-                .table_aliases
-                .lock()
-                .unwrap()
-                .get_one_of_uniq_id(UniqueIdVendor::all_prefixes(&their_table_name));
+            let their_table_alias =
+                aliases.get_one_of_uniq_id(UniqueIdVendor::all_prefixes(&their_table_name));
             their_table.set_alias(&their_table_alias);
         };
-        let their_table_id = their_table.id();
 
         // Give alias to our table as well
         if self.table_alias.is_none() {
-            let our_table_alias = self
-                .table_aliases
-                .lock()
-                .unwrap()
-                .get_one_of_uniq_id(UniqueIdVendor::all_prefixes(&self.table_name));
+            let our_table_alias =
+                aliases.get_one_of_uniq_id(UniqueIdVendor::all_prefixes(&self.table_name));
             self.set_alias(&our_table_alias);
         }
         let their_table_alias = their_table.table_alias.as_ref().unwrap().clone();
 
         let mut on_condition = QueryConditions::on();
-        on_condition.add_condition(
-            self.get_column(our_foreign_id)
-                .ok_or_else(|| anyhow!("Table '{}' has no field '{}'", &self, &our_foreign_id))
-                .unwrap()
-                .eq(&their_table_id)
-                .render_chunk(),
-        );
+        on_condition.add_condition(on(self, &their_table).render_chunk());
 
         // Any condition in their_table should be moved into ON condition
         for condition in their_table.conditions.iter() {
@@ -261,19 +405,187 @@ impl<T: DataSource, E: Entity> Table<T, E> {
 
         // Create a join
         let join = JoinQuery::new(
-            JoinType::Left,
-            crate::sql::query::QuerySource::Table(
-                their_table_name,
-                Some(their_table_alias.clone()),
-            ),
+            join_type,
+            their_table.query_source(Some(their_table_alias.clone())),
             on_condition,
         );
         self.joins.insert(
             their_table_alias.clone(),
-            Arc::new(Join::new(their_table.into_entity(), join)),
+            Arc::new(Join::new(their_table.into_entity(), join, keys)),
         );
 
-        self.get_join(&their_table_alias).unwrap()
+        Ok(self.get_join(&their_table_alias).unwrap())
+    }
+
+    /// Columns of joined tables are fetched with a join alias prefix (`i_stock`).
+    /// Returns joined column for such a prefixed name.
+    pub(super) fn search_for_prefixed_column(&self, name: &str) -> Option<Arc<Column>> {
+        self.joins.iter().find_map(|(alias, join)| {
+            join.table()
+                .get_column(name.strip_prefix(&format!("{}_", alias))?)
+        })
+    }
+
+    /// Returns alias and join for a joined table called `name`. Used for nested
+    /// struct fields, see [`Table::hydrate_nested()`].
+    pub(super) fn get_join_by_table_name(&self, name: &str) -> Option<(&String, &Arc<Join<T>>)> {
+        self.joins
+            .iter()
+            .find(|(_, join)| join.table().table_name == name)
+    }
+
+    /// Collects prefixed columns of each joined table (`i_stock`) into a nested object
+    /// named after the joined table (`inventory: { stock }`), so that the row can be
+    /// deserialized into a struct with a nested field:
+    ///
+    /// ```
+    /// struct ProductInventory {
+    ///     name: String,
+    ///     inventory: Option<Inventory>,
+    /// }
+    /// ```
+    ///
+    /// If all the joined columns are NULL (nothing was joined), nested value is NULL.
+    pub(super) fn hydrate_nested(&self, row: &mut Map<String, Value>) {
+        for (alias, join) in &self.joins {
+            let name = &join.table().table_name;
+            if row.contains_key(name) {
+                continue;
+            }
+            let prefix = format!("{}_", alias);
+            let nested = row
+                .iter()
+                .filter_map(|(k, v)| Some((k.strip_prefix(&prefix)?.to_string(), v.clone())))
+                .collect::<Map<String, Value>>();
+            if nested.is_empty() {
+                continue;
+            }
+            let value = if nested.values().all(Value::is_null) {
+                Value::Null
+            } else {
+                Value::Object(nested)
+            };
+            row.insert(name.clone(), value);
+        }
+    }
+
+    /// Values of a record for the columns of a joined table. A value can be given
+    /// with the column name, with the alias prefix (`i_stock`) or inside a nested
+    /// object named after the joined table. Columns of our own table take precedence
+    /// over unprefixed names.
+    fn joined_values(
+        &self,
+        alias: &str,
+        join: &Join<T>,
+        record: &Map<String, Value>,
+    ) -> Map<String, Value> {
+        let nested = match record.get(&join.table().table_name) {
+            Some(Value::Object(nested)) => Some(nested),
+            _ => None,
+        };
+        let mut values = Map::new();
+        for column_name in join.table().columns().keys() {
+            let value = nested
+                .and_then(|nested| nested.get(column_name))
+                .or_else(|| record.get(&format!("{}_{}", alias, column_name)))
+                .or_else(|| {
+                    if self.get_column(column_name).is_some() {
+                        None
+                    } else {
+                        record.get(column_name)
+                    }
+                });
+            if let Some(value) = value {
+                values.insert(column_name.clone(), value.clone());
+            }
+        }
+        values
+    }
+
+    /// Inserts a record into our table and all the joined tables in a transaction.
+    ///
+    /// If we reference the joined table by a foreign key (`user.role_id = role.id`),
+    /// the joined row is inserted first and its id is stored in our foreign key.
+    /// If the joined table references our id (`product.id = inventory.product_id`),
+    /// our row is inserted first and its id is stored in the joined row.
+    pub(super) async fn insert_with_joins(&self, record: impl Serialize) -> Result<Option<Value>> {
+        let Value::Object(mut values) = serde_json::to_value(record)? else {
+            return Err(anyhow!("Values must be a struct"));
+        };
+
+        self.data_source.begin_transaction().await?;
+        match self.insert_joined_rows(&mut values).await {
+            Ok(id) => {
+                self.data_source.commit_transaction().await?;
+                Ok(id)
+            }
+            Err(e) => {
+                if let Err(rollback) = self.data_source.rollback_transaction().await {
+                    log::error!("Unable to roll back insert into {}: {:#}", self, rollback);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    async fn insert_joined_rows(&self, values: &mut Map<String, Value>) -> Result<Option<Value>> {
+        let our_id_column = self.id_column.as_deref().unwrap_or("id");
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+        for (alias, join) in &self.joins {
+            let Some((our_column, their_column)) = join.keys() else {
+                return Err(anyhow!(
+                    "Can't insert into {}, join '{}' is on a custom condition",
+                    self,
+                    alias
+                ));
+            };
+            if our_column == our_id_column {
+                after.push((alias, join, their_column));
+            } else {
+                before.push((alias, join, our_column, their_column));
+            }
+        }
+
+        for (alias, join, our_column, their_id) in before {
+            let query = join
+                .table()
+                .get_insert_query(self.joined_values(alias, join, values))?
+                .with_returning(&[their_id]);
+            let result = self.data_source.query_exec(&query).await?;
+            let id = result.row().and_then(|row| row.get(their_id)).cloned();
+            let id = id.ok_or_else(|| {
+                anyhow!("Insert into {} did not return '{}'", join.table(), their_id)
+            })?;
+            values.insert(our_column.to_string(), id);
+        }
+
+        let mut query = self.get_insert_query(&*values)?;
+        if !after.is_empty() {
+            query = query.with_returning(&[our_id_column]);
+        }
+        let result = self.data_source.query_exec(&query).await?;
+        let our_id = result.row().and_then(|row| row.get(our_id_column)).cloned();
+
+        for (alias, join, their_column) in after {
+            let our_id = our_id
+                .clone()
+                .or_else(|| values.get(our_id_column).cloned())
+                .ok_or_else(|| {
+                    anyhow!("Insert into {} did not return '{}'", self, our_id_column)
+                })?;
+            let mut joined = self.joined_values(alias, join, values);
+            joined.insert(their_column.to_string(), our_id);
+            let query = join.table().get_insert_query(joined)?;
+            self.data_source.query_exec(&query).await?;
+        }
+
+        let id = self
+            .id_column
+            .as_ref()
+            .and_then(|_| our_id.or_else(|| values.get(our_id_column).cloned()));
+        self.hooks.after_insert(self, id.as_ref())?;
+        Ok(id)
     }
 }
 
@@ -285,8 +597,9 @@ mod tests {
 
     use super::*;
     use crate::{
+        dataset::WritableDataSet,
         mocks::datasource::MockDataSource,
-        prelude::{Chunk, EmptyEntity, Operations, TableWithQueries},
+        prelude::{Chunk, EmptyEntity, JoinType, Operations, QueryMatcher, TableWithQueries},
         sql::Condition,
     };
     #[test]
@@ -312,6 +625,53 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_nested_struct() {
+        use crate::dataset::ReadableDataSet;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+        struct Inventory {
+            stock: i64,
+        }
+        #[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+        struct ProductInventory {
+            name: String,
+            i_stock: Option<i64>,
+            inventory: Option<Inventory>,
+        }
+        impl Entity for ProductInventory {}
+
+        let data = json!([
+            { "name": "Cake", "i_stock": 5 },
+            { "name": "Pie", "i_stock": null },
+        ]);
+        let db = MockDataSource::new(&data);
+
+        let products = Table::new("product", db.clone())
+            .with_alias("p")
+            .with_id_column("id")
+            .with_column("name");
+        let inventory = Table::new("inventory", db.clone())
+            .with_alias("i")
+            .with_id_column("product_id")
+            .with_column("stock");
+        let products = products.with_join::<ProductInventory, _>(inventory, "id");
+
+        assert_eq!(
+            products
+                .get_select_query_for_struct(ProductInventory::default())
+                .preview(),
+            "SELECT p.name, i.stock AS i_stock, i.product_id AS i_product_id \
+            FROM product AS p LEFT JOIN inventory AS i ON (p.id = i.product_id)"
+        );
+
+        let result = products.get().await.unwrap();
+        assert_eq!(result[0].i_stock, Some(5));
+        assert_eq!(result[0].inventory, Some(Inventory { stock: 5 }));
+        assert_eq!(result[1].inventory, None);
+    }
+
     #[ignore = "broken for now TODO fix"]
     #[test]
     fn join_table_with_joins() {
@@ -369,6 +729,47 @@ mod tests {
         assert_eq!(query.1[0], json!("admin"));
     }
 
+    #[test]
+    fn test_stable_aliases() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let users = || {
+            Table::new("users", db.clone())
+                .with_column("name")
+                .with_column("role_id")
+                .with_column("unit_id")
+        };
+        let roles = Table::new("roles", db.clone()).with_id_column("id");
+        let units = Table::new("units", db.clone()).with_id_column("id");
+
+        let joined = users()
+            .with_join::<EmptyEntity, _>(roles.clone(), "role_id")
+            .with_join::<EmptyEntity, _>(units.clone(), "unit_id");
+        let sql = joined.get_select_query().preview();
+        assert_eq!(
+            sql,
+            "SELECT u.name, u.role_id, u.unit_id, r.id AS r_id, un.id AS un_id FROM users AS u \
+            LEFT JOIN roles AS r ON (u.role_id = r.id) LEFT JOIN units AS un ON (u.unit_id = un.id)"
+        );
+
+        // a clone, or a table built again, renders the same SQL
+        assert_eq!(joined.clone().get_select_query().preview(), sql);
+        let rebuilt = users()
+            .with_join::<EmptyEntity, _>(roles, "role_id")
+            .with_join::<EmptyEntity, _>(units, "unit_id");
+        assert_eq!(rebuilt.get_select_query().preview(), sql);
+
+        // aliases of tables, which were not joined, are not reserved
+        let used = Table::new("users", db.clone())
+            .with_alias("r")
+            .with_alias("us");
+        assert_eq!(
+            used.used_aliases().conflicts(&joined.used_aliases()),
+            vec![] as Vec<String>
+        );
+    }
+
     #[test]
     fn test_conditions_moved_into_on() {
         let data = json!([]);
@@ -426,22 +827,42 @@ mod tests {
 
         let query = user_table.get_select_query().render_chunk().split();
 
-        // TODO: due to Condition::or() implementation, it renders second argument
-        // into expression. In fact we push our luck here - perhaps the field we
-        // are recursively changing is not even of our table.
-        //
-        // Ideally table alias should be set before a bunch of Fields are given away
         assert_eq!(
             query.0,
             "SELECT u.name, u.role_id, r.id AS r_id, r.role_type AS r_role_type FROM users AS u \
             LEFT JOIN roles AS r ON (u.role_id = r.id) AND \
-            ((r.role_type = {}) OR (role_type = {}))"
+            ((r.role_type = {}) OR (r.role_type = {}))"
         );
         assert_eq!(query.1[0], json!("admin"));
     }
 
     #[test]
-    #[should_panic]
+    fn test_column_conditions_moved_into_on() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let mut user_table = Table::new("users", db.clone())
+            .with_column("level")
+            .with_column("role_id");
+        let mut role_table = Table::new("roles", db.clone())
+            .with_column("id")
+            .with_column("min_level");
+
+        // neither table has an alias yet, but only columns of roles are re-aliased
+        let level = user_table.get_column("level").unwrap();
+        role_table.add_condition(role_table.get_column("min_level").unwrap().lt_col(level));
+
+        user_table.add_join(role_table, "role_id");
+
+        assert_eq!(
+            user_table.get_select_query().render_chunk().sql(),
+            "SELECT u.level, u.role_id, r.id AS r_id, r.min_level AS r_min_level FROM users AS u \
+            LEFT JOIN roles AS r ON (u.role_id = r.id) AND (r.min_level < level)"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "joining 'users' with 'roles': both tables use alias u")]
     fn test_join_panic() {
         let data = json!([]);
         let db = MockDataSource::new(&data);
@@ -452,4 +873,216 @@ mod tests {
         // will panic, both tables want "u" alias
         user_table.with_join::<EmptyEntity, _>(role_table, "role_id");
     }
+
+    #[test]
+    fn test_try_add_join() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let mut user_table = Table::new("users", db.clone())
+            .with_alias("u")
+            .with_column("role_id");
+        let role_table = Table::new("roles", db.clone()).with_alias("u");
+
+        assert!(matches!(
+            user_table.try_add_join(role_table, "role_id"),
+            Err(Error::AliasConflict { aliases, .. }) if aliases == vec!["u".to_string()]
+        ));
+
+        let role_table = Table::new("roles", db.clone()).with_column("role_type");
+        assert!(matches!(
+            user_table.try_add_join(role_table, "role_id"),
+            Err(Error::MissingColumn { column, .. }) if column == "id"
+        ));
+    }
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Default)]
+    struct ProductInventory {
+        name: String,
+        stock: i64,
+    }
+    impl Entity for ProductInventory {}
+
+    #[tokio::test]
+    async fn test_insert_into_joined_table() {
+        let db = MockDataSource::new(&json!([]))
+            .with_expectation(QueryMatcher::insert("product"), &json!([{"id": 7}]))
+            .with_expectation(QueryMatcher::insert("inventory"), &json!([]));
+
+        let products = Table::new("product", db.clone())
+            .with_id_column("id")
+            .with_column("name")
+            .with_join::<ProductInventory, _>(
+                Table::new("inventory", db.clone())
+                    .with_alias("i")
+                    .with_id_column("product_id")
+                    .with_column("stock"),
+                "id",
+            );
+
+        let id = products
+            .insert(ProductInventory {
+                name: "Pie".to_string(),
+                stock: 3,
+            })
+            .await
+            .unwrap();
+        assert_eq!(id, Some(json!(7)));
+
+        let calls: Vec<String> = db.calls().into_iter().map(|call| call.sql).collect();
+        assert_eq!(
+            calls,
+            vec![
+                "INSERT INTO product (name) VALUES ({}) RETURNING id",
+                "INSERT INTO inventory (product_id, stock) VALUES ({}, {}) RETURNING product_id",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_insert_with_joined_foreign_key() {
+        let db = MockDataSource::new(&json!([]))
+            .with_expectation(QueryMatcher::insert("roles"), &json!([{"id": 3}]))
+            .with_expectation(QueryMatcher::insert("users"), &json!([]));
+
+        let users = Table::new("users", db.clone())
+            .with_column("name")
+            .with_column("role_id")
+            .with_join::<EmptyEntity, _>(
+                Table::new("roles", db.clone())
+                    .with_id_column("id")
+                    .with_column("role_type"),
+                "role_id",
+            );
+
+        users
+            .insert_with_joins(json!({"name": "John", "r_role_type": "admin"}))
+            .await
+            .unwrap();
+
+        let calls: Vec<String> = db.calls().into_iter().map(|call| call.sql).collect();
+        assert_eq!(
+            calls,
+            vec![
+                "INSERT INTO roles (role_type) VALUES ({}) RETURNING id",
+                "INSERT INTO users (name, role_id) VALUES ({}, {})",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_join_type() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let products = Table::new("product", db.clone())
+            .with_id_column("id")
+            .with_column("name")
+            .with_join_type::<EmptyEntity, _>(
+                Table::new("inventory", db.clone())
+                    .with_alias("i")
+                    .with_id_column("product_id")
+                    .with_column("stock"),
+                "id",
+                JoinType::Inner,
+            );
+
+        assert_eq!(
+            products.get_select_query().preview(),
+            "SELECT p.id, p.name, i.product_id AS i_product_id, i.stock AS i_stock \
+            FROM product AS p JOIN inventory AS i ON (p.id = i.product_id)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reverse_join() {
+        let db = MockDataSource::new(&json!([]))
+            .with_expectation(QueryMatcher::insert("users"), &json!([{"id": 5}]))
+            .with_expectation(QueryMatcher::insert("profiles"), &json!([]));
+
+        let users = Table::new("users", db.clone())
+            .with_id_column("id")
+            .with_column("name")
+            .with_reverse_join::<EmptyEntity, _>(
+                Table::new("profiles", db.clone())
+                    .with_id_column("id")
+                    .with_column("user_id")
+                    .with_column("bio"),
+                "user_id",
+            );
+
+        assert_eq!(
+            users.get_select_query().preview(),
+            "SELECT u.id, u.name, p.id AS p_id, p.user_id AS p_user_id, p.bio AS p_bio \
+            FROM users AS u LEFT JOIN profiles AS p ON (u.id = p.user_id)"
+        );
+
+        users
+            .insert_with_joins(json!({"name": "John", "bio": "Likes pie"}))
+            .await
+            .unwrap();
+        let calls: Vec<String> = db.calls().into_iter().map(|call| call.sql).collect();
+        assert_eq!(
+            calls,
+            vec![
+                "INSERT INTO users (name) VALUES ({}) RETURNING id",
+                "INSERT INTO profiles (user_id, bio) VALUES ({}, {}) RETURNING id",
+            ]
+        );
+
+        let mut users = Table::new("users", db.clone()).with_column("name");
+        assert!(matches!(
+            users.try_add_reverse_join(Table::new("profiles", db.clone()), "user_id"),
+            Err(Error::MissingColumn { column, .. }) if column == "id"
+        ));
+    }
+
+    crate::table_columns! {
+        enum KeyCol {
+            Sku = "sku",
+            Region = "region",
+        }
+    }
+
+    #[tokio::test]
+    async fn test_join_on() {
+        let db = MockDataSource::new(&json!([]));
+
+        let prices = Table::new("price", db.clone())
+            .with_column("sku")
+            .with_column("region")
+            .with_column("amount");
+        let mut products = Table::new("product", db.clone())
+            .with_id_column("id")
+            .with_column("sku")
+            .with_column("region")
+            .with_join_on::<EmptyEntity, _>(prices, |ours, theirs| {
+                ours.col(KeyCol::Sku)
+                    .eq_col(theirs.col(KeyCol::Sku))
+                    .and(ours.col(KeyCol::Region).eq_col(theirs.col(KeyCol::Region)))
+            });
+
+        assert_eq!(
+            products.get_select_query().preview(),
+            "SELECT pr.id, pr.sku, pr.region, p.sku AS p_sku, p.region AS p_region, \
+            p.amount AS p_amount FROM product AS pr LEFT JOIN price AS p \
+            ON ((pr.sku = p.sku) AND (pr.region = p.region))"
+        );
+
+        let discounts = Table::new("discount", db.clone()).with_column("sku");
+        products
+            .try_add_join_on(discounts, JoinType::Inner, |ours, theirs| {
+                ours.col(KeyCol::Sku).eq_col(theirs.col(KeyCol::Sku))
+            })
+            .unwrap();
+        assert!(products
+            .get_select_query()
+            .preview()
+            .ends_with("JOIN discount AS d ON (pr.sku = d.sku)"));
+
+        assert!(products
+            .insert_with_joins(json!({"sku": "PIE-1", "amount": 3}))
+            .await
+            .is_err());
+    }
 }
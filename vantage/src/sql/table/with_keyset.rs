@@ -0,0 +1,263 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+
+use crate::expr_arc;
+use crate::prelude::AssociatedQuery;
+use crate::sql::query::{Direction, SqlQuery};
+use crate::sql::table::Table;
+use crate::sql::{Chunk, Expression, ExpressionArc};
+use crate::traits::datasource::DataSource;
+use crate::traits::entity::Entity;
+use crate::Error;
+
+use super::TableWithColumns;
+
+/// Position in a keyset-paginated [`Table`]. Cursor contains values of the keyset
+/// columns for the last record of a page. It can be converted to an opaque string
+/// and back, so that you can pass it to the client of your API:
+///
+/// ```
+/// let next: String = cursor.to_string();
+/// let cursor: Cursor = next.parse()?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    values: Vec<Value>,
+}
+
+impl Cursor {
+    pub fn new(values: Vec<Value>) -> Self {
+        Cursor { values }
+    }
+
+    pub fn values(&self) -> &Vec<Value> {
+        &self.values
+    }
+}
+
+impl From<Value> for Cursor {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Array(values) => Cursor::new(values),
+            value => Cursor::new(vec![value]),
+        }
+    }
+}
+
+impl Display for Cursor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let json = Value::Array(self.values.clone()).to_string();
+        for byte in json.as_bytes() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if !s.is_ascii() || !s.len().is_multiple_of(2) {
+            return Err(anyhow!("Malformed cursor"));
+        }
+        let bytes = s
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(std::str::from_utf8(pair)?, 16).map_err(Into::into))
+            .collect::<Result<Vec<u8>>>()
+            .context("Malformed cursor")?;
+        let Value::Array(values) = serde_json::from_slice(&bytes).context("Malformed cursor")?
+        else {
+            return Err(anyhow!("Malformed cursor"));
+        };
+        Ok(Cursor::new(values))
+    }
+}
+
+/// # Keyset pagination
+///
+/// OFFSET pagination becomes slow on large tables, because the database still has
+/// to go through all the skipped rows. Keyset pagination instead remembers the last
+/// record of the page and asks for records that come after it:
+///
+/// ```
+/// let products = Product::table().with_keyset(&["price"]);
+///
+/// let page = products.keyset_query(None)?.with_limit(10).get().await?;
+/// let cursor = products.cursor_for(page.last().unwrap())?;
+///
+/// let next_page = products.after(cursor)?.with_limit(10).get().await?;
+/// // WHERE ((price, id) > ({}, {})) ORDER BY price ASC, id ASC LIMIT 10
+/// ```
+///
+/// The id column is always added as the last keyset column, to guarantee a stable
+/// order even if other columns are not unique.
+impl<T: DataSource, E: Entity> Table<T, E> {
+    pub fn with_keyset(mut self, columns: &[&str]) -> Self {
+        self.keyset = columns.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    fn keyset_columns(&self) -> Vec<String> {
+        let id_column = self.id_column.clone().unwrap_or("id".to_string());
+        let mut columns = self.keyset.clone();
+        if !columns.contains(&id_column) {
+            columns.push(id_column);
+        }
+        columns
+    }
+
+    fn keyset_fields(&self) -> Result<Vec<Expression>> {
+        self.keyset_columns()
+            .iter()
+            .map(|name| {
+                self.search_for_field(name)
+                    .map(|field| field.render_chunk())
+                    .ok_or_else(|| Error::missing_column(self, name).into())
+            })
+            .collect()
+    }
+
+    /// Returns query for a page of records following the `cursor`, or for the first page
+    /// if cursor is `None`. Use [`AssociatedQuery::with_limit()`] to set page size.
+    ///
+    /// Fails with [`Error::MissingColumn`] if a keyset column does not exist.
+    pub fn keyset_query(&self, cursor: Option<&Cursor>) -> Result<AssociatedQuery<T, E>> {
+        let fields = self.keyset_fields()?;
+        let (mut query, _) = self.select_query_for_struct(E::default())?;
+
+        if let Some(cursor) = cursor {
+            let placeholders = Expression::new(
+                vec!["{}"; cursor.values().len()].join(", "),
+                cursor.values().clone(),
+            );
+            query.get_where_conditions_mut().add_condition(
+                expr_arc!(
+                    "(({}) > ({}))",
+                    Expression::from_vec(fields.clone(), ", "),
+                    placeholders
+                )
+                .render_chunk(),
+            );
+        }
+
+        // Query renders latest order first, so add them in reverse
+        for field in fields.iter().rev() {
+            query.add_order_by(Direction::Asc.order(field));
+        }
+
+        Ok(AssociatedQuery::new(query, self.data_source.clone()))
+    }
+
+    /// Returns query for records following the cursor. A single value (such as id)
+    /// or an array of values can be used in place of a cursor.
+    pub fn after(&self, cursor: impl Into<Cursor>) -> Result<AssociatedQuery<T, E>> {
+        self.keyset_query(Some(&cursor.into()))
+    }
+
+    /// Build a cursor pointing at the `record`, to fetch the next page.
+    pub fn cursor_for(&self, record: &E) -> Result<Cursor> {
+        let Value::Object(map) = serde_json::to_value(record)? else {
+            return Err(anyhow!("Entity must serialize into a struct"));
+        };
+        let values = self
+            .keyset_columns()
+            .iter()
+            .map(|name| {
+                map.get(name)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("Entity has no keyset field '{}'", name))
+            })
+            .collect::<Result<Vec<Value>>>()?;
+        Ok(Cursor::new(values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::mocks::datasource::MockDataSource;
+
+    #[derive(Serialize, Deserialize, Clone, Default, Debug)]
+    struct Product {
+        id: i64,
+        name: String,
+        price: i64,
+    }
+    impl Entity for Product {}
+
+    fn products() -> Table<MockDataSource, Product> {
+        Table::new_with_entity("product", MockDataSource::new(&json!([])))
+            .with_id_column("id")
+            .with_column("name")
+            .with_column("price")
+    }
+
+    #[test]
+    fn test_keyset_by_id() {
+        let products = products();
+
+        assert_eq!(
+            products
+                .keyset_query(None)
+                .unwrap()
+                .with_limit(10)
+                .preview(),
+            "SELECT id, name, price FROM product ORDER BY id ASC LIMIT 10::int4"
+        );
+        assert_eq!(
+            products.after(json!(5)).unwrap().with_limit(10).preview(),
+            "SELECT id, name, price FROM product WHERE ((id) > (5)) ORDER BY id ASC LIMIT 10::int4"
+        );
+    }
+
+    #[test]
+    fn test_keyset_by_tuple() {
+        let products = products().with_keyset(&["price"]);
+        let cursor = products
+            .cursor_for(&Product {
+                id: 5,
+                name: "Cake".to_string(),
+                price: 120,
+            })
+            .unwrap();
+
+        assert_eq!(cursor.values(), &vec![json!(120), json!(5)]);
+        assert_eq!(
+            products.after(cursor).unwrap().with_limit(10).preview(),
+            "SELECT id, name, price FROM product WHERE ((price, id) > (120, 5)) \
+            ORDER BY price ASC, id ASC LIMIT 10::int4"
+        );
+    }
+
+    #[test]
+    fn test_keyset_missing_column() {
+        let products = products().with_keyset(&["weight"]);
+
+        let err = products.keyset_query(None).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::MissingColumn { column, .. }) if column == "weight"
+        ));
+        assert!(products.after(json!(5)).is_err());
+    }
+
+    #[test]
+    fn test_cursor_string() {
+        let cursor = Cursor::new(vec![json!("Cake"), json!(5)]);
+        let encoded = cursor.to_string();
+
+        assert_eq!(encoded.parse::<Cursor>().unwrap(), cursor);
+        assert!("zz".parse::<Cursor>().is_err());
+        assert!("aéb".parse::<Cursor>().is_err());
+        assert!("éé".parse::<Cursor>().is_err());
+    }
+}
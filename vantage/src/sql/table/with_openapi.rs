@@ -0,0 +1,320 @@
+use indexmap::IndexMap;
+use serde_json::{json, Map, Value};
+
+use crate::sql::table::Table;
+use crate::traits::datasource::DataSource;
+use crate::traits::entity::Entity;
+
+use super::entity_fields::{EntityField, FieldType};
+use super::Operation;
+
+/// # OpenAPI document
+///
+/// Describes REST API for tables, so the field lists are not repeated. Enabled with
+/// `openapi` feature:
+///
+/// ```
+/// let api = OpenApi::new("Bakery API", "1.0")
+///     .with_table("/orders", "Order", &Order::table(), &[Operation::List, Operation::Get])
+///     .with_table("/products", "Product", &Product::table(), &Operation::ALL);
+///
+/// // GET /openapi.json
+/// async fn openapi() -> Json<Value> {
+///     Json(api.to_json())
+/// }
+/// ```
+///
+/// Component schema follows the entity, same as [`Table::graphql_type()`] does. Id,
+/// expressions, imported fields and generated columns are marked `readOnly`.
+/// List operation takes `page`, `per_page`, `sort` and `filter` parameters, as
+/// used by `vantage::web::ListParams`.
+///
+/// [`Table::graphql_type()`]: Table::graphql_type()
+#[derive(Debug, Clone)]
+pub struct OpenApi {
+    title: String,
+    version: String,
+    schemas: IndexMap<String, Value>,
+    paths: IndexMap<String, Map<String, Value>>,
+}
+
+impl OpenApi {
+    pub fn new(title: &str, version: &str) -> OpenApi {
+        OpenApi {
+            title: title.to_string(),
+            version: version.to_string(),
+            schemas: IndexMap::new(),
+            paths: IndexMap::new(),
+        }
+    }
+
+    /// Adds component schema `name` for records of the table and `operations`
+    /// under `path`.
+    pub fn with_table<T: DataSource, E: Entity>(
+        mut self,
+        path: &str,
+        name: &str,
+        table: &Table<T, E>,
+        operations: &[Operation],
+    ) -> Self {
+        self.add_table(path, name, table, operations);
+        self
+    }
+
+    pub fn add_table<T: DataSource, E: Entity>(
+        &mut self,
+        path: &str,
+        name: &str,
+        table: &Table<T, E>,
+        operations: &[Operation],
+    ) {
+        let fields = table.entity_fields();
+        let id_schema = fields
+            .iter()
+            .find(|field| field.is_id)
+            .map(field_schema)
+            .unwrap_or_else(|| json!({"type": "string"}));
+        self.schemas
+            .insert(name.to_string(), entity_schema(&fields));
+
+        let reference = json!({"$ref": format!("#/components/schemas/{}", name)});
+        let record_path = format!("{}/{{id}}", path.trim_end_matches('/'));
+        let not_found = json!({"description": format!("{} not found", name)});
+
+        for operation in operations {
+            let (path, method, mut definition) = match operation {
+                Operation::List => (
+                    path.to_string(),
+                    "get",
+                    json!({
+                        "summary": format!("List {} records", name),
+                        "operationId": format!("list{}", name),
+                        "parameters": [
+                            query_parameter("page", "Page number, starting with 0", json!({"type": "integer", "minimum": 0})),
+                            query_parameter("per_page", "Records per page", json!({"type": "integer", "minimum": 1})),
+                            query_parameter("sort", "Comma separated fields, prefixed with - for descending order", json!({"type": "string"})),
+                            query_parameter("filter", "JSON filter document, such as {\"price\": {\"gt\": 10}}", json!({"type": "string"})),
+                        ],
+                        "responses": {
+                            "200": {
+                                "description": format!("Page of {} records", name),
+                                "headers": {
+                                    "X-Total-Count": {"schema": {"type": "integer"}},
+                                },
+                                "content": {
+                                    "application/json": {
+                                        "schema": {"type": "array", "items": reference},
+                                    },
+                                },
+                            },
+                        },
+                    }),
+                ),
+                Operation::Get => (
+                    record_path.clone(),
+                    "get",
+                    json!({
+                        "summary": format!("Get {} by id", name),
+                        "operationId": format!("get{}", name),
+                        "responses": {
+                            "200": {
+                                "description": name,
+                                "content": {"application/json": {"schema": reference}},
+                            },
+                            "404": not_found,
+                        },
+                    }),
+                ),
+                Operation::Create => (
+                    path.to_string(),
+                    "post",
+                    json!({
+                        "summary": format!("Create {}", name),
+                        "operationId": format!("create{}", name),
+                        "requestBody": {
+                            "required": true,
+                            "content": {"application/json": {"schema": reference}},
+                        },
+                        "responses": {
+                            "201": {"description": format!("{} created", name)},
+                        },
+                    }),
+                ),
+                Operation::Update => (
+                    record_path.clone(),
+                    "patch",
+                    json!({
+                        "summary": format!("Update fields of {}", name),
+                        "operationId": format!("update{}", name),
+                        "requestBody": {
+                            "required": true,
+                            "content": {"application/json": {"schema": reference}},
+                        },
+                        "responses": {
+                            "204": {"description": format!("{} updated", name)},
+                            "404": not_found,
+                        },
+                    }),
+                ),
+                Operation::Delete => (
+                    record_path.clone(),
+                    "delete",
+                    json!({
+                        "summary": format!("Delete {}", name),
+                        "operationId": format!("delete{}", name),
+                        "responses": {
+                            "204": {"description": format!("{} deleted", name)},
+                        },
+                    }),
+                ),
+            };
+            if path == record_path {
+                definition["parameters"] = json!([{
+                    "name": "id",
+                    "in": "path",
+                    "required": true,
+                    "schema": id_schema,
+                }]);
+            }
+            self.paths
+                .entry(path)
+                .or_default()
+                .insert(method.to_string(), definition);
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "openapi": "3.0.3",
+            "info": {"title": self.title, "version": self.version},
+            "paths": self.paths,
+            "components": {"schemas": self.schemas},
+        })
+    }
+}
+
+fn query_parameter(name: &str, description: &str, schema: Value) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "required": false,
+        "description": description,
+        "schema": schema,
+    })
+}
+
+fn field_schema(field: &EntityField) -> Value {
+    match field.field_type {
+        FieldType::Integer => json!({"type": "integer", "format": "int64"}),
+        FieldType::Float => json!({"type": "number"}),
+        FieldType::Boolean => json!({"type": "boolean"}),
+        FieldType::String => json!({"type": "string"}),
+        FieldType::Uuid => json!({"type": "string", "format": "uuid"}),
+        FieldType::Timestamp => json!({"type": "string", "format": "date-time"}),
+        FieldType::Json => json!({}),
+    }
+}
+
+fn entity_schema(fields: &[EntityField]) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for field in fields {
+        let mut schema = field_schema(field);
+        if field.nullable {
+            schema["nullable"] = true.into();
+        } else {
+            required.push(field.name.clone());
+        }
+        if field.is_id || field.read_only {
+            schema["readOnly"] = true.into();
+        }
+        properties.insert(field.name.clone(), schema);
+    }
+    json!({
+        "type": "object",
+        "required": required,
+        "properties": properties,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+    struct Product {
+        id: i64,
+        name: String,
+        price: f64,
+        note: Option<String>,
+        in_stock: bool,
+    }
+    impl Entity for Product {}
+
+    fn products() -> Table<MockDataSource, Product> {
+        Table::new_with_entity("product", MockDataSource::new(&json!([])))
+            .with_id_column("id")
+            .with_column("name")
+            .with_typed_column("price", SqlType::Numeric)
+            .with_column("note")
+            .with_expression("in_stock", |_| expr!("true"))
+    }
+
+    #[test]
+    fn test_openapi() {
+        let api = OpenApi::new("Shop", "1.0").with_table(
+            "/products",
+            "Product",
+            &products(),
+            &[Operation::List, Operation::Get, Operation::Delete],
+        );
+        let json = api.to_json();
+
+        assert_eq!(
+            json["components"]["schemas"]["Product"],
+            json!({
+                "type": "object",
+                "required": ["id", "name", "price", "in_stock"],
+                "properties": {
+                    "id": {"type": "integer", "format": "int64", "readOnly": true},
+                    "name": {"type": "string"},
+                    "price": {"type": "number"},
+                    "note": {"type": "string", "nullable": true},
+                    "in_stock": {"type": "boolean", "readOnly": true},
+                },
+            })
+        );
+        assert_eq!(
+            json["paths"]
+                .as_object()
+                .unwrap()
+                .iter()
+                .map(|(path, methods)| (
+                    path.as_str(),
+                    methods.as_object().unwrap().keys().cloned().collect()
+                ))
+                .collect::<Vec<(&str, Vec<String>)>>(),
+            vec![
+                ("/products", vec!["get".to_string()]),
+                (
+                    "/products/{id}",
+                    vec!["get".to_string(), "delete".to_string()]
+                ),
+            ]
+        );
+        assert_eq!(
+            json["paths"]["/products/{id}"]["get"]["parameters"][0]["schema"],
+            json!({"type": "integer", "format": "int64"})
+        );
+        assert_eq!(
+            json["paths"]["/products"]["get"]["responses"]["200"]["content"]["application/json"]
+                ["schema"]["items"],
+            json!({"$ref": "#/components/schemas/Product"})
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+
+use crate::sql::query::SqlQuery;
+use crate::sql::table::Table;
+use crate::sql::Query;
+use crate::traits::datasource::DataSource;
+use crate::traits::entity::Entity;
+
+use super::TableWithColumns;
+
+/// # Eager loading
+///
+/// Traversing a reference for each record would execute a query per record. Instead
+/// you can preload the reference, and related records will be fetched with a single
+/// extra query, then placed into a field named after the reference:
+///
+/// ```
+/// #[derive(Serialize, Deserialize, Clone, Default)]
+/// struct OrderWithClient {
+///     id: i64,
+///     client: Option<Client>, // reference defined with with_one()
+/// }
+///
+/// let orders = Order::table().with_preload("client");
+/// for order in orders.get_as::<OrderWithClient>().await? {
+///     // order.client is populated
+/// }
+/// ```
+///
+/// For references defined with [`with_many()`] the field will contain an array, so
+/// it should be `Vec<Order>`.
+///
+/// [`with_many()`]: Table::with_many()
+impl<T: DataSource, E: Entity> Table<T, E> {
+    pub fn with_preload(mut self, relation: &str) -> Self {
+        self.add_preload(relation);
+        self
+    }
+
+    pub fn add_preload(&mut self, relation: &str) {
+        self.preload.push(relation.to_string());
+    }
+
+    /// Makes sure that the query fetches columns, which are needed to match
+    /// preloaded records.
+    pub(super) fn add_preload_keys_into_query(&self, mut query: Query) -> Query {
+        for relation in &self.preload {
+            let Some(keys) = self.refs.get(relation).and_then(|r| r.get_keys(self)) else {
+                continue;
+            };
+            if let Some(field) = self.search_for_field(&keys.our_key) {
+                query.add_field(Some(keys.our_key), Arc::new(field));
+            }
+        }
+        query
+    }
+
+    /// Fetches related records for each preloaded reference and places them
+    /// into the rows.
+    pub(super) async fn preload_into(&self, rows: &mut [Map<String, Value>]) -> Result<()> {
+        for relation in &self.preload {
+            let reference = self
+                .refs
+                .get(relation)
+                .ok_or_else(|| anyhow!("Reference '{}' not found in {}", relation, self))?;
+            let keys = reference
+                .get_keys(self)
+                .ok_or_else(|| anyhow!("Reference '{}' can't be preloaded", relation))?;
+
+            let related = reference.get_related_set(self);
+            let related_rows = self
+                .data_source
+                .query_fetch(&related.try_get_select_query()?)
+                .await?;
+
+            let mut grouped: HashMap<String, Vec<Value>> = HashMap::new();
+            for row in related_rows {
+                let key = row.get(&keys.their_key).unwrap_or(&Value::Null).to_string();
+                grouped.entry(key).or_default().push(Value::Object(row));
+            }
+
+            for row in rows.iter_mut() {
+                let key = row.get(&keys.our_key).unwrap_or(&Value::Null).to_string();
+                let matching = grouped.get(&key).cloned().unwrap_or_default();
+                let value = if keys.many {
+                    Value::Array(matching)
+                } else {
+                    matching.into_iter().next().unwrap_or(Value::Null)
+                };
+                row.insert(relation.clone(), value);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use crate::{dataset::ReadableDataSet, mocks::datasource::MockDataSource, prelude::*};
+
+    #[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+    struct Person {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+    struct PersonWithRelatives {
+        name: String,
+        parent: Option<Person>,
+        children: Vec<Person>,
+    }
+
+    fn persons() -> Table<MockDataSource, EmptyEntity> {
+        let data = json!([
+            { "id": 1, "name": "Alice", "parent_id": null },
+            { "id": 2, "name": "Bob", "parent_id": 1 },
+            { "id": 3, "name": "Carol", "parent_id": 1 },
+        ]);
+        Table::new("persons", MockDataSource::new(&data))
+            .with_id_column("id")
+            .with_column("name")
+            .with_column("parent_id")
+            .with_one("parent", "parent_id", || Box::new(persons()))
+            .with_many("children", "parent_id", || Box::new(persons()))
+    }
+
+    #[tokio::test]
+    async fn test_preload() {
+        let persons = persons().with_preload("parent").with_preload("children");
+        let result = persons.get_as::<PersonWithRelatives>().await.unwrap();
+
+        let alice = Person {
+            id: 1,
+            name: "Alice".to_string(),
+        };
+        assert_eq!(result[0].parent, None);
+        assert_eq!(result[0].children.len(), 2);
+        assert_eq!(result[1].parent, Some(alice));
+        assert_eq!(result[1].children, vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_preload_single_record() {
+        let persons = persons().with_preload("parent").with_preload("children");
+
+        let alice = persons
+            .get_some_as::<PersonWithRelatives>()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(alice.name, "Alice");
+        assert_eq!(alice.parent, None);
+        assert_eq!(
+            alice.children.iter().map(|p| p.id).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_preload_missing_reference() {
+        let persons = persons().with_preload("grandparent");
+        assert!(persons.get_as::<PersonWithRelatives>().await.is_err());
+    }
+}
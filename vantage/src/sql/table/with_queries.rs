@@ -1,3 +1,4 @@
+use anyhow::{anyhow, Result};
 use indexmap::IndexMap;
 use serde::Serialize;
 use serde_json::{to_value, Value};
@@ -5,29 +6,43 @@ use std::sync::Arc;
 
 use super::{AnyTable, Column, TableWithColumns};
 use crate::prelude::AssociatedQuery;
-use crate::sql::query::{QueryType, SqlQuery};
+use crate::sql::query::{Direction, LockMode, QueryType, SqlQuery};
 use crate::sql::table::Table;
-use crate::sql::Query;
+use crate::sql::{Chunk, Expression, Query};
 use crate::traits::column::SqlField;
 use crate::traits::datasource::DataSource;
 use crate::traits::entity::Entity;
+use crate::Error;
 
 use super::RelatedTable;
 
 pub trait TableWithQueries: AnyTable {
     fn get_empty_query(&self) -> Query;
+    /// Same as [`try_get_select_query()`], but panics if an extension fails.
+    ///
+    /// [`try_get_select_query()`]: TableWithQueries::try_get_select_query()
     fn get_select_query(&self) -> Query;
+    /// Builds SELECT query for all the columns of the table and passes it through
+    /// the extensions, which may fail, for example if a tenant is not set.
+    fn try_get_select_query(&self) -> Result<Query>;
     fn get_select_query_for_fields(
         &self,
         fields: IndexMap<String, Arc<Box<dyn SqlField>>>,
     ) -> Query;
     fn get_select_query_for_field_names(&self, field_names: &[&str]) -> Query;
+    /// Same as [`try_get_select_query_for_field()`], but panics if an extension
+    /// fails.
+    ///
+    /// [`try_get_select_query_for_field()`]: TableWithQueries::try_get_select_query_for_field()
     fn get_select_query_for_field(&self, field: Box<dyn SqlField>) -> Query;
+    fn try_get_select_query_for_field(&self, field: Box<dyn SqlField>) -> Result<Query>;
 }
 
 impl<T: DataSource, E: Entity> TableWithQueries for Table<T, E> {
     fn get_empty_query(&self) -> Query {
-        let mut query = Query::new().with_table(&self.table_name, self.table_alias.clone());
+        let mut query = Query::new()
+            .with_dialect(self.data_source.dialect())
+            .with_source(self.query_source(self.table_alias.clone()));
         for condition in self.conditions.iter() {
             query = query.with_condition(condition.clone());
         }
@@ -38,10 +53,18 @@ impl<T: DataSource, E: Entity> TableWithQueries for Table<T, E> {
     }
 
     fn get_select_query(&self) -> Query {
+        self.try_get_select_query()
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_get_select_query(&self) -> Result<Query> {
         let mut query = self.get_empty_query();
         query = self.add_columns_into_query(query, None);
-        self.hooks.before_select_query(self, &mut query).unwrap();
-        query
+        for order_by in self.order_by.iter() {
+            query = query.with_order_by(order_by.clone());
+        }
+        self.hooks.before_select_query(self, &mut query)?;
+        Ok(query)
     }
 
     fn get_select_query_for_fields(
@@ -53,6 +76,9 @@ impl<T: DataSource, E: Entity> TableWithQueries for Table<T, E> {
             let field_val = field_val.clone();
             query.add_field(Some(field_alias), field_val);
         }
+        for order_by in self.order_by.iter() {
+            query.add_order_by(order_by.clone());
+        }
         query
     }
 
@@ -66,10 +92,15 @@ impl<T: DataSource, E: Entity> TableWithQueries for Table<T, E> {
     }
 
     fn get_select_query_for_field(&self, field: Box<dyn SqlField>) -> Query {
+        self.try_get_select_query_for_field(field)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_get_select_query_for_field(&self, field: Box<dyn SqlField>) -> Result<Query> {
         let mut q = self.get_empty_query();
         q.add_field(None, Arc::new(field));
-        self.hooks.before_select_query(self, &mut q).unwrap();
-        q
+        self.hooks.before_select_query(self, &mut q)?;
+        Ok(q)
     }
 }
 
@@ -87,6 +118,69 @@ impl<D: DataSource, E: Entity> Table<D, E> {
         )
     }
 
+    /// Combines records of two tables with identical structure, such as
+    /// partitions of the same data. Fields of the entity are selected from both
+    /// tables and combined with `UNION ALL`.
+    ///
+    /// ```
+    /// let all_orders = Order::table().union_with(Order::archive_table());
+    /// ```
+    ///
+    /// Both tables must use the same type of data source, as the query is executed
+    /// by the data source of this table.
+    pub fn union_with<E2: Entity>(&self, other: &Table<D, E2>) -> AssociatedQuery<D, E> {
+        let query = self
+            .get_select_query_for_struct(E::default())
+            .union_all(other.get_select_query_for_struct(E::default()));
+        AssociatedQuery::new(query, self.data_source.clone())
+    }
+
+    /// Returns one record for each distinct value of `column` - the first one when
+    /// ordered by `order_column` descending, such as the latest order per client:
+    ///
+    /// ```
+    /// let latest_orders = Order::table().latest_per("client_id", Order::table().created_at())?;
+    /// // SELECT DISTINCT ON (client_id) .. FROM ord ORDER BY client_id ASC, created_at DESC
+    /// ```
+    ///
+    /// Fails with [`Error::MissingColumn`] if the table has no such column.
+    pub fn latest_per(
+        &self,
+        column: &str,
+        order_column: impl Chunk,
+    ) -> Result<AssociatedQuery<D, E>> {
+        let column = self
+            .get_column(column)
+            .ok_or_else(|| Error::missing_column(self, column))?;
+        let (mut query, _) = self.select_query_for_struct(E::default())?;
+        query.add_distinct_on(column.render_chunk());
+
+        // Query renders latest order first, so DISTINCT ON column goes last
+        query.add_order_by(Direction::Desc.order(&order_column));
+        query.add_order_by(Direction::Asc.order(&column));
+        Ok(AssociatedQuery::new(query, self.data_source.clone()))
+    }
+
+    /// Returns query, which locks selected records until the end of transaction,
+    /// so that they can be safely processed:
+    ///
+    /// ```
+    /// let pending = Order::table().with_condition(Order::table().status().eq(&"pending"));
+    /// let orders = pending.for_update().get().await?;
+    /// // SELECT .. FROM ord WHERE (status = 'pending') FOR UPDATE
+    /// ```
+    ///
+    /// Use [`Query::with_lock()`] for other lock modes.
+    pub fn for_update(&self) -> AssociatedQuery<D, E> {
+        let query = self
+            .get_select_query_for_struct(E::default())
+            .with_lock(LockMode::ForUpdate {
+                skip_locked: false,
+                nowait: false,
+            });
+        AssociatedQuery::new(query, self.data_source.clone())
+    }
+
     pub fn query_for_fields(
         &self,
         fields: IndexMap<String, Arc<Box<dyn SqlField>>>,
@@ -104,44 +198,106 @@ impl<D: DataSource, E: Entity> Table<D, E> {
         )
     }
 
+    /// Builds SELECT query for the fields of a struct. Besides table columns and
+    /// expressions, a field may refer to a column of a joined table by its prefixed
+    /// name (`i_stock`) or contain a nested struct named after the joined table,
+    /// in which case all the columns of the joined table are selected.
+    ///
+    /// Fields are named as the struct is serialized, so `#[serde(rename)]` and
+    /// `#[serde(rename_all)]` are respected and `#[serde(skip)]` fields are not
+    /// selected. Fields, which don't match anything, are ignored. Use
+    /// [`try_get_select_query_for_struct()`] to have them reported.
+    ///
+    /// Panics if an extension fails or `default` is not a struct.
+    ///
+    /// [`try_get_select_query_for_struct()`]: Table::try_get_select_query_for_struct()
     pub fn get_select_query_for_struct<R: Serialize>(&self, default: R) -> Query {
-        let json_value = to_value(default).unwrap();
+        self.select_query_for_struct(default)
+            .unwrap_or_else(|e| panic!("{}", e))
+            .0
+    }
 
-        let field_names = match json_value {
-            Value::Object(map) => {
-                let field_names = map.keys().cloned().collect::<Vec<String>>();
-                field_names
+    /// Same as [`get_select_query_for_struct()`], but fails with
+    /// [`Error::UnmatchedFields`] if some fields of the struct can't be selected
+    /// from a column, a joined table, an expression or a preloaded reference.
+    /// Errors of the extensions are returned as well.
+    ///
+    /// [`get_select_query_for_struct()`]: Table::get_select_query_for_struct()
+    pub fn try_get_select_query_for_struct<R: Serialize>(&self, default: R) -> Result<Query> {
+        let (query, unmatched) = self.select_query_for_struct(default)?;
+        if unmatched.is_empty() {
+            Ok(query)
+        } else {
+            Err(Error::UnmatchedFields {
+                table: self.table_name.clone(),
+                fields: unmatched,
             }
-            _ => panic!("Expected argument to be a struct"),
-        };
+            .into())
+        }
+    }
 
-        let i = field_names
-            .into_iter()
-            .filter_map(|f| self.search_for_field(&f).map(|c| (f, Arc::new(c))));
+    /// Returns query and names of the fields, which can't be selected.
+    pub(crate) fn select_query_for_struct<R: Serialize>(
+        &self,
+        default: R,
+    ) -> Result<(Query, Vec<String>)> {
+        let map = match to_value(default)? {
+            Value::Object(map) => map,
+            value => return Err(anyhow!("Expected argument to be a struct, got {}", value)),
+        };
 
-        let i = i.collect::<IndexMap<_, _>>();
+        let mut fields: IndexMap<String, Arc<Box<dyn SqlField>>> = IndexMap::new();
+        let mut unmatched = Vec::new();
+        for (name, value) in map {
+            if let Some(field) = self.search_for_field(&name) {
+                fields.insert(name, Arc::new(field));
+            } else if let Some(column) = self.search_for_prefixed_column(&name) {
+                fields.insert(name, Arc::new(Box::new(column)));
+            } else if let (Value::Object(_) | Value::Null, Some((alias, join))) =
+                (&value, self.get_join_by_table_name(&name))
+            {
+                for (column_name, column) in join.table().columns() {
+                    fields.insert(
+                        format!("{}_{}", alias, column_name),
+                        Arc::new(Box::new(column.clone())),
+                    );
+                }
+            } else if !self.preload.contains(&name) {
+                unmatched.push(name);
+            }
+        }
 
-        let mut q = self.get_select_query_for_fields(i);
-        self.hooks.before_select_query(self, &mut q).unwrap();
-        q
+        let mut q = self.get_select_query_for_fields(fields);
+        self.hooks.before_select_query(self, &mut q)?;
+        Ok((q, unmatched))
     }
 
-    pub fn get_insert_query<E2>(&self, values: E2) -> Query
+    /// Sets table columns present in `values` on the query. Values of typed
+    /// columns are validated (see [`SqlType`]) and generated columns are skipped.
+    ///
+    /// [`SqlType`]: crate::sql::SqlType
+    fn with_set_fields_from<E2>(&self, mut query: Query, values: E2) -> Result<Query>
     where
         E2: Serialize,
     {
-        let mut query = Query::new()
-            .with_table(&self.table_name, None)
-            .with_type(QueryType::Insert);
+        for (field, value) in self.set_field_values(values)? {
+            query = query.with_set_field_expression(&field, value);
+        }
+        Ok(query)
+    }
 
-        let serde_json::Value::Object(value_map) = serde_json::to_value(values).unwrap() else {
-            panic!("Values must be a struct");
+    /// Renders values of the struct for non-calculated columns of the table.
+    fn set_field_values<E2>(&self, values: E2) -> Result<IndexMap<String, Expression>>
+    where
+        E2: Serialize,
+    {
+        let Value::Object(value_map) = serde_json::to_value(values)? else {
+            return Err(anyhow!("Values must be a struct"));
         };
 
-        for (field, _) in &self.columns {
-            let field_object = Arc::new(Column::new(field.clone(), self.table_alias.clone()));
-
-            if field_object.calculated() {
+        let mut result = IndexMap::new();
+        for (field, column) in &self.columns {
+            if column.calculated() || column.is_generated() {
                 continue;
             };
 
@@ -149,40 +305,97 @@ impl<D: DataSource, E: Entity> Table<D, E> {
                 continue;
             };
 
-            query = query.with_set_field(field, value.clone());
+            result.insert(field.clone(), column.render_value(value.clone())?);
         }
-        query
+        Ok(result)
     }
 
-    pub fn get_update_query<E2>(&self, values: E2) -> Query
+    fn get_empty_insert_query(&self) -> Query {
+        let query = Query::new()
+            .with_dialect(self.data_source.dialect())
+            .with_table(&self.table_name, None)
+            .with_type(QueryType::Insert);
+
+        match &self.id_column {
+            Some(id_column) => query.with_returning(&[id_column]),
+            None => query,
+        }
+    }
+
+    pub fn get_insert_query<E2>(&self, values: E2) -> Result<Query>
     where
         E2: Serialize,
     {
-        let mut query = Query::new()
-            .with_table(&self.table_name, None)
-            .with_type(QueryType::Update);
+        self.ensure_writable()?;
+        let mut query = self.with_set_fields_from(self.get_empty_insert_query(), values)?;
+        self.hooks.before_insert_query(self, &mut query)?;
+        Ok(query)
+    }
 
-        let serde_json::Value::Object(value_map) = serde_json::to_value(values).unwrap() else {
-            panic!("Values must be a struct");
+    /// Builds a single INSERT query for several records:
+    ///
+    /// ```
+    /// let query = products.get_insert_batch_query(&[cake, pie])?;
+    /// // INSERT INTO product (name, price) VALUES ({}, {}), ({}, {}) RETURNING id
+    /// ```
+    pub fn get_insert_batch_query<E2>(&self, records: &[E2]) -> Result<Query>
+    where
+        E2: Serialize,
+    {
+        self.ensure_writable()?;
+        let Some((first, rest)) = records.split_first() else {
+            return Err(anyhow!("No records to insert"));
         };
+        let mut query = self.with_set_fields_from(self.get_empty_insert_query(), first)?;
+        for record in rest {
+            query.add_values_row(self.set_field_values(record)?);
+        }
+        self.hooks.before_insert_query(self, &mut query)?;
+        Ok(query)
+    }
 
-        for (field, _) in &self.columns {
-            let field_object = Arc::new(Column::new(field.clone(), self.table_alias.clone()));
+    pub fn get_update_query<E2>(&self, values: E2) -> Result<Query>
+    where
+        E2: Serialize,
+    {
+        self.ensure_writable()?;
+        let query = Query::new()
+            .with_dialect(self.data_source.dialect())
+            .with_table(&self.table_name, None)
+            .with_type(QueryType::Update);
 
-            if field_object.calculated() {
-                continue;
-            };
+        let mut query = self.with_set_fields_from(query, values)?;
+        for condition in self.conditions.iter() {
+            query = query.with_condition(condition.clone());
+        }
+        self.hooks.before_update_query(self, &mut query)?;
+        Ok(query)
+    }
 
-            let Some(value) = value_map.get(field) else {
-                continue;
-            };
+    /// Builds UPDATE query for all records in the table, setting columns
+    /// to the result of an expression:
+    ///
+    /// ```
+    /// let query = products.get_update_all_query(vec![
+    ///     (products.price(), expr!("price * 1.1")),
+    /// ])?;
+    /// // UPDATE product SET price = price * 1.1 WHERE ...
+    /// ```
+    pub fn get_update_all_query(&self, values: Vec<(Arc<Column>, Expression)>) -> Result<Query> {
+        self.ensure_writable()?;
+        let mut query = Query::new()
+            .with_dialect(self.data_source.dialect())
+            .with_table(&self.table_name, None)
+            .with_type(QueryType::Update);
 
-            query = query.with_set_field(field, value.clone());
+        for (column, expression) in values {
+            query = query.with_set_field_expression(&column.name(), expression);
         }
         for condition in self.conditions.iter() {
             query = query.with_condition(condition.clone());
         }
-        query
+        self.hooks.before_update_query(self, &mut query)?;
+        Ok(query)
     }
 }
 
@@ -192,9 +405,9 @@ mod tests {
 
     use crate::prelude::*;
     use serde::{Deserialize, Serialize};
-    use serde_json::json;
+    use serde_json::{json, Value};
 
-    use crate::{expr_arc, mocks::datasource::MockDataSource, prelude::Chunk};
+    use crate::{expr_arc, mocks::datasource::MockDataSource, prelude::Chunk, Error};
 
     #[derive(Serialize, Deserialize, Clone, Default)]
     struct User {
@@ -219,17 +432,110 @@ mod tests {
                 name: "John".to_string(),
                 surname: "Doe".to_string(),
             })
+            .unwrap()
             .render_chunk()
             .split();
 
-        assert_eq!(
-            query.0,
-            "INSERT INTO users (name, surname) VALUES ({}, {}) returning id"
-        );
+        assert_eq!(query.0, "INSERT INTO users (name, surname) VALUES ({}, {})");
         assert_eq!(query.1[0], json!("John"));
         assert_eq!(query.1[1], json!("Doe"));
     }
 
+    #[test]
+    fn test_insert_query_returning_id() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let table: Table<MockDataSource, User> = Table::new_with_entity("users", db)
+            .with_id_column("user_id")
+            .with_column("name")
+            .with_column("surname");
+
+        let query = table
+            .get_insert_query(User {
+                name: "John".to_string(),
+                surname: "Doe".to_string(),
+            })
+            .unwrap();
+
+        assert_eq!(
+            query.preview(),
+            "INSERT INTO users (name, surname) VALUES ('John', 'Doe') RETURNING user_id"
+        );
+    }
+
+    #[test]
+    fn test_typed_insert_query() {
+        #[derive(Serialize, Deserialize, Clone)]
+        struct Product {
+            name: String,
+            price: Value,
+        }
+
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let products = Table::new("product", db)
+            .with_typed_column("name", SqlType::Text)
+            .with_typed_column("price", SqlType::Int8);
+
+        let query = products
+            .get_insert_query(Product {
+                name: "Cake".to_string(),
+                price: json!(120),
+            })
+            .unwrap();
+        assert_eq!(
+            query.preview(),
+            "INSERT INTO product (name, price) VALUES ('Cake'::text, 120::int8)"
+        );
+
+        assert!(products
+            .get_update_query(Product {
+                name: "Cake".to_string(),
+                price: json!("expensive"),
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn test_column_def_insert_query() {
+        #[derive(Serialize, Deserialize, Clone, Default)]
+        struct LineItem {
+            id: Option<i64>,
+            qty: Option<i64>,
+            note: Option<String>,
+        }
+        impl Entity for LineItem {}
+
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let items = Table::new_with_entity("line_item", db)
+            .with_column_def(ColumnDef::new("id").bigint().generated())
+            .with_column_def(ColumnDef::new("qty").int().not_null().default(0))
+            .with_column_def(ColumnDef::new("note").text().not_null());
+
+        let item = LineItem {
+            id: Some(5),
+            qty: None,
+            note: Some("fragile".to_string()),
+        };
+        assert_eq!(
+            items.get_insert_query(item.clone()).unwrap().preview(),
+            "INSERT INTO line_item (qty, note) VALUES (0, 'fragile'::text)"
+        );
+        assert!(items.validate(&item).is_ok());
+
+        let err = items.validate(&LineItem::default()).unwrap_err();
+        let errors = err.downcast_ref::<ValidationErrors>().unwrap();
+        assert_eq!(
+            errors.get("note").unwrap(),
+            &vec!["can't be null".to_string()]
+        );
+        assert!(errors.get("qty").is_none());
+    }
+
     #[test]
     fn test_update_query() {
         #[derive(Serialize, Deserialize, Clone)]
@@ -251,6 +557,7 @@ mod tests {
             .get_update_query(UserName {
                 name: "John".to_string(),
             })
+            .unwrap()
             .render_chunk()
             .split();
 
@@ -259,6 +566,143 @@ mod tests {
         assert_eq!(query.1[1], json!(1));
     }
 
+    #[test]
+    fn test_update_all_query() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let mut products = Table::new("product", db)
+            .with_column("name")
+            .with_column("price")
+            .with_extension(SoftDelete::new("is_deleted"));
+        products.add_condition(products.get_column("name").unwrap().eq(&"Cake"));
+
+        let query = products
+            .get_update_all_query(vec![(
+                products.get_column("price").unwrap(),
+                expr!("price * {}", 1.1),
+            )])
+            .unwrap()
+            .render_chunk()
+            .split();
+
+        assert_eq!(
+            query.0,
+            "UPDATE product SET price = price * {} WHERE (name = {}) AND (is_deleted = {})"
+        );
+        assert_eq!(query.1, vec![json!(1.1), json!("Cake"), json!(false)]);
+
+        let view = products.clone().into_view();
+        assert!(view
+            .table()
+            .get_update_all_query(vec![(
+                products.get_column("price").unwrap(),
+                expr!("price * {}", 1.1),
+            )])
+            .is_err());
+    }
+
+    #[test]
+    fn test_union_with() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let users: Table<MockDataSource, User> = Table::new_with_entity("users", db.clone())
+            .with_column("name")
+            .with_column("surname");
+        let archived_users = Table::new("users_archive", db)
+            .with_column("name")
+            .with_column("surname");
+
+        assert_eq!(
+            users.union_with(&archived_users).preview(),
+            "SELECT * FROM ((SELECT name, surname FROM users) UNION ALL \
+            (SELECT name, surname FROM users_archive)) AS u"
+        );
+    }
+
+    #[test]
+    fn test_latest_per() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let orders = Table::new("orders", db)
+            .with_id_column("id")
+            .with_column("client_id")
+            .with_column("created_at");
+
+        assert_eq!(
+            orders
+                .latest_per("client_id", orders.get_column("created_at").unwrap())
+                .unwrap()
+                .preview(),
+            "SELECT DISTINCT ON (client_id) * FROM orders \
+            ORDER BY client_id ASC, created_at DESC"
+        );
+
+        let err = orders
+            .latest_per("customer_id", orders.get_column("created_at").unwrap())
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::MissingColumn { column, .. }) if column == "customer_id"
+        ));
+    }
+
+    #[test]
+    fn test_for_update() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let orders = Table::new("orders", db)
+            .with_id_column("id")
+            .with_column("status");
+        let orders = orders.clone().with_condition(
+            orders
+                .get_column("status")
+                .unwrap()
+                .eq(&"pending".to_string()),
+        );
+
+        assert_eq!(
+            orders.for_update().preview(),
+            "SELECT * FROM orders WHERE (status = 'pending') FOR UPDATE"
+        );
+    }
+
+    #[test]
+    fn test_insert_batch_query() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let users: Table<MockDataSource, User> = Table::new_with_entity("users", db)
+            .with_id_column("id")
+            .with_column("name")
+            .with_column("surname");
+
+        let query = users
+            .get_insert_batch_query(&[
+                User {
+                    name: "John".to_string(),
+                    surname: "Doe".to_string(),
+                },
+                User {
+                    name: "Jane".to_string(),
+                    surname: "Roe".to_string(),
+                },
+            ])
+            .unwrap()
+            .render_chunk()
+            .split();
+
+        assert_eq!(
+            query.0,
+            "INSERT INTO users (name, surname) VALUES ({}, {}), ({}, {}) RETURNING id"
+        );
+        assert_eq!(query.1.len(), 4);
+        assert!(users.get_insert_batch_query::<User>(&[]).is_err());
+    }
+
     #[test]
     fn test_expression_query() {
         let data = json!([]);
@@ -296,5 +740,89 @@ mod tests {
             query.0,
             "SELECT price, qty, (price*qty) AS total FROM orders"
         );
+
+        let orders = orders.with_expressions_included();
+        assert_eq!(
+            orders.get_select_query().preview(),
+            "SELECT price, qty, (price*qty) AS total FROM orders"
+        );
+
+        let orders = Table::new("orders", db)
+            .with_column("price")
+            .with_column("qty")
+            .with_computed_column("total", |t| {
+                t.get_column("price")
+                    .unwrap()
+                    .mul(t.get_column("qty").unwrap())
+            });
+        assert_eq!(
+            orders.get_select_query().preview(),
+            "SELECT price, qty, ((price) * (qty)) AS total FROM orders"
+        );
+        assert_eq!(
+            orders
+                .get_insert_query(ItemLine {
+                    price: 2.0,
+                    qty: 3,
+                    total: 6.0
+                })
+                .unwrap()
+                .preview(),
+            "INSERT INTO orders (price, qty) VALUES (2.0, 3)"
+        );
+    }
+
+    #[test]
+    fn test_select_query_for_serde_struct() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+
+        let users = Table::new("users", db)
+            .with_column("name")
+            .with_column("surname");
+
+        #[derive(Serialize, Deserialize, Default)]
+        struct Person {
+            #[serde(rename = "surname")]
+            last_name: String,
+            #[serde(skip)]
+            _cache: Option<String>,
+        }
+
+        assert_eq!(
+            users
+                .try_get_select_query_for_struct(Person::default())
+                .unwrap()
+                .preview(),
+            "SELECT surname FROM users"
+        );
+
+        #[derive(Serialize, Deserialize, Default)]
+        struct Contact {
+            name: String,
+            email: String,
+            phone: String,
+        }
+
+        assert_eq!(
+            users
+                .get_select_query_for_struct(Contact::default())
+                .preview(),
+            "SELECT name FROM users"
+        );
+        assert_eq!(
+            users
+                .try_get_select_query_for_struct(Contact::default())
+                .unwrap_err()
+                .to_string(),
+            "Table 'users' has nothing to select for fields: email, phone"
+        );
+        assert_eq!(
+            users
+                .try_get_select_query_for_struct(5)
+                .unwrap_err()
+                .to_string(),
+            "Expected argument to be a struct, got 5"
+        );
     }
 }
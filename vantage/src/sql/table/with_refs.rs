@@ -2,13 +2,24 @@ use std::sync::Arc;
 
 use anyhow::{anyhow, Context, Result};
 
-use super::reference::{many::ReferenceMany, one::ReferenceOne, RelatedSqlTable};
-use crate::sql::Chunk;
+use super::reference::{
+    many::ReferenceMany,
+    many_via::ReferenceManyVia,
+    one::ReferenceOne,
+    polymorphic::{PolymorphicTableFx, ReferencePolymorphic},
+    RelatedSqlTable, SubqueryStrategy,
+};
+use crate::datasource::associated_query::AssociatedQuery;
+use crate::sql::query::{JoinQuery, JoinType, QueryConditions, QuerySource};
+use crate::sql::{Chunk, Expression, ExpressionArc, Operations, Query};
 use crate::traits::datasource::DataSource;
 use crate::traits::entity::Entity;
+use crate::uniqid::UniqueIdVendor;
+use crate::Error;
+use crate::{expr, expr_arc};
 use crate::{prelude::EmptyEntity, sql::table::Table};
 
-use super::SqlTable;
+use super::{AnyTable, RelatedTable, SqlTable, TableWithColumns, TableWithQueries};
 
 impl<T: DataSource, E: Entity> Table<T, E> {
     pub fn with_many(
@@ -21,6 +32,36 @@ impl<T: DataSource, E: Entity> Table<T, E> {
         self
     }
 
+    /// Many-to-many reference through a junction table, which holds `our_key` pointing to
+    /// this table and `their_key` pointing to the related table:
+    ///
+    /// ```
+    /// let products = Table::new("product", postgres())
+    ///     .with_id_column("id")
+    ///     .with_many_via("tags", "product_tag", "product_id", "tag_id", || {
+    ///         Box::new(Tag::table())
+    ///     });
+    /// ```
+    pub fn with_many_via(
+        mut self,
+        relation: &str,
+        junction_table: &str,
+        our_key: &str,
+        their_key: &str,
+        cb: impl Fn() -> Box<dyn SqlTable> + Send + Sync + 'static,
+    ) -> Self {
+        self.add_ref(
+            relation,
+            Box::new(ReferenceManyVia::new(
+                junction_table,
+                our_key,
+                their_key,
+                cb,
+            )),
+        );
+        self
+    }
+
     pub fn with_one(
         mut self,
         relation: &str,
@@ -33,20 +74,131 @@ impl<T: DataSource, E: Entity> Table<T, E> {
         self
     }
 
+    /// Reference to one of several tables, depending on the value of `type_column`.
+    /// Each variant maps a value of the type column to a table:
+    ///
+    /// ```
+    /// let notifications = Table::new("notification", postgres())
+    ///     .with_id_column("id")
+    ///     .with_column("subject_type")
+    ///     .with_column("subject_id")
+    ///     .with_polymorphic("subject", "subject_type", "subject_id", &[
+    ///         ("order", || Box::new(Order::table())),
+    ///         ("invoice", || Box::new(Invoice::table())),
+    ///     ]);
+    ///
+    /// let orders = notifications.get_polymorphic_ref("subject", "order")?;
+    /// ```
+    pub fn with_polymorphic(
+        mut self,
+        relation: &str,
+        type_column: &str,
+        id_column: &str,
+        variants: &[(&str, PolymorphicTableFx)],
+    ) -> Self {
+        self.polymorphic_refs.insert(
+            relation.to_string(),
+            Arc::new(ReferencePolymorphic::new(type_column, id_column, variants)),
+        );
+        self
+    }
+
+    pub fn get_polymorphic_ref(&self, ref_name: &str, variant: &str) -> Result<Box<dyn SqlTable>> {
+        self.polymorphic_refs
+            .get(ref_name)
+            .ok_or_else(|| anyhow!("Reference not found"))?
+            .get_related_set(self, variant)
+    }
+
+    pub fn get_polymorphic_ref_as<T2: DataSource, E2: Entity>(
+        &self,
+        ref_name: &str,
+        variant: &str,
+    ) -> Result<Table<T2, E2>> {
+        self.get_polymorphic_ref(ref_name, variant)?
+            .as_any_ref()
+            .downcast_ref::<Table<T2, E2>>()
+            .ok_or_else(|| anyhow!("Failed to downcast to specific table type"))
+            .cloned()
+    }
+
+    pub fn get_polymorphic_subquery(
+        &self,
+        ref_name: &str,
+        variant: &str,
+    ) -> Result<Box<dyn SqlTable>> {
+        self.polymorphic_refs
+            .get(ref_name)
+            .ok_or_else(|| anyhow!("Reference not found"))?
+            .get_linked_set(self, variant)
+    }
+
+    /// Returns all descendants of the records in this table, for hierarchical data where
+    /// `parent_column` references the id of the same table. Uses a recursive query:
+    ///
+    /// ```
+    /// let sub_teams = teams.with_id(1.into()).ref_descendants("parent_id")?;
+    /// // SELECT .. FROM team WHERE (id IN (WITH RECURSIVE descendants AS (..) SELECT id FROM descendants))
+    /// ```
+    ///
+    /// Fails with [`Error::MissingColumn`] if the table has no id or parent column.
+    pub fn ref_descendants(&self, parent_column: &str) -> Result<Self> {
+        let id_column = self.try_id()?;
+        let id = id_column.name();
+        let parent = self
+            .get_column(parent_column)
+            .ok_or_else(|| Error::missing_column(self, parent_column))?
+            .name();
+
+        // direct children of the records in this table
+        let ids = self.try_get_select_query_for_field(Box::new(id_column))?;
+        let base = Query::new()
+            .with_source(self.query_source(None))
+            .with_column_field(&id)
+            .with_condition(expr_arc!(format!("{} IN ({{}})", parent), ids));
+
+        // children of the records found so far
+        let recursive = Query::new()
+            .with_source(self.query_source(Some("child".to_string())))
+            .with_field(id.clone(), expr!(format!("child.{}", id)))
+            .with_join(JoinQuery::new(
+                JoinType::Inner,
+                QuerySource::Table("descendants".to_string(), None),
+                QueryConditions::on()
+                    .with_condition(expr!(format!("child.{} = descendants.{}", parent, id))),
+            ));
+
+        let descendants = Query::new()
+            .with_recursive("descendants", base, recursive)
+            .with_table("descendants", None)
+            .with_column_field(&id);
+
+        let mut target = self.clone();
+        target.conditions = Vec::new();
+        target.add_condition(target.id().in_expr(&descendants));
+        Ok(target)
+    }
+
+    /// Imports fields of a related record as expressions, named `{relation}_{field}`.
+    /// Relation may be a path through several one-references:
+    ///
+    /// ```
+    /// let orders = Order::table()
+    ///     .with_imported_fields("client", &["name"])
+    ///     .with_imported_fields("client.bakery", &["name"]);
+    /// // SELECT .., (SELECT name FROM client WHERE (client.id = ord.client_id)) AS client_name,
+    /// //   (SELECT name FROM bakery WHERE (id IN (SELECT bakery_id FROM client WHERE (client.id = ord.client_id))))
+    /// //   AS client_bakery_name FROM ord
+    /// ```
+    ///
+    /// Related tables are only created when the query is rendered, which panics if a field
+    /// is missing. Use [`try_add_imported_fields()`] to check fields right away.
+    ///
+    /// [`try_add_imported_fields()`]: Table::try_add_imported_fields()
     pub fn add_imported_fields(&mut self, relation: &str, field_names: &[&str]) {
         for field_name in field_names {
-            let field_name = field_name.to_string();
-            let name = format!("{}_{}", &relation, &field_name);
-            let relation = relation.to_string();
-            self.add_expression(&name, move |t| {
-                let tt = t
-                    .get_subquery(&relation)
-                    .with_context(|| format!("Failed to get subquery for '{}'", &relation))
-                    .unwrap();
-
-                tt.get_select_query_for_field(Box::new(tt.get_column(&field_name).unwrap()))
-                    .render_chunk()
-            });
+            let alias = format!("{}_{}", relation.replace('.', "_"), field_name);
+            self.add_imported_column_expr(relation, field_name, &alias);
         }
     }
 
@@ -55,6 +207,137 @@ impl<T: DataSource, E: Entity> Table<T, E> {
         self
     }
 
+    /// Same as [`add_imported_fields()`], but first checks that references exist and
+    /// the related table has all the fields, returning [`Error::MissingColumn`] otherwise.
+    /// Don't call it while building the related table itself, as with self-references
+    /// this would recurse.
+    ///
+    /// [`add_imported_fields()`]: Table::add_imported_fields()
+    /// [`Error::MissingColumn`]: crate::Error::MissingColumn
+    pub fn try_add_imported_fields(&mut self, relation: &str, field_names: &[&str]) -> Result<()> {
+        let related = self.get_imported_subquery(relation)?;
+        for field_name in field_names {
+            if related.get_column(field_name).is_none() {
+                return Err(Error::missing_column(relation, field_name).into());
+            }
+        }
+        self.add_imported_fields(relation, field_names);
+        Ok(())
+    }
+
+    /// Imports a single field of a related table under a name of your choice:
+    ///
+    /// ```
+    /// let orders = Order::table().with_imported_column_expr("client", "name", "client");
+    /// // SELECT .., (SELECT name FROM client WHERE (client.id = ord.client_id)) AS client FROM ord
+    /// ```
+    pub fn with_imported_column_expr(
+        mut self,
+        relation: &str,
+        field_name: &str,
+        alias: &str,
+    ) -> Self {
+        self.add_imported_column_expr(relation, field_name, alias);
+        self
+    }
+
+    pub fn add_imported_column_expr(&mut self, relation: &str, field_name: &str, alias: &str) {
+        let field_name = field_name.to_string();
+        let relation = relation.to_string();
+        self.add_expression(alias, move |t| {
+            let tt = t.get_imported_subquery(&relation).unwrap();
+            let column = tt
+                .get_column(&field_name)
+                .unwrap_or_else(|| panic!("{}", Error::missing_column(&relation, &field_name)));
+
+            tt.get_select_query_for_field(Box::new(column))
+                .render_chunk()
+        });
+    }
+
+    /// Related table for a path of references, such as `client.bakery`, limited to
+    /// the record of the current row.
+    fn get_imported_subquery(&self, path: &str) -> Result<Box<dyn SqlTable>> {
+        let mut relations = path.split('.');
+        let first = relations.next().unwrap_or_default();
+        let mut related = self
+            .get_subquery(first)
+            .with_context(|| format!("Failed to get subquery for '{}'", first))?;
+        for relation in relations {
+            related = related
+                .get_ref(relation)
+                .with_context(|| format!("Failed to get subquery for '{}'", path))?;
+        }
+        Ok(related)
+    }
+
+    /// Correlated scalar subquery for a related table. Callback receives the related
+    /// table, limited to the record of the current row, and returns a query for a
+    /// single value. Use it to define expressions:
+    ///
+    /// ```
+    /// let orders = Order::table().with_expression("client_name", |t| {
+    ///     t.ref_scalar("client", |c| c.field_query(c.title()))
+    /// });
+    /// // SELECT .., (SELECT name FROM client WHERE (client.id = ord.client_id)) AS client_name FROM ord
+    /// ```
+    ///
+    /// If the related table would clash with this one, for example when referencing a
+    /// parent record in the same table, it is given a unique alias. Related table must
+    /// use [`EmptyEntity`].
+    pub fn ref_scalar<E2: Entity>(
+        &self,
+        relation: &str,
+        cb: impl FnOnce(&Table<T, EmptyEntity>) -> AssociatedQuery<T, E2>,
+    ) -> Expression {
+        let mut related = self
+            .get_subquery_as::<EmptyEntity>(relation)
+            .with_context(|| anyhow!("Failed to get subquery for '{}' of {}", relation, self))
+            .unwrap();
+
+        let ours = self.table_alias.as_ref().unwrap_or(&self.table_name);
+        let theirs = related.table_alias.as_ref().unwrap_or(&related.table_name);
+        if ours == theirs {
+            let mut aliases = self.used_aliases();
+            aliases.avoid(ours);
+            let alias =
+                aliases.get_one_of_uniq_id(UniqueIdVendor::all_prefixes(&related.table_name));
+            related.set_alias(&alias);
+        }
+
+        cb(&related).render_chunk()
+    }
+
+    /// Change how [`get_ref()`] limits related records. By default `IN (subquery)`
+    /// is used, but for large sets `EXISTS` may perform better:
+    ///
+    /// ```
+    /// let clients = Client::table()
+    ///     .with_ref_strategy("orders", SubqueryStrategy::Exists)?;
+    ///
+    /// let orders = clients.get_ref("orders")?;
+    /// // SELECT .. FROM ord WHERE (EXISTS (SELECT (1) FROM client WHERE (client.id = ord.client_id)))
+    /// ```
+    ///
+    /// Fails if the reference does not exist or doesn't support strategies.
+    ///
+    /// [`get_ref()`]: Table::get_ref()
+    pub fn with_ref_strategy(mut self, relation: &str, strategy: SubqueryStrategy) -> Result<Self> {
+        self.set_ref_strategy(relation, strategy)?;
+        Ok(self)
+    }
+
+    pub fn set_ref_strategy(&mut self, relation: &str, strategy: SubqueryStrategy) -> Result<()> {
+        let reference = self
+            .refs
+            .get(relation)
+            .ok_or_else(|| anyhow!("Reference '{}' not found", relation))?
+            .with_strategy(strategy)
+            .ok_or_else(|| anyhow!("Reference '{}' doesn't support strategies", relation))?;
+        self.refs.insert(relation.to_string(), Arc::new(reference));
+        Ok(())
+    }
+
     pub fn add_ref(&mut self, relation: &str, reference: Box<dyn RelatedSqlTable>) {
         self.refs.insert(relation.to_string(), Arc::new(reference));
     }
@@ -93,17 +376,70 @@ impl<T: DataSource, E: Entity> Table<T, E> {
             .cloned()
     }
 
-    pub fn get_ref_as<T2: DataSource, E2: Entity>(&self, field: &str) -> Result<Table<T2, E2>> {
-        let table = self.get_ref(field)?;
+    /// Traverses a reference and returns the related table with entity `E2`. Related
+    /// tables defined with [`EmptyEntity`] are converted into `E2`:
+    ///
+    /// ```
+    /// let orders = clients.get_ref_as::<Order>("orders")?;
+    /// ```
+    pub fn get_ref_as<E2: Entity>(&self, relation: &str) -> Result<Table<T, E2>> {
+        let table = self.get_ref(relation)?;
+        // TODO: not sure why we can't as_any().downcast() here
+        let table = table.as_any_ref();
+        if let Some(table) = table.downcast_ref::<Table<T, E2>>() {
+            return Ok(table.clone());
+        }
         table
-            // TODO: not sure why we can't as_any().downcast() here
-            .as_any_ref()
-            .downcast_ref::<Table<T2, E2>>()
-            .ok_or_else(|| anyhow!("Failed to downcast to specific table type"))
-            .cloned()
+            .downcast_ref::<Table<T, EmptyEntity>>()
+            .map(|table| table.clone().into_entity())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Reference '{}' of {} is not a table of {}",
+                    relation,
+                    self,
+                    std::any::type_name::<E2>()
+                )
+            })
     }
 }
 
+/// Defines a trait with typed methods for traversing references of a table, so
+/// that they don't need to be written by hand:
+///
+/// ```
+/// table_refs! {
+///     pub trait ClientRefs for Table<Postgres, Client> {
+///         fn ref_bakery("bakery") -> Bakery;
+///         fn ref_orders("orders") -> Order;
+///     }
+/// }
+///
+/// let orders: Table<Postgres, Order> = Client::table().ref_orders();
+/// ```
+///
+/// Methods panic if the reference is missing or points to a table of a different
+/// entity, see [`Table::get_ref_as()`].
+#[macro_export]
+macro_rules! table_refs {
+    (
+        $vis:vis trait $name:ident for Table<$ds:ty, $entity:ty> {
+            $(fn $method:ident($relation:literal) -> $target:ty;)*
+        }
+    ) => {
+        $vis trait $name {
+            $(fn $method(&self) -> $crate::sql::table::Table<$ds, $target>;)*
+        }
+
+        impl $name for $crate::sql::table::Table<$ds, $entity> {
+            $(
+                fn $method(&self) -> $crate::sql::table::Table<$ds, $target> {
+                    self.get_ref_as($relation).unwrap()
+                }
+            )*
+        }
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
@@ -143,9 +479,9 @@ mod tests {
         );
 
         let grand_children = john
-            .get_ref_as::<MockDataSource, EmptyEntity>("children")
+            .get_ref_as::<EmptyEntity>("children")
             .unwrap()
-            .get_ref_as::<MockDataSource, EmptyEntity>("children")
+            .get_ref_as::<EmptyEntity>("children")
             .unwrap();
 
         let query = grand_children.get_select_query().render_chunk().split();
@@ -172,6 +508,107 @@ mod tests {
         );
     }
 
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Default)]
+    struct Person {
+        name: String,
+    }
+    impl Entity for Person {}
+
+    table_refs! {
+        trait PersonRefs for Table<MockDataSource, Person> {
+            fn ref_children("children") -> Person;
+        }
+    }
+
+    #[test]
+    fn test_typed_refs() {
+        let data = json!([]);
+        let db = MockDataSource::new(&data);
+        let persons = Table::new("persons", db.clone())
+            .with_id_column("id")
+            .with_column("name")
+            .with_column("parent_id");
+        let typed = persons.clone().with_id(1.into());
+        let typed: Table<MockDataSource, Person> = typed
+            .with_many("children", "parent_id", move || Box::new(persons.clone()))
+            .into_entity();
+
+        let children = typed.ref_children();
+        assert_eq!(
+            children.get_select_query().preview(),
+            "SELECT id, name, parent_id FROM persons WHERE (parent_id IN (SELECT id FROM persons WHERE (id = 1)))"
+        );
+
+        let err = children.get_ref_as::<Person>("parent").unwrap_err();
+        assert_eq!(err.to_string(), "Reference not found");
+        let err = typed.get_ref_as::<EmptyEntity>("children");
+        assert!(err.is_ok());
+    }
+
+    #[test]
+    fn test_ref_strategy() {
+        let db = MockDataSource::new(&json!([]));
+        let orders = Table::new("ord", db.clone())
+            .with_id_column("id")
+            .with_column("client_id");
+        let clients = Table::new("client", db).with_id_column("id").with_many(
+            "orders",
+            "client_id",
+            move || Box::new(orders.clone()),
+        );
+
+        let exists = clients
+            .clone()
+            .with_ref_strategy("orders", SubqueryStrategy::Exists)
+            .unwrap();
+        assert_eq!(
+            exists
+                .get_ref_as::<EmptyEntity>("orders")
+                .unwrap()
+                .get_select_query()
+                .preview(),
+            "SELECT id, client_id FROM ord WHERE (EXISTS (SELECT (1) FROM client WHERE (client.id = ord.client_id)))"
+        );
+
+        let err = clients
+            .with_ref_strategy("invoices", SubqueryStrategy::Exists)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Reference 'invoices' not found");
+    }
+
+    #[test]
+    fn test_descendants() {
+        let data = json!([]);
+        let teams = Table::new("teams", MockDataSource::new(&data))
+            .with_id_column("id")
+            .with_column("name")
+            .with_column("parent_id");
+
+        let sub_teams = teams
+            .clone()
+            .with_id(1.into())
+            .ref_descendants("parent_id")
+            .unwrap();
+
+        assert_eq!(
+            sub_teams.get_select_query().preview(),
+            "SELECT id, name, parent_id FROM teams WHERE (id IN (\
+            WITH RECURSIVE descendants AS (\
+            SELECT id FROM teams WHERE parent_id IN (SELECT id FROM teams WHERE (id = 1)) \
+            UNION ALL SELECT (child.id) AS id FROM teams AS child \
+            JOIN descendants ON child.parent_id = descendants.id\
+            ) SELECT id FROM descendants))"
+        );
+
+        let err = teams.ref_descendants("manager_id").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<crate::Error>(),
+            Some(crate::Error::MissingColumn { column, .. }) if column == "manager_id"
+        ));
+        let no_id = Table::new("teams", MockDataSource::new(&data)).with_column("parent_id");
+        assert!(no_id.ref_descendants("parent_id").is_err());
+    }
+
     #[test]
     fn test_field_importing() {
         let data =
@@ -224,5 +661,91 @@ mod tests {
                 .preview(),
             "SELECT name, (SELECT name FROM roles WHERE (roles.id = users.role_id)) AS role_name, (SELECT permission FROM roles WHERE (roles.id = users.role_id)) AS role_permission FROM users"
         );
+
+        let users = users.with_imported_column_expr("role", "name", "role");
+        assert_eq!(
+            users.get_select_query_for_field_names(&["role"]).preview(),
+            "SELECT (SELECT name FROM roles WHERE (roles.id = users.role_id)) AS role FROM users"
+        );
+    }
+
+    #[test]
+    fn test_import_fields_through_references() {
+        let data = json!([]);
+        let data_source = MockDataSource::new(&data);
+
+        let bakeries = Table::new("bakery", data_source.clone())
+            .with_id_column("id")
+            .with_title_column("name");
+        let clients = Table::new("client", data_source.clone())
+            .with_id_column("id")
+            .with_title_column("name")
+            .with_column("bakery_id")
+            .with_one("bakery", "bakery_id", move || Box::new(bakeries.clone()));
+        let mut orders = Table::new("ord", data_source.clone())
+            .with_id_column("id")
+            .with_column("client_id")
+            .with_one("client", "client_id", move || Box::new(clients.clone()));
+
+        orders
+            .try_add_imported_fields("client.bakery", &["name"])
+            .unwrap();
+        assert_eq!(
+            orders
+                .get_select_query_for_field_names(&["id", "client_bakery_name"])
+                .preview(),
+            "SELECT id, (SELECT name FROM bakery WHERE (id IN (SELECT bakery_id FROM client WHERE (client.id = ord.client_id)))) AS client_bakery_name FROM ord"
+        );
+
+        let err = orders
+            .try_add_imported_fields("client", &["name", "address"])
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Table 'client' has no field 'address'");
+        assert!(orders
+            .try_add_imported_fields("client.owner", &["name"])
+            .is_err());
+        assert!(orders.search_for_field("client_name").is_none());
+    }
+
+    #[test]
+    fn test_ref_scalar() {
+        let data = json!([]);
+        let data_source = MockDataSource::new(&data);
+
+        let clients = Table::new("client", data_source.clone())
+            .with_id_column("id")
+            .with_title_column("name");
+        let persons = Table::new("persons", data_source.clone())
+            .with_id_column("id")
+            .with_title_column("name")
+            .with_column("parent_id");
+
+        let orders = Table::new("ord", data_source.clone())
+            .with_id_column("id")
+            .with_column("client_id")
+            .with_one("client", "client_id", move || Box::new(clients.clone()))
+            .with_alias("o")
+            .with_expression("client_name", |t| {
+                t.ref_scalar("client", |c| c.field_query(c.title()))
+            });
+        assert_eq!(
+            orders
+                .get_select_query_for_field_names(&["id", "client_name"])
+                .preview(),
+            "SELECT o.id, (SELECT name FROM client WHERE (client.id = o.client_id)) AS client_name FROM ord AS o"
+        );
+
+        let parent = persons.clone();
+        let persons = persons
+            .with_one("parent", "parent_id", move || Box::new(parent.clone()))
+            .with_expression("parent_name", |t| {
+                t.ref_scalar("parent", |p| p.field_query(p.title()))
+            });
+        assert_eq!(
+            persons
+                .get_select_query_for_field_names(&["name", "parent_name"])
+                .preview(),
+            "SELECT name, (SELECT p.name FROM persons AS p WHERE (p.id = persons.parent_id)) AS parent_name FROM persons"
+        );
     }
 }
@@ -1,34 +1,66 @@
+use std::sync::Arc;
+
 use crate::{
-    dataset::WritableDataSet, prelude::Entity, sql::query::QueryType,
-    traits::datasource::DataSource,
+    dataset::WritableDataSet,
+    prelude::{Entity, Expression},
+    sql::{
+        query::{QueryType, SqlQuery},
+        Operations, Query,
+    },
+    traits::datasource::{DataSource, ExecResult},
 };
 
-use super::{AnyTable, Table, TableWithQueries};
-use anyhow::Result;
+use super::{AnyTable, Column, Record, Table, TableWithColumns, TableWithQueries};
+use anyhow::{anyhow, Result};
 use serde::Serialize;
 use serde_json::Value;
 
 // You should be able to insert and delete data in a table
 impl<T: DataSource, E: Entity> WritableDataSet<E> for Table<T, E> {
     async fn insert(&self, record: E) -> Result<Option<Value>> {
-        let query = self.get_insert_query(record);
-        let Some(id) = self.data_source.query_exec(&query).await? else {
-            return Ok(None);
-        };
-        if self.id_column.is_none() {
-            return Ok(None);
+        self.validate(&record)?;
+        if !self.joins.is_empty() {
+            return self.insert_with_joins(&record).await;
         }
-        let Some(id) = id.get(self.id_column.as_ref().unwrap()) else {
-            return Ok(None);
+        let query = self.get_insert_query(record)?;
+        let result = self.data_source.query_exec(&query).await?;
+        let id = match (result.row(), &self.id_column) {
+            (Some(row), Some(id_column)) => row.get(id_column).cloned(),
+            _ => None,
         };
-        Ok(Some(id.clone()))
+        self.hooks.after_insert(self, id.as_ref())?;
+        Ok(id)
     }
 
-    async fn update<F>(&self, _f: F) -> Result<()> {
-        todo!()
+    async fn update<F>(&self, mut f: F) -> Result<ExecResult>
+    where
+        F: FnMut(&mut E),
+    {
+        let Some(id_column) = self.id_column.clone() else {
+            return Err(anyhow::anyhow!(
+                "Table {} must have id column for update()",
+                self
+            ));
+        };
+
+        let (mut query, _) = self.select_query_for_struct(E::default())?;
+        query.add_field(Some(id_column.clone()), Arc::new(Box::new(self.id())));
+
+        let mut result = ExecResult::default();
+        for row in self.data_source.query_fetch(&query).await? {
+            let Some(id) = row.get(&id_column).cloned() else {
+                return Err(anyhow::anyhow!("Row is missing id column {}", id_column));
+            };
+            let entity: E = serde_json::from_value(Value::Object(row))?;
+
+            let mut record = Record::new(self.clone().with_id(id), entity)?;
+            f(&mut record);
+            result.merge(record.save().await?);
+        }
+        Ok(result)
     }
 
-    async fn update_with<F, T2>(&self, values: T2) -> Result<()>
+    async fn update_with<F, T2>(&self, values: T2) -> Result<ExecResult>
     where
         T2: Serialize + Clone,
     {
@@ -43,13 +75,266 @@ impl<T: DataSource, E: Entity> WritableDataSet<E> for Table<T, E> {
             }
         }
 
-        let query = self.get_update_query(values);
-        self.data_source.query_exec(&query).await.map(|_| ())
+        let query = self.get_update_query(values)?;
+        let result = self.data_source.query_exec(&query).await?;
+        self.hooks().after_update_query(self, &query, &result)?;
+        Ok(result)
     }
 
-    async fn delete(&self) -> Result<()> {
+    async fn delete(&self) -> Result<ExecResult> {
+        self.ensure_writable()?;
         let mut query = self.get_empty_query().with_type(QueryType::Delete);
-        self.hooks().before_delete_query(self, &mut query).unwrap();
-        self.data_source.query_exec(&query).await.map(|_| ())
+        self.hooks().before_delete_query(self, &mut query)?;
+        let result = self.data_source.query_exec(&query).await?;
+        self.hooks().after_delete(self, &query)?;
+        Ok(result)
+    }
+}
+
+/// Maximum number of rows inserted with a single query by [`Table::insert_batch()`]
+const INSERT_BATCH_SIZE: usize = 1000;
+
+impl<T: DataSource, E: Entity> Table<T, E> {
+    /// Insert many records using multi-row INSERT queries, up to 1000 rows per
+    /// query, which is much faster than inserting records one by one. Returns ids
+    /// of inserted records, if the table has an id column.
+    ///
+    /// ```
+    /// let ids = Product::table().insert_batch(products).await?;
+    /// ```
+    pub async fn insert_batch(&self, records: Vec<E>) -> Result<Vec<Value>> {
+        for record in &records {
+            self.validate(record)?;
+        }
+        let mut ids = Vec::new();
+        for chunk in records.chunks(INSERT_BATCH_SIZE) {
+            let query = self.get_insert_batch_query(chunk)?;
+            let Some(id_column) = &self.id_column else {
+                self.data_source.query_exec(&query).await?;
+                for _ in chunk {
+                    self.hooks.after_insert(self, None)?;
+                }
+                continue;
+            };
+            for row in self.data_source.query_fetch(&query).await? {
+                let id = row.get(id_column).cloned().unwrap_or(Value::Null);
+                self.hooks.after_insert(self, Some(&id))?;
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Update all records in the table, setting columns to expressions. Unlike
+    /// [`WritableDataSet::update()`] records are not fetched, and a single UPDATE
+    /// query is executed instead.
+    ///
+    /// ```
+    /// products.update_all(|t| vec![(t.price(), expr!("price * 1.1"))]).await?;
+    /// ```
+    pub async fn update_all<F>(&self, f: F) -> Result<ExecResult>
+    where
+        F: FnOnce(&Self) -> Vec<(Arc<Column>, Expression)>,
+    {
+        let query = self.get_update_all_query(f(self))?;
+        self.data_source.query_exec(&query).await
+    }
+
+    /// Query, which deletes up to `batch_size` records of the table. MySQL
+    /// accepts `DELETE .. LIMIT`, for other databases records are picked by id
+    /// with a subquery:
+    ///
+    /// ```sql
+    /// DELETE FROM order WHERE (..) LIMIT 1000
+    /// DELETE FROM order WHERE (id IN (SELECT id FROM order WHERE (..) LIMIT 1000))
+    /// ```
+    ///
+    /// MySQL rejects LIMIT in a subquery of IN, while other databases don't
+    /// support it in DELETE.
+    pub fn get_delete_batch_query(&self, batch_size: i64) -> Result<Query> {
+        self.ensure_writable()?;
+        let mut query = if self.data_source.dialect().supports_delete_limit() {
+            self.get_empty_query()
+                .with_type(QueryType::Delete)
+                .with_limit(batch_size)
+        } else {
+            let id = self.try_id()?;
+            let ids = self
+                .try_get_select_query_for_field(Box::new(id.clone()))?
+                .with_limit(batch_size);
+            Query::new()
+                .with_dialect(self.data_source.dialect())
+                .with_table(&self.table_name, self.table_alias.clone())
+                .with_type(QueryType::Delete)
+                .with_condition(id.in_expr(&ids))
+        };
+        self.hooks.before_delete_query(self, &mut query)?;
+        Ok(query)
+    }
+
+    /// Delete records of the table with a series of queries, each removing up to
+    /// `batch_size` records, until a query deletes fewer. Unlike
+    /// [`WritableDataSet::delete()`] every query only locks its own batch, which
+    /// keeps other writers going while millions of rows are purged:
+    ///
+    /// ```
+    /// let expired = Session::table().with_condition(Session::table().expires().lt(&now));
+    /// let purged = expired.delete_in_batches(10_000).await?;
+    /// println!("Purged {} sessions", purged.rows_affected);
+    /// ```
+    ///
+    /// Table must have an id column, unless the database supports `DELETE .. LIMIT`.
+    pub async fn delete_in_batches(&self, batch_size: i64) -> Result<ExecResult> {
+        if batch_size < 1 {
+            return Err(anyhow!("Batch size must be positive, got {}", batch_size));
+        }
+        let query = self.get_delete_batch_query(batch_size)?;
+        let mut result = ExecResult::default();
+        loop {
+            let batch = self.data_source.query_exec(&query).await?;
+            self.hooks.after_delete(self, &query)?;
+            let done = batch.rows_affected < batch_size as u64;
+            result.merge(batch);
+            if done {
+                return Ok(result);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        expr,
+        mocks::datasource::{MockDataSource, MockError, QueryMatcher},
+        sql::dialect::MySqlDialect,
+    };
+
+    #[derive(Serialize, Deserialize, Clone, Default, Debug)]
+    struct Order {
+        qty: i64,
+    }
+    impl Entity for Order {}
+
+    #[tokio::test]
+    async fn test_update() {
+        let data = json!([{ "id": 1, "qty": 3 }, { "id": 2, "qty": 5 }]);
+        let db = MockDataSource::new(&data);
+        let orders: Table<MockDataSource, Order> = Table::new_with_entity("orders", db.clone())
+            .with_id_column("id")
+            .with_column("qty");
+
+        let mut seen = vec![];
+        orders
+            .update(|order| {
+                seen.push(order.qty);
+                order.qty += 1;
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(seen, vec![3, 5]);
+        assert_eq!(
+            db.calls()
+                .into_iter()
+                .map(|call| call.sql)
+                .collect::<Vec<_>>(),
+            vec![
+                "SELECT qty, id FROM orders",
+                "UPDATE orders SET qty = {} WHERE (id = {})",
+                "UPDATE orders SET qty = {} WHERE (id = {})",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_error() {
+        let db = MockDataSource::new(&json!([]))
+            .with_expectation(
+                QueryMatcher::select("orders"),
+                &json!([{ "id": 1, "qty": 3 }, { "id": 2, "qty": 5 }]),
+            )
+            .with_error(
+                QueryMatcher::update("orders"),
+                MockError::ConstraintViolation("qty_positive".to_string()),
+            );
+        let orders: Table<MockDataSource, Order> = Table::new_with_entity("orders", db.clone())
+            .with_id_column("id")
+            .with_column("qty");
+
+        let error = orders.update(|order| order.qty = -1).await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<MockError>(),
+            Some(&MockError::ConstraintViolation("qty_positive".to_string()))
+        );
+        // first failing UPDATE stops the rest
+        assert_eq!(db.calls().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_update_without_id() {
+        let data = json!([{ "qty": 3 }]);
+        let orders: Table<MockDataSource, Order> =
+            Table::new_with_entity("orders", MockDataSource::new(&data)).with_column("qty");
+
+        assert!(orders.update(|order| order.qty += 1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delete_in_batches() {
+        let db = MockDataSource::new(&json!([]))
+            .with_expectation(QueryMatcher::delete("orders"), &json!([{}, {}]))
+            .with_expectation(QueryMatcher::delete("orders"), &json!([{}, {}]))
+            .with_expectation(QueryMatcher::delete("orders"), &json!([{}]));
+        let orders: Table<MockDataSource, Order> = Table::new_with_entity("orders", db.clone())
+            .with_id_column("id")
+            .with_column("qty");
+        let qty = orders.get_column("qty").unwrap();
+        let empty = orders.clone().with_condition(qty.eq(&0));
+
+        assert_eq!(
+            empty.get_delete_batch_query(2).unwrap().preview(),
+            "DELETE FROM orders WHERE (id IN (SELECT id FROM orders WHERE (qty = 0) LIMIT 2::int4))"
+        );
+        let result: ExecResult = empty.delete_in_batches(2).await.unwrap();
+        assert_eq!(result.rows_affected, 5);
+        assert_eq!(db.calls().len(), 3);
+        assert!(empty.delete_in_batches(0).await.is_err());
+
+        // MySQL rejects LIMIT in the subquery, but limits DELETE itself
+        let db = MockDataSource::new(&json!([])).with_dialect(MySqlDialect);
+        let orders: Table<MockDataSource, Order> =
+            Table::new_with_entity("orders", db).with_column("qty");
+        let qty = orders.get_column("qty").unwrap();
+        let empty = orders.clone().with_condition(qty.eq(&0));
+        assert_eq!(
+            empty.get_delete_batch_query(2).unwrap().preview(),
+            "DELETE FROM orders WHERE (qty = 0) LIMIT 2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_all() {
+        let db = MockDataSource::new(&json!([]));
+        let orders = Table::new("orders", db.clone()).with_column("qty");
+        let qty = orders.get_column("qty").unwrap();
+        let orders = orders.with_condition(qty.gt(0));
+
+        orders
+            .update_all(|t| vec![(t.get_column("qty").unwrap(), expr!("qty + {}", 1))])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.calls()
+                .into_iter()
+                .map(|call| call.sql)
+                .collect::<Vec<_>>(),
+            vec!["UPDATE orders SET qty = qty + {} WHERE (qty > {})"]
+        );
     }
 }
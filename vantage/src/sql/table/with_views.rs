@@ -0,0 +1,313 @@
+use anyhow::Result;
+use futures::Stream;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::dataset::ReadableDataSet;
+use crate::expr;
+use crate::prelude::ExecResult;
+use crate::sql::query::QueryType;
+use crate::sql::table::Table;
+use crate::sql::{Chunk, Condition, Expression, Query};
+use crate::traits::datasource::DataSource;
+use crate::traits::entity::Entity;
+
+/// Database view, mapped onto a [`Table`], created with [`Table::into_view()`].
+/// View implements [`ReadableDataSet`] only, so records can't be inserted, updated
+/// or deleted:
+///
+/// ```
+/// let active_clients = Table::new_with_entity::<Client>("active_client", postgres())
+///     .with_id_column("id")
+///     .with_column("name")
+///     .into_view();
+///
+/// for client in active_clients.get().await? {
+///     println!("{}", client.name);
+/// }
+/// ```
+///
+/// Underlying table is available through [`table()`](View::table) for building
+/// queries, but it is marked read-only, so writing through it fails as well.
+#[derive(Debug, Clone)]
+pub struct View<T: DataSource, E: Entity> {
+    table: Table<T, E>,
+}
+
+/// Materialized view, created with [`Table::into_materialized_view()`]. Same as
+/// [`View`], but stores its rows, which are brought up to date by
+/// [`refresh()`](MaterializedView::refresh).
+#[derive(Debug, Clone)]
+pub struct MaterializedView<T: DataSource, E: Entity> {
+    table: Table<T, E>,
+}
+
+impl<T: DataSource, E: Entity> View<T, E> {
+    pub fn table(&self) -> &Table<T, E> {
+        &self.table
+    }
+
+    pub fn with_condition(mut self, condition: Condition) -> Self {
+        self.table.add_condition(condition);
+        self
+    }
+
+    /// SQL creating the view from a query, to be used in migrations:
+    ///
+    /// ```
+    /// let sql = active_clients.create_sql(
+    ///     &Client::table()
+    ///         .with_condition(Client::table().is_active().eq(&true))
+    ///         .get_select_query(),
+    /// )?;
+    /// // CREATE OR REPLACE VIEW active_client AS SELECT id, name FROM client WHERE (is_active = true)
+    /// ```
+    ///
+    /// Views can't have parameters, so parameters of the query are rendered as
+    /// literals of the data source dialect, see [`Dialect::render_literal()`].
+    ///
+    /// [`Dialect::render_literal()`]: crate::sql::Dialect::render_literal()
+    pub fn create_sql(&self, definition: &Query) -> Result<String> {
+        Ok(format!(
+            "CREATE OR REPLACE VIEW {} AS {}",
+            self.name(),
+            inline_definition(&self.table, definition)?
+        ))
+    }
+
+    /// SQL dropping the view, to be used in migrations.
+    pub fn drop_sql(&self) -> String {
+        format!("DROP VIEW IF EXISTS {}", self.name())
+    }
+
+    fn name(&self) -> String {
+        quoted_name(&self.table)
+    }
+}
+
+impl<T: DataSource, E: Entity> MaterializedView<T, E> {
+    pub fn table(&self) -> &Table<T, E> {
+        &self.table
+    }
+
+    pub fn with_condition(mut self, condition: Condition) -> Self {
+        self.table.add_condition(condition);
+        self
+    }
+
+    /// SQL creating the materialized view from a query, see [`View::create_sql()`].
+    pub fn create_sql(&self, definition: &Query) -> Result<String> {
+        Ok(format!(
+            "CREATE MATERIALIZED VIEW {} AS {}",
+            self.name(),
+            inline_definition(&self.table, definition)?
+        ))
+    }
+
+    /// SQL dropping the materialized view, to be used in migrations.
+    pub fn drop_sql(&self) -> String {
+        format!("DROP MATERIALIZED VIEW IF EXISTS {}", self.name())
+    }
+
+    /// Re-runs the query of the view and stores its result:
+    ///
+    /// ```
+    /// ClientStats::view().refresh(true).await?;
+    /// ```
+    ///
+    /// Concurrent refresh doesn't lock out readers of the view, but requires a
+    /// unique index on the view.
+    pub async fn refresh(&self, concurrently: bool) -> Result<ExecResult> {
+        let query = Query::new().with_type(QueryType::Expression(self.refresh_sql(concurrently)));
+        self.table.data_source.query_exec(&query).await
+    }
+
+    fn refresh_sql(&self, concurrently: bool) -> Expression {
+        expr!(format!(
+            "REFRESH MATERIALIZED VIEW {}{}",
+            if concurrently { "CONCURRENTLY " } else { "" },
+            self.name()
+        ))
+    }
+
+    fn name(&self) -> String {
+        quoted_name(&self.table)
+    }
+}
+
+/// Query of the view with parameters rendered as literals.
+fn inline_definition<T: DataSource, E: Entity>(
+    table: &Table<T, E>,
+    definition: &Query,
+) -> Result<String> {
+    let dialect = table.data_source.dialect();
+    Ok(definition
+        .try_render_chunk()?
+        .render_inline(dialect.as_ref()))
+}
+
+fn quoted_name<T: DataSource, E: Entity>(table: &Table<T, E>) -> String {
+    table
+        .data_source
+        .dialect()
+        .quote_identifier(&table.table_name)
+}
+
+impl<T: DataSource, E: Entity> Table<T, E> {
+    /// Map this table onto a database view. See [`View`].
+    pub fn into_view(mut self) -> View<T, E> {
+        self.read_only = true;
+        View { table: self }
+    }
+
+    /// Map this table onto a materialized view. See [`MaterializedView`].
+    pub fn into_materialized_view(mut self) -> MaterializedView<T, E> {
+        self.read_only = true;
+        MaterializedView { table: self }
+    }
+}
+
+macro_rules! impl_readable_view {
+    ($view:ident) => {
+        impl<T: DataSource, E: Entity> ReadableDataSet<E> for $view<T, E> {
+            fn select_query(&self) -> Query {
+                self.table.select_query()
+            }
+
+            async fn get_all_untyped(&self) -> Result<Vec<Map<String, Value>>> {
+                self.table.get_all_untyped().await
+            }
+
+            async fn get_row_untyped(&self) -> Result<Map<String, Value>> {
+                self.table.get_row_untyped().await
+            }
+
+            async fn get_col_untyped(&self) -> Result<Vec<Value>> {
+                self.table.get_col_untyped().await
+            }
+
+            async fn get_one_untyped(&self) -> Result<Value> {
+                self.table.get_one_untyped().await
+            }
+
+            async fn count(&self) -> Result<i64> {
+                ReadableDataSet::count(&self.table).await
+            }
+
+            async fn exists(&self) -> Result<bool> {
+                self.table.exists().await
+            }
+
+            async fn get(&self) -> Result<Vec<E>> {
+                self.table.get().await
+            }
+
+            fn get_stream(&self) -> impl Stream<Item = Result<E>> {
+                self.table.get_stream()
+            }
+
+            async fn get_as<T2: DeserializeOwned>(&self) -> Result<Vec<T2>> {
+                self.table.get_as().await
+            }
+
+            async fn get_as_lenient<T2: DeserializeOwned>(&self) -> Result<Vec<T2>> {
+                self.table.get_as_lenient().await
+            }
+
+            async fn get_some(&self) -> Result<Option<E>> {
+                self.table.get_some().await
+            }
+
+            async fn get_some_as<T2>(&self) -> Result<Option<T2>>
+            where
+                T2: DeserializeOwned + Default + serde::Serialize,
+            {
+                self.table.get_some_as().await
+            }
+        }
+    };
+}
+
+impl_readable_view!(View);
+impl_readable_view!(MaterializedView);
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use crate::{mocks::datasource::MockDataSource, prelude::*};
+
+    #[tokio::test]
+    async fn test_view() {
+        let db = MockDataSource::new(&json!([{"id": 1, "name": "Cake"}]));
+        let products = Table::new("product", db.clone())
+            .with_id_column("id")
+            .with_column("name")
+            .with_column("is_deleted");
+        let is_deleted = products.get_column("is_deleted").unwrap();
+        let definition = products
+            .clone()
+            .with_condition(is_deleted.eq(&false))
+            .get_select_query_for_field_names(&["id", "name"]);
+
+        let view = Table::new("active_product", db)
+            .with_id_column("id")
+            .with_column("name")
+            .into_view();
+
+        assert_eq!(
+            view.create_sql(&definition).unwrap(),
+            "CREATE OR REPLACE VIEW active_product AS SELECT id, name FROM product WHERE (is_deleted = false)"
+        );
+
+        // values are escaped as literals of the dialect
+        let name = products.get_column("name").unwrap();
+        let definition = products
+            .clone()
+            .with_condition(name.eq(&"O'Hara\\"))
+            .get_select_query_for_field_names(&["id"]);
+        assert_eq!(
+            view.create_sql(&definition).unwrap(),
+            "CREATE OR REPLACE VIEW active_product AS SELECT id FROM product WHERE (name = 'O''Hara\\')"
+        );
+        let mysql = Table::new(
+            "active_product",
+            MockDataSource::new(&json!([])).with_dialect(MySqlDialect),
+        )
+        .with_id_column("id")
+        .into_view();
+        assert_eq!(
+            mysql.create_sql(&definition).unwrap(),
+            "CREATE OR REPLACE VIEW active_product AS SELECT id FROM product WHERE (name = 'O\\'Hara\\\\')"
+        );
+        assert_eq!(view.drop_sql(), "DROP VIEW IF EXISTS active_product");
+        assert_eq!(view.get_all_untyped().await.unwrap().len(), 1);
+        assert!(view.table().insert(EmptyEntity {}).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_materialized_view() {
+        let db = MockDataSource::new(&json!([]));
+        let view = Table::new("order_stats", db.clone())
+            .with_column("total")
+            .into_materialized_view();
+
+        view.refresh(true).await.unwrap();
+        view.refresh(false).await.unwrap();
+        assert_eq!(
+            db.calls()
+                .into_iter()
+                .map(|call| call.sql)
+                .collect::<Vec<_>>(),
+            vec![
+                "REFRESH MATERIALIZED VIEW CONCURRENTLY order_stats",
+                "REFRESH MATERIALIZED VIEW order_stats",
+            ]
+        );
+        assert_eq!(
+            view.drop_sql(),
+            "DROP MATERIALIZED VIEW IF EXISTS order_stats"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::datasource::postgres::Postgres;
+use crate::sql::table::Table;
+use crate::traits::entity::Entity;
+
+/// Operation, which has changed a record
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ChangeKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// Change of a record, received from [`Table::watch()`]. Deleted records
+/// contain their last state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeEvent<E> {
+    pub kind: ChangeKind,
+    pub entity: E,
+}
+
+#[derive(Deserialize)]
+struct Payload {
+    op: ChangeKind,
+    row: Value,
+}
+
+fn parse_change<E: Entity>(payload: &str) -> Result<ChangeEvent<E>> {
+    let payload: Payload = serde_json::from_str(payload)?;
+    Ok(ChangeEvent {
+        kind: payload.op,
+        entity: serde_json::from_value(payload.row).map_err(crate::Error::from)?,
+    })
+}
+
+fn trigger_sql(table: &str, channel: &str) -> String {
+    format!(
+        r#"CREATE OR REPLACE FUNCTION "{channel}"() RETURNS trigger AS $$
+DECLARE
+    changed RECORD;
+BEGIN
+    IF TG_OP = 'DELETE' THEN changed := OLD; ELSE changed := NEW; END IF;
+    PERFORM pg_notify('{channel}', json_build_object('op', TG_OP, 'row', row_to_json(changed))::text);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+DROP TRIGGER IF EXISTS "{channel}" ON "{table}";
+CREATE TRIGGER "{channel}" AFTER INSERT OR UPDATE OR DELETE ON "{table}"
+    FOR EACH ROW EXECUTE FUNCTION "{channel}"();
+"#
+    )
+}
+
+impl<E: Entity> Table<Postgres, E> {
+    /// Channel, which the trigger of [`watch()`](Table::watch) notifies.
+    pub fn watch_channel(&self) -> String {
+        format!("vantage_{}", self.table_name)
+    }
+
+    /// SQL creating a trigger, which sends changed records to
+    /// [`watch_channel()`](Table::watch_channel). Can be added to migrations
+    /// instead of letting [`watch()`](Table::watch) install it.
+    pub fn watch_trigger_sql(&self) -> String {
+        trigger_sql(&self.table_name, &self.watch_channel())
+    }
+
+    /// Installs the trigger and streams changes of the table, made by any
+    /// connection:
+    ///
+    /// ```
+    /// let mut changes = Product::table().watch().await?;
+    /// while let Some(change) = changes.next().await {
+    ///     let change = change?;
+    ///     println!("{:?} {}", change.kind, change.entity.name);
+    /// }
+    /// ```
+    ///
+    /// Conditions of the table are not applied, all records are watched. Records
+    /// are sent with `pg_notify()`, so they must fit its 8000 byte limit.
+    pub async fn watch(&self) -> Result<impl Stream<Item = Result<ChangeEvent<E>>>> {
+        self.data_source
+            .client()
+            .batch_execute(&self.watch_trigger_sql())
+            .await?;
+        self.watch_installed().await
+    }
+
+    /// Same as [`watch()`](Table::watch), for a trigger installed beforehand.
+    pub async fn watch_installed(&self) -> Result<impl Stream<Item = Result<ChangeEvent<E>>>> {
+        let notifications = self.data_source.listen(&self.watch_channel()).await?;
+        Ok(notifications.map(|notification| parse_change(notification?.payload())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+    struct Product {
+        name: String,
+    }
+    impl Entity for Product {}
+
+    #[test]
+    fn test_parse_change() {
+        let change: ChangeEvent<Product> =
+            parse_change(r#"{"op": "DELETE", "row": {"id": 1, "name": "Cake"}}"#).unwrap();
+        assert_eq!(
+            change,
+            ChangeEvent {
+                kind: ChangeKind::Delete,
+                entity: Product {
+                    name: "Cake".to_string()
+                }
+            }
+        );
+        assert!(parse_change::<Product>(r#"{"op": "INSERT", "row": {"id": 1}}"#).is_err());
+    }
+
+    #[test]
+    fn test_trigger_sql() {
+        let sql = trigger_sql("product", "vantage_product");
+        assert!(sql.contains("PERFORM pg_notify('vantage_product', "));
+        assert!(sql.contains(r#"AFTER INSERT OR UPDATE OR DELETE ON "product""#));
+    }
+}
@@ -0,0 +1,182 @@
+//! Helpers for testing generated SQL.
+//!
+//! [`assert_sql!`] compares a query with an inline snapshot. Both sides are
+//! normalized, so the snapshot can be formatted freely and placeholders can be
+//! copied from a log - `$1`, `?` and `@P1` are all the same as `{}`:
+//!
+//! ```
+//! assert_sql!(Product::table().with_id(1.into()).get_select_query(), @"
+//!     SELECT id, name, price FROM product
+//!     WHERE (id = $1)
+//! ");
+//! ```
+//!
+//! With a name instead of the inline snapshot, the query is compared with
+//! `snapshots/<name>.sql` in the crate directory. Missing snapshots are written
+//! on the first run, set `VANTAGE_UPDATE_SNAPSHOTS=1` to overwrite changed ones:
+//!
+//! ```
+//! assert_sql!("client_orders", Client::table().ref_orders().get_select_query());
+//! ```
+
+use std::path::Path;
+
+use crate::sql::Chunk;
+
+/// Environment variable, which allows overwriting snapshot files
+pub const UPDATE_SNAPSHOTS: &str = "VANTAGE_UPDATE_SNAPSHOTS";
+
+/// Collapses whitespace, removes spaces inside parentheses and replaces
+/// placeholders (`$1`, `?`, `@P1`) with `{}`. String literals are kept as is.
+pub fn normalize_sql(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.trim().chars().peekable();
+    let mut quote: Option<char> = None;
+
+    while let Some(c) = chars.next() {
+        if let Some(q) = quote {
+            out.push(c);
+            if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '\'' | '"' => {
+                quote = Some(c);
+                out.push(c);
+            }
+            c if c.is_whitespace() => {
+                while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                    chars.next();
+                }
+                if !out.ends_with('(') && chars.peek() != Some(&')') {
+                    out.push(' ');
+                }
+            }
+            '$' if chars.peek().is_some_and(|c| c.is_ascii_digit()) => {
+                while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    chars.next();
+                }
+                out.push_str("{}");
+            }
+            '@' if chars.peek() == Some(&'P') => {
+                chars.next();
+                while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    chars.next();
+                }
+                out.push_str("{}");
+            }
+            '?' => out.push_str("{}"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// SQL of the query with `{}` placeholders, normalized with [`normalize_sql()`].
+pub fn render_sql(query: &impl Chunk) -> String {
+    normalize_sql(query.render_chunk().sql())
+}
+
+/// Used by [`assert_sql!`] with an inline snapshot.
+#[track_caller]
+pub fn assert_inline_snapshot(query: &impl Chunk, expected: &str) {
+    let actual = render_sql(query);
+    let expected = normalize_sql(expected);
+    if actual != expected {
+        panic!(
+            "SQL does not match the snapshot\n  actual: {}\nexpected: {}",
+            actual, expected
+        );
+    }
+}
+
+/// Used by [`assert_sql!`] with a named snapshot, stored in `snapshots`
+/// directory of `manifest_dir`.
+#[track_caller]
+pub fn assert_file_snapshot(manifest_dir: &str, name: &str, query: &impl Chunk) {
+    let path = Path::new(manifest_dir)
+        .join("snapshots")
+        .join(format!("{}.sql", name));
+    let actual = render_sql(query);
+    let update = std::env::var(UPDATE_SNAPSHOTS).is_ok_and(|v| v == "1");
+
+    match std::fs::read_to_string(&path) {
+        Ok(expected) if normalize_sql(&expected) == actual => {}
+        Ok(expected) if !update => panic!(
+            "SQL does not match the snapshot {}, run with {}=1 to update it\n  actual: {}\nexpected: {}",
+            path.display(),
+            UPDATE_SNAPSHOTS,
+            actual,
+            normalize_sql(&expected)
+        ),
+        _ => {
+            std::fs::create_dir_all(path.parent().unwrap())
+                .and_then(|_| std::fs::write(&path, format!("{}\n", actual)))
+                .unwrap_or_else(|e| panic!("Unable to write {}: {}", path.display(), e));
+        }
+    }
+}
+
+/// Compares SQL of a query with a snapshot, see [`testing`](crate::testing).
+#[macro_export]
+macro_rules! assert_sql {
+    ($name:literal, $query:expr) => {
+        $crate::testing::assert_file_snapshot(env!("CARGO_MANIFEST_DIR"), $name, &$query)
+    };
+    ($query:expr, @$expected:literal) => {
+        $crate::testing::assert_inline_snapshot(&$query, $expected)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::prelude::*;
+
+    fn products() -> Table<MockDataSource, EmptyEntity> {
+        Table::new("product", MockDataSource::new(&json!([])))
+            .with_id_column("id")
+            .with_column("name")
+    }
+
+    #[test]
+    fn test_normalize_sql() {
+        assert_eq!(
+            normalize_sql("\n  SELECT ( a )\n\tFROM t WHERE a = $1 AND b = ? OR c = @P12  "),
+            "SELECT (a) FROM t WHERE a = {} AND b = {} OR c = {}"
+        );
+        assert_eq!(
+            normalize_sql("SELECT 'a  ?  $1'  FROM t"),
+            "SELECT 'a  ?  $1' FROM t"
+        );
+    }
+
+    #[test]
+    fn test_assert_sql() {
+        let query = products().with_id(1.into()).get_select_query();
+        assert_sql!(query, @"
+            SELECT id, name FROM product
+            WHERE ( id = $1 )
+        ");
+
+        let dir = std::env::temp_dir().join(format!("vantage-snapshots-{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+        assert_file_snapshot(dir, "product", &query);
+        assert_file_snapshot(dir, "product", &query);
+        let changed = std::panic::catch_unwind(|| {
+            assert_file_snapshot(dir, "product", &products().get_select_query())
+        });
+        assert!(changed.is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "SQL does not match the snapshot")]
+    fn test_assert_sql_mismatch() {
+        assert_sql!(products().get_select_query(), @"SELECT id FROM product");
+    }
+}
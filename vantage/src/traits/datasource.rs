@@ -1,20 +1,87 @@
-#![allow(async_fn_in_trait)]
+use std::future::Future;
+use std::sync::Arc;
 
-use crate::sql::Query;
+use crate::sql::{Dialect, Query};
 use anyhow::Result;
+use futures::stream::BoxStream;
 use serde_json::{Map, Value};
 
+/// Outcome of a query executed with [`DataSource::query_exec()`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExecResult {
+    /// Number of inserted, updated or deleted rows
+    pub rows_affected: u64,
+    /// Rows returned by the `RETURNING` clause
+    pub returned: Vec<Map<String, Value>>,
+}
+
+impl ExecResult {
+    /// First returned row, such as the id of an inserted record.
+    pub fn row(&self) -> Option<&Map<String, Value>> {
+        self.returned.first()
+    }
+
+    /// Values of `id_column` in the returned rows.
+    pub fn returned_ids(&self, id_column: &str) -> Vec<Value> {
+        self.returned
+            .iter()
+            .filter_map(|row| row.get(id_column).cloned())
+            .collect()
+    }
+
+    /// Adds up results of several queries.
+    pub fn merge(&mut self, other: ExecResult) {
+        self.rows_affected += other.rows_affected;
+        self.returned.extend(other.returned);
+    }
+}
+
 pub trait DataSource: Clone + Send + PartialEq + Sync + std::fmt::Debug + 'static {
+    // SQL dialect, which queries built for this data source should be rendered with
+    fn dialect(&self) -> Arc<dyn Dialect>;
+
     // Provided with an arbitrary query, fetch the results and return (Value = arbytrary )
-    async fn query_fetch(&self, query: &Query) -> Result<Vec<Map<String, Value>>>;
+    fn query_fetch(
+        &self,
+        query: &Query,
+    ) -> impl Future<Output = Result<Vec<Map<String, Value>>>> + Send;
 
-    // Execute a query without returning any results (e.g. DELETE, UPDATE, ALTER, etc.)
-    async fn query_exec(&self, query: &Query) -> Result<Option<Value>>;
+    // Execute a query, which modifies data (e.g. DELETE, UPDATE, ALTER, etc.), returning number
+    // of affected rows and rows from the RETURNING clause
+    fn query_exec(&self, query: &Query) -> impl Future<Output = Result<ExecResult>> + Send;
 
     // Insert ordered list of rows into a table as described by query columns
-    async fn query_insert(&self, query: &Query, rows: Vec<Vec<Value>>) -> Result<()>;
+    fn query_insert(
+        &self,
+        query: &Query,
+        rows: Vec<Vec<Value>>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn query_one(&self, query: &Query) -> impl Future<Output = Result<Value>> + Send;
+    fn query_row(&self, query: &Query) -> impl Future<Output = Result<Map<String, Value>>> + Send;
+    fn query_col(&self, query: &Query) -> impl Future<Output = Result<Vec<Value>>> + Send;
+
+    // Execute query and return rows one by one, as they arrive, without buffering the whole result
+    fn query_stream(
+        &self,
+        query: &Query,
+    ) -> impl Future<Output = Result<BoxStream<'static, Result<Map<String, Value>>>>> + Send;
+
+    // Clone, which cancels a query on the server when its future is dropped before
+    // completion, such as after a timeout. Queries of other clones are not affected.
+    // Data sources which can't cancel queries return a plain clone
+    fn cancellable(&self) -> Self {
+        self.clone()
+    }
 
-    async fn query_one(&self, query: &Query) -> Result<Value>;
-    async fn query_row(&self, query: &Query) -> Result<Map<String, Value>>;
-    async fn query_col(&self, query: &Query) -> Result<Vec<Value>>;
+    // Transaction for the following queries. Data sources without transactions do nothing
+    fn begin_transaction(&self) -> impl Future<Output = Result<()>> + Send {
+        async { Ok(()) }
+    }
+    fn commit_transaction(&self) -> impl Future<Output = Result<()>> + Send {
+        async { Ok(()) }
+    }
+    fn rollback_transaction(&self) -> impl Future<Output = Result<()>> + Send {
+        async { Ok(()) }
+    }
 }
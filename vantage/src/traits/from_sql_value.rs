@@ -0,0 +1,223 @@
+//! Conversion of a single value, returned by a query, into a Rust type.
+//!
+//! Data sources don't agree on how scalars are represented in JSON. ClickHouse
+//! returns 64-bit integers as strings, numeric columns may arrive as a string or
+//! as a number and booleans are sometimes `0` and `1`. [`FromSqlValue`] accepts
+//! all of those:
+//!
+//! ```
+//! let count = orders.count().get_one_as::<i64>().await?;
+//! let total = orders.sum(orders.total()).get_one_as::<Option<Decimal>>().await?;
+//! ```
+
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use serde::de::Error as _;
+use serde_json::Value;
+
+use crate::Error;
+
+pub trait FromSqlValue: Sized {
+    fn from_sql_value(value: Value) -> Result<Self, Error>;
+}
+
+fn mismatch(value: &Value, expected: &str) -> Error {
+    Error::Deserialize {
+        row: None,
+        column: None,
+        source: serde_json::Error::custom(format!("expected {}, got {}", expected, value)),
+    }
+}
+
+/// Parses a number, which may also be passed as a string
+fn parse_number<V: FromStr>(value: Value, expected: &str) -> Result<V, Error> {
+    let parsed = match &value {
+        Value::Number(n) => n.to_string().parse().ok(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    };
+    parsed.ok_or_else(|| mismatch(&value, expected))
+}
+
+impl FromSqlValue for Value {
+    fn from_sql_value(value: Value) -> Result<Self, Error> {
+        Ok(value)
+    }
+}
+
+impl FromSqlValue for i64 {
+    fn from_sql_value(value: Value) -> Result<Self, Error> {
+        parse_number(value, "integer")
+    }
+}
+
+impl FromSqlValue for i32 {
+    fn from_sql_value(value: Value) -> Result<Self, Error> {
+        parse_number(value, "integer")
+    }
+}
+
+impl FromSqlValue for f64 {
+    fn from_sql_value(value: Value) -> Result<Self, Error> {
+        parse_number(value, "float")
+    }
+}
+
+impl FromSqlValue for Decimal {
+    fn from_sql_value(value: Value) -> Result<Self, Error> {
+        let parsed = match &value {
+            // exponent notation is not accepted by Decimal::from_str
+            Value::Number(n) => Decimal::from_str(&n.to_string())
+                .or_else(|_| Decimal::from_scientific(&n.to_string()))
+                .ok(),
+            Value::String(s) => Decimal::from_str(s.trim()).ok(),
+            _ => None,
+        };
+        parsed.ok_or_else(|| mismatch(&value, "decimal"))
+    }
+}
+
+impl FromSqlValue for String {
+    fn from_sql_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::String(s) => Ok(s),
+            Value::Number(n) => Ok(n.to_string()),
+            value => Err(mismatch(&value, "string")),
+        }
+    }
+}
+
+impl FromSqlValue for bool {
+    fn from_sql_value(value: Value) -> Result<Self, Error> {
+        match &value {
+            Value::Bool(b) => Ok(*b),
+            Value::Number(n) if n.as_i64() == Some(0) => Ok(false),
+            Value::Number(n) if n.as_i64() == Some(1) => Ok(true),
+            Value::String(s) => match s.as_str() {
+                "t" | "true" | "1" => Ok(true),
+                "f" | "false" | "0" => Ok(false),
+                _ => Err(mismatch(&value, "boolean")),
+            },
+            _ => Err(mismatch(&value, "boolean")),
+        }
+    }
+}
+
+impl<V: FromSqlValue> FromSqlValue for Option<V> {
+    fn from_sql_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Null => Ok(None),
+            value => V::from_sql_value(value).map(Some),
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod datetime {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+    use serde_json::Value;
+
+    use super::{mismatch, FromSqlValue};
+    use crate::Error;
+
+    fn parse_string<V>(
+        value: Value,
+        expected: &str,
+        parse: impl FnOnce(&str) -> Option<V>,
+    ) -> Result<V, Error> {
+        match &value {
+            Value::String(s) => parse(s.trim()).ok_or_else(|| mismatch(&value, expected)),
+            _ => Err(mismatch(&value, expected)),
+        }
+    }
+
+    impl FromSqlValue for NaiveDate {
+        fn from_sql_value(value: Value) -> Result<Self, Error> {
+            parse_string(value, "date", |s| s.parse().ok())
+        }
+    }
+
+    impl FromSqlValue for NaiveTime {
+        fn from_sql_value(value: Value) -> Result<Self, Error> {
+            parse_string(value, "time", |s| s.parse().ok())
+        }
+    }
+
+    impl FromSqlValue for NaiveDateTime {
+        fn from_sql_value(value: Value) -> Result<Self, Error> {
+            parse_string(value, "timestamp", |s| {
+                NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+                    .or_else(|_| s.parse())
+                    .ok()
+            })
+        }
+    }
+
+    impl FromSqlValue for DateTime<Utc> {
+        fn from_sql_value(value: Value) -> Result<Self, Error> {
+            parse_string(value, "timestamp with time zone", |s| {
+                DateTime::parse_from_rfc3339(s)
+                    .ok()
+                    .map(|dt| dt.with_timezone(&Utc))
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_from_sql_value() {
+        assert_eq!(i64::from_sql_value(json!(42)).unwrap(), 42);
+        assert_eq!(i64::from_sql_value(json!("42")).unwrap(), 42);
+        assert_eq!(i32::from_sql_value(json!(7)).unwrap(), 7);
+        assert!(i32::from_sql_value(json!(5_000_000_000_i64)).is_err());
+        assert_eq!(f64::from_sql_value(json!(2.5)).unwrap(), 2.5);
+        assert_eq!(
+            Decimal::from_sql_value(json!(12.30)).unwrap(),
+            Decimal::from_str("12.30").unwrap()
+        );
+        assert_eq!(
+            Decimal::from_sql_value(json!("0.1")).unwrap(),
+            Decimal::from_str("0.1").unwrap()
+        );
+        assert!(bool::from_sql_value(json!(1)).unwrap());
+        assert_eq!(String::from_sql_value(json!("Cake")).unwrap(), "Cake");
+        assert_eq!(Option::<i64>::from_sql_value(Value::Null).unwrap(), None);
+        assert_eq!(Option::<i64>::from_sql_value(json!(3)).unwrap(), Some(3));
+
+        let e = i64::from_sql_value(json!("many")).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Unable to deserialize record: expected integer, got \"many\""
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_from_sql_value_datetime() {
+        use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+        assert_eq!(
+            NaiveDate::from_sql_value(json!("2024-05-01")).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()
+        );
+        let timestamp = NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_opt(13, 45, 0)
+            .unwrap();
+        assert_eq!(
+            NaiveDateTime::from_sql_value(json!("2024-05-01 13:45:00")).unwrap(),
+            timestamp
+        );
+        assert_eq!(
+            DateTime::<Utc>::from_sql_value(json!("2024-05-01T15:45:00+02:00")).unwrap(),
+            timestamp.and_utc()
+        );
+    }
+}
@@ -2,6 +2,7 @@ pub mod column;
 pub mod dataset;
 pub mod datasource;
 pub mod entity;
+pub mod from_sql_value;
 // pub mod postgres;
 //
-pub use datasource::DataSource;
+pub use datasource::{DataSource, ExecResult};
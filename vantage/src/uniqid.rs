@@ -33,10 +33,6 @@ impl UniqueIdVendor {
         self.avoid.insert(name.to_string());
     }
 
-    pub fn dont_avoid(&mut self, name: &str) {
-        self.avoid.remove(name);
-    }
-
     // Provided desired names ("n", "na", "nam") find available one
     // If none are available, will add _2, _3 to last option.
     pub fn get_one_of_uniq_id(&mut self, desired_names: Vec<&str>) -> String {
@@ -54,27 +50,26 @@ impl UniqueIdVendor {
         self.get_uniq_id(last_option)
     }
 
+    // Single-letter names have no shorter prefix, so the name itself is returned
     pub fn all_prefixes(name: &str) -> Vec<&str> {
-        (1..name.len()).into_iter().map(|i| &name[..i]).collect()
+        if name.len() <= 1 {
+            return vec![name];
+        }
+        (1..name.len()).map(|i| &name[..i]).collect()
     }
 
     // Check for identical keys in either the avoid set or map between two vendors
-    pub fn has_conflict(&self, other: &UniqueIdVendor) -> bool {
-        // Check if any key in self.avoid is in other.avoid or other.map
-        for key in &self.avoid {
-            if other.avoid.contains(key) || other.map.contains_key(key) {
-                return true;
-            }
-        }
-
-        // Check if any key in self.map is in other.avoid or other.map
-        for key in self.map.keys() {
-            if other.avoid.contains(key) || other.map.contains_key(key) {
-                return true;
-            }
-        }
-
-        false
+    pub fn conflicts(&self, other: &UniqueIdVendor) -> Vec<String> {
+        let mut conflicts: Vec<String> = self
+            .avoid
+            .iter()
+            .chain(self.map.keys())
+            .filter(|key| other.avoid.contains(*key) || other.map.contains_key(*key))
+            .cloned()
+            .collect();
+        conflicts.sort();
+        conflicts.dedup();
+        conflicts
     }
 
     pub fn merge(&mut self, other: UniqueIdVendor) {
@@ -102,7 +97,22 @@ mod conflict_tests {
             .map
             .insert("conflict".to_string(), "value".to_string());
 
-        assert!(vendor1.has_conflict(&vendor2));
+        assert_eq!(vendor1.conflicts(&vendor2), vec!["conflict".to_string()]);
+    }
+
+    #[test]
+    fn test_single_letter_name() {
+        let mut vendor = UniqueIdVendor::new();
+
+        assert_eq!(UniqueIdVendor::all_prefixes("t"), vec!["t"]);
+        assert_eq!(
+            vendor.get_one_of_uniq_id(UniqueIdVendor::all_prefixes("t")),
+            "t"
+        );
+        assert_eq!(
+            vendor.get_one_of_uniq_id(UniqueIdVendor::all_prefixes("t")),
+            "t_2"
+        );
     }
 
     #[test]
@@ -115,7 +125,7 @@ mod conflict_tests {
             .map
             .insert("unique2".to_string(), "value".to_string());
 
-        assert!(!vendor1.has_conflict(&vendor2));
+        assert!(vendor1.conflicts(&vendor2).is_empty());
     }
 }
 
@@ -0,0 +1,679 @@
+//! Helpers for [axum](https://docs.rs/axum) handlers. Enabled with `axum` feature.
+//!
+//! [`ListParams`] extracts filters, sorting and pagination from the query string
+//! and [`DataSetJson`] responds with a page of records as a JSON array:
+//!
+//! ```
+//! // GET /products?category=cake&sort=-price,name&page=1&per_page=20
+//! async fn list_products(params: ListParams) -> Result<DataSetJson<Product>, ApiError> {
+//!     let products = params.apply(Product::table(), &["name", "category", "price"])?;
+//!     DataSetJson::from_table(&products, &params).await
+//! }
+//! ```
+//!
+//! Only the listed columns can be used for filtering and sorting, anything else
+//! is rejected with `400 Bad Request`. Response has `X-Total-Count`, `X-Page`
+//! and `X-Per-Page` headers. Pages start with 0.
+//!
+//! [`crud_router()`] builds list, get, create, update and delete routes for a
+//! table with these helpers.
+
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use axum::async_trait;
+use axum::extract::{FromRequestParts, Path, Query};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::MethodRouter;
+use axum::{Json, Router};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::dataset::{ReadableDataSet, WritableDataSet};
+use crate::sql::query::Direction;
+use crate::sql::table::{AnyTable, Column, Entry, Operation, Table, TableWithColumns};
+use crate::sql::Operations;
+use crate::traits::datasource::DataSource;
+use crate::traits::entity::Entity;
+use crate::Error;
+
+/// Records per page, unless `per_page` is specified
+pub const DEFAULT_PER_PAGE: i64 = 10;
+/// Upper limit for `per_page`
+pub const MAX_PER_PAGE: i64 = 100;
+
+/// Error, which is converted into a response with a matching status code:
+/// [`Error::NotFound`] becomes `404 Not Found`, invalid query parameters become
+/// `400 Bad Request`, [`Error::Timeout`] becomes `504 Gateway Timeout` and
+/// anything else is `500 Internal Server Error`.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    error: anyhow::Error,
+}
+
+impl ApiError {
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        ApiError {
+            status: StatusCode::BAD_REQUEST,
+            error: anyhow!(message.into()),
+        }
+    }
+
+    pub fn forbidden() -> Self {
+        ApiError {
+            status: StatusCode::FORBIDDEN,
+            error: anyhow!("Forbidden"),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl<E: Into<anyhow::Error>> From<E> for ApiError {
+    fn from(error: E) -> Self {
+        let error = error.into();
+        let status = match error.downcast_ref::<Error>() {
+            Some(Error::NotFound { .. }) => StatusCode::NOT_FOUND,
+            Some(Error::InvalidFilter(_)) => StatusCode::BAD_REQUEST,
+            Some(Error::Timeout(_)) => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError { status, error }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if self.status.is_server_error() {
+            log::error!("{:#}", self.error);
+            return self.status.into_response();
+        }
+        (self.status, self.error.to_string()).into_response()
+    }
+}
+
+/// Query string parameters of a list request. `sort`, `page` and `per_page`
+/// are reserved, all other parameters are filters: `?status=paid` only returns
+/// records, where `status` equals `paid`. More complex conditions can be passed
+/// in `filter` as a JSON document, see [`Table::condition_from_json()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListParams {
+    filters: Vec<(String, Value)>,
+    filter: Option<Value>,
+    sort: Vec<(String, Direction)>,
+    page: i64,
+    per_page: i64,
+}
+
+impl Default for ListParams {
+    fn default() -> Self {
+        ListParams {
+            filters: Vec::new(),
+            filter: None,
+            sort: Vec::new(),
+            page: 0,
+            per_page: DEFAULT_PER_PAGE,
+        }
+    }
+}
+
+impl ListParams {
+    pub fn from_pairs(pairs: impl IntoIterator<Item = (String, String)>) -> Result<Self, ApiError> {
+        let mut params = ListParams::default();
+        for (name, value) in pairs {
+            match name.as_str() {
+                "sort" => {
+                    for field in value.split(',').filter(|f| !f.is_empty()) {
+                        params.sort.push(match field.strip_prefix('-') {
+                            Some(field) => (field.to_string(), Direction::Desc),
+                            None => (field.to_string(), Direction::Asc),
+                        });
+                    }
+                }
+                "filter" => {
+                    params.filter = Some(serde_json::from_str(&value).map_err(|e| {
+                        ApiError::bad_request(format!("Parameter 'filter' must be JSON: {}", e))
+                    })?)
+                }
+                "page" => params.page = Self::parse_number(&name, &value, 0)?,
+                "per_page" => {
+                    params.per_page = Self::parse_number(&name, &value, 1)?.min(MAX_PER_PAGE)
+                }
+                _ => params.filters.push((name, Self::parse_value(&value))),
+            }
+        }
+        if params.page.checked_mul(params.per_page).is_none() {
+            return Err(ApiError::bad_request("Parameter 'page' is too large"));
+        }
+        Ok(params)
+    }
+
+    fn parse_number(name: &str, value: &str, min: i64) -> Result<i64, ApiError> {
+        match value.parse::<i64>() {
+            Ok(n) if n >= min => Ok(n),
+            _ => Err(ApiError::bad_request(format!(
+                "Parameter '{}' must be a number, not less than {}",
+                name, min
+            ))),
+        }
+    }
+
+    /// Numbers and booleans are compared as such, everything else as a string
+    fn parse_value(value: &str) -> Value {
+        if let Ok(n) = value.parse::<i64>() {
+            return n.into();
+        }
+        if let Ok(b) = value.parse::<bool>() {
+            return b.into();
+        }
+        Value::String(value.to_string())
+    }
+
+    pub fn page(&self) -> i64 {
+        self.page
+    }
+
+    pub fn per_page(&self) -> i64 {
+        self.per_page
+    }
+
+    /// Number of records before the requested page
+    pub fn skip(&self) -> i64 {
+        self.page * self.per_page
+    }
+
+    /// Adds conditions and order to the table. Fails if a parameter refers
+    /// to a column, which is not listed in `allowed_columns`. Records are ordered
+    /// by id last, so that pages are consistent.
+    pub fn apply<T: DataSource, E: Entity>(
+        &self,
+        mut table: Table<T, E>,
+        allowed_columns: &[&str],
+    ) -> Result<Table<T, E>, ApiError> {
+        for (field, value) in &self.filters {
+            let column = Self::allowed_column(&table, field, allowed_columns, "filter by")?;
+            table.add_condition(column.eq(value));
+        }
+        if let Some(filter) = &self.filter {
+            table = table.with_json_filter(filter, allowed_columns)?;
+        }
+        // order added later takes precedence
+        if let Ok(id) = table.try_id() {
+            table.add_order_by(id, Direction::Asc);
+        }
+        for (field, direction) in self.sort.iter().rev() {
+            let column = Self::allowed_column(&table, field, allowed_columns, "sort by")?;
+            table.add_order_by(column, direction.clone());
+        }
+        Ok(table)
+    }
+
+    fn allowed_column<T: DataSource, E: Entity>(
+        table: &Table<T, E>,
+        field: &str,
+        allowed_columns: &[&str],
+        action: &str,
+    ) -> Result<Arc<Column>, ApiError> {
+        allowed_columns
+            .contains(&field)
+            .then(|| table.get_column(field))
+            .flatten()
+            .ok_or_else(|| ApiError::bad_request(format!("Can't {} '{}'", action, field)))
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ListParams {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Query(pairs) = Query::<Vec<(String, String)>>::try_from_uri(&parts.uri)
+            .map_err(|e| ApiError::bad_request(e.body_text()))?;
+        ListParams::from_pairs(pairs)
+    }
+}
+
+/// Records, responded with as a JSON array. Pagination headers are added,
+/// when the records were fetched with [`DataSetJson::from_table()`].
+#[derive(Debug, Clone)]
+pub struct DataSetJson<E> {
+    records: Vec<E>,
+    total: i64,
+    page: Option<(i64, i64)>,
+}
+
+impl<E: Serialize> DataSetJson<E> {
+    pub fn new(records: Vec<E>) -> Self {
+        DataSetJson {
+            total: records.len() as i64,
+            records,
+            page: None,
+        }
+    }
+
+    /// Page of records, requested by `params`, out of `total` records.
+    pub fn new_page(records: Vec<E>, total: i64, params: &ListParams) -> Self {
+        DataSetJson {
+            records,
+            total,
+            page: Some((params.page, params.per_page)),
+        }
+    }
+
+    /// Fetches all records of a dataset.
+    pub async fn from_dataset(dataset: &impl ReadableDataSet<E>) -> Result<Self, ApiError> {
+        Ok(DataSetJson::new(dataset.get().await?))
+    }
+
+    /// Fetches the page of records, requested by `params`, and counts all the
+    /// records of the table.
+    pub async fn from_table<T: DataSource>(
+        table: &Table<T, E>,
+        params: &ListParams,
+    ) -> Result<Self, ApiError>
+    where
+        E: Entity,
+    {
+        let query = table
+            .query()
+            .with_skip_and_limit(params.skip(), params.per_page);
+
+        let (records, total) = futures::try_join!(query.get(), ReadableDataSet::count(table))?;
+        Ok(DataSetJson::new_page(records, total, params))
+    }
+
+    pub fn records(&self) -> &[E] {
+        &self.records
+    }
+
+    pub fn total(&self) -> i64 {
+        self.total
+    }
+
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-total-count", HeaderValue::from(self.total));
+        if let Some((page, per_page)) = self.page {
+            headers.insert("x-page", HeaderValue::from(page));
+            headers.insert("x-per-page", HeaderValue::from(per_page));
+        }
+        headers
+    }
+}
+
+impl<E: Serialize> IntoResponse for DataSetJson<E> {
+    fn into_response(self) -> Response {
+        (self.headers(), Json(self.records)).into_response()
+    }
+}
+
+type AuthHook = Arc<dyn Fn(&Parts, Operation) -> Result<(), ApiError> + Send + Sync>;
+
+/// Creates [`CrudRouter`] for records of the table, returned by `table`. The
+/// function is called for every request. Only [`Operation::READ`] are routed,
+/// unless other operations are enabled explicitly, preferably together with
+/// an auth hook:
+///
+/// ```
+/// let app = Router::new().nest(
+///     "/products",
+///     crud_router(Product::table)
+///         .with_columns(&["name", "price"])
+///         .with_operations(&Operation::ALL)
+///         .with_auth(|parts, operation| match operation {
+///             Operation::List | Operation::Get => Ok(()),
+///             _ if parts.headers.contains_key("x-admin") => Ok(()),
+///             _ => Err(ApiError::forbidden()),
+///         })
+///         .into_router(),
+/// );
+/// ```
+pub fn crud_router<T: DataSource, E: Entity>(
+    table: impl Fn() -> Table<T, E> + Send + Sync + 'static,
+) -> CrudRouter<T, E> {
+    CrudRouter {
+        table: Arc::new(table),
+        columns: Vec::new(),
+        operations: Operation::READ.to_vec(),
+        auth: None,
+    }
+}
+
+/// Routes for records of a table, see [`crud_router()`]:
+///
+///  - `GET /` - page of records, see [`ListParams`];
+///  - `GET /:id` - record or `404 Not Found`;
+///  - `POST /` - inserts record, responds with `201 Created` and `{"id": ..}`;
+///  - `PATCH /:id` - updates fields present in the body, responds with `204 No Content`;
+///  - `DELETE /:id` - deletes record, responds with `204 No Content`.
+pub struct CrudRouter<T: DataSource, E: Entity> {
+    table: Arc<dyn Fn() -> Table<T, E> + Send + Sync>,
+    columns: Vec<String>,
+    operations: Vec<Operation>,
+    auth: Option<AuthHook>,
+}
+
+impl<T: DataSource, E: Entity> CrudRouter<T, E> {
+    /// Columns, which can be used for filtering and sorting the list.
+    pub fn with_columns(mut self, columns: &[&str]) -> Self {
+        self.columns = columns.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    /// Only route these operations. Other requests are responded with
+    /// `405 Method Not Allowed`. Default is [`Operation::READ`].
+    pub fn with_operations(mut self, operations: &[Operation]) -> Self {
+        self.operations = operations.to_vec();
+        self
+    }
+
+    /// Checks every request before it is executed. Returned error is used as
+    /// a response.
+    pub fn with_auth(
+        mut self,
+        auth: impl Fn(&Parts, Operation) -> Result<(), ApiError> + Send + Sync + 'static,
+    ) -> Self {
+        self.auth = Some(Arc::new(auth));
+        self
+    }
+
+    pub fn into_router<S: Clone + Send + Sync + 'static>(self) -> Router<S> {
+        let this = Arc::new(self);
+        let mut records = MethodRouter::new();
+        let mut record = MethodRouter::new();
+
+        for operation in this.operations.clone() {
+            let this = this.clone();
+            match operation {
+                Operation::List => {
+                    records = records.get(move |parts: Parts, params: ListParams| async move {
+                        this.list(parts, params).await
+                    })
+                }
+                Operation::Create => {
+                    records = records.post(move |parts: Parts, Json(entity): Json<E>| async move {
+                        this.create(parts, entity).await
+                    })
+                }
+                Operation::Get => {
+                    record = record.get(move |parts: Parts, Path(id): Path<String>| async move {
+                        this.get(parts, id).await
+                    })
+                }
+                Operation::Update => {
+                    record = record.patch(
+                        move |parts: Parts,
+                              Path(id): Path<String>,
+                              Json(values): Json<Map<String, Value>>| async move {
+                            this.update(parts, id, values).await
+                        },
+                    )
+                }
+                Operation::Delete => {
+                    record = record.delete(move |parts: Parts, Path(id): Path<String>| async move {
+                        this.delete(parts, id).await
+                    })
+                }
+            }
+        }
+
+        Router::new().route("/", records).route("/:id", record)
+    }
+
+    fn authorize(&self, parts: &Parts, operation: Operation) -> Result<(), ApiError> {
+        match &self.auth {
+            Some(auth) => auth(parts, operation),
+            None => Ok(()),
+        }
+    }
+
+    /// Entry for the id from the path. Numeric ids are parsed as numbers.
+    fn entry(&self, id: &str) -> Entry<T, E> {
+        (self.table)().entry(ListParams::parse_value(id))
+    }
+
+    /// Update and delete don't fail for a missing record, so check it first.
+    async fn existing_entry(&self, id: &str) -> Result<Entry<T, E>, ApiError> {
+        let entry = self.entry(id);
+        if entry.try_get().await?.is_none() {
+            return Err(entry.not_found().into());
+        }
+        Ok(entry)
+    }
+
+    async fn list(&self, parts: Parts, params: ListParams) -> Result<DataSetJson<E>, ApiError> {
+        self.authorize(&parts, Operation::List)?;
+        let columns: Vec<&str> = self.columns.iter().map(|c| c.as_str()).collect();
+        let table = params.apply((self.table)(), &columns)?;
+        DataSetJson::from_table(&table, &params).await
+    }
+
+    async fn get(&self, parts: Parts, id: String) -> Result<Json<E>, ApiError> {
+        self.authorize(&parts, Operation::Get)?;
+        Ok(Json(self.entry(&id).get().await?))
+    }
+
+    async fn create(&self, parts: Parts, entity: E) -> Result<Response, ApiError> {
+        self.authorize(&parts, Operation::Create)?;
+        let id = (self.table)().insert(entity).await?;
+        Ok((StatusCode::CREATED, Json(json!({ "id": id }))).into_response())
+    }
+
+    async fn update(
+        &self,
+        parts: Parts,
+        id: String,
+        values: Map<String, Value>,
+    ) -> Result<StatusCode, ApiError> {
+        self.authorize(&parts, Operation::Update)?;
+        self.existing_entry(&id).await?.patch(values).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    async fn delete(&self, parts: Parts, id: String) -> Result<StatusCode, ApiError> {
+        self.authorize(&parts, Operation::Delete)?;
+        self.existing_entry(&id).await?.delete().await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::to_bytes;
+    use axum::http::Request;
+    use pretty_assertions::assert_eq;
+    use serde::Deserialize;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+    struct Product {
+        id: i64,
+        name: String,
+        price: i64,
+    }
+    impl Entity for Product {}
+
+    fn products() -> Table<MockDataSource, Product> {
+        Table::new_with_entity("product", MockDataSource::new(&json!([])))
+            .with_id_column("id")
+            .with_column("name")
+            .with_column("price")
+    }
+
+    async fn extract(uri: &str) -> Result<ListParams, ApiError> {
+        let (mut parts, _) = Request::get(uri).body(()).unwrap().into_parts();
+        ListParams::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_list_params() {
+        let params = extract("/products?price=12&sort=-price,name&page=2&per_page=500")
+            .await
+            .unwrap();
+        assert_eq!(params.page(), 2);
+        assert_eq!(params.per_page(), MAX_PER_PAGE);
+
+        let products = params.apply(products(), &["name", "price"]).unwrap();
+        assert_eq!(
+            products.get_select_query().preview(),
+            "SELECT id, name, price FROM product WHERE (price = 12) ORDER BY price DESC, name ASC, id ASC"
+        );
+
+        let err = params.apply(products.clone(), &["name"]).unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.error.to_string(), "Can't filter by 'price'");
+
+        let err = extract("/products?page=-1").await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+
+        let err = extract("/products?page=9223372036854775807&per_page=2")
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.error.to_string(), "Parameter 'page' is too large");
+    }
+
+    #[tokio::test]
+    async fn test_filter_param() {
+        // filter={"or":[{"price":{"lt":5}},{"name":"Pie"}]}
+        let params = extract("/products?filter=%7B%22or%22%3A%5B%7B%22price%22%3A%7B%22lt%22%3A5%7D%7D%2C%7B%22name%22%3A%22Pie%22%7D%5D%7D")
+            .await
+            .unwrap();
+        assert_eq!(
+            params.apply(products(), &["name", "price"]).unwrap().get_select_query().preview(),
+            "SELECT id, name, price FROM product WHERE ((price < 5) OR (name = 'Pie')) ORDER BY id ASC"
+        );
+        let err = params.apply(products(), &["name"]).unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+
+        let err = extract("/products?filter=%7B").await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_response() {
+        let response = DataSetJson::new(vec![Product {
+            id: 1,
+            name: "Cake".to_string(),
+            price: 12,
+        }])
+        .into_response();
+        assert_eq!(response.headers()["x-total-count"], "1");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&body).unwrap(),
+            json!([{"id": 1, "name": "Cake", "price": 12}])
+        );
+
+        let not_found: ApiError = anyhow::Error::from(Error::NotFound {
+            table: "product".to_string(),
+            id: json!(7),
+        })
+        .into();
+        assert_eq!(not_found.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    async fn call(app: &Router, method: &str, uri: &str, body: Value) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .header("x-admin", "1")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_crud_router() {
+        let memory = MemoryDataSource::new().with_table(
+            "product",
+            vec![json!({"id": 1, "name": "Cake", "price": 12})
+                .as_object()
+                .unwrap()
+                .clone()],
+        );
+        let app: Router = crud_router(move || {
+            Table::<_, Product>::new_with_entity("product", memory.clone())
+                .with_id_column("id")
+                .with_column("name")
+                .with_column("price")
+        })
+        .with_columns(&["name"])
+        .with_operations(&Operation::ALL)
+        .with_auth(|parts, operation| match operation {
+            Operation::Delete if !parts.headers.contains_key("x-admin") => {
+                Err(ApiError::forbidden())
+            }
+            _ => Ok(()),
+        })
+        .into_router();
+
+        let product = json!({"id": 2, "name": "Pie", "price": 8});
+        let (status, _) = call(&app, "POST", "/", product.clone()).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let (_, body) = call(&app, "GET", "/2", Value::Null).await;
+        assert_eq!(body, product);
+
+        let (status, _) = call(&app, "GET", "/?price=8", Value::Null).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = call(&app, "PATCH", "/2", json!({"price": 9})).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (_, body) = call(&app, "GET", "/2", Value::Null).await;
+        assert_eq!(body["price"], 9);
+
+        let (status, _) = call(&app, "DELETE", "/2", Value::Null).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = call(&app, "GET", "/2", Value::Null).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = call(&app, "PATCH", "/2", json!({"price": 9})).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let request = Request::delete("/1")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_crud_router_read_only() {
+        let memory = MemoryDataSource::new().with_table(
+            "product",
+            vec![json!({"id": 1, "name": "Cake", "price": 12})
+                .as_object()
+                .unwrap()
+                .clone()],
+        );
+        let app: Router = crud_router(move || {
+            Table::<_, Product>::new_with_entity("product", memory.clone())
+                .with_id_column("id")
+                .with_column("name")
+                .with_column("price")
+        })
+        .into_router();
+
+        let (status, body) = call(&app, "GET", "/1", Value::Null).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["name"], "Cake");
+
+        let (status, _) = call(&app, "POST", "/", json!({"id": 2, "name": "Pie"})).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        let (status, _) = call(&app, "DELETE", "/1", Value::Null).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
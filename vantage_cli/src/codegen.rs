@@ -0,0 +1,333 @@
+//! Rust source for entities, following the layout of `bakery_model`: an entity
+//! struct, `table()` definition with references, a trait with column accessors
+//! and `table_refs!` for traversing references.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use vantage::prelude::{ColumnSchema, TableSchema};
+
+/// Entity name for a table: `line_item` -> `LineItem`
+pub fn entity_name(table: &str) -> String {
+    table
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Field type for a column, based on `information_schema` data type.
+fn rust_type(column: &ColumnSchema, is_id: bool) -> String {
+    let rust_type = match column.data_type.as_str() {
+        "smallint" | "integer" | "bigint" => "i64",
+        "numeric" | "real" | "double precision" => "f64",
+        "boolean" => "bool",
+        "json" | "jsonb" => "serde_json::Value",
+        _ => "String",
+    };
+    match column.nullable && !is_id {
+        true => format!("Option<{}>", rust_type),
+        false => rust_type.to_string(),
+    }
+}
+
+/// Column name as an identifier, `type` becomes `r#type`.
+fn ident(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else",
+        "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+        "move", "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "type",
+        "unsafe", "use", "where", "while", "yield",
+    ];
+    match KEYWORDS.contains(&name) {
+        true => format!("r#{}", name),
+        false => name.to_string(),
+    }
+}
+
+/// Reference of a table, derived from foreign keys.
+struct Reference {
+    name: String,
+    column: String,
+    table: String,
+    many: bool,
+}
+
+/// `with_one` for every foreign key of the table and `with_many` for every
+/// foreign key of other tables, which points to this table.
+fn references(schema: &TableSchema, tables: &[TableSchema]) -> Vec<Reference> {
+    let mut references: Vec<Reference> = schema
+        .foreign_keys
+        .iter()
+        .map(|fk| Reference {
+            name: fk
+                .column
+                .strip_suffix("_id")
+                .unwrap_or(&fk.foreign_table)
+                .to_string(),
+            column: fk.column.clone(),
+            table: fk.foreign_table.clone(),
+            many: false,
+        })
+        .collect();
+
+    for other in tables {
+        for fk in &other.foreign_keys {
+            if fk.foreign_table == schema.name {
+                references.push(Reference {
+                    name: format!("{}s", other.name),
+                    column: fk.column.clone(),
+                    table: other.name.clone(),
+                    many: true,
+                });
+            }
+        }
+    }
+
+    // relation names must be unique and can't clash with columns
+    let mut names = BTreeSet::new();
+    references.retain(|r| names.insert(r.name.clone()) && schema.get_column(&r.name).is_none());
+    references
+}
+
+/// Source of the module for a table. `tables` are used to find references
+/// from other tables.
+pub fn generate_entity(schema: &TableSchema, tables: &[TableSchema]) -> String {
+    let entity = entity_name(&schema.name);
+    let id_column = match schema.primary_key.as_slice() {
+        [id] => Some(id.as_str()),
+        _ => None,
+    };
+    let references = references(schema, tables);
+
+    let mut imports: BTreeSet<String> = references
+        .iter()
+        .filter(|r| r.table != schema.name)
+        .map(|r| format!("{}::{}", r.table, entity_name(&r.table)))
+        .collect();
+    imports.insert("postgres".to_string());
+
+    let mut out = String::new();
+    let imports = imports.into_iter().collect::<Vec<_>>().join(", ");
+    writeln!(out, "use crate::{{{}}};", imports).unwrap();
+    out.push_str(
+        "use serde::{Deserialize, Serialize};\n\
+        use std::sync::{Arc, OnceLock};\n\
+        use vantage::prelude::*;\n\n",
+    );
+
+    out.push_str("#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]\n");
+    writeln!(out, "pub struct {} {{", entity).unwrap();
+    for column in &schema.columns {
+        let is_id = Some(column.name.as_str()) == id_column;
+        writeln!(
+            out,
+            "    pub {}: {},",
+            ident(&column.name),
+            rust_type(column, is_id)
+        )
+        .unwrap();
+    }
+    writeln!(out, "}}\nimpl Entity for {} {{}}\n", entity).unwrap();
+
+    writeln!(out, "impl {} {{", entity).unwrap();
+    writeln!(
+        out,
+        "    pub fn static_table() -> &'static Table<Postgres, {0}> {{\n        \
+        static TABLE: OnceLock<Table<Postgres, {0}>> = OnceLock::new();\n\n        \
+        TABLE.get_or_init(|| {{\n            \
+        Table::new_with_entity(\"{1}\", postgres())",
+        entity, schema.name
+    )
+    .unwrap();
+    for column in &schema.columns {
+        let method = match Some(column.name.as_str()) == id_column {
+            true => "with_id_column",
+            false => "with_column",
+        };
+        writeln!(out, "                .{}(\"{}\")", method, column.name).unwrap();
+    }
+    for r in &references {
+        writeln!(
+            out,
+            "                .{}(\"{}\", \"{}\", || Box::new({}::table()))",
+            if r.many { "with_many" } else { "with_one" },
+            r.name,
+            r.column,
+            entity_name(&r.table)
+        )
+        .unwrap();
+    }
+    writeln!(
+        out,
+        "        }})\n    }}\n    pub fn table() -> Table<Postgres, {0}> {{\n        \
+        {0}::static_table().clone()\n    }}\n}}\n",
+        entity
+    )
+    .unwrap();
+
+    writeln!(out, "pub trait {}Table: AnyTable {{", entity).unwrap();
+    for column in &schema.columns {
+        if Some(column.name.as_str()) == id_column {
+            continue;
+        }
+        writeln!(
+            out,
+            "    fn {}(&self) -> Arc<Column> {{\n        \
+            self.get_column(\"{}\").unwrap()\n    }}",
+            ident(&column.name),
+            column.name
+        )
+        .unwrap();
+    }
+    writeln!(
+        out,
+        "}}\nimpl {0}Table for Table<Postgres, {0}> {{}}",
+        entity
+    )
+    .unwrap();
+
+    if !references.is_empty() {
+        writeln!(
+            out,
+            "\ntable_refs! {{\n    pub trait {0}Refs for Table<Postgres, {0}> {{",
+            entity
+        )
+        .unwrap();
+        for r in &references {
+            writeln!(
+                out,
+                "        fn ref_{}(\"{}\") -> {};",
+                r.name,
+                r.name,
+                entity_name(&r.table)
+            )
+            .unwrap();
+        }
+        out.push_str("    }\n}\n");
+    }
+    out
+}
+
+/// Module declarations for `lib.rs`, re-exporting all entities.
+pub fn generate_mods(tables: &[TableSchema]) -> String {
+    tables
+        .iter()
+        .map(|t| format!("pub mod {0};\npub use {0}::*;\n", t.name))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use vantage::prelude::ForeignKeySchema;
+
+    use super::*;
+
+    fn column(name: &str, data_type: &str, nullable: bool) -> ColumnSchema {
+        ColumnSchema {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable,
+        }
+    }
+
+    fn schemas() -> Vec<TableSchema> {
+        vec![
+            TableSchema {
+                name: "bakery".to_string(),
+                columns: vec![
+                    column("id", "integer", false),
+                    column("name", "text", false),
+                ],
+                primary_key: vec!["id".to_string()],
+                foreign_keys: vec![],
+            },
+            TableSchema {
+                name: "product".to_string(),
+                columns: vec![
+                    column("id", "integer", false),
+                    column("type", "text", true),
+                    column("price", "numeric", false),
+                    column("bakery_id", "integer", false),
+                ],
+                primary_key: vec!["id".to_string()],
+                foreign_keys: vec![ForeignKeySchema {
+                    column: "bakery_id".to_string(),
+                    foreign_table: "bakery".to_string(),
+                    foreign_column: "id".to_string(),
+                }],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_generate_entity() {
+        let tables = schemas();
+        assert_eq!(
+            generate_entity(&tables[1], &tables),
+            r#"use crate::{bakery::Bakery, postgres};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+use vantage::prelude::*;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct Product {
+    pub id: i64,
+    pub r#type: Option<String>,
+    pub price: f64,
+    pub bakery_id: i64,
+}
+impl Entity for Product {}
+
+impl Product {
+    pub fn static_table() -> &'static Table<Postgres, Product> {
+        static TABLE: OnceLock<Table<Postgres, Product>> = OnceLock::new();
+
+        TABLE.get_or_init(|| {
+            Table::new_with_entity("product", postgres())
+                .with_id_column("id")
+                .with_column("type")
+                .with_column("price")
+                .with_column("bakery_id")
+                .with_one("bakery", "bakery_id", || Box::new(Bakery::table()))
+        })
+    }
+    pub fn table() -> Table<Postgres, Product> {
+        Product::static_table().clone()
+    }
+}
+
+pub trait ProductTable: AnyTable {
+    fn r#type(&self) -> Arc<Column> {
+        self.get_column("type").unwrap()
+    }
+    fn price(&self) -> Arc<Column> {
+        self.get_column("price").unwrap()
+    }
+    fn bakery_id(&self) -> Arc<Column> {
+        self.get_column("bakery_id").unwrap()
+    }
+}
+impl ProductTable for Table<Postgres, Product> {}
+
+table_refs! {
+    pub trait ProductRefs for Table<Postgres, Product> {
+        fn ref_bakery("bakery") -> Bakery;
+    }
+}
+"#
+        );
+
+        let bakery = generate_entity(&tables[0], &tables);
+        assert!(bakery.starts_with("use crate::{postgres, product::Product};"));
+        assert!(bakery
+            .contains(r#".with_many("products", "bakery_id", || Box::new(Product::table()))"#));
+    }
+}
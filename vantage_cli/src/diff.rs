@@ -0,0 +1,161 @@
+//! Compares table definitions in Rust sources with the database schema.
+//!
+//! Sources are not compiled - definitions are found by looking for
+//! `Table::new("..")` / `Table::new_with_entity("..")` followed by `with_column`,
+//! `with_id_column`, `with_title_column` and `with_typed_column` calls. Expressions,
+//! joins and other computed fields are ignored.
+
+use indexmap::IndexMap;
+use vantage::prelude::TableSchema;
+
+const TABLE_CONSTRUCTORS: &[&str] = &["Table::new_with_entity(", "Table::new("];
+const COLUMN_METHODS: &[&str] = &[
+    ".with_column(",
+    ".with_id_column(",
+    ".with_title_column(",
+    ".with_typed_column(",
+];
+
+/// Columns of each table, defined in the sources. Definitions of the same table
+/// are merged.
+pub type Definitions = IndexMap<String, Vec<String>>;
+
+/// String literal at the start of `source`, skipping whitespace.
+fn string_literal(source: &str) -> Option<&str> {
+    let source = source.trim_start().strip_prefix('"')?;
+    source.find('"').map(|end| &source[..end])
+}
+
+/// Positions of table constructors in the source.
+fn constructors(source: &str) -> Vec<(usize, &str)> {
+    let mut found: Vec<(usize, &str)> = TABLE_CONSTRUCTORS
+        .iter()
+        .flat_map(|constructor| source.match_indices(constructor))
+        .collect();
+    found.sort();
+    found
+}
+
+pub fn parse_definitions(source: &str, definitions: &mut Definitions) {
+    let constructors = constructors(source);
+    for (i, (pos, constructor)) in constructors.iter().enumerate() {
+        let start = pos + constructor.len();
+        let Some(table) = string_literal(&source[start..]) else {
+            continue;
+        };
+        let end = constructors
+            .get(i + 1)
+            .map_or(source.len(), |(next, _)| *next);
+        let body = &source[start..end];
+
+        let columns = definitions.entry(table.to_string()).or_default();
+        let mut calls: Vec<(usize, &str)> = COLUMN_METHODS
+            .iter()
+            .flat_map(|method| {
+                body.match_indices(method)
+                    .filter_map(|(p, m)| Some((p, string_literal(&body[p + m.len()..])?)))
+            })
+            .collect();
+        calls.sort();
+        for (_, column) in calls {
+            if !columns.iter().any(|c| c == column) {
+                columns.push(column.to_string());
+            }
+        }
+    }
+}
+
+/// Differences between the definitions and the schema, one per line. Tables
+/// missing in the sources are reported only if they are listed in `schemas`.
+pub fn diff(definitions: &Definitions, schemas: &[TableSchema]) -> Vec<String> {
+    let mut differences = Vec::new();
+    for schema in schemas {
+        let Some(columns) = definitions.get(&schema.name) else {
+            differences.push(format!("table '{}' is not defined in Rust", schema.name));
+            continue;
+        };
+        for column in columns {
+            if schema.get_column(column).is_none() {
+                differences.push(format!(
+                    "column '{}.{}' is missing in the database",
+                    schema.name, column
+                ));
+            }
+        }
+        for column in &schema.columns {
+            if !columns.contains(&column.name) {
+                differences.push(format!(
+                    "column '{}.{}' is not defined in Rust",
+                    schema.name, column.name
+                ));
+            }
+        }
+    }
+    for table in definitions.keys() {
+        if !schemas.iter().any(|s| &s.name == table) {
+            differences.push(format!("table '{}' is missing in the database", table));
+        }
+    }
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use vantage::prelude::ColumnSchema;
+
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let source = r#"
+            Table::new_with_entity("product", postgres())
+                .with_id_column("id")
+                .with_title_column("name")
+                .with_column("calories")
+                .with_expression("total", |t| expr!("1"))
+                .with_one("bakery", "bakery_id", || Box::new(Bakery::table()))
+            // ...
+            let t = self.with_join::<ProductInventory, EmptyEntity>(
+                Table::new("inventory", postgres())
+                    .with_alias("i")
+                    .with_id_column("product_id"),
+                "id",
+            );
+        "#;
+        let mut definitions = Definitions::new();
+        parse_definitions(source, &mut definitions);
+        assert_eq!(
+            definitions,
+            IndexMap::from([
+                (
+                    "product".to_string(),
+                    vec!["id".to_string(), "name".to_string(), "calories".to_string()]
+                ),
+                ("inventory".to_string(), vec!["product_id".to_string()]),
+            ])
+        );
+
+        let product = TableSchema {
+            name: "product".to_string(),
+            columns: ["id", "name", "price"]
+                .iter()
+                .map(|name| ColumnSchema {
+                    name: name.to_string(),
+                    data_type: "integer".to_string(),
+                    nullable: false,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(
+            diff(&definitions, &[product, TableSchema::new("bakery")]),
+            vec![
+                "column 'product.calories' is missing in the database",
+                "column 'product.price' is not defined in Rust",
+                "table 'bakery' is not defined in Rust",
+                "table 'inventory' is missing in the database",
+            ]
+        );
+    }
+}
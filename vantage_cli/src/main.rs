@@ -0,0 +1,133 @@
+//! Command line tool for working with vantage models:
+//!
+//! ```sh
+//! # write src/product.rs, src/bakery.rs, .. for all tables of the database
+//! vantage_cli generate --out bakery_model/src
+//!
+//! # compare definitions in bakery_model with the database
+//! vantage_cli diff --src bakery_model/src product bakery
+//! ```
+//!
+//! Generated modules expect `crate::postgres()` to return the [`Postgres`]
+//! data source, same as in `bakery_model`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, Subcommand};
+use vantage::prelude::{ConnectOptions, Postgres, TableSchema};
+
+mod codegen;
+mod diff;
+
+#[derive(Parser)]
+#[command(about = "Generates vantage entities from a Postgres database")]
+struct Cli {
+    /// Postgres connection string
+    #[arg(
+        long,
+        env = "DATABASE_URL",
+        default_value = "postgres://postgres@localhost:5432/postgres"
+    )]
+    database_url: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write a module with an entity and its table for each table
+    Generate {
+        /// Directory for the modules
+        #[arg(long, default_value = "src")]
+        out: PathBuf,
+        /// Overwrite existing modules
+        #[arg(long)]
+        force: bool,
+        /// Tables to generate, all tables by default
+        tables: Vec<String>,
+    },
+    /// Compare table definitions in Rust sources with the database
+    Diff {
+        /// Directory with the sources
+        #[arg(long, default_value = "src")]
+        src: PathBuf,
+        /// Tables to compare, all tables by default
+        tables: Vec<String>,
+    },
+}
+
+async fn introspect(postgres: &Postgres, tables: &[String]) -> Result<Vec<TableSchema>> {
+    let tables = match tables.is_empty() {
+        true => postgres.list_tables().await?,
+        false => tables.to_vec(),
+    };
+    let mut schemas = Vec::new();
+    for table in tables {
+        schemas.push(postgres.introspect_table(&table).await?);
+    }
+    Ok(schemas)
+}
+
+fn generate(schemas: &[TableSchema], out: &Path, force: bool) -> Result<()> {
+    std::fs::create_dir_all(out)?;
+    for schema in schemas {
+        let path = out.join(format!("{}.rs", schema.name));
+        if path.exists() && !force {
+            return Err(anyhow!(
+                "{} already exists, use --force to overwrite",
+                path.display()
+            ));
+        }
+        std::fs::write(&path, codegen::generate_entity(schema, schemas))?;
+        eprintln!("Written {}", path.display());
+    }
+    println!("// Add to lib.rs:\n{}", codegen::generate_mods(schemas));
+    Ok(())
+}
+
+/// Reads definitions from all `.rs` files in the directory and its subdirectories.
+fn read_definitions(dir: &Path, definitions: &mut diff::Definitions) -> Result<()> {
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("Unable to read {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_dir() {
+            read_definitions(&path, definitions)?;
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            diff::parse_definitions(&std::fs::read_to_string(&path)?, definitions);
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let postgres = Postgres::connect(&cli.database_url, ConnectOptions::default()).await?;
+
+    match cli.command {
+        Command::Generate { out, force, tables } => {
+            let schemas = introspect(&postgres, &tables).await?;
+            generate(&schemas, &out, force)
+        }
+        Command::Diff { src, tables } => {
+            let schemas = introspect(&postgres, &tables).await?;
+            let mut definitions = diff::Definitions::new();
+            read_definitions(&src, &mut definitions)?;
+            if !tables.is_empty() {
+                definitions.retain(|table, _| tables.contains(table));
+            }
+
+            let differences = diff::diff(&definitions, &schemas);
+            for difference in &differences {
+                println!("{}", difference);
+            }
+            match differences.is_empty() {
+                true => Ok(()),
+                false => Err(anyhow!("{} differences found", differences.len())),
+            }
+        }
+    }
+}
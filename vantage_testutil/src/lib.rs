@@ -0,0 +1,211 @@
+//! Ephemeral Postgres for tests.
+//!
+//! [`TestPostgres::start()`] runs Postgres in a container, applies the schema
+//! to a template database and waits until it accepts connections. Every call
+//! to [`datasource()`](TestPostgres::datasource) creates a fresh database from
+//! the template, so tests don't see each other's changes:
+//!
+//! ```
+//! let db = TestPostgres::start(include_str!("../schema-pg.sql")).await?;
+//! let postgres = db.datasource().await?;
+//!
+//! let products = Table::new("product", postgres).with_column("name");
+//! // ...
+//! db.cleanup().await?;
+//! ```
+//!
+//! Set `VANTAGE_TEST_DATABASE_URL` to use an existing server instead of a
+//! container. Databases are created next to the one in the URL and removed by
+//! [`cleanup()`](TestPostgres::cleanup).
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use testcontainers_modules::postgres::Postgres as PostgresImage;
+use testcontainers_modules::testcontainers::runners::AsyncRunner;
+use testcontainers_modules::testcontainers::ContainerAsync;
+use vantage::prelude::{ConnectOptions, Postgres};
+
+/// Environment variable with a server to use instead of a container
+pub const DATABASE_URL: &str = "VANTAGE_TEST_DATABASE_URL";
+
+/// How long to wait for the server to accept connections
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Unique database name, as tests in other processes may share the server.
+fn database_name(prefix: &str) -> String {
+    format!(
+        "{}_{}_{}",
+        prefix,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// `url` with the database replaced by `database`.
+fn database_url(url: &str, database: &str) -> String {
+    let server = match url.rfind('/') {
+        Some(pos) if pos > url.find("//").map_or(0, |p| p + 1) => &url[..pos],
+        _ => url,
+    };
+    format!("{}/{}", server, database)
+}
+
+/// Options for connecting to a server, which may still be starting up.
+fn connect_options() -> ConnectOptions {
+    ConnectOptions::default()
+        .with_retries(8)
+        .with_connect_timeout(CONNECT_TIMEOUT)
+}
+
+/// Postgres server with a schema, handing out isolated databases.
+pub struct TestPostgres {
+    url: String,
+    admin: Postgres,
+    template: String,
+    databases: Mutex<Vec<String>>,
+    _container: Option<ContainerAsync<PostgresImage>>,
+}
+
+impl TestPostgres {
+    /// Starts a container, unless `VANTAGE_TEST_DATABASE_URL` is set, and
+    /// applies `schema`.
+    pub async fn start(schema: &str) -> Result<TestPostgres> {
+        if let Ok(url) = std::env::var(DATABASE_URL) {
+            return Self::with_server(&url, schema, None).await;
+        }
+
+        let container = PostgresImage::default()
+            .start()
+            .await
+            .context("Unable to start Postgres container")?;
+        let url = format!(
+            "postgres://postgres:postgres@{}:{}/postgres",
+            container.get_host().await?,
+            container.get_host_port_ipv4(5432).await?
+        );
+        Self::with_server(&url, schema, Some(container)).await
+    }
+
+    /// Uses an existing server, `url` must allow creating databases.
+    pub async fn connect(url: &str, schema: &str) -> Result<TestPostgres> {
+        Self::with_server(url, schema, None).await
+    }
+
+    async fn with_server(
+        url: &str,
+        schema: &str,
+        container: Option<ContainerAsync<PostgresImage>>,
+    ) -> Result<TestPostgres> {
+        let admin = Postgres::connect(url, connect_options()).await?;
+        let template = database_name("vantage_template");
+        admin
+            .client()
+            .batch_execute(&format!("CREATE DATABASE {}", template))
+            .await?;
+
+        let harness = TestPostgres {
+            url: url.to_string(),
+            admin,
+            template: template.clone(),
+            databases: Mutex::new(vec![template.clone()]),
+            _container: container,
+        };
+
+        // connections to the template must be closed before it can be copied
+        let client = Postgres::connect(&database_url(url, &template), connect_options()).await?;
+        client
+            .client()
+            .batch_execute(schema)
+            .await
+            .context("Unable to apply the schema")?;
+        drop(client);
+        Ok(harness)
+    }
+
+    /// Creates a database from the template and connects to it.
+    pub async fn datasource(&self) -> Result<Postgres> {
+        let database = database_name("vantage_test");
+        // template may still have a connection, which is being closed
+        let start = Instant::now();
+        while let Err(e) = self
+            .admin
+            .client()
+            .batch_execute(&format!(
+                "CREATE DATABASE {} TEMPLATE {}",
+                database, self.template
+            ))
+            .await
+        {
+            if start.elapsed() > CONNECT_TIMEOUT {
+                return Err(e).context("Unable to create a database from the template");
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        self.databases.lock().unwrap().push(database.clone());
+
+        Postgres::connect(&database_url(&self.url, &database), connect_options()).await
+    }
+
+    /// Drops created databases and stops the container. Datasources should
+    /// not be used after this.
+    pub async fn cleanup(self) -> Result<()> {
+        let databases = std::mem::take(&mut *self.databases.lock().unwrap());
+        for database in databases.iter().rev() {
+            self.admin
+                .client()
+                .batch_execute(&format!(
+                    "DROP DATABASE IF EXISTS {} WITH (FORCE)",
+                    database
+                ))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vantage::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_database_url() {
+        assert_eq!(
+            database_url("postgres://postgres@localhost:5432/postgres", "test"),
+            "postgres://postgres@localhost:5432/test"
+        );
+        assert_eq!(
+            database_url("postgres://postgres@localhost:5432", "test"),
+            "postgres://postgres@localhost:5432/test"
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "requires docker"]
+    async fn test_isolated_databases() -> Result<()> {
+        let db = TestPostgres::start(
+            "CREATE TABLE product (id SERIAL PRIMARY KEY, name TEXT);
+            INSERT INTO product (name) VALUES ('Cake');",
+        )
+        .await?;
+
+        let products = |postgres| {
+            Table::new("product", postgres)
+                .with_id_column("id")
+                .with_column("name")
+        };
+        let first = products(db.datasource().await?);
+        let second = products(db.datasource().await?);
+        first.clone().with_id(1.into()).delete().await?;
+
+        assert_eq!(first.count().get_one_as::<i64>().await?, 0);
+        assert_eq!(second.count().get_one_as::<i64>().await?, 1);
+        db.cleanup().await
+    }
+}
@@ -0,0 +1,285 @@
+//! Behaviour of the Postgres data source, which needs a server. Run with
+//! `cargo test -- --ignored`, see [`TestPostgres`] for using an existing server.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use vantage::prelude::*;
+use vantage::sql::query::QueryType;
+use vantage_testutil::TestPostgres;
+
+const SCHEMA: &str = "CREATE TABLE product (id SERIAL PRIMARY KEY, name TEXT NOT NULL);";
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+struct Product {
+    name: String,
+}
+impl Entity for Product {}
+
+fn products(postgres: Postgres) -> Table<Postgres, Product> {
+    Table::new_with_entity("product", postgres)
+        .with_id_column("id")
+        .with_column("name")
+}
+
+fn cake() -> Product {
+    Product {
+        name: "Cake".to_string(),
+    }
+}
+
+async fn count(postgres: &Postgres) -> Result<i64> {
+    products(postgres.clone()).count().get_one_as::<i64>().await
+}
+
+#[tokio::test]
+#[ignore = "requires docker"]
+async fn test_transaction_belongs_to_task() -> Result<()> {
+    let db = TestPostgres::start(SCHEMA).await?;
+    let postgres = db.datasource().await?;
+
+    postgres.begin_transaction().await?;
+    products(postgres.clone()).insert(cake()).await?;
+    assert_eq!(count(&postgres).await?, 1);
+
+    // other tasks don't see the uncommitted record
+    let other = postgres.clone();
+    assert_eq!(tokio::spawn(async move { count(&other).await }).await??, 0);
+    let other = postgres.clone();
+    assert!(
+        tokio::spawn(async move { other.commit_transaction().await })
+            .await?
+            .is_err()
+    );
+
+    postgres.rollback_transaction().await?;
+    assert_eq!(count(&postgres).await?, 0);
+    db.cleanup().await
+}
+
+#[tokio::test]
+#[ignore = "requires docker"]
+async fn test_nested_transaction() -> Result<()> {
+    let db = TestPostgres::start(SCHEMA).await?;
+    let postgres = db.datasource().await?;
+
+    postgres.begin_transaction().await?;
+    products(postgres.clone()).insert(cake()).await?;
+
+    // inner transaction is rolled back to its savepoint
+    postgres.begin_transaction().await?;
+    products(postgres.clone()).insert(cake()).await?;
+    postgres.rollback_transaction().await?;
+    assert_eq!(count(&postgres).await?, 1);
+
+    // savepoints of other tasks don't clash
+    let other = postgres.clone();
+    tokio::spawn(async move {
+        other.begin_transaction().await?;
+        other.begin_transaction().await?;
+        products(other.clone()).insert(cake()).await?;
+        other.commit_transaction().await?;
+        other.commit_transaction().await
+    })
+    .await??;
+
+    postgres.commit_transaction().await?;
+    assert_eq!(count(&postgres).await?, 2);
+    db.cleanup().await
+}
+
+/// Value of `app.user` as seen by a query, which runs long enough to overlap
+/// with queries of other tasks.
+async fn current_user(postgres: &Postgres) -> Result<Value> {
+    postgres
+        .query_one(&Query::new().with_type(QueryType::Expression(expr!(
+            "SELECT current_setting('app.user', true) FROM pg_sleep(0.05)"
+        ))))
+        .await
+}
+
+#[tokio::test]
+#[ignore = "requires docker"]
+async fn test_settings_are_not_shared() -> Result<()> {
+    // like a row-level security policy, the owner is taken from the settings
+    let db = TestPostgres::start(&format!(
+        "{} ALTER TABLE product ADD COLUMN owner TEXT DEFAULT current_setting('app.user', true);",
+        SCHEMA
+    ))
+    .await?;
+    let postgres = db.datasource().await?;
+
+    let mut tasks = Vec::new();
+    for user in ["alice", "bob", ""] {
+        let postgres = match user {
+            "" => postgres.clone(),
+            user => postgres.clone().with_settings([("app.user", user)]),
+        };
+        tasks.push(tokio::spawn(async move {
+            let mut seen = Vec::new();
+            for _ in 0..5 {
+                seen.push(current_user(&postgres).await?);
+                products(postgres.clone()).insert(cake()).await?;
+                products(postgres.clone())
+                    .copy_from_iter(vec![cake()])
+                    .await?;
+            }
+            anyhow::Ok(seen)
+        }));
+    }
+
+    for (task, user) in tasks.into_iter().zip(["alice", "bob", ""]) {
+        for seen in task.await?? {
+            // without settings the parameter is unset or reset to empty
+            assert!(
+                seen.as_str().unwrap_or_default() == user,
+                "{} saw {}",
+                user,
+                seen
+            );
+        }
+        let owned = Query::new().with_type(QueryType::Expression(expr!(
+            "SELECT count(*) FROM product WHERE coalesce(owner, '') = {}",
+            user
+        )));
+        assert_eq!(postgres.query_one(&owned).await?, 10, "rows of {}", user);
+    }
+    db.cleanup().await
+}
+
+/// Query, which sleeps on the server for `seconds`.
+fn sleep(postgres: &Postgres, seconds: f64) -> AssociatedQuery<Postgres, EmptyEntity> {
+    AssociatedQuery::new(
+        Query::new().with_type(QueryType::Expression(expr!(
+            "SELECT 1 FROM pg_sleep({}::float8)",
+            seconds
+        ))),
+        postgres.clone(),
+    )
+}
+
+#[tokio::test]
+#[ignore = "requires docker"]
+async fn test_timeout_cancels_own_query() -> Result<()> {
+    let db = TestPostgres::start(SCHEMA).await?;
+    let postgres = db.datasource().await?;
+
+    // runs on the shared connection while the other query times out
+    let other = sleep(&postgres, 0.5);
+    let other = tokio::spawn(async move { other.get_one_as::<i64>().await });
+
+    let error = sleep(&postgres, 5.0)
+        .with_timeout(Duration::from_millis(100))
+        .get_one_as::<i64>()
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<vantage::Error>(),
+        Some(vantage::Error::Timeout(_))
+    ));
+    assert_eq!(other.await??, 1);
+
+    // the abandoned query no longer runs on the server
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let running = Query::new().with_type(QueryType::Expression(expr!(
+        "SELECT count(*) FROM pg_stat_activity WHERE datname = current_database() \
+        AND state = 'active' AND query LIKE 'SELECT 1 FROM pg_sleep%'"
+    )));
+    assert_eq!(postgres.query_one(&running).await?, 0);
+    db.cleanup().await
+}
+
+#[tokio::test]
+#[ignore = "requires docker"]
+async fn test_listen_quotes_channel() -> Result<()> {
+    let db = TestPostgres::start(SCHEMA).await?;
+    let postgres = db.datasource().await?;
+
+    let channel = "cache\"; DROP TABLE product; --";
+    let mut notifications = postgres.listen(channel).await?;
+    postgres
+        .query_one(&Query::new().with_type(QueryType::Expression(expr!(
+            "SELECT 1 FROM pg_notify({}, 'cake')",
+            channel
+        ))))
+        .await?;
+
+    let notification = notifications.next().await.unwrap()?;
+    assert_eq!(notification.channel(), channel);
+    assert_eq!(notification.payload(), "cake");
+    assert_eq!(count(&postgres).await?, 0);
+    db.cleanup().await
+}
+
+#[tokio::test]
+#[ignore = "requires docker"]
+async fn test_copy_from_iter() -> Result<()> {
+    let db = TestPostgres::start(SCHEMA).await?;
+    let postgres = db.datasource().await?;
+
+    let view = products(postgres.clone()).into_view();
+    assert!(view.table().copy_from_iter(vec![cake()]).await.is_err());
+    assert!(view.table().copy_from_iter(vec![]).await.is_err());
+
+    assert_eq!(
+        products(postgres.clone())
+            .copy_from_iter(vec![cake()])
+            .await?,
+        1
+    );
+    assert_eq!(count(&postgres).await?, 1);
+
+    // COPY joins the transaction of the task
+    postgres.begin_transaction().await?;
+    products(postgres.clone())
+        .copy_from_iter(vec![cake()])
+        .await?;
+    postgres.rollback_transaction().await?;
+    assert_eq!(count(&postgres).await?, 1);
+    db.cleanup().await
+}
+
+#[tokio::test]
+#[ignore = "requires docker"]
+async fn test_copy_in_quotes_names() -> Result<()> {
+    let db = TestPostgres::start("CREATE TABLE \"order\" (\"unit price\" TEXT);").await?;
+    let postgres = db.datasource().await?;
+
+    let rows = futures::stream::iter(vec![Ok(vec![Value::from("9.99")])]);
+    assert_eq!(postgres.copy_in("order", &["unit price"], rows).await?, 1);
+    db.cleanup().await
+}
+
+#[tokio::test]
+#[ignore = "requires docker"]
+async fn test_parameters_out_of_range() -> Result<()> {
+    let db = TestPostgres::start(SCHEMA).await?;
+    let postgres = db.datasource().await?;
+    let select = |expression| Query::new().with_type(QueryType::Expression(expression));
+
+    assert_eq!(
+        postgres
+            .query_one(&select(expr!("SELECT {}::int2::int4", 7000)))
+            .await?,
+        7000
+    );
+    assert!(postgres
+        .query_one(&select(expr!("SELECT {}::int2", 70000)))
+        .await
+        .is_err());
+    assert!(postgres
+        .query_one(&select(expr!("SELECT {}::int2[]", vec![1, 70000])))
+        .await
+        .is_err());
+    assert!(postgres
+        .query_one(&select(expr!(
+            "SELECT {}::int4[]",
+            serde_json::json!([1, "a"])
+        )))
+        .await
+        .is_err());
+    db.cleanup().await
+}